@@ -1,19 +1,14 @@
 use std::path::PathBuf;
 
 fn main() {
-    let params = typster::CompileParams {
-        input: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("examples")
-            .join("sample.typ"),
-        output: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("examples")
-            .join("sample.pdf"),
-        font_paths: vec![],
-        dict: vec![("input".to_string(), "value".to_string())],
-        ppi: None,
-        package_path: None,
-        package_cache_path: None,
-    };
+    let params = typster::CompileParams::builder()
+        .input(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("examples")
+                .join("sample.typ"),
+        )
+        .dict("input", "value")
+        .build();
 
     typster::list_fonts(&params.font_paths)
         .iter()
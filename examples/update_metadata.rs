@@ -8,15 +8,16 @@ fn main() {
     custom_properties.insert("custom".to_string(), "properties".to_string());
 
     let metadata = PdfMetadata {
-        title: "Title (typster)".to_string(),
+        title: "Title (typster)".into(),
         author: "Author (typster)".to_string(),
         application: "Application (typster)".to_string(),
-        subject: "Subject (typster)".to_string(),
+        subject: "Subject (typster)".into(),
         copyright_status: true,
         copyright_notice: "Copyright notice (typster)".to_string(),
         keywords: vec!["typster".to_string(), "rust".to_string(), "pdf".to_string()],
         language: "en".to_string(),
         custom_properties,
+        ..Default::default()
     };
 
     let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
@@ -5,19 +5,15 @@ use typster::FittingType;
 
 fn main() {
     let rt = Runtime::new().unwrap();
-    let params = typster::CompileParams {
-        input: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("examples")
-            .join("sample.typ"),
-        output: PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("examples")
-            .join("sample.pdf"),
-        font_paths: vec!["assets".into()],
-        dict: vec![("input".to_string(), "value".to_string())],
-        ppi: None,
-        package_path: None,
-        package_cache_path: None,
-    };
+    let params = typster::CompileParams::builder()
+        .input(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("examples")
+                .join("sample.typ"),
+        )
+        .font_path("assets")
+        .dict("input", "value")
+        .build();
 
     rt.block_on(async {
         if let Err(error) =
@@ -0,0 +1,57 @@
+/* C declarations of the `ffi` feature of typster. See README.md for the conventions. */
+
+#ifndef TYPSTER_H
+#define TYPSTER_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Status code returned by the functions. Values are stable across releases. */
+typedef enum TypsterStatus {
+    TYPSTER_OK = 0,
+    TYPSTER_INVALID_ARGUMENT = 1,
+    TYPSTER_WORLD = 2,
+    TYPSTER_DIAGNOSTICS = 3,
+    TYPSTER_IO = 4,
+    TYPSTER_PDF = 5,
+    TYPSTER_FONT = 6,
+    TYPSTER_PARAMS = 7,
+    TYPSTER_LIMIT_EXCEEDED = 8,
+    TYPSTER_TIMED_OUT = 9,
+    TYPSTER_IMAGE = 10,
+    TYPSTER_UNKNOWN = 99,
+} TypsterStatus;
+
+/*
+ * Parameters are JSON of the corresponding Rust structs. On failure, an error message is stored
+ * into `error`, if not null, which must be freed with `typster_string_free()`. On success, `error`
+ * is set to null.
+ */
+
+/* Compiles a Typst document. `params` is JSON of `CompileParams`. */
+TypsterStatus typster_compile(const char *params, char **error);
+
+/*
+ * Formats a Typst file. `params` is JSON of `FormatParams`. The formatted text is stored into
+ * `output`, which must be freed with `typster_string_free()`.
+ */
+TypsterStatus typster_format(const char *params, char **output, char **error);
+
+/* Updates the metadata of a PDF file in place. `metadata` is JSON of `PdfMetadata`. */
+TypsterStatus typster_update_metadata(const char *path, const char *metadata, char **error);
+
+/* Sets the permission of a PDF file. `params` is JSON of `PermissionParams`. */
+TypsterStatus typster_set_permission(const char *input,
+                                     const char *output,
+                                     const char *params,
+                                     char **error);
+
+/* Frees a string returned by the functions above. Does nothing if `s` is null. */
+void typster_string_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* TYPSTER_H */
@@ -0,0 +1,137 @@
+use std::{error::Error, path::Path};
+
+use lopdf::{Dictionary, Document, Object, StringFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    merge_pdfs::inline_inherited_attributes,
+    overlay::{display_box, transform_rect},
+};
+
+/// A link to add to a page with [`add_links()`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    /// 1-based page number to add the link to.
+    pub page: u32,
+
+    /// Clickable area of the link as `[left, bottom, right, top]`, in points from the bottom-left
+    /// corner of the page as displayed, i.e. with its rotation applied.
+    pub rect: [f32; 4],
+
+    /// Where the link goes.
+    pub target: LinkTarget,
+}
+
+/// Target of a [`Link`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkTarget {
+    /// External URI, e.g. `https://example.com/dashboard`. Only ASCII characters are allowed, so
+    /// others must be percent-encoded.
+    Uri(String),
+    /// 1-based page number of the same document. The page is fitted in the window.
+    Page(u32),
+}
+
+/// Adds link annotations to pages of a PDF file, e.g. to link the numbers of a generated table to
+/// external dashboards in a post-processing step.
+///
+/// Links are invisible, i.e. drawn without a border, and added on top of the existing annotations
+/// of each page.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file, which may be the same as the input file.
+/// - `links` - [`Link`]s to add.
+///
+/// # Example
+///
+/// Following is an example of how to use the `add_links` function:
+///
+/// ```no_run
+/// typster::add_links(
+///     std::path::Path::new("report.pdf"),
+///     std::path::Path::new("report-linked.pdf"),
+///     &[
+///         typster::Link {
+///             page: 1,
+///             rect: [72.0, 700.0, 200.0, 714.0],
+///             target: typster::LinkTarget::Uri("https://example.com/dashboard".to_string()),
+///         },
+///         typster::Link {
+///             page: 1,
+///             rect: [72.0, 680.0, 200.0, 694.0],
+///             target: typster::LinkTarget::Page(3),
+///         },
+///     ],
+/// )
+/// .unwrap();
+/// ```
+pub fn add_links(input: &Path, output: &Path, links: &[Link]) -> Result<(), Box<dyn Error>> {
+    let mut doc = Document::load(input)?;
+    let pages = doc.get_pages().into_values().collect::<Vec<_>>();
+
+    // Validate all links before any change.
+    let page = |number: u32| {
+        number
+            .checked_sub(1)
+            .and_then(|i| pages.get(i as usize))
+            .copied()
+            .ok_or_else(|| format!("page {number} does not exist"))
+    };
+    for link in links {
+        page(link.page)?;
+        match &link.target {
+            LinkTarget::Uri(uri) if !uri.is_ascii() => {
+                return Err(format!("URI must consist of ASCII characters: {uri}").into());
+            }
+            LinkTarget::Page(number) => {
+                page(*number)?;
+            }
+            _ => {}
+        }
+    }
+    inline_inherited_attributes(&mut doc, &pages)?;
+
+    for link in links {
+        let id = page(link.page)?;
+
+        // Map the rectangle on the page as displayed to the user space of the page.
+        let rect = transform_rect(display_box(doc.get_dictionary(id)?)?.matrix, link.rect);
+
+        let mut annot = Dictionary::new();
+        annot.set("Type", Object::Name(b"Annot".to_vec()));
+        annot.set("Subtype", Object::Name(b"Link".to_vec()));
+        annot.set("Rect", rect.map(Object::Real).to_vec());
+        annot.set("Border", vec![Object::Integer(0), Object::Integer(0), Object::Integer(0)]);
+        annot.set("P", id);
+        match &link.target {
+            LinkTarget::Uri(uri) => {
+                let mut action = Dictionary::new();
+                action.set("S", Object::Name(b"URI".to_vec()));
+                action.set("URI", Object::String(uri.as_bytes().to_vec(), StringFormat::Literal));
+                annot.set("A", action);
+            }
+            LinkTarget::Page(number) => {
+                annot.set(
+                    "Dest",
+                    vec![Object::Reference(page(*number)?), Object::Name(b"Fit".to_vec())],
+                );
+            }
+        }
+        let annot = Object::Reference(doc.add_object(annot));
+
+        // Annotations may be an array shared with other pages, or an indirect object.
+        let mut annots = match doc.get_dictionary(id)?.get(b"Annots") {
+            Ok(annots) => doc.dereference(annots)?.1.as_array()?.clone(),
+            Err(_) => vec![],
+        };
+        annots.push(annot);
+        doc.get_dictionary_mut(id)?.set("Annots", annots);
+    }
+
+    doc.save(output)?;
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::{collections::HashMap, error::Error};
+
+use ecow::EcoVec;
+use serde::{Deserialize, Serialize};
+use typst::{
+    diag::{SourceDiagnostic, Warned},
+    syntax::{ast, LinkedNode, Source, Span, SyntaxKind},
+};
+
+use crate::{
+    compile::typst_compile, error::Diagnostics, world::SystemWorld, CompileParams, SourceLocation,
+};
+
+/// Labels and references of a Typst document, with the problems found.
+///
+/// See also [`analyze_refs()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefReport {
+    /// Labels attached to content, e.g. `<intro>` of `= Introduction <intro>`, in the order of
+    /// the sources.
+    pub labels: Vec<LabelDefinition>,
+
+    /// References, e.g. `@intro`, in the order of the sources.
+    pub references: Vec<Reference>,
+
+    /// Problems found, sorted by location.
+    pub findings: Vec<RefFinding>,
+}
+
+/// A label attached to content.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabelDefinition {
+    /// Name of the label without the angle brackets.
+    pub name: String,
+
+    /// Where the label is defined.
+    pub location: SourceLocation,
+}
+
+/// A reference to a label or a bibliography entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reference {
+    /// Name of the referenced label or bibliography key without the `@`.
+    pub target: String,
+
+    /// Where the reference is.
+    pub location: SourceLocation,
+}
+
+/// A problem found by [`analyze_refs()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefFinding {
+    /// A reference to a label or a bibliography entry which does not exist.
+    UnresolvedReference(Reference),
+    /// A label which is neither referenced nor used in code, e.g. by a show rule or `query()`.
+    UnusedLabel(LabelDefinition),
+    /// A label defined more than once. Reported for each definition but the first.
+    DuplicateLabel(LabelDefinition),
+}
+
+impl RefFinding {
+    fn location(&self) -> &SourceLocation {
+        match self {
+            Self::UnresolvedReference(reference) => &reference.location,
+            Self::UnusedLabel(label) | Self::DuplicateLabel(label) => &label.location,
+        }
+    }
+}
+
+/// Lists the labels defined and the references used in a Typst document, and reports unresolved
+/// references as well as unused and duplicate labels, e.g. as a pre-commit check of a large
+/// document.
+///
+/// Labels and references are collected from the syntax of all sources of the project the
+/// document reads, except packages, while whether a reference resolves, including to a
+/// bibliography entry, is decided by compiling the document. A label used in code, e.g.
+/// `#show <intro>: ...`, counts as used; one created with the `label()` function is not collected.
+/// The output file of `params` is not written.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`] of the document.
+///
+/// # Returns
+///
+/// Result containing the [`RefReport`] of the document, or an error if the document fails to
+/// compile for another reason than unresolved references.
+///
+/// # Example
+///
+/// Following is an example of how to use the `analyze_refs` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("thesis.typ").build();
+/// let report = typster::analyze_refs(&params).unwrap();
+/// for finding in &report.findings {
+///     println!("{finding:?}");
+/// }
+/// assert!(report.findings.is_empty());
+/// ```
+pub fn analyze_refs(params: &CompileParams) -> Result<RefReport, Box<dyn Error>> {
+    let world = SystemWorld::new(params).map_err(|err| err.to_string())?;
+    let Warned { output, warnings } = typst_compile(&world);
+    let errors = output.err().unwrap_or_default();
+
+    let mut report = RefReport::default();
+    let mut used = vec![];
+    let mut unresolved = vec![];
+    for source in world.sources() {
+        Visitor {
+            source: &source,
+            errors: &errors,
+            report: &mut report,
+            used: &mut used,
+            unresolved: &mut unresolved,
+        }
+        .visit(&LinkedNode::new(source.root()));
+    }
+
+    // Any other error means the document stopped compiling before all references were checked.
+    let others = errors
+        .iter()
+        .filter(|error| !unresolved.contains(&error.span))
+        .cloned()
+        .collect::<EcoVec<_>>();
+    if !others.is_empty() {
+        return Err(Diagnostics::new(warnings, others).into());
+    }
+
+    let mut counts = HashMap::new();
+    for label in &report.labels {
+        let count = counts.entry(label.name.as_str()).or_insert(0);
+        *count += 1;
+        if *count > 1 {
+            report.findings.push(RefFinding::DuplicateLabel(label.clone()));
+        }
+        let referenced = report
+            .references
+            .iter()
+            .any(|reference| reference.target == label.name);
+        if !referenced && !used.contains(&label.name) {
+            report.findings.push(RefFinding::UnusedLabel(label.clone()));
+        }
+    }
+    report.findings.sort_by(|a, b| a.location().cmp(b.location()));
+
+    Ok(report)
+}
+
+/// Walks the syntax tree of a source to collect labels and references.
+struct Visitor<'a> {
+    source: &'a Source,
+    /// Errors of the compilation, to tell unresolved references.
+    errors: &'a EcoVec<SourceDiagnostic>,
+    report: &'a mut RefReport,
+    /// Names of the labels used in code.
+    used: &'a mut Vec<String>,
+    /// Spans of the unresolved references.
+    unresolved: &'a mut Vec<Span>,
+}
+
+impl Visitor<'_> {
+    fn visit(&mut self, node: &LinkedNode) {
+        match node.kind() {
+            SyntaxKind::Label => {
+                let Some(label) = node.cast::<ast::Label>() else {
+                    return;
+                };
+                let name = label.get().to_string();
+                // Labels in markup are attached to the preceding content, while others are
+                // values, e.g. of a show rule selector.
+                if node.parent_kind() == Some(SyntaxKind::Markup) {
+                    let location = self.location(node);
+                    self.report.labels.push(LabelDefinition { name, location });
+                } else {
+                    self.used.push(name);
+                }
+            }
+            SyntaxKind::Ref => {
+                if let Some(reference) = node.cast::<ast::Ref>() {
+                    let reference = Reference {
+                        target: reference.target().to_string(),
+                        location: self.location(node),
+                    };
+                    if self.errors.iter().any(|error| error.span == node.span()) {
+                        self.unresolved.push(node.span());
+                        self.report
+                            .findings
+                            .push(RefFinding::UnresolvedReference(reference.clone()));
+                    }
+                    self.report.references.push(reference);
+                }
+            }
+            _ => {}
+        }
+        for child in node.children() {
+            self.visit(&child);
+        }
+    }
+
+    fn location(&self, node: &LinkedNode) -> SourceLocation {
+        SourceLocation::at(self.source, node.offset())
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    io::{Cursor, Read},
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use flate2::read::GzDecoder;
+use typst::foundations::Bytes;
+use zip::ZipArchive;
+
+use crate::{compile::compile_world, world::SystemWorld, CompileParams, Limits, TypsterError};
+
+/// A zip or tar (optionally gzip-compressed) archive of a Typst project.
+///
+/// See also [`compile_archive()`].
+#[derive(Debug, Clone)]
+pub enum ProjectArchive {
+    /// Contents of the archive.
+    Bytes(Vec<u8>),
+    /// Path to the archive file.
+    Path(PathBuf),
+}
+
+impl From<Vec<u8>> for ProjectArchive {
+    fn from(bytes: Vec<u8>) -> Self {
+        ProjectArchive::Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for ProjectArchive {
+    fn from(bytes: &[u8]) -> Self {
+        ProjectArchive::Bytes(bytes.to_vec())
+    }
+}
+
+impl From<PathBuf> for ProjectArchive {
+    fn from(path: PathBuf) -> Self {
+        ProjectArchive::Path(path)
+    }
+}
+
+impl From<&Path> for ProjectArchive {
+    fn from(path: &Path) -> Self {
+        ProjectArchive::Path(path.to_path_buf())
+    }
+}
+
+/// Compiles a Typst project packed in a zip or tar archive, without unpacking it to disk.
+///
+/// The archive is mounted as the project root, so that `entry` and every file it reads are looked
+/// up inside the archive. Packages are still resolved as usual. The format is detected from the
+/// contents; zip, tar, and gzip-compressed tar are supported.
+///
+/// Note that [`CompileParams::input`], [`CompileParams::shadow_files`], and
+/// [`CompileParams::sandbox`] are ignored. [`Limits::max_file_size`] and [`Limits::max_memory`] of
+/// [`CompileParams::limits`] are also enforced while unpacking, so that an archive inflating to
+/// more than allowed, e.g. a zip bomb, fails with [`TypsterError::LimitExceeded`] without being
+/// fully inflated.
+///
+/// # Arguments
+///
+/// - `archive` - [`ProjectArchive`], or anything convertible into it, such as bytes or a path.
+/// - `entry` - Path to the main Typst file, relative to the archive root.
+/// - `params` - [`CompileParams`] struct.
+///
+/// # Returns
+///
+/// Result containing the [`Duration`] of the compilation.
+///
+/// # Example
+///
+/// Following is an example of how to use the `compile_archive` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().output("project.pdf").build();
+/// match typster::compile_archive(std::path::Path::new("project.zip"), "main.typ", &params) {
+///     Ok(duration) => println!("Compilation succeeded in {duration:?}"),
+///     Err(why) => eprintln!("{why}"),
+/// }
+/// ```
+pub fn compile_archive(
+    archive: impl Into<ProjectArchive>,
+    entry: impl AsRef<Path>,
+    params: &CompileParams,
+) -> Result<Duration, Box<dyn Error>> {
+    let bytes = match archive.into() {
+        ProjectArchive::Bytes(bytes) => bytes,
+        ProjectArchive::Path(path) => fs::read(path)?,
+    };
+
+    let world = SystemWorld::from_archive(unpack(bytes, &params.limits)?, entry.as_ref(), params)
+        .map_err(|err| err.to_string())?;
+    Ok(compile_world(&world, params)?)
+}
+
+/// Reads all regular files of an archive into memory, keyed by their path relative to the archive
+/// root.
+fn unpack(bytes: Vec<u8>, limits: &Limits) -> Result<HashMap<PathBuf, Bytes>, Box<dyn Error>> {
+    match bytes.as_slice() {
+        [b'P', b'K', 0x03, 0x04, ..] => unpack_zip(bytes, limits),
+        [0x1f, 0x8b, ..] => unpack_tar(GzDecoder::new(Cursor::new(bytes)), limits),
+        _ => unpack_tar(Cursor::new(bytes), limits),
+    }
+}
+
+/// Reads all regular files of a zip archive.
+fn unpack_zip(bytes: Vec<u8>, limits: &Limits) -> Result<HashMap<PathBuf, Bytes>, Box<dyn Error>> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    let mut files = HashMap::new();
+    let mut total = 0;
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file.is_dir() {
+            continue;
+        }
+        // Skip entries which would escape the root, e.g. `../foo.typ`.
+        let Some(path) = file.enclosed_name() else {
+            continue;
+        };
+        // The size in the header is not trusted, since the archive may be crafted.
+        files.insert(path, read_entry(&mut file, limits, &mut total)?);
+    }
+
+    Ok(files)
+}
+
+/// Reads all regular files of a tar archive.
+fn unpack_tar(
+    reader: impl Read,
+    limits: &Limits,
+) -> Result<HashMap<PathBuf, Bytes>, Box<dyn Error>> {
+    let mut archive = tar::Archive::new(reader);
+    let mut files = HashMap::new();
+    let mut total = 0;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        // Skip entries which would escape the root, e.g. `../foo.typ`.
+        let Some(path) = enclosed_name(&entry.path()?) else {
+            continue;
+        };
+        files.insert(path, read_entry(&mut entry, limits, &mut total)?);
+    }
+
+    Ok(files)
+}
+
+/// Reads an entry of an archive, adding its size to the `total` of the entries read so far, and
+/// fails as soon as it exceeds `max_file_size` or the total exceeds `max_memory`, without
+/// inflating more than one byte past the limit.
+fn read_entry(entry: impl Read, limits: &Limits, total: &mut u64) -> Result<Bytes, TypsterError> {
+    let remaining = limits.max_memory.map(|max| max.saturating_sub(*total));
+    let max = limits
+        .max_file_size
+        .into_iter()
+        .chain(remaining)
+        .min()
+        .unwrap_or(u64::MAX);
+    let mut buf = Vec::new();
+    entry.take(max.saturating_add(1)).read_to_end(&mut buf)?;
+
+    let len = buf.len() as u64;
+    if let Some(max) = limits.max_file_size.filter(|max| len > *max) {
+        return Err(TypsterError::LimitExceeded(format!("read a file larger than {max} bytes")));
+    }
+    *total += len;
+    if let Some(max) = limits.max_memory.filter(|max| *total > *max) {
+        return Err(TypsterError::LimitExceeded(format!("read more than {max} bytes of files")));
+    }
+    Ok(Bytes::from(buf))
+}
+
+/// Strips leading `./` and `/` from a path in a tar archive, or returns [`None`] if it refers to
+/// a parent directory.
+fn enclosed_name(path: &Path) -> Option<PathBuf> {
+    path.components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(Some(name)),
+            Component::ParentDir => Some(None),
+            _ => None,
+        })
+        .collect()
+}
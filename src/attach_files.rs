@@ -0,0 +1,188 @@
+use std::{collections::BTreeMap, path::Path};
+
+use lopdf::{text_string, Dictionary, Document, Object, Stream};
+use serde::{Deserialize, Serialize};
+
+/// A file to embed into a PDF file.
+///
+/// See also [`attach_files()`].
+#[derive(Debug, Clone, Default)]
+pub struct Attachment {
+    /// File name of the attachment, e.g. `invoice.xml`.
+    pub name: String,
+
+    /// Content of the attachment.
+    pub bytes: Vec<u8>,
+
+    /// MIME type of the attachment, e.g. `text/csv`. Leave empty if unknown.
+    pub mime: String,
+
+    /// Relationship between the attachment and the document, which is required for PDF/A-3.
+    pub relationship: AttachmentRelationship,
+}
+
+/// Relationship between an [`Attachment`] and the document, i.e. `AFRelationship` of PDF/A-3.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttachmentRelationship {
+    /// The original source material of the document, e.g. the Typst source.
+    Source,
+    /// Information used to derive a visual presentation, e.g. data for a table or a chart.
+    Data,
+    /// An alternative representation of the document, e.g. a machine-readable invoice.
+    Alternative,
+    /// A supplemental representation of the original source or data.
+    Supplement,
+    /// The relationship is not known or cannot be described with the above.
+    #[default]
+    Unspecified,
+}
+
+impl AttachmentRelationship {
+    fn name(&self) -> &'static [u8] {
+        match self {
+            AttachmentRelationship::Source => b"Source",
+            AttachmentRelationship::Data => b"Data",
+            AttachmentRelationship::Alternative => b"Alternative",
+            AttachmentRelationship::Supplement => b"Supplement",
+            AttachmentRelationship::Unspecified => b"Unspecified",
+        }
+    }
+}
+
+/// Embeds files into a PDF file, e.g. the Typst source, CSV data, or a machine-readable invoice.
+///
+/// The files are added to the `EmbeddedFiles` name tree, so that viewers list them as attachments,
+/// and to the associated files (`AF`) of the document, as required by PDF/A-3. Existing attachments
+/// are kept, except those with the same name, which are replaced.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file, which will be updated in place.
+/// - `attachments` - [`Attachment`]s to embed.
+///
+/// # Example
+///
+/// Following is an example of how to use the `attach_files` function:
+///
+/// ```no_run
+/// typster::attach_files(
+///     std::path::Path::new("sample.pdf"),
+///     vec![typster::Attachment {
+///         name: "sample.typ".to_string(),
+///         bytes: std::fs::read("sample.typ").unwrap(),
+///         mime: "text/vnd.typst".to_string(),
+///         relationship: typster::AttachmentRelationship::Source,
+///     }],
+/// )
+/// .unwrap();
+/// ```
+pub fn attach_files(
+    path: &Path,
+    attachments: Vec<Attachment>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut doc = Document::load(path)?;
+    attach(&mut doc, attachments)?;
+
+    // Drop the replaced attachments, if any.
+    doc.prune_objects();
+    doc.save(path)?;
+
+    Ok(())
+}
+
+/// Embeds files into a PDF document in memory. See [`attach_files()`] for details.
+pub(crate) fn attach(
+    doc: &mut Document,
+    attachments: Vec<Attachment>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut names = doc
+        .catalog()?
+        .get_deref(b"Names", doc)
+        .and_then(Object::as_dict)
+        .cloned()
+        .unwrap_or_default();
+    let mut files = BTreeMap::new();
+    if let Ok(tree) = names.get_deref(b"EmbeddedFiles", doc).and_then(Object::as_dict) {
+        collect_names(doc, tree, &mut files, 0);
+    }
+    let mut associated = doc
+        .catalog()?
+        .get_deref(b"AF", doc)
+        .and_then(Object::as_array)
+        .cloned()
+        .unwrap_or_default();
+
+    for attachment in attachments {
+        let mut params = Dictionary::new();
+        params.set("Size", attachment.bytes.len() as i64);
+        let mut dict = Dictionary::new();
+        dict.set("Type", Object::Name(b"EmbeddedFile".to_vec()));
+        if !attachment.mime.is_empty() {
+            dict.set("Subtype", Object::Name(attachment.mime.into_bytes()));
+        }
+        dict.set("Params", params);
+        let file = doc.add_object(Stream::new(dict, attachment.bytes));
+
+        let mut ef = Dictionary::new();
+        ef.set("F", file);
+        let mut spec = Dictionary::new();
+        spec.set("Type", Object::Name(b"Filespec".to_vec()));
+        spec.set("F", text_string(&attachment.name));
+        spec.set("UF", text_string(&attachment.name));
+        spec.set("EF", ef);
+        spec.set("AFRelationship", Object::Name(attachment.relationship.name().to_vec()));
+        let spec = doc.add_object(spec);
+
+        let key = text_string(&attachment.name).as_str()?.to_vec();
+        if let Some(Object::Reference(replaced)) = files.insert(key, Object::Reference(spec)) {
+            associated.retain(|object| object.as_reference().ok() != Some(replaced));
+        }
+        associated.push(Object::Reference(spec));
+    }
+
+    // Rewrite the name tree as a single node, with the keys sorted as required.
+    let mut tree = Dictionary::new();
+    tree.set(
+        "Names",
+        files
+            .into_iter()
+            .flat_map(|(key, spec)| [Object::string_literal(key), spec])
+            .collect::<Vec<_>>(),
+    );
+    names.set("EmbeddedFiles", tree);
+
+    let catalog = doc.catalog_mut()?;
+    catalog.set("Names", names);
+    catalog.set("AF", associated);
+
+    Ok(())
+}
+
+/// Collects the key-value pairs of a name tree node and its kids. `depth` guards against malformed
+/// files whose nodes form a cycle.
+pub(crate) fn collect_names(
+    doc: &Document,
+    node: &Dictionary,
+    names: &mut BTreeMap<Vec<u8>, Object>,
+    depth: usize,
+) {
+    if depth > 32 {
+        return;
+    }
+
+    if let Ok(pairs) = node.get_deref(b"Names", doc).and_then(Object::as_array) {
+        for pair in pairs.chunks(2) {
+            if let [Object::String(key, _), value] = pair {
+                names.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    if let Ok(kids) = node.get_deref(b"Kids", doc).and_then(Object::as_array) {
+        for kid in kids {
+            if let Ok((_, Object::Dictionary(kid))) = doc.dereference(kid) {
+                collect_names(doc, kid, names, depth + 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    path::Path,
+};
+
+use lopdf::{content::Content, Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+
+/// Operators which paint text, paths, images, or shadings.
+const PAINTING_OPERATORS: [&str; 16] =
+    ["Tj", "TJ", "'", "\"", "S", "s", "f", "F", "f*", "B", "B*", "b", "b*", "Do", "sh", "BI"];
+
+/// An accessibility issue found by [`audit_accessibility()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessibilityFinding {
+    /// Kind of the issue.
+    pub issue: AccessibilityIssue,
+    /// 1-based number of the page the issue is on, or [`None`] if it's about the whole document
+    /// or the page is unknown.
+    pub page: Option<u32>,
+    /// Description of the issue.
+    pub message: String,
+}
+
+/// Kind of an [`AccessibilityFinding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessibilityIssue {
+    /// The catalog has no `Lang`, so screen readers can't choose a pronunciation.
+    MissingLanguage,
+    /// The document isn't marked as tagged, or has no structure tree. The other structure checks
+    /// are skipped.
+    NotTagged,
+    /// A page paints content which is neither in the structure tree nor marked as an artifact.
+    UntaggedContent,
+    /// A `Figure` or `Formula` structure element has neither `Alt` nor `ActualText`.
+    MissingAltText,
+    /// A heading skips a level, e.g. an `H3` right after an `H1`.
+    HeadingOrder,
+}
+
+/// Audits a PDF file for common accessibility issues, as a first check before a full PDF/UA
+/// validation, e.g. with [veraPDF](https://verapdf.org/) or PAC: a missing document language,
+/// missing or incomplete tagging, figures without alternative text, and skipped heading levels.
+///
+/// Typst 0.12 doesn't tag its PDF output, so compiled documents are reported as not tagged until
+/// they are post-processed by a tagging tool. Set `text(lang: ..)` in a Typst document to set its
+/// language.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file.
+///
+/// # Returns
+///
+/// Result containing the issues found, in the order of the checks, or empty if none.
+///
+/// # Example
+///
+/// Following is an example of how to use the `audit_accessibility` function:
+///
+/// ```no_run
+/// let findings = typster::audit_accessibility(std::path::Path::new("report.pdf")).unwrap();
+/// for finding in findings {
+///     println!("{:?} on page {:?}: {}", finding.issue, finding.page, finding.message);
+/// }
+/// ```
+pub fn audit_accessibility(path: &Path) -> Result<Vec<AccessibilityFinding>, Box<dyn Error>> {
+    let doc = Document::load(path)?;
+    let catalog = doc.catalog()?;
+    let mut findings = vec![];
+
+    let lang = catalog.get(b"Lang").and_then(Object::as_str).unwrap_or_default();
+    if lang.iter().all(u8::is_ascii_whitespace) {
+        findings.push(finding(
+            AccessibilityIssue::MissingLanguage,
+            None,
+            "the document has no language",
+        ));
+    }
+
+    let marked = catalog
+        .get(b"MarkInfo")
+        .and_then(|info| resolve(&doc, info).as_dict())
+        .and_then(|info| info.get(b"Marked"))
+        .and_then(Object::as_bool)
+        .unwrap_or(false);
+    let root = catalog
+        .get(b"StructTreeRoot")
+        .and_then(|root| resolve(&doc, root).as_dict())
+        .ok();
+    let Some(root) = root.filter(|_| marked) else {
+        findings.push(finding(AccessibilityIssue::NotTagged, None, "the document is not tagged"));
+        return Ok(findings);
+    };
+
+    for (number, id) in doc.get_pages() {
+        let untagged = untagged_operations(&doc, id);
+        if untagged > 0 {
+            findings.push(finding(
+                AccessibilityIssue::UntaggedContent,
+                Some(number),
+                &format!("{untagged} painting operations are neither tagged nor artifacts"),
+            ));
+        }
+    }
+
+    let mut auditor = StructureAuditor {
+        doc: &doc,
+        pages: doc.get_pages().into_iter().map(|(number, id)| (id, number)).collect(),
+        role_map: root.get(b"RoleMap").and_then(|map| resolve(&doc, map).as_dict()).ok(),
+        visited: HashSet::new(),
+        heading_level: 0,
+        findings,
+    };
+    if let Ok(kids) = root.get(b"K") {
+        auditor.visit(kids, None);
+    }
+    Ok(auditor.findings)
+}
+
+/// Creates a finding.
+fn finding(issue: AccessibilityIssue, page: Option<u32>, message: &str) -> AccessibilityFinding {
+    AccessibilityFinding { issue, page, message: message.to_string() }
+}
+
+/// Resolves an object if it's a reference, or returns it as is.
+fn resolve<'a>(doc: &'a Document, object: &'a Object) -> &'a Object {
+    match object {
+        Object::Reference(id) => doc.get_object(*id).unwrap_or(object),
+        _ => object,
+    }
+}
+
+/// Counts the painting operations of a page which are outside any marked-content sequence with
+/// an MCID, i.e. in the structure tree, or tagged as an artifact.
+fn untagged_operations(doc: &Document, page: ObjectId) -> usize {
+    let Some(content) = doc
+        .get_page_content(page)
+        .ok()
+        .and_then(|content| Content::decode(&content).ok())
+    else {
+        return 0;
+    };
+
+    // Whether each open marked-content sequence is tagged, including the enclosing ones.
+    let mut tagged: Vec<bool> = vec![];
+    let mut untagged = 0;
+    for operation in content.operations {
+        match operation.operator.as_str() {
+            "BMC" | "BDC" => {
+                let artifact = operation
+                    .operands
+                    .first()
+                    .and_then(|tag| tag.as_name().ok())
+                    .is_some_and(|tag| tag == b"Artifact");
+                // Named properties in the resources are assumed to carry an MCID.
+                let mcid = match operation.operands.get(1) {
+                    Some(Object::Dictionary(properties)) => properties.has(b"MCID"),
+                    Some(Object::Name(_)) => true,
+                    _ => false,
+                };
+                let enclosing = tagged.last().copied().unwrap_or(false);
+                tagged.push(enclosing || artifact || mcid);
+            }
+            "EMC" => {
+                tagged.pop();
+            }
+            operator
+                if PAINTING_OPERATORS.contains(&operator)
+                    && !tagged.last().copied().unwrap_or(false) =>
+            {
+                untagged += 1;
+            }
+            _ => {}
+        }
+    }
+    untagged
+}
+
+/// Walks the structure tree in document order.
+struct StructureAuditor<'a> {
+    doc: &'a Document,
+    /// Page numbers of the page objects.
+    pages: HashMap<ObjectId, u32>,
+    /// Mapping of custom structure types to standard ones.
+    role_map: Option<&'a Dictionary>,
+    /// Structure elements already visited, to stop at cycles.
+    visited: HashSet<ObjectId>,
+    /// Level of the last heading, or 0 before the first one.
+    heading_level: u8,
+    findings: Vec<AccessibilityFinding>,
+}
+
+impl<'a> StructureAuditor<'a> {
+    /// Visits the kids of a structure element, on the page of the element if known.
+    fn visit(&mut self, kids: &'a Object, page: Option<u32>) {
+        match kids {
+            Object::Array(kids) => kids.iter().for_each(|kid| self.visit(kid, page)),
+            Object::Reference(id) if self.visited.insert(*id) => {
+                if let Ok(kid) = self.doc.get_object(*id) {
+                    self.visit(kid, page);
+                }
+            }
+            Object::Dictionary(element) if element.has(b"S") => self.element(element, page),
+            // Marked-content and object references, and MCIDs, have no structure type.
+            _ => {}
+        }
+    }
+
+    /// Audits a structure element, then visits its kids.
+    fn element(&mut self, element: &'a Dictionary, page: Option<u32>) {
+        let page = element
+            .get(b"Pg")
+            .and_then(Object::as_reference)
+            .ok()
+            .and_then(|id| self.pages.get(&id).copied())
+            .or(page);
+        let kind = self.standard_type(element);
+
+        if matches!(kind.as_slice(), b"Figure" | b"Formula")
+            && !element.has(b"Alt")
+            && !element.has(b"ActualText")
+        {
+            self.findings.push(finding(
+                AccessibilityIssue::MissingAltText,
+                page,
+                &format!("a {} has no alternative text", String::from_utf8_lossy(&kind)),
+            ));
+        }
+
+        if let [b'H', level @ b'1'..=b'6'] = kind.as_slice() {
+            let level = level - b'0';
+            if level > self.heading_level + 1 {
+                self.findings.push(finding(
+                    AccessibilityIssue::HeadingOrder,
+                    page,
+                    &format!("an H{level} follows an H{}", self.heading_level),
+                ));
+            }
+            self.heading_level = level;
+        }
+
+        if let Ok(kids) = element.get(b"K") {
+            self.visit(kids, page);
+        }
+    }
+
+    /// Returns the structure type of an element, mapped to a standard type through the role map.
+    fn standard_type(&self, element: &Dictionary) -> Vec<u8> {
+        let mut kind = element
+            .get(b"S")
+            .and_then(Object::as_name)
+            .unwrap_or_default()
+            .to_vec();
+        // Follow a few mappings, to stop at cycles.
+        for _ in 0..8 {
+            let Some(mapped) = self
+                .role_map
+                .and_then(|map| map.get(&kind).ok())
+                .and_then(|mapped| mapped.as_name().ok())
+            else {
+                break;
+            };
+            kind = mapped.to_vec();
+        }
+        kind
+    }
+}
@@ -0,0 +1,337 @@
+use std::{error::Error, fs, path::PathBuf, process::ExitCode};
+
+use clap::{Args, Parser, Subcommand};
+use serde::de::DeserializeOwned;
+use tokio::runtime::Runtime;
+use typster::{CompileParams, FormatParams, PdfMetadata, PermissionParams, PrintPermission};
+
+/// Compile and format Typst documents, and update metadata and permission of PDF files.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Compile a Typst file to a PDF or PNG file.
+    Compile {
+        #[command(flatten)]
+        world: WorldArgs,
+
+        /// Path to the output file. Defaults to the input with the `pdf` extension.
+        output: Option<PathBuf>,
+
+        /// Pixels per inch of a PNG output.
+        #[arg(long)]
+        ppi: Option<f32>,
+    },
+
+    /// Watch a Typst file along with its dependencies, and recompile it on changes.
+    Watch {
+        #[command(flatten)]
+        world: WorldArgs,
+
+        /// Open the preview in the browser.
+        #[arg(long)]
+        open: bool,
+
+        /// Application to open the preview with, e.g. `Google Chrome.app`, or a command template
+        /// with `{url}`, e.g. `firefox --new-window {url}`.
+        #[arg(long, requires = "open")]
+        app: Option<String>,
+
+        /// Write the URL of the preview to `.typster/preview-url` next to the input, for editor
+        /// tooling.
+        #[arg(long)]
+        url_file: bool,
+    },
+
+    /// Format a Typst file, and print the result.
+    Format {
+        /// Path to the Typst file.
+        input: PathBuf,
+
+        /// Width of the output.
+        #[arg(long, default_value_t = 120)]
+        column: usize,
+
+        /// Overwrite the file instead of printing the result.
+        #[arg(short, long)]
+        in_place: bool,
+    },
+
+    /// Print the metadata of a PDF file as JSON, or update it in place if any is given.
+    Meta {
+        /// Path to the PDF file.
+        pdf: PathBuf,
+
+        /// JSON file of the metadata, i.e. `PdfMetadata`, which the other options override. Without
+        /// it, the other options change only their fields of the current metadata.
+        #[arg(long, value_name = "FILE")]
+        metadata: Option<PathBuf>,
+
+        /// Title of the document.
+        #[arg(long)]
+        title: Option<String>,
+
+        /// Author of the document.
+        #[arg(long)]
+        author: Option<String>,
+
+        /// Subject of the document.
+        #[arg(long)]
+        subject: Option<String>,
+
+        /// Keyword of the document. Can be repeated.
+        #[arg(long = "keyword", value_name = "KEYWORD")]
+        keywords: Vec<String>,
+
+        /// Language of the document, e.g. `en`.
+        #[arg(long)]
+        language: Option<String>,
+    },
+
+    /// Encrypt a PDF file, and set its permission.
+    Protect {
+        /// Path to the input PDF file.
+        input: PathBuf,
+
+        /// Path to the output PDF file.
+        output: PathBuf,
+
+        /// JSON file of the permission, i.e. `PermissionParams`, which the other options
+        /// override.
+        #[arg(long, value_name = "FILE")]
+        params: Option<PathBuf>,
+
+        /// Password to open the file.
+        #[arg(long)]
+        user_password: Option<String>,
+
+        /// Password to change the permission.
+        #[arg(long)]
+        owner_password: Option<String>,
+
+        /// Printing allowed: `full`, `low`, or `none`.
+        #[arg(long, value_parser = json_str::<PrintPermission>)]
+        allow_print: Option<PrintPermission>,
+    },
+
+    /// Print the elements with a label of a Typst document as JSON, like `typst query`.
+    Query {
+        #[command(flatten)]
+        world: WorldArgs,
+
+        /// Label of the elements, without angle brackets.
+        label: String,
+
+        /// Field of the elements to print instead of the elements themselves.
+        #[arg(long)]
+        field: Option<String>,
+    },
+
+    /// List the available font families.
+    Fonts {
+        /// Additional directory to search for fonts. Can be repeated.
+        #[arg(long = "font-path", value_name = "DIR")]
+        font_paths: Vec<PathBuf>,
+
+        /// Also list the style and weight of each font.
+        #[arg(long)]
+        variants: bool,
+    },
+}
+
+/// Arguments to set up the compilation of a Typst file.
+#[derive(Debug, Args)]
+struct WorldArgs {
+    /// Path to the input Typst file.
+    input: PathBuf,
+
+    /// Additional directory to search for fonts. Can be repeated.
+    #[arg(long = "font-path", value_name = "DIR")]
+    font_paths: Vec<PathBuf>,
+
+    /// String key-value pair visible through `sys.inputs`. Can be repeated.
+    #[arg(long = "input", value_name = "KEY=VALUE", value_parser = key_value)]
+    dict: Vec<(String, String)>,
+
+    /// Directory of local packages.
+    #[arg(long)]
+    package_path: Option<PathBuf>,
+
+    /// Directory to cache downloaded packages in.
+    #[arg(long)]
+    package_cache_path: Option<PathBuf>,
+}
+
+impl WorldArgs {
+    fn params(self) -> CompileParams {
+        let mut builder = CompileParams::builder()
+            .output(self.input.with_extension("pdf"))
+            .input(self.input);
+        for (key, value) in self.dict {
+            builder = builder.dict(key, value);
+        }
+        for path in self.font_paths {
+            builder = builder.font_path(path);
+        }
+        if let Some(path) = self.package_path {
+            builder = builder.package_path(path);
+        }
+        if let Some(path) = self.package_cache_path {
+            builder = builder.package_cache_path(path);
+        }
+        builder.build()
+    }
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(why) => {
+            eprintln!("{why}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Compile { world, output, ppi } => {
+            let mut params = world.params();
+            if let Some(output) = output {
+                params.output = output;
+            }
+            params.ppi = ppi;
+            let duration = typster::compile(&params)?;
+            eprintln!("compiled {} in {duration:?}", params.output.display());
+        }
+        Command::Watch { world, open, app, url_file } => {
+            let params = world.params();
+            Runtime::new()?.block_on(async {
+                if !url_file {
+                    return typster::watch(&params, open, app.as_deref(), None).await;
+                }
+                let mut handle = typster::spawn_watch(&params, open, app.as_deref(), None).await?;
+                let path = handle.persist_url()?;
+                eprintln!("preview at {}, written to {}", handle.url(), path.display());
+                tokio::signal::ctrl_c().await?;
+                handle.shutdown();
+                handle.wait().await
+            })?;
+        }
+        Command::Format { input, column, in_place } => {
+            let formatted = typster::format(&FormatParams { input: input.clone(), column })?;
+            if in_place {
+                fs::write(input, formatted)?;
+            } else {
+                print!("{formatted}");
+            }
+        }
+        Command::Meta {
+            pdf,
+            metadata,
+            title,
+            author,
+            subject,
+            keywords,
+            language,
+        } => {
+            let update = metadata.is_some()
+                || title.is_some()
+                || author.is_some()
+                || subject.is_some()
+                || !keywords.is_empty()
+                || language.is_some();
+            if !update {
+                let metadata = typster::get_metadata(&pdf)?;
+                println!("{}", serde_json::to_string_pretty(&metadata)?);
+                return Ok(());
+            }
+
+            let mut metadata: PdfMetadata = match metadata {
+                Some(path) => json_file(path)?,
+                None => typster::get_metadata(&pdf)?,
+            };
+            if let Some(title) = title {
+                metadata.title = title.into();
+            }
+            if let Some(author) = author {
+                metadata.author = author;
+            }
+            if let Some(subject) = subject {
+                metadata.subject = subject.into();
+            }
+            if !keywords.is_empty() {
+                metadata.keywords = keywords;
+            }
+            if let Some(language) = language {
+                metadata.language = language;
+            }
+            typster::update_metadata(&pdf, &metadata)?;
+        }
+        Command::Protect {
+            input,
+            output,
+            params,
+            user_password,
+            owner_password,
+            allow_print,
+        } => {
+            let mut params: PermissionParams = match params {
+                Some(path) => json_file(path)?,
+                None => PermissionParams::default(),
+            };
+            if user_password.is_some() {
+                params.user_password = user_password;
+            }
+            if owner_password.is_some() {
+                params.owner_password = owner_password;
+            }
+            if let Some(allow_print) = allow_print {
+                params.allow_print = allow_print;
+            }
+            typster::set_permission(input, output, &params)?;
+        }
+        Command::Query { world, label, field } => {
+            let values = typster::query(&world.params(), &label, field.as_deref())?;
+            println!("{}", serde_json::to_string_pretty(&values)?);
+        }
+        Command::Fonts { font_paths, variants } => {
+            let mut fonts = typster::list_fonts(&font_paths).into_iter().collect::<Vec<_>>();
+            fonts.sort_by(|a, b| a.0.cmp(&b.0));
+            for (family, infos) in fonts {
+                println!("{family}");
+                if variants {
+                    for info in infos {
+                        println!(
+                            "- Style: {:?}, Weight: {:?}",
+                            info.variant.style, info.variant.weight
+                        );
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Parses a `KEY=VALUE` pair.
+fn key_value(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, got `{s}`"))
+}
+
+/// Parses a string as the JSON string of `T`, e.g. a unit variant of an enum.
+fn json_str<T: DeserializeOwned>(s: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(s.to_string())).map_err(|err| err.to_string())
+}
+
+/// Reads a JSON file of `T`.
+fn json_file<T: DeserializeOwned>(path: PathBuf) -> Result<T, Box<dyn Error>> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
@@ -1,25 +1,50 @@
 use std::{
+    collections::HashMap,
     error::Error,
-    fs,
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    panic,
     path::{Path, PathBuf},
-    time::Duration,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
 use ecow::eco_format;
+use serde::{Deserialize, Serialize};
+use tracing::{info_span, instrument};
 use typst::{
     diag::{At, SourceResult, Warned},
     foundations::Smart,
     model::Document,
+    World,
 };
 use typst_pdf::{PdfOptions, PdfStandards};
 use typst_syntax::Span;
 
-use crate::world::SystemWorld;
+#[cfg(feature = "strip")]
+use crate::strip::{export_strip, Strip};
+#[cfg(feature = "zip_export")]
+use crate::zip_export::export_zip;
+use crate::{
+    error::Diagnostics, timing_session::discard_timings, world::SystemWorld, TypsterError,
+};
 
 /// Parameters for Typst document compilation.
 ///
+/// Fields may be added in any release, so construct it with [`builder()`](CompileParams::builder()),
+/// or from [`Default`] and assign the fields. It can also be deserialized, e.g. from a checked-in
+/// configuration file with [`from_file()`](CompileParams::from_file()), where omitted fields take
+/// their default values.
+///
 /// See also [`compile()`].
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
 pub struct CompileParams {
     /// Path to the input Typst file.
     pub input: PathBuf,
@@ -28,7 +53,8 @@ pub struct CompileParams {
     pub dict: Vec<(String, String)>,
 
     /// Path to the output file (PDF, PNG). Output format is determined by extension, and only PNG
-    /// and PDF are supported.
+    /// and PDF are supported, or PNG and WebP with [`strip`](CompileParams::strip), or a zip
+    /// archive of one image per page with the `zip_export` feature.
     pub output: PathBuf,
 
     /// Adds additional directories to search for fonts.
@@ -42,6 +68,424 @@ pub struct CompileParams {
 
     /// Custom path to package cache, defaults to system-dependent location
     pub package_cache_path: Option<PathBuf>,
+
+    /// In-memory contents which override files on disk, keyed by path. Relative paths are resolved
+    /// against the directory of `input`. Useful for compiling unsaved editor buffers, while all
+    /// other files are still read from disk.
+    pub shadow_files: HashMap<PathBuf, String>,
+
+    /// Restricts which files the document may read, for compiling untrusted documents. [`None`]
+    /// means any file under the directory of `input` can be read, though symlinks may point
+    /// outside of it.
+    pub sandbox: Option<Sandbox>,
+
+    /// Resolves symlinks and denies reading any file outside of the directory of `input`, except
+    /// for packages. Always enabled when [`sandbox`](CompileParams::sandbox) is set.
+    pub deny_symlink_escape: bool,
+
+    /// Callback invoked on every file read during compilation, e.g. to log the dependencies of
+    /// a document or collect performance metrics. Not serialized.
+    #[serde(skip)]
+    pub on_file_access: Option<FileAccessHook>,
+
+    /// PDF standards the output must conform to, e.g. PDF/A-2b for archiving. Compilation fails if
+    /// the document can't conform to them. Ignored for PNG output.
+    pub pdf_standards: Vec<PdfStandard>,
+
+    /// Shares package files of at least this many bytes, e.g. large images or data files, between
+    /// all compilations of the process, so that each one is read once instead of being copied on
+    /// every cold compilation, e.g. in a batch compilation service. [`None`] means all files are
+    /// read by each compilation.
+    ///
+    /// Files of the project are always read, since they may change. A shared file is read again
+    /// when it's modified, and the least recently used ones are dropped beyond 256 MiB in total.
+    pub shared_file_threshold: Option<u64>,
+
+    /// Resource limits of the compilation, for compiling untrusted documents, e.g. in a hosted
+    /// service. Unlimited by default.
+    pub limits: Limits,
+
+    /// Maximum duration to wait for the compilation, e.g. so that a document with heavy loops
+    /// can't block the caller indefinitely. [`None`] means no timeout.
+    ///
+    /// [`compile()`] gives up waiting after the timeout and returns [`TypsterError::TimedOut`].
+    /// This doesn't abort the compilation: Typst can't be interrupted, so it runs on a background
+    /// thread until Typst is done, which a pathological document may never be. Only its file
+    /// reads fail and nothing is exported once it has timed out. Other functions compiling a
+    /// document only check the timeout on file reads and before exporting.
+    ///
+    /// A hosted service compiling untrusted documents must isolate each compilation in a process
+    /// it can kill, e.g. `typster compile` under a process timeout, since every timed out
+    /// compilation otherwise keeps a CPU busy.
+    pub timeout: Option<Duration>,
+
+    /// Skips rendering the pages of a PNG output which are identical to the previous export, e.g.
+    /// when recompiling a document on changes. The hash of each page is kept next to the output,
+    /// in a file named after it with the `.hashes.json` suffix, e.g. `page-{n}.png.hashes.json`.
+    /// Ignored for PDF output.
+    pub png_page_cache: bool,
+
+    /// Embeds the main Typst file into the output PDF as an attachment, so that the PDF is
+    /// self-describing and reproducible. Ignored for PNG output.
+    #[cfg(feature = "pdf_edit")]
+    pub embed_source: bool,
+
+    /// Embeds all files the document read, e.g. imported Typst files and data, except packages,
+    /// into the output PDF as attachments, in addition to the main Typst file. Ignored for PNG
+    /// output.
+    #[cfg(feature = "pdf_edit")]
+    pub embed_dependencies: bool,
+
+    /// Exports all pages as one tall image, stacked vertically, instead of one image per page,
+    /// e.g. for chat apps and review tools which display a short document as a single image. The
+    /// output must have the `png` or `webp` extension, and needs no `{n}` template.
+    #[cfg(feature = "strip")]
+    pub strip: Option<Strip>,
+
+    /// Template of the names of the entries of a zip output, one per page, e.g. `page-{n}.svg`,
+    /// with the same placeholders as a PNG output. Its extension, `png` or `svg`, selects the
+    /// format of the pages. [`None`] means `{0p}.png`. Ignored unless the output has the `zip`
+    /// extension.
+    #[cfg(feature = "zip_export")]
+    pub zip_entry: Option<String>,
+}
+
+impl CompileParams {
+    /// Creates a builder of parameters, starting from the defaults.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// let params = typster::CompileParams::builder()
+    ///     .input("report.typ")
+    ///     .font_path("fonts")
+    ///     .dict("version", "1.0")
+    ///     .build();
+    /// assert_eq!(params.output, std::path::PathBuf::from("report.pdf"));
+    /// ```
+    pub fn builder() -> CompileParamsBuilder {
+        CompileParamsBuilder::default()
+    }
+
+    /// Loads parameters from a TOML or JSON file, chosen by its extension, e.g. to keep the
+    /// settings of a build script in a checked-in configuration file.
+    ///
+    /// Relative `input`, `output`, `font_paths`, `package_path`, and `package_cache_path` are
+    /// resolved against the directory of the file, so that the configuration works regardless of
+    /// the current directory.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` - Path to the configuration file, with the `toml` or `json` extension.
+    ///
+    /// # Returns
+    ///
+    /// Result containing the loaded [`CompileParams`].
+    ///
+    /// # Example
+    ///
+    /// Following is an example of how to use the `from_file` function, with `typster.toml` like:
+    ///
+    /// ```toml
+    /// input = "main.typ"
+    /// output = "out/main.pdf"
+    /// font_paths = ["fonts"]
+    /// dict = [["version", "1.0"]]
+    /// ```
+    ///
+    /// ```no_run
+    /// let path = std::path::Path::new("typster.toml");
+    /// let params = typster::CompileParams::from_file(path).unwrap();
+    /// typster::compile(&params).unwrap();
+    /// ```
+    pub fn from_file(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        let mut params: Self = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => toml::from_str(&text)?,
+            Some("json") => serde_json::from_str(&text)?,
+            _ => return Err(format!("unsupported configuration file: {}", path.display()).into()),
+        };
+
+        params.resolve_paths(path.parent().unwrap_or(Path::new("")));
+        Ok(params)
+    }
+
+    /// Resolves relative paths of the parameters against `dir`.
+    pub(crate) fn resolve_paths(&mut self, dir: &Path) {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() && !path.as_os_str().is_empty() {
+                *path = dir.join(&*path);
+            }
+        };
+        resolve(&mut self.input);
+        resolve(&mut self.output);
+        self.font_paths.iter_mut().for_each(resolve);
+        self.package_path.iter_mut().for_each(resolve);
+        self.package_cache_path.iter_mut().for_each(resolve);
+    }
+}
+
+/// Builder of [`CompileParams`], created with [`CompileParams::builder()`].
+///
+/// Each method sets the field of the same name, or adds an item to it for the collections.
+#[derive(Debug, Clone, Default)]
+pub struct CompileParamsBuilder {
+    params: CompileParams,
+}
+
+impl CompileParamsBuilder {
+    /// Sets the path to the input Typst file.
+    pub fn input(mut self, input: impl Into<PathBuf>) -> Self {
+        self.params.input = input.into();
+        self
+    }
+
+    /// Sets the path to the output file. Defaults to the input with the `pdf` extension.
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.params.output = output.into();
+        self
+    }
+
+    /// Adds a key-value pair visible through `sys.inputs`.
+    pub fn dict(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.params.dict.push((key.into(), value.into()));
+        self
+    }
+
+    /// Adds a directory to search for fonts.
+    pub fn font_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.params.font_paths.push(path.into());
+        self
+    }
+
+    /// Sets the PPI for PNG export.
+    pub fn ppi(mut self, ppi: f32) -> Self {
+        self.params.ppi = Some(ppi);
+        self
+    }
+
+    /// Sets the path to local packages.
+    pub fn package_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.params.package_path = Some(path.into());
+        self
+    }
+
+    /// Sets the path to the package cache.
+    pub fn package_cache_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.params.package_cache_path = Some(path.into());
+        self
+    }
+
+    /// Adds in-memory contents which override a file on disk.
+    pub fn shadow_file(mut self, path: impl Into<PathBuf>, text: impl Into<String>) -> Self {
+        self.params.shadow_files.insert(path.into(), text.into());
+        self
+    }
+
+    /// Restricts which files the document may read.
+    pub fn sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.params.sandbox = Some(sandbox);
+        self
+    }
+
+    /// Sets whether to deny reading files outside of the directory of the input through symlinks.
+    pub fn deny_symlink_escape(mut self, deny: bool) -> Self {
+        self.params.deny_symlink_escape = deny;
+        self
+    }
+
+    /// Sets the callback invoked on every file read during compilation.
+    pub fn on_file_access(mut self, hook: FileAccessHook) -> Self {
+        self.params.on_file_access = Some(hook);
+        self
+    }
+
+    /// Adds a PDF standard the output must conform to.
+    pub fn pdf_standard(mut self, standard: PdfStandard) -> Self {
+        self.params.pdf_standards.push(standard);
+        self
+    }
+
+    /// Sets the size in bytes from which package files are shared by all compilations.
+    pub fn shared_file_threshold(mut self, bytes: u64) -> Self {
+        self.params.shared_file_threshold = Some(bytes);
+        self
+    }
+
+    /// Sets the resource limits of the compilation.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.params.limits = limits;
+        self
+    }
+
+    /// Sets the maximum duration of the compilation.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.params.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets whether to skip rendering the pages of a PNG output identical to the previous export.
+    pub fn png_page_cache(mut self, cache: bool) -> Self {
+        self.params.png_page_cache = cache;
+        self
+    }
+
+    /// Sets whether to embed the main Typst file into the output PDF.
+    #[cfg(feature = "pdf_edit")]
+    pub fn embed_source(mut self, embed: bool) -> Self {
+        self.params.embed_source = embed;
+        self
+    }
+
+    /// Sets whether to embed all files the document read into the output PDF.
+    #[cfg(feature = "pdf_edit")]
+    pub fn embed_dependencies(mut self, embed: bool) -> Self {
+        self.params.embed_dependencies = embed;
+        self
+    }
+
+    /// Sets the options of the export of all pages as one tall image.
+    #[cfg(feature = "strip")]
+    pub fn strip(mut self, strip: Strip) -> Self {
+        self.params.strip = Some(strip);
+        self
+    }
+
+    /// Sets the template of the names of the entries of a zip output.
+    #[cfg(feature = "zip_export")]
+    pub fn zip_entry(mut self, template: impl Into<String>) -> Self {
+        self.params.zip_entry = Some(template.into());
+        self
+    }
+
+    /// Builds the parameters.
+    pub fn build(mut self) -> CompileParams {
+        if self.params.output.as_os_str().is_empty() && !self.params.input.as_os_str().is_empty() {
+            self.params.output = self.params.input.with_extension("pdf");
+        }
+        self.params
+    }
+}
+
+/// A PDF standard for [`CompileParams::pdf_standards`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PdfStandard {
+    /// PDF 1.7.
+    #[serde(rename = "1.7")]
+    V1_7,
+    /// PDF/A-2b, for long-term archiving.
+    #[serde(rename = "a-2b")]
+    A2b,
+}
+
+impl From<PdfStandard> for typst_pdf::PdfStandard {
+    fn from(standard: PdfStandard) -> Self {
+        match standard {
+            PdfStandard::V1_7 => typst_pdf::PdfStandard::V_1_7,
+            PdfStandard::A2b => typst_pdf::PdfStandard::A_2b,
+        }
+    }
+}
+
+/// File access restrictions for a compilation.
+///
+/// When enabled, the document can only read files within the directory of
+/// [`CompileParams::input`], its packages, and the paths in the [`allowlist`](Sandbox::allowlist).
+/// Symlinks are resolved before checking, so they can't be used to escape.
+///
+/// See also [`CompileParams::sandbox`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Sandbox {
+    /// Additional files or directories which can be read. Relative paths are resolved against the
+    /// directory of [`CompileParams::input`].
+    pub allowlist: Vec<PathBuf>,
+}
+
+/// Resource limits of a compilation, for [`CompileParams::limits`]. [`None`] means unlimited.
+///
+/// Typst can't interrupt a compilation, so the limits are checked whenever the document reads a
+/// file, and once more when it has been laid out, before anything is exported. A compilation
+/// which exceeds one fails with [`TypsterError::LimitExceeded`], but a document which loops
+/// without reading files runs until it's laid out. Run compilations in a separate process with OS
+/// limits, e.g. `setrlimit`, if they must be stopped no matter what.
+///
+/// # Example
+///
+/// Following is an example of how to use the `Limits`:
+///
+/// ```no_run
+/// let limits = typster::Limits {
+///     max_duration: Some(std::time::Duration::from_secs(10)),
+///     max_file_reads: Some(100),
+///     max_file_size: Some(10 * 1024 * 1024),
+///     ..Default::default()
+/// };
+/// let params = typster::CompileParams::builder().input("untrusted.typ").limits(limits).build();
+/// match typster::compile(&params) {
+///     Err(typster::TypsterError::LimitExceeded(why)) => eprintln!("rejected: {why}"),
+///     result => println!("{result:?}"),
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Limits {
+    /// Maximum number of bytes of all the files read, which bounds the memory held by the data of
+    /// the document. The memory used for layout is not counted, since Typst doesn't report it.
+    pub max_memory: Option<u64>,
+    /// Maximum wall-clock time of the compilation, excluding the font search and the export.
+    pub max_duration: Option<Duration>,
+    /// Maximum number of files read, including the main file and the files of packages.
+    pub max_file_reads: Option<usize>,
+    /// Maximum size of a file read, in bytes. Files on disk are checked before they are read.
+    pub max_file_size: Option<u64>,
+}
+
+/// A file read during compilation, passed to [`CompileParams::on_file_access`].
+#[derive(Debug, Clone)]
+pub struct FileAccess {
+    /// The package the file belongs to, e.g. `@preview/example:0.1.0`, or [`None`] for a file of
+    /// the project.
+    pub package: Option<String>,
+    /// Path of the file relative to the project or package root.
+    pub path: PathBuf,
+    /// Size of the file in bytes. Zero if it couldn't be read.
+    pub bytes: usize,
+    /// Time taken to read, and parse if it's a source file.
+    pub duration: Duration,
+    /// Whether the file was served from the cache of the current compilation, without reading it
+    /// again.
+    pub cache_hit: bool,
+}
+
+/// Callback for [`CompileParams::on_file_access`].
+///
+/// # Example
+///
+/// ```rust
+/// let hook = typster::FileAccessHook::new(|access| {
+///     println!("{} ({} bytes) in {:?}", access.path.display(), access.bytes, access.duration)
+/// });
+/// ```
+#[derive(Clone)]
+pub struct FileAccessHook(Arc<dyn Fn(&FileAccess) + Send + Sync>);
+
+impl FileAccessHook {
+    /// Creates a new hook from a callback.
+    pub fn new(f: impl Fn(&FileAccess) + Send + Sync + 'static) -> Self {
+        Self(Arc::new(f))
+    }
+
+    /// Invokes the callback.
+    pub(crate) fn call(&self, access: &FileAccess) {
+        (self.0)(access)
+    }
+}
+
+impl fmt::Debug for FileAccessHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FileAccessHook(..)")
+    }
 }
 
 /// Compiles an input file into a supported output format.
@@ -52,26 +496,29 @@ pub struct CompileParams {
 ///
 /// # Returns
 ///
-/// Result containing the [`Duration`] of the compilation.
+/// Result containing the [`Duration`] of the compilation, or a [`TypsterError`] telling why it
+/// failed, e.g. [`TypsterError::Diagnostics`] if the document has errors, or
+/// [`TypsterError::TimedOut`] if it took longer than [`CompileParams::timeout`].
 ///
 /// # Example
 ///
 /// Following is an example of how to use the `compile` function:
 ///
 /// ```rust
-/// let params = typster::CompileParams {
-///     input: std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-///         .join("examples")
-///         .join("sample.typ"),
-///     output: std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-///         .join("examples")
-///         .join("sample.pdf"),
-///     font_paths: vec!["assets".into()],
-///     dict: vec![("input".to_string(), "value".to_string())],
-///     ppi: None,
-///     package_path: None,
-///     package_cache_path: None,
-/// };
+/// let params = typster::CompileParams::builder()
+///     .input(
+///         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///             .join("examples")
+///             .join("sample.typ"),
+///     )
+///     .output(
+///         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///             .join("examples")
+///             .join("sample.pdf"),
+///     )
+///     .font_path("assets")
+///     .dict("input", "value")
+///     .build();
 /// match typster::compile(&params) {
 ///     Ok(duration) => println!("Compilation succeeded in {duration:?}"),
 ///     Err(why) => eprintln!("{why}"),
@@ -83,44 +530,192 @@ pub struct CompileParams {
 /// ```console
 /// $ typst compile examples/sample.typ examples/sample.pdf
 /// ```
-pub fn compile(params: &CompileParams) -> Result<Duration, Box<dyn Error>> {
-    let world = SystemWorld::new(
-        &params.input,
-        &params.font_paths,
-        params.dict.clone(),
-        &params.package_path,
-        &params.package_cache_path,
-    )
-    .map_err(|err| err.to_string())?;
-    let start = std::time::Instant::now();
-
-    let Warned { output, warnings } = typst::compile(&world);
-    let result = output.and_then(|document| export(&document, params));
+#[instrument(skip_all, fields(input = %params.input.display()))]
+pub fn compile(params: &CompileParams) -> Result<Duration, TypsterError> {
+    if let Some(timeout) = params.timeout {
+        return compile_with_timeout(params, timeout);
+    }
+    let world = SystemWorld::new(params)?;
+    compile_world(&world, params)
+}
+
+/// Compiles on a background thread, and stops waiting for it after `timeout`. The thread isn't
+/// stopped: it keeps running until Typst is done, if ever, but the world fails file reads and
+/// export once it has timed out.
+fn compile_with_timeout(
+    params: &CompileParams,
+    timeout: Duration,
+) -> Result<Duration, TypsterError> {
+    let (sender, receiver) = mpsc::channel();
+    let params = params.clone();
+    let handle = thread::Builder::new()
+        .name("typster-compile".to_string())
+        .spawn(move || {
+            let result = SystemWorld::new(&params)
+                .map_err(TypsterError::from)
+                .and_then(|world| compile_world(&world, &params));
+            let _ = sender.send(result);
+        })?;
+
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Err(TypsterError::TimedOut(timeout)),
+        // The thread panicked before sending, e.g. exporting PNGs, so panic as without a timeout.
+        Err(RecvTimeoutError::Disconnected) => match handle.join() {
+            Err(payload) => panic::resume_unwind(payload),
+            Ok(()) => unreachable!("the compilation thread always sends its result"),
+        },
+    }
+}
+
+/// Report of a compilation by [`compile_to_writer()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompileReport {
+    /// Time taken to compile the document and write the PDF.
+    pub duration: Duration,
+
+    /// Number of pages of the document.
+    pub pages: usize,
+
+    /// Size of the PDF held in memory before writing, in bytes, which is the largest buffer of
+    /// the export. The laid-out document is released before the PDF is written.
+    pub buffered_bytes: usize,
+}
+
+/// Compiles an input file into a PDF, and writes it to a writer, e.g. a socket or a compressor,
+/// instead of the output file, releasing the laid-out document before writing to reduce the
+/// peak memory of very large documents.
+///
+/// The PDF is written regardless of the extension of the output file of `params`, which is
+/// ignored. Sources embedded as specified by `params` are written directly to `writer` without
+/// another copy of the PDF in memory.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`] struct.
+/// - `writer` - Where to write the PDF. Wrap it in a [`BufWriter`] if it's unbuffered, e.g. a
+///   [`File`].
+///
+/// # Returns
+///
+/// Result containing the [`CompileReport`] of the compilation, or a [`TypsterError`] telling why
+/// it failed, e.g. [`TypsterError::Diagnostics`] if the document has errors, in which case
+/// nothing is written.
+///
+/// # Example
+///
+/// Following is an example of how to use the `compile_to_writer` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("book.typ").build();
+/// let file = std::fs::File::create("book.pdf").unwrap();
+/// let report = typster::compile_to_writer(&params, std::io::BufWriter::new(file)).unwrap();
+/// println!("{} pages, {} bytes buffered", report.pages, report.buffered_bytes);
+/// ```
+#[instrument(skip_all, fields(input = %params.input.display()))]
+pub fn compile_to_writer(
+    params: &CompileParams,
+    mut writer: impl Write,
+) -> Result<CompileReport, TypsterError> {
+    let world = SystemWorld::new(params)?;
+    let start = Instant::now();
+
+    // The document is dropped once exported, so that it's not held while writing.
+    let Warned { output, warnings } = typst_compile(&world);
+    world.check_limits()?;
+    let (pdf, pages) = output
+        .and_then(|document| Ok((pdf_bytes(&document, params)?, document.pages.len())))
+        .map_err(|errors| Diagnostics::new(warnings, errors))?;
+
+    let buffered_bytes = pdf.len();
+    write_pdf(pdf, &world, params, &mut writer).map_err(|err| {
+        match err.downcast::<io::Error>() {
+            Ok(err) => TypsterError::Io(*err),
+            Err(err) => TypsterError::Pdf(err.to_string()),
+        }
+    })?;
+    writer.flush()?;
+
+    Ok(CompileReport { duration: start.elapsed(), pages, buffered_bytes })
+}
+
+/// Compiles the main file of the world and exports it as specified by `params`.
+pub(crate) fn compile_world(
+    world: &SystemWorld,
+    params: &CompileParams,
+) -> Result<Duration, TypsterError> {
+    compile_world_document(world, params, |_| {}).map(|(duration, _)| duration)
+}
+
+/// Stage of a compilation of the main file of a world, reported by [`compile_world_document()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CompileStage {
+    /// The main file is parsed.
+    Parsing,
+    /// The document is evaluated and laid out, parsing the files it imports along the way.
+    Layout,
+    /// The document is exported into the output file.
+    Export,
+}
+
+/// Compiles the main file of the world like [`compile_world()`], calling `on_stage` when each
+/// stage of the compilation starts, and also returns the document.
+pub(crate) fn compile_world_document(
+    world: &SystemWorld,
+    params: &CompileParams,
+    mut on_stage: impl FnMut(CompileStage),
+) -> Result<(Duration, Document), TypsterError> {
+    let start = Instant::now();
+
+    on_stage(CompileStage::Parsing);
+    // The parsed source is cached by the world, so it's not parsed again when compiled. Errors
+    // are reported by the compilation.
+    _ = world.source(world.main());
+    on_stage(CompileStage::Layout);
+    let Warned { output, warnings } = typst_compile(world);
+    // Nothing is exported once a limit is exceeded, even if the document compiled.
+    world.check_limits()?;
+    let result = output.and_then(|document| {
+        on_stage(CompileStage::Export);
+        export(&document, world, params)?;
+        Ok(document)
+    });
 
     match result {
-        Ok(()) => Ok(start.elapsed()),
-        Err(errors) => Err(warnings
-            .into_iter()
-            .chain(errors)
-            .map(|diagnostic| {
-                format!(
-                    "{:?}: {}\n{}",
-                    diagnostic.severity,
-                    diagnostic.message.clone(),
-                    diagnostic
-                        .hints
-                        .iter()
-                        .map(|e| format!("hint: {e}"))
-                        .collect::<Vec<String>>()
-                        .join("\n")
-                )
-            })
-            .collect::<Vec<String>>()
-            .join("\n")
-            .into()),
+        Ok(document) => Ok((start.elapsed(), document)),
+        Err(errors) => Err(Diagnostics::new(warnings, errors).into()),
     }
 }
 
+/// Compiles the main file of the world into a document, e.g. to query its elements.
+pub(crate) fn compile_document(world: &SystemWorld) -> Result<Document, TypsterError> {
+    let Warned { output, warnings } = typst_compile(world);
+    output.map_err(|errors| Diagnostics::new(warnings, errors).into())
+}
+
+/// Compiles the main file of the world into a PDF in memory.
+#[cfg(feature = "pdf_stamp")]
+pub(crate) fn compile_pdf(world: &SystemWorld) -> Result<Vec<u8>, TypsterError> {
+    let Warned { output, warnings } = typst_compile(world);
+    let options = PdfOptions {
+        ident: Smart::Auto,
+        timestamp: None,
+        page_ranges: None,
+        standards: PdfStandards::default(),
+    };
+    output
+        .and_then(|document| typst_pdf::pdf(&document, &options))
+        .map_err(|errors| Diagnostics::new(warnings, errors).into())
+}
+
+/// Compiles the main file of the world into a document within a span.
+pub(crate) fn typst_compile(world: &SystemWorld) -> Warned<SourceResult<Document>> {
+    let warned = info_span!("typst_compile").in_scope(|| typst::compile(world));
+    discard_timings();
+    warned
+}
+
 /// Export into the target format.
 // fn export(document: &Document, params: &CompileParams) -> Result<(), Box<dyn std::error::Error>>
 // {     match params.output.extension() {
@@ -129,10 +724,21 @@ pub fn compile(params: &CompileParams) -> Result<Duration, Box<dyn Error>> {
 //     }
 // }
 
-fn export(document: &Document, params: &CompileParams) -> SourceResult<()> {
+#[instrument(skip_all, fields(output = %params.output.display()))]
+pub(crate) fn export(
+    document: &Document,
+    world: &SystemWorld,
+    params: &CompileParams,
+) -> SourceResult<()> {
+    #[cfg(feature = "strip")]
+    if let Some(strip) = &params.strip {
+        return export_strip(document, params, strip);
+    }
     match params.output.extension() {
         Some(ext) if ext.eq_ignore_ascii_case("png") => export_image(document, params),
-        _ => export_pdf(document, params),
+        #[cfg(feature = "zip_export")]
+        Some(ext) if ext.eq_ignore_ascii_case("zip") => export_zip(document, params),
+        _ => export_pdf(document, world, params),
     }
 }
 
@@ -145,7 +751,15 @@ fn export_image(document: &Document, params: &CompileParams) -> SourceResult<()>
         panic!("{}", "cannot export multiple images without `{{n}}` in output path");
     }
 
-    document.pages.iter().enumerate().for_each(|(i, page)| {
+    let ppi = params.ppi.unwrap_or(144.0);
+    let cache = params.png_page_cache.then(|| page_cache_path(&params.output));
+    let previous: HashMap<String, String> = cache
+        .as_ref()
+        .and_then(|cache| serde_json::from_slice(&fs::read(cache).ok()?).ok())
+        .unwrap_or_default();
+    let mut hashes = HashMap::new();
+
+    for (i, page) in document.pages.iter().enumerate() {
         let storage;
         let path = if can_handle_multiple {
             storage = output_template::format(output, i + 1, document.pages.len());
@@ -153,29 +767,162 @@ fn export_image(document: &Document, params: &CompileParams) -> SourceResult<()>
         } else {
             params.output.as_path()
         };
-        let pixmap = typst_render::render(page, params.ppi.unwrap_or(144.0) / 72.0);
-        let buf = pixmap.encode_png().unwrap();
-        fs::write(path, buf).unwrap();
-    });
+
+        // The hash covers everything the pixels depend on, i.e. the frame and the fill of the
+        // page, the resolution, and the renderer.
+        let key = path.display().to_string();
+        let hash = format!(
+            "{:032x}",
+            typst_utils::hash128(&(
+                &page.frame,
+                &page.fill,
+                ppi.to_bits(),
+                env!("CARGO_PKG_VERSION")
+            ))
+        );
+        if previous.get(&key) != Some(&hash) || !path.exists() {
+            let pixmap = typst_render::render(page, ppi / 72.0);
+            let buf = pixmap.encode_png().unwrap();
+            fs::write(path, buf).unwrap();
+        }
+        hashes.insert(key, hash);
+    }
+
+    if let Some(cache) = cache {
+        serde_json::to_vec(&hashes)
+            .map_err(io::Error::other)
+            .and_then(|json| fs::write(cache, json))
+            .map_err(|err| eco_format!("failed to write page hashes: {err}"))
+            .at(Span::detached())?;
+    }
 
     Ok(())
 }
 
-/// Export to a PDF.
-fn export_pdf(document: &Document, params: &CompileParams) -> SourceResult<()> {
+/// Returns the path of the file keeping the page hashes of a PNG output, next to it.
+fn page_cache_path(output: &Path) -> PathBuf {
+    let mut name = output.file_name().unwrap_or_default().to_os_string();
+    name.push(".hashes.json");
+    output.with_file_name(name)
+}
+
+/// Export to a PDF, writing it to the output file without buffering it again.
+fn export_pdf(
+    document: &Document,
+    world: &SystemWorld,
+    params: &CompileParams,
+) -> SourceResult<()> {
+    let pdf = pdf_bytes(document, params)?;
+    let write = || -> Result<(), Box<dyn Error>> {
+        let mut file = BufWriter::new(File::create(&params.output)?);
+        write_pdf(pdf, world, params, &mut file)?;
+        Ok(file.flush()?)
+    };
+    write()
+        .map_err(|err| eco_format!("failed to write PDF: {err}"))
+        .at(Span::detached())
+}
+
+/// Export to a PDF in memory, with the source files embedded as specified by `params`.
+#[cfg(feature = "pipeline")]
+#[cfg_attr(not(feature = "pdf_edit"), allow(unused_variables))]
+pub(crate) fn export_pdf_bytes(
+    document: &Document,
+    world: &SystemWorld,
+    params: &CompileParams,
+) -> SourceResult<Vec<u8>> {
+    let buf = pdf_bytes(document, params)?;
+    #[cfg(feature = "pdf_edit")]
+    let buf = if params.embed_source || params.embed_dependencies {
+        let mut embedded = Vec::new();
+        embed_sources(buf, world, params, &mut embedded)
+            .map_err(|err| eco_format!("failed to embed source files: {err}"))
+            .at(Span::detached())?;
+        embedded
+    } else {
+        buf
+    };
+    Ok(buf)
+}
+
+/// Exports a document into a PDF in memory with typst-pdf, which can't write it incrementally.
+fn pdf_bytes(document: &Document, params: &CompileParams) -> SourceResult<Vec<u8>> {
     let options = PdfOptions {
         ident: Smart::Auto,
         timestamp: None,
         page_ranges: None,
-        standards: PdfStandards::default(),
+        standards: pdf_standards(&params.pdf_standards)?,
     };
-    fs::write(&params.output, typst_pdf::pdf(document, &options)?)
-        .map_err(|err| eco_format!("failed to write PDF: {err}"))
-        .at(Span::detached())?;
+    typst_pdf::pdf(document, &options)
+}
+
+/// Writes a PDF exported by typst-pdf to `writer`, with the source files embedded as specified by
+/// `params`. The PDF is taken by value, so that it's released as soon as it's no longer needed.
+#[cfg_attr(not(feature = "pdf_edit"), allow(unused_variables))]
+fn write_pdf(
+    pdf: Vec<u8>,
+    world: &SystemWorld,
+    params: &CompileParams,
+    writer: &mut impl Write,
+) -> Result<(), Box<dyn Error>> {
+    #[cfg(feature = "pdf_edit")]
+    if params.embed_source || params.embed_dependencies {
+        return embed_sources(pdf, world, params, writer);
+    }
+    writer.write_all(&pdf)?;
+    Ok(())
+}
+
+/// Converts the PDF standards of the parameters into those of `typst_pdf`, checking that they can
+/// be combined.
+fn pdf_standards(standards: &[PdfStandard]) -> SourceResult<PdfStandards> {
+    let standards = standards.iter().map(|&standard| standard.into()).collect::<Vec<_>>();
+    PdfStandards::new(&standards).at(Span::detached())
+}
+
+/// Embeds the main Typst file, and optionally all its dependencies, into a PDF.
+#[cfg(feature = "pdf_edit")]
+#[instrument(skip_all)]
+fn embed_sources(
+    pdf: Vec<u8>,
+    world: &SystemWorld,
+    params: &CompileParams,
+    writer: &mut impl Write,
+) -> Result<(), Box<dyn Error>> {
+    use crate::{attach_files::attach, Attachment, AttachmentRelationship};
+
+    let main = world.main();
+    let attachments = world
+        .dependencies()
+        .into_iter()
+        .filter(|(id, _)| params.embed_dependencies || *id == main)
+        .map(|(id, bytes)| {
+            let is_typst = id
+                .vpath()
+                .as_rootless_path()
+                .extension()
+                .is_some_and(|ext| ext == "typ");
+            Attachment {
+                name: id.vpath().as_rootless_path().to_string_lossy().replace('\\', "/"),
+                bytes,
+                mime: if is_typst { "text/vnd.typst" } else { "" }.to_string(),
+                relationship: if is_typst {
+                    AttachmentRelationship::Source
+                } else {
+                    AttachmentRelationship::Data
+                },
+            }
+        })
+        .collect();
+
+    let mut doc = lopdf::Document::load_mem(&pdf)?;
+    drop(pdf);
+    attach(&mut doc, attachments)?;
+    doc.save_to(writer)?;
     Ok(())
 }
 
-mod output_template {
+pub(crate) mod output_template {
     const INDEXABLE: [&str; 3] = ["{p}", "{0p}", "{n}"];
 
     pub fn has_indexable_template(output: &str) -> bool {
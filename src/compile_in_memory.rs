@@ -0,0 +1,188 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use typst::{
+    diag::{FileError, FileResult, PackageError, Warned},
+    foundations::{Bytes, Datetime, Dict, IntoValue, Smart},
+    syntax::{FileId, Source, VirtualPath},
+    text::{Font, FontBook},
+    utils::LazyHash,
+    Library, World,
+};
+use typst_pdf::{PdfOptions, PdfStandards};
+
+use crate::{error::Diagnostics, TypsterError};
+
+/// Parameters for compilation of a Typst project held in memory.
+///
+/// See also [`compile_in_memory()`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InMemoryParams {
+    /// Files of the project, e.g. Typst sources, images, and data, keyed by their path relative to
+    /// the project root, e.g. `chapters/intro.typ`.
+    pub files: HashMap<PathBuf, Vec<u8>>,
+
+    /// Path to the main Typst file, which must be one of `files`.
+    pub main: PathBuf,
+
+    /// String key-value pairs visible through `sys.inputs` in the document.
+    pub dict: Vec<(String, String)>,
+
+    /// Contents of additional font files, e.g. fetched by the browser. The fonts of typst-cli are
+    /// always available.
+    pub fonts: Vec<Vec<u8>>,
+}
+
+/// Compiles a Typst project held in memory into a PDF, without touching the filesystem or the
+/// network, e.g. for in-browser preview on `wasm32-unknown-unknown`.
+///
+/// Packages can't be downloaded, so importing one fails, and `datetime.today()` is not available
+/// since there may be no clock.
+///
+/// # Arguments
+///
+/// - `params` - [`InMemoryParams`] of the project.
+///
+/// # Returns
+///
+/// Result containing the contents of the PDF file, or [`TypsterError::Diagnostics`] if the
+/// document has errors.
+///
+/// # Example
+///
+/// Following is an example of how to use the `compile_in_memory` function:
+///
+/// ```rust
+/// let params = typster::InMemoryParams {
+///     files: [("main.typ".into(), b"= Hello\nfrom #sys.inputs.name".to_vec())].into(),
+///     main: "main.typ".into(),
+///     dict: vec![("name".to_string(), "typster".to_string())],
+///     ..Default::default()
+/// };
+/// let pdf = typster::compile_in_memory(&params).unwrap();
+/// assert!(pdf.starts_with(b"%PDF"));
+/// ```
+pub fn compile_in_memory(params: &InMemoryParams) -> Result<Vec<u8>, TypsterError> {
+    let world = MemoryWorld::new(params)?;
+    let Warned { output, warnings } =
+        tracing::info_span!("typst_compile").in_scope(|| typst::compile(&world));
+    let options = PdfOptions {
+        ident: Smart::Auto,
+        timestamp: None,
+        page_ranges: None,
+        standards: PdfStandards::default(),
+    };
+    output
+        .and_then(|document| typst_pdf::pdf(&document, &options))
+        .map_err(|errors| Diagnostics::new(warnings, errors).into())
+}
+
+/// A world whose files and fonts are all in memory.
+struct MemoryWorld<'a> {
+    /// Files of the project, keyed by their path relative to the project root.
+    files: &'a HashMap<PathBuf, Vec<u8>>,
+    /// The main source file.
+    main: FileId,
+    /// Typst's standard library.
+    library: LazyHash<Library>,
+    /// Metadata about the fonts.
+    book: LazyHash<FontBook>,
+    /// The fonts, in the order of the book.
+    fonts: Vec<Font>,
+    /// Sources parsed in the current compilation.
+    sources: Mutex<HashMap<FileId, Source>>,
+}
+
+impl<'a> MemoryWorld<'a> {
+    fn new(params: &'a InMemoryParams) -> Result<Self, TypsterError> {
+        if !params.files.contains_key(&params.main) {
+            return Err(TypsterError::World(format!(
+                "input file not found (searched at {})",
+                params.main.display()
+            )));
+        }
+
+        let mut fonts = typst_assets::fonts()
+            .map(Bytes::from_static)
+            .flat_map(Font::iter)
+            .collect::<Vec<_>>();
+        for (i, data) in params.fonts.iter().enumerate() {
+            let len = fonts.len();
+            fonts.extend(Font::iter(Bytes::from(data.as_slice())));
+            if fonts.len() == len {
+                return Err(TypsterError::Font(format!("failed to load font #{i}")));
+            }
+        }
+
+        let inputs: Dict = params
+            .dict
+            .iter()
+            .map(|(k, v)| (k.as_str().into(), v.as_str().into_value()))
+            .collect();
+
+        Ok(Self {
+            files: &params.files,
+            main: FileId::new(None, VirtualPath::new(&params.main)),
+            library: LazyHash::new(Library::builder().with_inputs(inputs).build()),
+            book: LazyHash::new(FontBook::from_fonts(&fonts)),
+            fonts,
+            sources: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Returns the contents of a file of the project.
+    fn read(&self, id: FileId) -> FileResult<&'a [u8]> {
+        if let Some(spec) = id.package() {
+            return Err(FileError::Package(PackageError::NotFound(spec.clone())));
+        }
+        let path = id.vpath().as_rootless_path();
+        self.files
+            .get(path)
+            .map(Vec::as_slice)
+            .ok_or_else(|| FileError::NotFound(Path::new("/").join(path)))
+    }
+}
+
+impl World for MemoryWorld<'_> {
+    fn library(&self) -> &LazyHash<Library> {
+        &self.library
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        &self.book
+    }
+
+    fn main(&self) -> FileId {
+        self.main
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        let mut sources = self.sources.lock();
+        if let Some(source) = sources.get(&id) {
+            return Ok(source.clone());
+        }
+        let data = self.read(id)?;
+        // Remove UTF-8 BOM.
+        let text = std::str::from_utf8(data.strip_prefix(b"\xef\xbb\xbf").unwrap_or(data))?;
+        let source = Source::new(id, text.to_string());
+        sources.insert(id, source.clone());
+        Ok(source)
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.read(id).map(Bytes::from)
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.fonts.get(index).cloned()
+    }
+
+    fn today(&self, _: Option<i64>) -> Option<Datetime> {
+        None
+    }
+}
@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use typst::model::Document;
+
+use crate::{
+    compile::{compile_world_document, CompileStage},
+    world::SystemWorld,
+    CompileParams, TypsterError,
+};
+
+/// Maximum number of compilations a memoized result survives without being used.
+pub(crate) const MAX_CACHE_AGE: usize = 10;
+
+/// Compiles a Typst document repeatedly, keeping the fonts, the files read, and the memoized
+/// results of Typst between compilations, so that only the changed files are read again, e.g. to
+/// recompile a document on changes as [`watch()`](crate::watch()) does.
+///
+/// Fonts are searched only once, when the session is created. Share it between threads with
+/// `Arc<Mutex<CompilerSession>>`.
+///
+/// # Example
+///
+/// Following is an example of how to use the `CompilerSession`:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("main.typ").font_path("fonts").build();
+/// let mut session = typster::CompilerSession::new(&params).unwrap();
+/// let first = session.compile().unwrap();
+/// // Edit main.typ, then recompile.
+/// let second = session.compile().unwrap();
+/// println!("compiled in {first:?}, then in {second:?}");
+/// ```
+pub struct CompilerSession {
+    /// Parameters of the compilations.
+    params: CompileParams,
+    /// World kept alive across compilations.
+    world: SystemWorld,
+}
+
+impl CompilerSession {
+    /// Creates a session, searching the fonts of `params`, without compiling the document.
+    ///
+    /// # Arguments
+    ///
+    /// - `params` - [`CompileParams`] of the document.
+    ///
+    /// # Returns
+    ///
+    /// Result containing the session, or an error if the document can't be set up for
+    /// compilation, e.g. the input file doesn't exist.
+    pub fn new(params: &CompileParams) -> Result<Self, TypsterError> {
+        Ok(Self {
+            params: params.clone(),
+            world: SystemWorld::new(params)?,
+        })
+    }
+
+    /// Returns the parameters of the compilations.
+    pub fn params(&self) -> &CompileParams {
+        &self.params
+    }
+
+    /// Compiles the document and writes the output file, the same way as
+    /// [`compile()`](crate::compile()), reading again only the files changed since the previous
+    /// compilation.
+    ///
+    /// # Returns
+    ///
+    /// Result containing the duration of the compilation, or [`TypsterError::Diagnostics`] if the
+    /// document has errors.
+    pub fn compile(&mut self) -> Result<Duration, TypsterError> {
+        self.compile_document(|_| {}).map(|(duration, _)| duration)
+    }
+
+    /// Compiles the document like [`Self::compile()`], calling `on_stage` when each stage of the
+    /// compilation starts, and also returns the document.
+    pub(crate) fn compile_document(
+        &mut self,
+        on_stage: impl FnMut(CompileStage),
+    ) -> Result<(Duration, Document), TypsterError> {
+        self.world.reset();
+        let result = compile_world_document(&self.world, &self.params, on_stage);
+        comemo::evict(MAX_CACHE_AGE);
+        result
+    }
+
+    /// Returns the world of the session, e.g. to locate the diagnostics of a compilation.
+    #[cfg(feature = "watch")]
+    pub(crate) fn world(&self) -> &SystemWorld {
+        &self.world
+    }
+}
@@ -0,0 +1,1064 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    path::Path,
+};
+
+use fontdb::Database;
+use lopdf::{
+    content::Content, decode_text_string, xref::XrefType, Dictionary, Document, Object, ObjectId,
+    Stream,
+};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+use ttf_parser::{Face, Permissions, PlatformId};
+use xmp_toolkit::{
+    xmp_ns::{DC, PDF, XMP},
+    XmpMeta, XmpValue,
+};
+
+use crate::{
+    attach_files::collect_names,
+    document_id::DocumentId,
+    preflight::is_embedded,
+    update_metadata::to_xmp_date,
+    xmp::{read_xmp, write_xmp},
+};
+
+/// Namespace of the PDF/A identification schema.
+const PDFA_ID: &str = "http://www.aiim.org/pdfa/ns/id/";
+
+/// Annotation flags which must not be set in PDF/A: Invisible, Hidden, NoView, and ToggleNoView.
+const HIDING_FLAGS: i64 = 1 | 2 | 32 | 256;
+
+/// Print annotation flag, which must be set in PDF/A.
+const PRINT_FLAG: i64 = 4;
+
+/// Metric-compatible substitutes of the standard 14 fonts, by PostScript name. The symbolic fonts,
+/// i.e. Symbol and ZapfDingbats, have none.
+const STANDARD_SUBSTITUTES: [(&str, [&str; 2]); 12] = [
+    ("Courier", ["LiberationMono", "CourierNewPSMT"]),
+    ("Courier-Bold", ["LiberationMono-Bold", "CourierNewPS-BoldMT"]),
+    ("Courier-BoldOblique", ["LiberationMono-BoldItalic", "CourierNewPS-BoldItalicMT"]),
+    ("Courier-Oblique", ["LiberationMono-Italic", "CourierNewPS-ItalicMT"]),
+    ("Helvetica", ["LiberationSans", "ArialMT"]),
+    ("Helvetica-Bold", ["LiberationSans-Bold", "Arial-BoldMT"]),
+    ("Helvetica-BoldOblique", ["LiberationSans-BoldItalic", "Arial-BoldItalicMT"]),
+    ("Helvetica-Oblique", ["LiberationSans-Italic", "Arial-ItalicMT"]),
+    ("Times-Bold", ["LiberationSerif-Bold", "TimesNewRomanPS-BoldMT"]),
+    ("Times-BoldItalic", ["LiberationSerif-BoldItalic", "TimesNewRomanPS-BoldItalicMT"]),
+    ("Times-Italic", ["LiberationSerif-Italic", "TimesNewRomanPS-ItalicMT"]),
+    ("Times-Roman", ["LiberationSerif", "TimesNewRomanPSMT"]),
+];
+
+/// Characters of the codes from 0x80 to 0x9F in WinAnsiEncoding, where it differs from Latin-1.
+/// `\0` marks undefined codes.
+const WIN_ANSI_HIGH: [char; 32] = [
+    '\u{20AC}', '\0', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\0', '\u{017D}', '\0', '\0',
+    '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}', '\u{02DC}',
+    '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\0', '\u{017E}', '\u{0178}',
+];
+
+/// Description of the sRGB ICC profile of the added output intent.
+const SRGB: &str = "sRGB IEC61966-2.1";
+
+/// PDF/A conformance level to convert a PDF file to with [`convert_to_pdfa()`].
+///
+/// Only the basic conformance levels, which preserve the visual appearance, are supported, since
+/// the accessible ones require a tagged document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PdfaLevel {
+    /// PDF/A-1b (ISO 19005-1), based on PDF 1.4. Transparency, optional content, embedded files,
+    /// and OpenType fonts are not allowed.
+    A1b,
+    /// PDF/A-2b (ISO 19005-2), based on PDF 1.7.
+    #[default]
+    A2b,
+    /// PDF/A-3b (ISO 19005-3), which allows embedded files of any type in addition to PDF/A-2b.
+    A3b,
+}
+
+impl PdfaLevel {
+    /// Part of the PDF/A standard, i.e. `pdfaid:part`.
+    fn part(self) -> &'static str {
+        match self {
+            PdfaLevel::A1b => "1",
+            PdfaLevel::A2b => "2",
+            PdfaLevel::A3b => "3",
+        }
+    }
+
+    /// PDF version the part is based on.
+    fn version(self) -> &'static str {
+        match self {
+            PdfaLevel::A1b => "1.4",
+            _ => "1.7",
+        }
+    }
+}
+
+/// Result of converting a PDF file with [`convert_to_pdfa()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PdfaReport {
+    /// Descriptions of the problems fixed, e.g. `embedded font Helvetica as LiberationSans`.
+    pub fixed: Vec<String>,
+
+    /// Descriptions of the problems which could not be fixed, e.g. `font Symbol is not embedded:
+    /// no matching font is installed`. The output file does not conform to PDF/A unless this is
+    /// empty, and even then, it should be checked with a validator such as
+    /// [veraPDF](https://verapdf.org/).
+    pub unresolved: Vec<String>,
+}
+
+/// Converts a PDF file toward PDF/A on a best-effort basis, e.g. to archive legacy PDF files next
+/// to documents compiled with a PDF/A standard.
+///
+/// The conversion
+///
+/// - embeds fonts which are not embedded, if the same font, or a metric-compatible substitute of
+///   the standard 14 fonts such as Liberation Sans for Helvetica, is installed on the system. The
+///   whole font is embedded, i.e. it is not subsetted.
+/// - adds an sRGB output intent, unless the file already has a PDF/A one.
+/// - declares the conformance level in the XMP metadata, and synchronizes it with the document
+///   information dictionary.
+/// - removes JavaScript and other actions which are not allowed, and additional actions.
+/// - makes annotations printable and visible, and removes their alternate appearances.
+/// - recompresses LZW-compressed streams with Flate.
+/// - adds a document ID if missing.
+///
+/// Everything else which prevents conformance, e.g. transparency in PDF/A-1 or device colors which
+/// do not match the output intent, is reported but left as is.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file, which must not be encrypted.
+/// - `output` - Path to the output PDF file, which may be the same as the input file.
+/// - `level` - [`PdfaLevel`] to convert to.
+///
+/// # Returns
+///
+/// Result containing a [`PdfaReport`] of what was fixed and what could not be.
+///
+/// # Example
+///
+/// Following is an example of how to use the `convert_to_pdfa` function:
+///
+/// ```no_run
+/// let report = typster::convert_to_pdfa(
+///     std::path::Path::new("legacy.pdf"),
+///     std::path::Path::new("legacy-pdfa.pdf"),
+///     typster::PdfaLevel::A2b,
+/// )
+/// .unwrap();
+/// for problem in &report.unresolved {
+///     eprintln!("{problem}");
+/// }
+/// ```
+#[instrument(skip_all, fields(input = %input.display(), output = %output.display(), ?level))]
+pub fn convert_to_pdfa(
+    input: &Path,
+    output: &Path,
+    level: PdfaLevel,
+) -> Result<PdfaReport, Box<dyn Error>> {
+    let mut doc = Document::load(input)?;
+    if doc.is_encrypted() {
+        return Err(
+            "encrypted PDF files cannot be converted to PDF/A; remove the permission first".into(),
+        );
+    }
+
+    let mut report = PdfaReport::default();
+    remove_actions(&mut doc, level, &mut report);
+    fix_annotations(&mut doc, level, &mut report)?;
+    fix_form(&mut doc, &mut report);
+    recompress_lzw(&mut doc, &mut report);
+    embed_fonts(&mut doc, level, &mut report)?;
+    fix_embedded_files(&mut doc, level, &mut report)?;
+    let components = add_output_intent(&mut doc, &mut report)?;
+    check_colors(&doc, components, &mut report);
+    if level == PdfaLevel::A1b {
+        check_pdf_1_4(&doc, &mut report)?;
+    }
+    set_metadata(&mut doc, level)?;
+    if doc.trailer.get(b"ID").is_err() {
+        DocumentId::Regenerate.apply(&mut doc);
+        report.fixed.push("added a document ID".to_string());
+    }
+
+    // lopdf writes the version as the first line as is, so the comment of bytes above 127 which
+    // PDF/A requires as the second line is appended to it; the characters are two bytes each in
+    // UTF-8.
+    doc.version = format!("{}\n%âãÏÓ", level.version());
+    doc.reference_table.cross_reference_type = XrefType::CrossReferenceTable;
+    doc.prune_objects();
+    doc.save(output)?;
+
+    Ok(report)
+}
+
+/// Calls `f` for each dictionary of an object, including nested ones and those of streams.
+fn visit(object: &Object, f: &mut impl FnMut(&Dictionary)) {
+    match object {
+        Object::Dictionary(dict) | Object::Stream(Stream { dict, .. }) => {
+            f(dict);
+            dict.iter().for_each(|(_, value)| visit(value, f));
+        }
+        Object::Array(array) => array.iter().for_each(|value| visit(value, f)),
+        _ => {}
+    }
+}
+
+/// Mutable version of [`visit()`].
+fn visit_mut(object: &mut Object, f: &mut impl FnMut(&mut Dictionary)) {
+    match object {
+        Object::Dictionary(dict) | Object::Stream(Stream { dict, .. }) => {
+            f(dict);
+            dict.iter_mut().for_each(|(_, value)| visit_mut(value, f));
+        }
+        Object::Array(array) => array.iter_mut().for_each(|value| visit_mut(value, f)),
+        _ => {}
+    }
+}
+
+/// Returns the type of an action if it is not allowed at the level.
+fn forbidden_action(action: &Dictionary, level: PdfaLevel) -> Option<String> {
+    let kind = action.get(b"S").and_then(Object::as_name).ok()?;
+    let allowed = match kind {
+        b"GoTo" | b"GoToR" | b"Thread" | b"URI" | b"SubmitForm" => true,
+        b"GoToE" => level != PdfaLevel::A1b,
+        b"Hide" => level == PdfaLevel::A1b,
+        b"Named" => matches!(
+            action.get(b"N").and_then(Object::as_name),
+            Ok(b"NextPage" | b"PrevPage" | b"FirstPage" | b"LastPage")
+        ),
+        _ => false,
+    };
+    (!allowed).then(|| String::from_utf8_lossy(kind).to_string())
+}
+
+/// Removes actions which are not allowed, additional actions, and document-level JavaScript.
+fn remove_actions(doc: &mut Document, level: PdfaLevel, report: &mut PdfaReport) {
+    // Actions are mostly indirect objects, so resolve them before changing anything.
+    let mut forbidden = BTreeMap::new();
+    for object in doc.objects.values() {
+        visit(object, &mut |dict| {
+            for key in [b"A".as_slice(), b"OpenAction"] {
+                if let Ok(Object::Reference(id)) = dict.get(key) {
+                    let action = doc.get_dictionary(*id).ok();
+                    if let Some(kind) = action.and_then(|action| forbidden_action(action, level)) {
+                        forbidden.insert(*id, kind);
+                    }
+                }
+            }
+        });
+    }
+
+    let mut removed = BTreeSet::new();
+    let mut additional = false;
+    for object in doc.objects.values_mut() {
+        visit_mut(object, &mut |dict| {
+            for key in [b"A".as_slice(), b"OpenAction"] {
+                let kind = match dict.get(key) {
+                    Ok(Object::Reference(id)) => forbidden.get(id).cloned(),
+                    Ok(Object::Dictionary(action)) => forbidden_action(action, level),
+                    _ => None,
+                };
+                if let Some(kind) = kind {
+                    dict.remove(key);
+                    removed.insert(kind);
+                }
+            }
+            additional |= dict.remove(b"AA").is_some();
+        });
+    }
+    if let Some(names) = catalog_entry_mut(doc, b"Names") {
+        if names.remove(b"JavaScript").is_some() {
+            removed.insert("JavaScript".to_string());
+        }
+    }
+
+    for kind in removed {
+        report.fixed.push(format!("removed {kind} actions"));
+    }
+    if additional {
+        report.fixed.push("removed additional actions".to_string());
+    }
+}
+
+/// Returns a dictionary of the document catalog, which may be an indirect object.
+fn catalog_entry_mut<'a>(doc: &'a mut Document, key: &[u8]) -> Option<&'a mut Dictionary> {
+    match doc.catalog().ok()?.get(key).and_then(Object::as_reference) {
+        Ok(id) => doc.get_dictionary_mut(id).ok(),
+        Err(_) => doc.catalog_mut().ok()?.get_mut(key).ok()?.as_dict_mut().ok(),
+    }
+}
+
+/// Removes annotations which are not allowed, makes the others printable and visible, and reports
+/// those without an appearance.
+fn fix_annotations(
+    doc: &mut Document,
+    level: PdfaLevel,
+    report: &mut PdfaReport,
+) -> Result<(), Box<dyn Error>> {
+    let forbidden: &[&[u8]] = match level {
+        PdfaLevel::A1b => &[b"FileAttachment", b"Sound", b"Movie", b"Screen", b"3D", b"RichMedia"],
+        _ => &[b"Sound", b"Movie", b"Screen", b"3D", b"RichMedia"],
+    };
+    let mut flagged = false;
+    let mut alternates = false;
+
+    for (number, id) in doc.get_pages() {
+        let Ok(annots) = doc.get_dictionary(id)?.get_deref(b"Annots", doc) else {
+            continue;
+        };
+        let mut annots = annots.as_array()?.clone();
+        let mut changed = false;
+
+        annots.retain_mut(|annot| {
+            let (annot_id, dict) = match annot {
+                Object::Reference(annot_id) => match doc.get_dictionary_mut(*annot_id) {
+                    Ok(dict) => (Some(*annot_id), dict),
+                    Err(_) => return true,
+                },
+                Object::Dictionary(dict) => (None, dict),
+                _ => return true,
+            };
+            let subtype = dict.get(b"Subtype").and_then(Object::as_name).unwrap_or_default();
+            let subtype = String::from_utf8_lossy(subtype).to_string();
+            if forbidden.contains(&subtype.as_bytes()) {
+                report
+                    .fixed
+                    .push(format!("removed {subtype} annotation on page {number}"));
+                changed = true;
+                return false;
+            }
+
+            let flags = dict.get(b"F").and_then(Object::as_i64).unwrap_or(0);
+            if flags & HIDING_FLAGS != 0 || flags & PRINT_FLAG == 0 {
+                dict.set("F", (flags & !HIDING_FLAGS) | PRINT_FLAG);
+                flagged = true;
+            }
+            let has_appearance = dict.has(b"AP");
+            if let Ok(appearance) = dict.get_mut(b"AP").and_then(Object::as_dict_mut) {
+                alternates |= appearance.remove(b"D").is_some();
+                alternates |= appearance.remove(b"R").is_some();
+            }
+            // PDF/A-1 does not require appearances.
+            if !has_appearance
+                && level != PdfaLevel::A1b
+                && !["Popup", "Link"].contains(&subtype.as_str())
+                && !is_empty_rect(dict)
+            {
+                report.unresolved.push(format!(
+                    "{subtype} annotation on page {number} has no appearance stream"
+                ));
+            }
+            changed |= annot_id.is_none();
+            true
+        });
+
+        if changed {
+            doc.get_dictionary_mut(id)?.set("Annots", annots);
+        }
+    }
+
+    if flagged {
+        report
+            .fixed
+            .push("made annotations printable and visible".to_string());
+    }
+    if alternates {
+        report
+            .fixed
+            .push("removed alternate appearances of annotations".to_string());
+    }
+
+    Ok(())
+}
+
+/// Returns whether the rectangle of an annotation has no area, which exempts it from having an
+/// appearance.
+fn is_empty_rect(annot: &Dictionary) -> bool {
+    let rect = annot
+        .get(b"Rect")
+        .and_then(Object::as_array)
+        .map(|rect| rect.iter().flat_map(Object::as_float).collect::<Vec<_>>())
+        .unwrap_or_default();
+    match rect[..] {
+        [left, bottom, right, top] => left == right || bottom == top,
+        _ => true,
+    }
+}
+
+/// Removes the `NeedAppearances` flag and XFA forms, which viewers would use instead of the
+/// appearances of the fields.
+fn fix_form(doc: &mut Document, report: &mut PdfaReport) {
+    let Some(form) = catalog_entry_mut(doc, b"AcroForm") else {
+        return;
+    };
+    if form.remove(b"NeedAppearances").is_some() {
+        report
+            .fixed
+            .push("removed the NeedAppearances flag of the form".to_string());
+    }
+    if form.remove(b"XFA").is_some() {
+        report.fixed.push("removed the XFA form".to_string());
+    }
+}
+
+/// Recompresses streams compressed with LZW, which is not allowed, with Flate.
+fn recompress_lzw(doc: &mut Document, report: &mut PdfaReport) {
+    let mut recompressed = 0;
+    let mut failed = 0;
+    for object in doc.objects.values_mut() {
+        let Object::Stream(stream) = object else {
+            continue;
+        };
+        if !stream
+            .filters()
+            .is_ok_and(|filters| filters.iter().any(|f| f == "LZWDecode"))
+        {
+            continue;
+        }
+        // Images cannot be decompressed by lopdf, as their filters may be image codecs.
+        if stream
+            .dict
+            .get(b"Subtype")
+            .and_then(Object::as_name)
+            .is_ok_and(|name| name == b"Image")
+        {
+            failed += 1;
+            continue;
+        }
+        stream.decompress();
+        if stream.dict.has(b"Filter") || stream.compress().is_err() {
+            failed += 1;
+            continue;
+        }
+        recompressed += 1;
+    }
+
+    if recompressed > 0 {
+        report
+            .fixed
+            .push(format!("recompressed {recompressed} LZW streams with Flate"));
+    }
+    if failed > 0 {
+        report
+            .unresolved
+            .push(format!("{failed} streams are compressed with LZW"));
+    }
+}
+
+/// Embeds fonts which are not embedded yet, and reports those which could not be.
+fn embed_fonts(
+    doc: &mut Document,
+    level: PdfaLevel,
+    report: &mut PdfaReport,
+) -> Result<(), Box<dyn Error>> {
+    let fonts = doc
+        .objects
+        .iter()
+        .filter(|(_, object)| object.type_name().is_ok_and(|name| name == "Font"))
+        .map(|(id, _)| *id)
+        .collect::<Vec<_>>();
+    let mut db = None;
+
+    for id in fonts {
+        let font = doc.get_dictionary(id)?;
+        let base = font.get(b"BaseFont").and_then(Object::as_name).unwrap_or_default();
+        let base = String::from_utf8_lossy(base).to_string();
+        match font.get(b"Subtype").and_then(Object::as_name).unwrap_or_default() {
+            b"Type3" => continue,
+            b"Type0" => {
+                let descendant = font
+                    .get_deref(b"DescendantFonts", doc)
+                    .and_then(Object::as_array)
+                    .ok()
+                    .and_then(|fonts| fonts.first())
+                    .and_then(|font| doc.dereference(font).ok())
+                    .and_then(|(_, font)| font.as_dict().ok());
+                if !descendant.is_some_and(|font| is_embedded(doc, font)) {
+                    report.unresolved.push(format!("font {base} is not embedded"));
+                }
+                continue;
+            }
+            _ if is_embedded(doc, font) => continue,
+            _ => {}
+        }
+
+        let db = db.get_or_insert_with(|| {
+            let mut db = Database::new();
+            db.load_system_fonts();
+            db
+        });
+        match embed_font(doc, id, db, level) {
+            Ok(name) if name == base => report.fixed.push(format!("embedded font {base}")),
+            Ok(name) => report.fixed.push(format!("embedded font {base} as {name}")),
+            Err(reason) => report
+                .unresolved
+                .push(format!("font {base} is not embedded: {reason}")),
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the character of a code in WinAnsiEncoding.
+fn win_ansi(code: u8) -> Option<char> {
+    match code {
+        0x80..=0x9F => Some(WIN_ANSI_HIGH[code as usize - 0x80]).filter(|&c| c != '\0'),
+        32.. => Some(code as char),
+        _ => None,
+    }
+}
+
+/// Embeds the program of a simple font from the installed fonts, and returns its PostScript name.
+///
+/// Only fonts with the standard or WinAnsi encoding are supported, which are then encoded with
+/// WinAnsiEncoding. Both agree on printable ASCII characters except the quotes `'` and `` ` ``.
+fn embed_font(
+    doc: &mut Document,
+    id: ObjectId,
+    db: &Database,
+    level: PdfaLevel,
+) -> Result<String, String> {
+    let font = doc.get_dictionary(id).map_err(|e| e.to_string())?.clone();
+    let base = font.get(b"BaseFont").and_then(Object::as_name).unwrap_or_default();
+    let base = String::from_utf8_lossy(base).to_string();
+    match font.get(b"Encoding") {
+        Err(_) => {}
+        Ok(Object::Name(name))
+            if [b"WinAnsiEncoding".as_slice(), b"StandardEncoding"].contains(&name.as_slice()) => {}
+        _ => return Err("its encoding is not supported".to_string()),
+    }
+
+    // Strip the tag of a subset font, e.g. ABCDEF+Helvetica.
+    let name = match base.split_once('+') {
+        Some((tag, name)) if tag.len() == 6 => name,
+        _ => base.as_str(),
+    };
+    let substitutes = STANDARD_SUBSTITUTES
+        .iter()
+        .find(|(standard, _)| *standard == name)
+        .map(|(_, substitutes)| substitutes.as_slice())
+        .unwrap_or_default();
+    let (name, data) = std::iter::once(name)
+        .chain(substitutes.iter().copied())
+        .find_map(|candidate| {
+            let face = db.faces().find(|face| face.post_script_name == candidate)?;
+            // A font of a collection cannot be embedded as is.
+            let data = db.with_face_data(face.id, |data, index| {
+                (index == 0 && !data.starts_with(b"ttcf")).then(|| data.to_vec())
+            })??;
+            Some((candidate.to_string(), data))
+        })
+        .ok_or_else(|| "no matching font is installed".to_string())?;
+
+    let face = Face::parse(&data, 0).map_err(|e| e.to_string())?;
+    if face.permissions() == Some(Permissions::Restricted) {
+        return Err(format!("the license of {name} does not allow embedding"));
+    }
+    let truetype = face.tables().glyf.is_some();
+    if !truetype && level == PdfaLevel::A1b {
+        return Err(format!("{name} has CFF outlines, which PDF/A-1 does not allow"));
+    }
+    let unicode = face.tables().cmap.is_some_and(|cmap| {
+        cmap.subtables.into_iter().any(|subtable| {
+            subtable.platform_id == PlatformId::Windows && subtable.encoding_id == 1
+        })
+    });
+    if !unicode {
+        return Err(format!("{name} has no Unicode character map"));
+    }
+
+    let units = face.units_per_em() as f32;
+    let scale = |value: f32| (value * 1000.0 / units).round() as i64;
+    let width = |code: u8| {
+        let glyph = face.glyph_index(win_ansi(code)?)?;
+        face.glyph_hor_advance(glyph).map(|advance| scale(advance as f32))
+    };
+
+    // Keep the widths the text was laid out with, as long as the font agrees with them.
+    let mut widths = None;
+    if let Ok(existing) = font.get_deref(b"Widths", doc).and_then(Object::as_array) {
+        let first = font.get(b"FirstChar").and_then(Object::as_i64).unwrap_or(0);
+        for (code, expected) in (first..).zip(existing) {
+            let expected = expected.as_float().unwrap_or(0.0);
+            let actual = u8::try_from(code).ok().and_then(width);
+            if expected > 0.0 && actual.is_some_and(|actual| (actual as f32 - expected).abs() > 1.0)
+            {
+                return Err(format!("the widths of {name} differ from those of the font"));
+            }
+        }
+    } else {
+        widths = Some(
+            (32..=255)
+                .map(|code| Object::Integer(width(code).unwrap_or(0)))
+                .collect(),
+        );
+    }
+
+    let mut descriptor = font
+        .get_deref(b"FontDescriptor", doc)
+        .and_then(Object::as_dict)
+        .cloned()
+        .unwrap_or_default();
+    let bbox = face.global_bounding_box();
+    let mut flags = 32;
+    if face.is_monospaced() {
+        flags |= 1;
+    }
+    if face.is_italic() {
+        flags |= 64;
+    }
+    // The font is no longer symbolic once encoded with WinAnsiEncoding.
+    let flags = descriptor
+        .get(b"Flags")
+        .and_then(Object::as_i64)
+        .map_or(flags, |existing| (existing & !4) | 32);
+    let defaults = [
+        ("FontName", Object::Name(base.clone().into_bytes())),
+        (
+            "FontBBox",
+            [bbox.x_min, bbox.y_min, bbox.x_max, bbox.y_max]
+                .map(|value| Object::Integer(scale(value as f32)))
+                .to_vec()
+                .into(),
+        ),
+        ("ItalicAngle", Object::Real(face.italic_angle())),
+        ("Ascent", Object::Integer(scale(face.ascender() as f32))),
+        ("Descent", Object::Integer(scale(face.descender() as f32))),
+        (
+            "CapHeight",
+            Object::Integer(scale(face.capital_height().unwrap_or(face.ascender()) as f32)),
+        ),
+        ("StemV", Object::Integer(if face.is_bold() { 120 } else { 80 })),
+    ];
+
+    descriptor.set("Type", Object::Name(b"FontDescriptor".to_vec()));
+    descriptor.set("Flags", flags);
+    for (key, value) in defaults {
+        if !descriptor.has(key.as_bytes()) {
+            descriptor.set(key, value);
+        }
+    }
+    let mut file = if truetype {
+        let length = data.len() as i64;
+        let mut file = Stream::new(Dictionary::new(), data);
+        file.dict.set("Length1", length);
+        file
+    } else {
+        Stream::new(Dictionary::from_iter([("Subtype", "OpenType".into())]), data)
+    };
+    let _ = file.compress();
+    let file = doc.add_object(file);
+    descriptor.set(if truetype { "FontFile2" } else { "FontFile3" }, file);
+    let descriptor = doc.add_object(descriptor);
+
+    let font = doc.get_dictionary_mut(id).map_err(|e| e.to_string())?;
+    font.set(
+        "Subtype",
+        Object::Name(if truetype { b"TrueType".to_vec() } else { b"Type1".to_vec() }),
+    );
+    font.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
+    font.set("FontDescriptor", descriptor);
+    if let Some(widths) = widths {
+        font.set("FirstChar", Object::Integer(32));
+        font.set("LastChar", Object::Integer(255));
+        font.set("Widths", Object::Array(widths));
+    }
+
+    Ok(name)
+}
+
+/// Associates embedded files with the document as PDF/A-3 requires, or reports them for the other
+/// levels.
+fn fix_embedded_files(
+    doc: &mut Document,
+    level: PdfaLevel,
+    report: &mut PdfaReport,
+) -> Result<(), Box<dyn Error>> {
+    let mut files = BTreeMap::new();
+    let names = doc.catalog()?.get_deref(b"Names", doc).and_then(Object::as_dict);
+    if let Ok(tree) = names.and_then(|names| names.get_deref(b"EmbeddedFiles", doc)) {
+        collect_names(doc, tree.as_dict()?, &mut files, 0);
+    }
+    if files.is_empty() {
+        return Ok(());
+    }
+
+    match level {
+        PdfaLevel::A1b => {
+            report
+                .unresolved
+                .push("embedded files are not allowed in PDF/A-1".to_string());
+        }
+        PdfaLevel::A2b => report
+            .unresolved
+            .push("embedded files must conform to PDF/A themselves, which is not verified".into()),
+        PdfaLevel::A3b => {
+            let mut associated = doc
+                .catalog()?
+                .get_deref(b"AF", doc)
+                .and_then(Object::as_array)
+                .cloned()
+                .unwrap_or_default();
+            let mut fixed = false;
+            for spec in files.into_values() {
+                let Object::Reference(spec_id) = spec else {
+                    continue;
+                };
+                let Ok(dict) = doc.get_dictionary_mut(spec_id) else {
+                    continue;
+                };
+                if !dict.has(b"AFRelationship") {
+                    dict.set("AFRelationship", Object::Name(b"Unspecified".to_vec()));
+                    fixed = true;
+                }
+                if !associated.contains(&spec) {
+                    associated.push(spec);
+                    fixed = true;
+                }
+            }
+            if fixed {
+                doc.catalog_mut()?.set("AF", associated);
+                report
+                    .fixed
+                    .push("associated embedded files with the document".to_string());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Adds an sRGB output intent unless there is a PDF/A one, and returns the number of color
+/// components of the output intent.
+fn add_output_intent(doc: &mut Document, report: &mut PdfaReport) -> Result<i64, Box<dyn Error>> {
+    let mut intents = doc
+        .catalog()?
+        .get_deref(b"OutputIntents", doc)
+        .and_then(Object::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let existing = intents
+        .iter()
+        .filter_map(|intent| doc.dereference(intent).and_then(|(_, intent)| intent.as_dict()).ok())
+        .collect::<Vec<_>>();
+
+    if let Some(intent) = existing.iter().find(|intent| {
+        intent
+            .get(b"S")
+            .and_then(Object::as_name)
+            .is_ok_and(|name| name == b"GTS_PDFA1")
+    }) {
+        let profile = intent.get(b"DestOutputProfile").ok();
+        return Ok(profile.and_then(|profile| components(doc, profile)).unwrap_or(3));
+    }
+
+    // All output intents must share the same profile, so reuse that of another one, e.g. PDF/X.
+    let shared = existing.iter().find_map(|intent| {
+        let profile = intent.get(b"DestOutputProfile").ok()?;
+        Some((profile.clone(), components(doc, profile)?))
+    });
+    let (profile, components, identifier) = match shared {
+        Some((profile, components)) => (profile, components, "Custom"),
+        None => {
+            let mut stream =
+                Stream::new(Dictionary::from_iter([("N", Object::Integer(3))]), srgb_profile());
+            let _ = stream.compress();
+            (Object::Reference(doc.add_object(stream)), 3, SRGB)
+        }
+    };
+
+    let mut intent = Dictionary::new();
+    intent.set("Type", Object::Name(b"OutputIntent".to_vec()));
+    intent.set("S", Object::Name(b"GTS_PDFA1".to_vec()));
+    intent.set("OutputConditionIdentifier", Object::string_literal(identifier));
+    intent.set("Info", Object::string_literal(identifier));
+    if identifier == SRGB {
+        intent.set("RegistryName", Object::string_literal("http://www.color.org"));
+    }
+    intent.set("DestOutputProfile", profile);
+    intents.push(Object::Reference(doc.add_object(intent)));
+    doc.catalog_mut()?.set("OutputIntents", intents);
+
+    report.fixed.push(match identifier {
+        SRGB => "added an sRGB output intent".to_string(),
+        _ => "added a PDF/A output intent with the profile of the existing one".to_string(),
+    });
+
+    Ok(components)
+}
+
+/// Returns the number of color components of an ICC profile.
+fn components(doc: &Document, profile: &Object) -> Option<i64> {
+    let (_, profile) = doc.dereference(profile).ok()?;
+    profile.as_stream().ok()?.dict.get(b"N").and_then(Object::as_i64).ok()
+}
+
+/// Generates an ICC version 2 display profile of the sRGB color space.
+fn srgb_profile() -> Vec<u8> {
+    let s15_fixed16 = |value: f64| ((value * 65536.0).round() as i32).to_be_bytes();
+    let xyz = |[x, y, z]: [f64; 3]| {
+        [b"XYZ ".as_slice(), &[0; 4], &s15_fixed16(x), &s15_fixed16(y), &s15_fixed16(z)].concat()
+    };
+    let mut desc = [b"desc".as_slice(), &[0; 4], &(SRGB.len() as u32 + 1).to_be_bytes()].concat();
+    desc.extend(SRGB.as_bytes());
+    // The terminating null of the ASCII description, and the empty Unicode and ScriptCode ones.
+    desc.extend([0; 1 + 8 + 3 + 67]);
+    let mut curve = [b"curv".as_slice(), &[0; 4], &1024u32.to_be_bytes()].concat();
+    for i in 0..1024 {
+        let value = i as f64 / 1023.0;
+        let linear = match value {
+            value if value <= 0.04045 => value / 12.92,
+            value => ((value + 0.055) / 1.055).powf(2.4),
+        };
+        curve.extend(((linear * 65535.0).round() as u16).to_be_bytes());
+    }
+
+    // Colorants are adapted to the D50 illuminant of the profile connection space.
+    let tags = [
+        (b"desc", desc),
+        (b"cprt", [b"text".as_slice(), &[0; 4], b"No copyright, use freely\0"].concat()),
+        (b"wtpt", xyz([0.9505, 1.0, 1.089])),
+        (b"rXYZ", xyz([0.4361, 0.2225, 0.0139])),
+        (b"gXYZ", xyz([0.3851, 0.7169, 0.0971])),
+        (b"bXYZ", xyz([0.1431, 0.0606, 0.7141])),
+        (b"rTRC", curve.clone()),
+        (b"gTRC", curve.clone()),
+        (b"bTRC", curve),
+    ];
+
+    // The header is followed by the tag table, and then the data of the tags.
+    let start = 128 + 4 + 12 * tags.len();
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut data = vec![];
+    for (signature, tag) in &tags {
+        table.extend(signature.as_slice());
+        table.extend(((start + data.len()) as u32).to_be_bytes());
+        table.extend((tag.len() as u32).to_be_bytes());
+        data.extend(tag);
+        // Tags are aligned on 4 bytes.
+        data.resize(data.len().next_multiple_of(4), 0);
+    }
+
+    let mut header = vec![0; 128];
+    header[8..12].copy_from_slice(&[2, 0x10, 0, 0]);
+    header[12..16].copy_from_slice(b"mntr");
+    header[16..20].copy_from_slice(b"RGB ");
+    header[20..24].copy_from_slice(b"XYZ ");
+    // 2024-01-01 00:00:00
+    header[24..26].copy_from_slice(&2024u16.to_be_bytes());
+    header[26..28].copy_from_slice(&1u16.to_be_bytes());
+    header[28..30].copy_from_slice(&1u16.to_be_bytes());
+    header[36..40].copy_from_slice(b"acsp");
+    header[68..72].copy_from_slice(&s15_fixed16(0.9642));
+    header[72..76].copy_from_slice(&s15_fixed16(1.0));
+    header[76..80].copy_from_slice(&s15_fixed16(0.8249));
+
+    let mut profile = [header, table, data].concat();
+    let size = (profile.len() as u32).to_be_bytes();
+    profile[0..4].copy_from_slice(&size);
+    profile
+}
+
+/// Reports device colors which do not match the output intent.
+fn check_colors(doc: &Document, components: i64, report: &mut PdfaReport) {
+    let mut spaces = BTreeSet::new();
+    let mut add = |name: &[u8]| match name {
+        b"DeviceRGB" => {
+            spaces.insert(3);
+        }
+        b"DeviceCMYK" => {
+            spaces.insert(4);
+        }
+        _ => {}
+    };
+
+    // Color spaces of images, shadings, and transparency groups, and those defined as resources.
+    for object in doc.objects.values() {
+        visit(object, &mut |dict| {
+            for key in [b"ColorSpace".as_slice(), b"CS"] {
+                let Ok(space) = dict.get(key) else {
+                    continue;
+                };
+                let mut values = vec![space];
+                match space {
+                    Object::Array(array) => values.extend(array),
+                    Object::Dictionary(resources) => {
+                        for (_, value) in resources.iter() {
+                            values.push(value);
+                            if let Object::Array(array) = value {
+                                values.extend(array);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+                for value in values {
+                    if let Ok(name) = value.as_name() {
+                        add(name);
+                    }
+                }
+            }
+        });
+    }
+
+    // Colors set in content streams of pages and forms.
+    let mut contents = doc
+        .page_iter()
+        .filter_map(|page| doc.get_page_content(page).ok())
+        .collect::<Vec<_>>();
+    contents.extend(doc.objects.values().filter_map(|object| {
+        let stream = object.as_stream().ok()?;
+        (stream
+            .dict
+            .get(b"Subtype")
+            .and_then(Object::as_name)
+            .is_ok_and(|name| name == b"Form"))
+        .then(|| {
+            stream
+                .decompressed_content()
+                .unwrap_or_else(|_| stream.content.clone())
+        })
+    }));
+    for content in contents {
+        let Ok(content) = Content::decode(&content) else {
+            continue;
+        };
+        for operation in content.operations {
+            match operation.operator.as_str() {
+                "rg" | "RG" => add(b"DeviceRGB"),
+                "k" | "K" => add(b"DeviceCMYK"),
+                "cs" | "CS" => {
+                    if let Some(Ok(name)) = operation.operands.first().map(Object::as_name) {
+                        add(name);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    for space in spaces.into_iter().filter(|&space| space != components) {
+        let (name, intent) = match space {
+            3 => ("DeviceRGB", "an RGB"),
+            _ => ("DeviceCMYK", "a CMYK"),
+        };
+        report
+            .unresolved
+            .push(format!("{name} colors are used without {intent} output intent"));
+    }
+}
+
+/// Reports features of PDF 1.5 and later which PDF/A-1 does not allow.
+fn check_pdf_1_4(doc: &Document, report: &mut PdfaReport) -> Result<(), Box<dyn Error>> {
+    let mut transparency = false;
+    let mut jpeg2000 = false;
+    for object in doc.objects.values() {
+        if let Ok(stream) = object.as_stream() {
+            jpeg2000 |= stream
+                .filters()
+                .is_ok_and(|filters| filters.iter().any(|f| f == "JPXDecode"));
+        }
+        visit(object, &mut |dict| {
+            let opacity =
+                |key: &[u8]| dict.get(key).and_then(Object::as_float).is_ok_and(|a| a < 1.0);
+            transparency |= dict
+                .get(b"SMask")
+                .is_ok_and(|mask| mask.as_name().is_ok_and(|name| name != b"None"))
+                || opacity(b"CA")
+                || opacity(b"ca")
+                || dict
+                    .get(b"BM")
+                    .and_then(Object::as_name)
+                    .is_ok_and(|mode| mode != b"Normal" && mode != b"Compatible")
+                || dict
+                    .get(b"S")
+                    .and_then(Object::as_name)
+                    .is_ok_and(|name| name == b"Transparency");
+        });
+    }
+
+    if transparency {
+        report
+            .unresolved
+            .push("transparency is used, which PDF/A-1 does not allow".to_string());
+    }
+    if jpeg2000 {
+        report
+            .unresolved
+            .push("JPEG 2000 images are used, which PDF/A-1 does not allow".to_string());
+    }
+    if doc.catalog()?.has(b"OCProperties") {
+        report.unresolved.push(
+            "optional content, i.e. layers, is used, which PDF/A-1 does not allow".to_string(),
+        );
+    }
+
+    Ok(())
+}
+
+/// Declares the conformance level in the XMP metadata, and copies the entries of the document
+/// information dictionary to it, since both must be consistent.
+fn set_metadata(doc: &mut Document, level: PdfaLevel) -> Result<(), Box<dyn Error>> {
+    let mut xmp = match read_xmp(doc) {
+        Some(xmp) => xmp,
+        None => XmpMeta::new()?,
+    };
+    XmpMeta::register_namespace(PDFA_ID, "pdfaid")?;
+    xmp.set_property(PDFA_ID, "part", &XmpValue::from(level.part()))?;
+    xmp.set_property(PDFA_ID, "conformance", &XmpValue::from("B"))?;
+
+    let info = doc.trailer.get(b"Info").and_then(Object::as_reference);
+    let Some(info) = info.ok().filter(|&id| doc.get_dictionary(id).is_ok()) else {
+        return Ok(write_xmp(doc, &xmp)?);
+    };
+    let info = doc.get_dictionary_mut(info)?;
+
+    let text = |key: &[u8]| info.get(key).ok().and_then(|value| decode_text_string(value).ok());
+    if let Some(title) = text(b"Title") {
+        xmp.set_localized_text(DC, "title", None, "x-default", &title)?;
+    }
+    if let Some(author) = text(b"Author") {
+        xmp.delete_property(DC, "creator")?;
+        xmp.append_array_item(
+            DC,
+            &XmpValue::from("creator").set_is_ordered(true),
+            &XmpValue::from(author.as_str()),
+        )?;
+    }
+    if let Some(subject) = text(b"Subject") {
+        xmp.set_localized_text(DC, "description", None, "x-default", &subject)?;
+    }
+    for (key, ns, name) in [
+        (b"Keywords".as_slice(), PDF, "Keywords"),
+        (b"Producer", PDF, "Producer"),
+        (b"Creator", XMP, "CreatorTool"),
+    ] {
+        if let Some(value) = text(key) {
+            xmp.set_property(ns, name, &XmpValue::from(value.as_str()))?;
+        }
+    }
+    for (key, name) in [(b"CreationDate".as_slice(), "CreateDate"), (b"ModDate", "ModifyDate")] {
+        let Ok(date) = info.get(key) else {
+            continue;
+        };
+        match date.as_datetime() {
+            Some(date) => {
+                let date = to_xmp_date(Some(&date.fixed_offset()))?;
+                xmp.set_property_date(XMP, name, &XmpValue::from(date))?;
+            }
+            // A date which cannot be written to XMP cannot be consistent with it.
+            None => {
+                info.remove(key);
+            }
+        }
+    }
+
+    Ok(write_xmp(doc, &xmp)?)
+}
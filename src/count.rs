@@ -0,0 +1,180 @@
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+use typst::layout::{Abs, Frame, FrameItem, Point};
+
+use crate::{
+    compile::compile_document, extract_outline::headings, world::SystemWorld, CompileParams,
+    Heading, TypsterError,
+};
+
+/// Word and character counts of a compiled document.
+///
+/// See also [`count()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextStats {
+    /// Number of words. Each CJK character counts as a word, since Chinese and Japanese do not
+    /// separate words with spaces.
+    pub words: usize,
+
+    /// Number of characters, excluding whitespace.
+    pub characters: usize,
+
+    /// Number of CJK characters, i.e. ideographs, kana, and hangul, which are also counted in
+    /// `words` and `characters`.
+    pub cjk_characters: usize,
+
+    /// Counts of each section, in the order of the document.
+    pub sections: Vec<SectionStats>,
+}
+
+/// Word and character counts of a section of a compiled document.
+///
+/// See also [`TextStats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SectionStats {
+    /// Heading the section starts with, or [`None`] for the text before the first heading.
+    pub heading: Option<Heading>,
+
+    /// Number of words, including those of the heading.
+    pub words: usize,
+
+    /// Number of characters, excluding whitespace.
+    pub characters: usize,
+
+    /// Number of CJK characters.
+    pub cjk_characters: usize,
+}
+
+impl SectionStats {
+    /// Counts a character. `in_word` tells whether the previous character was part of a word.
+    fn add(&mut self, c: char, in_word: &mut bool) {
+        if c.is_whitespace() {
+            *in_word = false;
+            return;
+        }
+        self.characters += 1;
+        if is_cjk(c) {
+            self.cjk_characters += 1;
+            self.words += 1;
+            *in_word = false;
+        } else if c.is_alphanumeric() {
+            if !*in_word {
+                self.words += 1;
+            }
+            *in_word = true;
+        } else if !matches!(c, '\'' | '’' | '-') {
+            // Apostrophes and hyphens join words, e.g. `don't` or `well-known`.
+            *in_word = false;
+        }
+    }
+}
+
+/// Counts the words and characters of a Typst document, e.g. to check the limits of a submission
+/// or to track the progress of writing.
+///
+/// Everything laid out as text is counted, including numbering, headers, and footers, but not
+/// text in images. The document is split into sections at the headings returned by
+/// [`extract_outline()`](crate::extract_outline()), regardless of their level, so the counts of a
+/// section do not include those of its subsections. The output file of `params` is not written.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`] of the document.
+///
+/// # Returns
+///
+/// Result containing the [`TextStats`] of the document.
+///
+/// # Example
+///
+/// Following is an example of how to use the `count` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("essay.typ").build();
+/// let stats = typster::count(&params).unwrap();
+/// println!("{} words", stats.words);
+/// for section in stats.sections {
+///     if let Some(heading) = section.heading {
+///         println!("{}: {} words", heading.text, section.words);
+///     }
+/// }
+/// ```
+pub fn count(params: &CompileParams) -> Result<TextStats, Box<dyn Error>> {
+    let world = SystemWorld::new(params).map_err(TypsterError::from)?;
+    let document = compile_document(&world)?;
+
+    let headings = headings(&document);
+    let starts = headings
+        .iter()
+        .map(|(_, location)| {
+            let position = document.introspector.position(*location);
+            (position.page.get(), position.point.y)
+        })
+        .collect::<Vec<_>>();
+    let mut sections = std::iter::once(None)
+        .chain(headings.into_iter().map(|(heading, _)| Some(heading)))
+        .map(|heading| SectionStats { heading, ..Default::default() })
+        .collect::<Vec<_>>();
+
+    let mut texts = vec![];
+    for (i, page) in document.pages.iter().enumerate() {
+        collect_texts(&page.frame, Point::zero(), i + 1, &mut texts);
+    }
+
+    let mut in_word = false;
+    let mut line = None;
+    for (page, y, text) in texts {
+        // A word continues across text of the same line, e.g. when its style changes midway.
+        if line != Some((page, y)) {
+            in_word = false;
+            line = Some((page, y));
+        }
+        let section = &mut sections[starts.partition_point(|&start| start <= (page, y))];
+        for c in text.chars() {
+            section.add(c, &mut in_word);
+        }
+    }
+
+    if sections[0].characters == 0 {
+        sections.remove(0);
+    }
+    Ok(TextStats {
+        words: sections.iter().map(|section| section.words).sum(),
+        characters: sections.iter().map(|section| section.characters).sum(),
+        cjk_characters: sections.iter().map(|section| section.cjk_characters).sum(),
+        sections,
+    })
+}
+
+/// Collects the text of a frame and its groups with the page number and the vertical position of
+/// the baseline, in the order they are drawn.
+fn collect_texts<'a>(
+    frame: &'a Frame,
+    offset: Point,
+    page: usize,
+    texts: &mut Vec<(usize, Abs, &'a str)>,
+) {
+    for (position, item) in frame.items() {
+        let position = offset + *position;
+        match item {
+            FrameItem::Group(group) => collect_texts(&group.frame, position, page, texts),
+            FrameItem::Text(text) => texts.push((page, position.y, text.text.as_str())),
+            _ => {}
+        }
+    }
+}
+
+/// Returns whether a character is an ideograph, kana, or hangul.
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c,
+        '\u{3040}'..='\u{30FF}'
+            | '\u{3400}'..='\u{4DBF}'
+            | '\u{4E00}'..='\u{9FFF}'
+            | '\u{AC00}'..='\u{D7AF}'
+            | '\u{F900}'..='\u{FAFF}'
+            | '\u{FF66}'..='\u{FF9F}'
+            | '\u{20000}'..='\u{2FFFF}'
+    )
+}
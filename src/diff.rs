@@ -0,0 +1,205 @@
+use std::io::Cursor;
+#[cfg(feature = "pdf_render")]
+use std::path::Path;
+
+use image::{ImageFormat, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::{compile::compile_document, world::SystemWorld, CompileParams, TypsterError};
+
+/// Difference of a page between two documents, returned by [`diff()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageDiff {
+    /// 1-based page number.
+    pub page: usize,
+    /// Whether the page changed, or exists in only one of the documents.
+    pub status: PageStatus,
+    /// Number of pixels which differ. All pixels of an added or removed page differ.
+    pub changed_pixels: usize,
+    /// Number of pixels of the page, i.e. of the larger of both renderings if its size changed.
+    pub total_pixels: usize,
+    /// PNG image of the page after the change, faded, with the changed pixels in red. [`None`]
+    /// unless the page is [`Changed`](PageStatus::Changed).
+    pub image: Option<Vec<u8>>,
+}
+
+impl PageDiff {
+    /// Returns the fraction of the pixels which differ, from `0.0` to `1.0`.
+    pub fn changed_ratio(&self) -> f64 {
+        if self.total_pixels == 0 {
+            return 0.0;
+        }
+        self.changed_pixels as f64 / self.total_pixels as f64
+    }
+}
+
+/// Status of a page in [`PageDiff`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageStatus {
+    /// The page is pixel-identical in both documents.
+    Unchanged,
+    /// The page differs.
+    Changed,
+    /// The page exists only in the second document.
+    Added,
+    /// The page exists only in the first document.
+    Removed,
+}
+
+/// Compiles two Typst documents, e.g. a template before and after a change, rasterizes their
+/// pages, and compares them pixel by pixel, to review the visual effect of the change.
+///
+/// # Arguments
+///
+/// - `before` - [`CompileParams`] of the first document. Its `ppi`, `144.0` by default, is used
+///   for both documents. The output file is not written.
+/// - `after` - [`CompileParams`] of the second document. The output file is not written.
+///
+/// # Returns
+///
+/// Result containing the difference of each page, in page order, up to the page count of the
+/// longer document, or a [`TypsterError`] telling why it failed, e.g.
+/// [`TypsterError::Diagnostics`] if either document has errors.
+///
+/// # Example
+///
+/// Following is an example of how to use the `diff` function:
+///
+/// ```no_run
+/// let before = typster::CompileParams::builder().input("main.typ").build();
+/// let after = typster::CompileParams::builder().input("main.typ").dict("draft", "true").build();
+/// for page in typster::diff(&before, &after).unwrap() {
+///     if let Some(image) = &page.image {
+///         std::fs::write(format!("diff-{}.png", page.page), image).unwrap();
+///     }
+///     println!("page {}: {:?}, {:.1}%", page.page, page.status, page.changed_ratio() * 100.0);
+/// }
+/// ```
+pub fn diff(before: &CompileParams, after: &CompileParams) -> Result<Vec<PageDiff>, TypsterError> {
+    let ppi = before.ppi.unwrap_or(144.0);
+    compare(&render(before, ppi)?, &render(after, ppi)?)
+}
+
+/// Compares two PDF files, e.g. the outputs of two versions of a template, the same way as
+/// [`diff()`], rasterizing their pages with [`render_pdf()`](crate::render_pdf()).
+///
+/// # Arguments
+///
+/// - `before` - Path to the first PDF file.
+/// - `after` - Path to the second PDF file.
+/// - `ppi` - Pixels per inch, e.g. `144.0`.
+///
+/// # Returns
+///
+/// Result containing the difference of each page, in page order, up to the page count of the
+/// longer document, or a [`TypsterError`] telling why it failed, e.g. [`TypsterError::Pdf`] if
+/// either file can't be rendered.
+///
+/// # Example
+///
+/// Following is an example of how to use the `diff_pdfs` function:
+///
+/// ```no_run
+/// let before = std::path::Path::new("v1.pdf");
+/// let after = std::path::Path::new("v2.pdf");
+/// let changed = typster::diff_pdfs(before, after, 72.0)
+///     .unwrap()
+///     .into_iter()
+///     .filter(|page| page.status != typster::PageStatus::Unchanged)
+///     .count();
+/// println!("{changed} pages changed");
+/// ```
+#[cfg(feature = "pdf_render")]
+pub fn diff_pdfs(before: &Path, after: &Path, ppi: f32) -> Result<Vec<PageDiff>, TypsterError> {
+    let render = |path| -> Result<Vec<RgbaImage>, TypsterError> {
+        crate::render_pdf(path, &[], ppi)
+            .map_err(|err| TypsterError::Pdf(err.to_string()))?
+            .iter()
+            .map(|png| Ok(image::load_from_memory_with_format(png, ImageFormat::Png)?.into_rgba8()))
+            .collect()
+    };
+    compare(&render(before)?, &render(after)?)
+}
+
+/// Compiles a document, and rasterizes its pages.
+pub(crate) fn render(params: &CompileParams, ppi: f32) -> Result<Vec<RgbaImage>, TypsterError> {
+    let world = SystemWorld::new(params)?;
+    let document = compile_document(&world)?;
+    document
+        .pages
+        .iter()
+        .map(|page| {
+            let pixmap = typst_render::render(page, ppi / 72.0);
+            RgbaImage::from_raw(pixmap.width(), pixmap.height(), pixmap.data().to_vec())
+                .ok_or_else(|| TypsterError::Image("invalid pixmap".to_string()))
+        })
+        .collect()
+}
+
+/// Compares the rasterized pages of two documents.
+fn compare(before: &[RgbaImage], after: &[RgbaImage]) -> Result<Vec<PageDiff>, TypsterError> {
+    (0..before.len().max(after.len()))
+        .map(|i| {
+            let (status, changed_pixels, total_pixels, image) = match (before.get(i), after.get(i))
+            {
+                (Some(before), Some(after)) => {
+                    let (changed, image) = compare_page(before, after, 0);
+                    let total = image.width() as usize * image.height() as usize;
+                    if changed == 0 {
+                        (PageStatus::Unchanged, 0, total, None)
+                    } else {
+                        let mut png = vec![];
+                        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+                        (PageStatus::Changed, changed, total, Some(png))
+                    }
+                }
+                (None, Some(page)) => (PageStatus::Added, pixels(page), pixels(page), None),
+                (Some(page), None) => (PageStatus::Removed, pixels(page), pixels(page), None),
+                (None, None) => unreachable!("the index is within the longer document"),
+            };
+            Ok(PageDiff {
+                page: i + 1,
+                status,
+                changed_pixels,
+                total_pixels,
+                image,
+            })
+        })
+        .collect()
+}
+
+/// Compares two renderings of a page over the larger of both, and returns the number of pixels
+/// which differ by more than `tolerance` in any channel, with an image of the second rendering,
+/// faded, and the changed pixels in red.
+pub(crate) fn compare_page(
+    before: &RgbaImage,
+    after: &RgbaImage,
+    tolerance: u8,
+) -> (usize, RgbaImage) {
+    let width = before.width().max(after.width());
+    let height = before.height().max(after.height());
+    let mut changed = 0;
+    let image = RgbaImage::from_fn(width, height, |x, y| {
+        let pixel = after.get_pixel_checked(x, y);
+        let differs = match (before.get_pixel_checked(x, y), pixel) {
+            (Some(a), Some(b)) => a.0.iter().zip(b.0).any(|(a, b)| a.abs_diff(b) > tolerance),
+            _ => true,
+        };
+        if differs {
+            changed += 1;
+            return Rgba([255, 0, 0, 255]);
+        }
+        // Fade the unchanged content to a light gray, so that the changes stand out.
+        let [r, g, b, a] = pixel.map_or([0; 4], |pixel| pixel.0);
+        let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+        let gray = 255 - ((255 - luma) * a as u32 / 255) / 4;
+        Rgba([gray as u8, gray as u8, gray as u8, 255])
+    });
+    (changed, image)
+}
+
+/// Returns the number of pixels of a rendering.
+fn pixels(page: &RgbaImage) -> usize {
+    page.width() as usize * page.height() as usize
+}
@@ -0,0 +1,90 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// How to treat the document ID, i.e. the `ID` pair of the trailer, when post-processing a PDF
+/// file.
+///
+/// The first element of the pair is the permanent identifier of the document, and the second one
+/// changes whenever the document is modified. Signature and diff workflows depend on them.
+///
+/// See also `PdfMetadata::document_id` and `PermissionParams::document_id`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentId {
+    /// Keep the document ID of the input file.
+    #[default]
+    Preserve,
+    /// Set the document ID to the given pair.
+    Set {
+        /// The permanent identifier, i.e. the first element.
+        permanent: Vec<u8>,
+        /// The changing identifier, i.e. the second element.
+        changing: Vec<u8>,
+    },
+    /// Generate a new, random document ID.
+    Regenerate,
+}
+
+impl DocumentId {
+    /// Applies the document ID to a document loaded with lopdf.
+    #[cfg(feature = "pdf_metadata")]
+    pub(crate) fn apply(&self, doc: &mut lopdf::Document) {
+        use lopdf::{Object, StringFormat::Hexadecimal};
+
+        let (permanent, changing) = match self {
+            DocumentId::Preserve => return,
+            DocumentId::Set { permanent, changing } => (permanent.clone(), changing.clone()),
+            DocumentId::Regenerate => (generate(), generate()),
+        };
+        doc.trailer.set(
+            "ID",
+            vec![Object::String(permanent, Hexadecimal), Object::String(changing, Hexadecimal)],
+        );
+    }
+
+    /// Applies the document ID to a document loaded with qpdf.
+    ///
+    /// qpdf always keeps the first element of the trailer's document ID and regenerates the second
+    /// one when writing an encrypted file, so only the first element can be set or preserved.
+    #[cfg(feature = "pdf_permission")]
+    pub(crate) fn apply_qpdf(&self, pdf: &qpdf::QPdf) {
+        let Some(trailer) = pdf.get_trailer() else {
+            return;
+        };
+        match self {
+            DocumentId::Preserve => {}
+            DocumentId::Set { permanent, changing } => trailer.set(
+                "/ID",
+                pdf.new_array_from([
+                    pdf.new_binary_string(permanent).into(),
+                    pdf.new_binary_string(changing).into(),
+                ]),
+            ),
+            // Without a document ID, qpdf generates a new pair.
+            DocumentId::Regenerate => trailer.remove("/ID"),
+        }
+    }
+}
+
+/// Generates a random 16 bytes identifier, as long as an MD5 hash which is common for PDF files.
+fn generate() -> Vec<u8> {
+    let nanos = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+
+    // `RandomState` is seeded randomly for each instance.
+    [RandomState::new(), RandomState::new()]
+        .iter()
+        .flat_map(|state| {
+            let mut hasher = state.build_hasher();
+            hasher.write_u128(nanos);
+            hasher.finish().to_be_bytes()
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+use std::error::Error;
+
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use typst::foundations::{Datetime, Smart};
+
+use crate::{compile::compile_document, world::SystemWorld, CompileParams, TypsterError};
+
+/// Document-level metadata set in a Typst source with `set document(..)`.
+///
+/// See also [`document_info()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentInfo {
+    /// Title of the document, if any.
+    pub title: Option<String>,
+
+    /// Authors of the document.
+    pub authors: Vec<String>,
+
+    /// Keywords of the document.
+    pub keywords: Vec<String>,
+
+    /// Creation date of the document, with the time at midnight if not specified. [`None`] if the
+    /// date is `none`, or `auto`, i.e. the date of the compilation.
+    pub date: Option<NaiveDateTime>,
+}
+
+#[cfg(feature = "pdf_metadata")]
+impl DocumentInfo {
+    /// Copies the values set in the source to a [`PdfMetadata`](crate::PdfMetadata), i.e. the
+    /// title, the authors separated by commas, the keywords, and the date as the creation date in
+    /// UTC. Values not set in the source are left as they are.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// let params = typster::CompileParams::builder().input("report.typ").build();
+    /// typster::compile(&params).unwrap();
+    ///
+    /// let mut metadata = typster::PdfMetadata::default();
+    /// typster::document_info(&params).unwrap().apply(&mut metadata);
+    /// typster::update_metadata(&params.output, &metadata).unwrap();
+    /// ```
+    pub fn apply(&self, metadata: &mut crate::PdfMetadata) {
+        if let Some(title) = &self.title {
+            metadata.title = crate::LocalizedText::Default(title.clone());
+        }
+        if !self.authors.is_empty() {
+            metadata.author = self.authors.join(", ");
+        }
+        if !self.keywords.is_empty() {
+            metadata.keywords = self.keywords.clone();
+        }
+        if let Some(date) = self.date {
+            metadata.creation_date = Some(date.and_utc().fixed_offset());
+        }
+    }
+}
+
+/// Returns the document-level metadata a Typst document sets with `set document(..)`, i.e. the
+/// title, authors, keywords, and date, e.g. to drive [`update_metadata()`](crate::update_metadata())
+/// from the source instead of duplicating the values in Rust code.
+///
+/// The output file of `params` is not written.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`] of the document.
+///
+/// # Returns
+///
+/// Result containing the [`DocumentInfo`] of the document.
+///
+/// # Example
+///
+/// Following is an example of how to use the `document_info` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("report.typ").build();
+/// let info = typster::document_info(&params).unwrap();
+/// println!("{:?} by {}", info.title, info.authors.join(", "));
+/// ```
+pub fn document_info(params: &CompileParams) -> Result<DocumentInfo, Box<dyn Error>> {
+    let world = SystemWorld::new(params).map_err(TypsterError::from)?;
+    let info = compile_document(&world)?.info;
+
+    Ok(DocumentInfo {
+        title: info.title.map(|title| title.to_string()),
+        authors: info.author.iter().map(|author| author.to_string()).collect(),
+        keywords: info.keywords.iter().map(|keyword| keyword.to_string()).collect(),
+        date: match info.date {
+            Smart::Custom(Some(date)) => to_naive_date_time(&date),
+            _ => None,
+        },
+    })
+}
+
+/// Converts a Typst datetime to a [`NaiveDateTime`], or [`None`] if it has no date.
+fn to_naive_date_time(datetime: &Datetime) -> Option<NaiveDateTime> {
+    NaiveDate::from_ymd_opt(datetime.year()?, datetime.month()?.into(), datetime.day()?.into())?
+        .and_hms_opt(
+            datetime.hour().unwrap_or(0).into(),
+            datetime.minute().unwrap_or(0).into(),
+            datetime.second().unwrap_or(0).into(),
+        )
+}
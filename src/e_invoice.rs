@@ -0,0 +1,230 @@
+use std::path::Path;
+
+use lopdf::Document;
+use xmp_toolkit::{XmpError, XmpMeta, XmpValue};
+
+use crate::{
+    attach_files::attach,
+    xmp::{read_xmp, write_xmp},
+    Attachment, AttachmentRelationship,
+};
+
+/// Namespace of the Factur-X / ZUGFeRD XMP extension schema.
+const FX: &str = "urn:factur-x:pdfa:CrossIndustryDocument:invoice:1p0#";
+/// Namespace of the PDF/A identification schema.
+const PDFA_ID: &str = "http://www.aiim.org/pdfa/ns/id/";
+/// Namespaces of the PDF/A extension schema container, schemas, and properties.
+const PDFA_EXTENSION: &str = "http://www.aiim.org/pdfa/ns/extension/";
+const PDFA_SCHEMA: &str = "http://www.aiim.org/pdfa/ns/schema#";
+const PDFA_PROPERTY: &str = "http://www.aiim.org/pdfa/ns/property#";
+
+/// File name of the embedded invoice, which is fixed by the Factur-X / ZUGFeRD specification.
+const INVOICE_FILE_NAME: &str = "factur-x.xml";
+
+/// Parameters for a Factur-X / ZUGFeRD e-invoice.
+///
+/// See also [`embed_e_invoice()`].
+#[derive(Debug, Clone)]
+pub struct EInvoiceParams {
+    /// Invoice in the UN/CEFACT Cross Industry Invoice (CII) XML format.
+    pub xml: Vec<u8>,
+
+    /// Profile the invoice conforms to.
+    pub conformance_level: ConformanceLevel,
+
+    /// Version of the Factur-X / ZUGFeRD XMP extension schema.
+    pub version: String,
+}
+
+impl Default for EInvoiceParams {
+    fn default() -> Self {
+        Self {
+            xml: vec![],
+            conformance_level: ConformanceLevel::En16931,
+            version: "1.0".to_string(),
+        }
+    }
+}
+
+/// Factur-X / ZUGFeRD profile of an e-invoice, for [`EInvoiceParams`].
+#[derive(Debug, Clone, Copy)]
+pub enum ConformanceLevel {
+    /// `MINIMUM` profile, which is not a full invoice.
+    Minimum,
+    /// `BASIC WL` profile, which is not a full invoice.
+    BasicWl,
+    /// `BASIC` profile.
+    Basic,
+    /// `EN 16931` profile, a.k.a. `COMFORT`.
+    En16931,
+    /// `EXTENDED` profile.
+    Extended,
+    /// `XRECHNUNG` profile.
+    XRechnung,
+}
+
+impl ConformanceLevel {
+    fn name(&self) -> &'static str {
+        match self {
+            ConformanceLevel::Minimum => "MINIMUM",
+            ConformanceLevel::BasicWl => "BASIC WL",
+            ConformanceLevel::Basic => "BASIC",
+            ConformanceLevel::En16931 => "EN 16931",
+            ConformanceLevel::Extended => "EXTENDED",
+            ConformanceLevel::XRechnung => "XRECHNUNG",
+        }
+    }
+
+    /// The XML of the profiles which are not a full invoice is only supplementary data of the PDF,
+    /// while it's an alternative representation of the PDF for the others.
+    fn relationship(&self) -> AttachmentRelationship {
+        match self {
+            ConformanceLevel::Minimum | ConformanceLevel::BasicWl => AttachmentRelationship::Data,
+            _ => AttachmentRelationship::Alternative,
+        }
+    }
+}
+
+impl From<&str> for ConformanceLevel {
+    fn from(value: &str) -> Self {
+        match value.to_uppercase().replace('_', " ").as_str() {
+            "MINIMUM" => ConformanceLevel::Minimum,
+            "BASIC WL" => ConformanceLevel::BasicWl,
+            "BASIC" => ConformanceLevel::Basic,
+            "EXTENDED" => ConformanceLevel::Extended,
+            "XRECHNUNG" => ConformanceLevel::XRechnung,
+            _ => ConformanceLevel::En16931,
+        }
+    }
+}
+
+/// Turns a compiled invoice PDF file into a Factur-X / ZUGFeRD hybrid e-invoice.
+///
+/// The XML is embedded as `factur-x.xml` with the relationship required by the profile, and the
+/// XMP metadata is updated with the Factur-X extension schema and its description, and to declare
+/// PDF/A-3 conformance.
+///
+/// Note that the PDF file itself must otherwise conform to PDF/A, e.g. exported as PDF/A-2b, for
+/// the result to be compliant.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file, which will be updated in place.
+/// - `params` - [`EInvoiceParams`] to embed.
+///
+/// # Example
+///
+/// Following is an example of how to use the `embed_e_invoice` function:
+///
+/// ```no_run
+/// typster::embed_e_invoice(
+///     std::path::Path::new("invoice.pdf"),
+///     &typster::EInvoiceParams {
+///         xml: std::fs::read("factur-x.xml").unwrap(),
+///         conformance_level: typster::ConformanceLevel::En16931,
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+/// ```
+pub fn embed_e_invoice(
+    path: &Path,
+    params: &EInvoiceParams,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut doc = Document::load(path)?;
+
+    attach(
+        &mut doc,
+        vec![Attachment {
+            name: INVOICE_FILE_NAME.to_string(),
+            bytes: params.xml.clone(),
+            mime: "text/xml".to_string(),
+            relationship: params.conformance_level.relationship(),
+        }],
+    )?;
+
+    let mut xmp = match read_xmp(&doc) {
+        Some(xmp) => xmp,
+        None => XmpMeta::new()?,
+    };
+    set_factur_x(&mut xmp, params)?;
+    write_xmp(&mut doc, &xmp)?;
+
+    // Drop the replaced invoice, if any.
+    doc.prune_objects();
+    doc.save(path)?;
+
+    Ok(())
+}
+
+/// Sets the Factur-X properties, their extension schema description, and PDF/A-3 conformance.
+fn set_factur_x(xmp: &mut XmpMeta, params: &EInvoiceParams) -> Result<(), XmpError> {
+    XmpMeta::register_namespace(FX, "fx")?;
+    XmpMeta::register_namespace(PDFA_ID, "pdfaid")?;
+    XmpMeta::register_namespace(PDFA_EXTENSION, "pdfaExtension")?;
+    XmpMeta::register_namespace(PDFA_SCHEMA, "pdfaSchema")?;
+    XmpMeta::register_namespace(PDFA_PROPERTY, "pdfaProperty")?;
+
+    xmp.set_property(PDFA_ID, "part", &XmpValue::from("3"))?;
+    if !xmp.contains_property(PDFA_ID, "conformance") {
+        xmp.set_property(PDFA_ID, "conformance", &XmpValue::from("B"))?;
+    }
+
+    let properties = [
+        ("DocumentType", "INVOICE", "The type of the hybrid document"),
+        ("DocumentFileName", INVOICE_FILE_NAME, "The name of the embedded XML invoice file"),
+        ("Version", params.version.as_str(), "The version of the XML invoice"),
+        ("ConformanceLevel", params.conformance_level.name(), "The conformance level of the data"),
+    ];
+    for (name, value, _) in properties {
+        xmp.set_property(FX, name, &XmpValue::from(value))?;
+    }
+
+    // Describe the extension schema, unless a previous run already did.
+    let schemas = xmp.array_len(PDFA_EXTENSION, "schemas");
+    let described = (1..=schemas).any(|i| {
+        xmp.struct_field(PDFA_EXTENSION, &format!("schemas[{i}]"), PDFA_SCHEMA, "namespaceURI")
+            .is_some_and(|uri| uri.value == FX)
+    });
+    if described {
+        return Ok(());
+    }
+
+    xmp.append_array_item(
+        PDFA_EXTENSION,
+        &XmpValue::from("schemas").set_is_array(true),
+        &XmpValue::from("").set_is_struct(true),
+    )?;
+    let schema = format!("schemas[{}]", schemas + 1);
+    for (field, value) in
+        [("schema", "Factur-X PDFA Extension Schema"), ("namespaceURI", FX), ("prefix", "fx")]
+    {
+        xmp.set_struct_field(PDFA_EXTENSION, &schema, PDFA_SCHEMA, field, &XmpValue::from(value))?;
+    }
+
+    let property = format!("{schema}/pdfaSchema:property");
+    for (i, (name, _, description)) in properties.iter().enumerate() {
+        xmp.append_array_item(
+            PDFA_EXTENSION,
+            &XmpValue::from(property.as_str()).set_is_ordered(true),
+            &XmpValue::from("").set_is_struct(true),
+        )?;
+        let item = format!("{property}[{}]", i + 1);
+        for (field, value) in [
+            ("name", *name),
+            ("valueType", "Text"),
+            ("category", "external"),
+            ("description", *description),
+        ] {
+            xmp.set_struct_field(
+                PDFA_EXTENSION,
+                &item,
+                PDFA_PROPERTY,
+                field,
+                &XmpValue::from(value),
+            )?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,247 @@
+use std::{error::Error, path::PathBuf};
+
+use lopdf::{dictionary, Dictionary, Document, Object, StringFormat::Hexadecimal};
+use openssl::{
+    cms::{CMSOptions, CmsContentInfo},
+    error::ErrorStack,
+    rand::rand_bytes,
+    sha::Sha256,
+    stack::Stack,
+    symm::{encrypt, Cipher},
+    x509::X509,
+};
+use tracing::instrument;
+
+use crate::PrintPermission;
+
+/// Parameters for encrypting a PDF file to recipient certificates.
+///
+/// See also [`encrypt_for_recipients()`].
+#[derive(Debug, Clone)]
+pub struct RecipientParams {
+    /// Certificates of the recipients, in PEM or DER. A PEM may contain more than one certificate.
+    /// Any of the recipients can open the document with the private key of their certificate.
+    pub certificates: Vec<Vec<u8>>,
+
+    /// Allow content copying for accessibility.
+    pub allow_accessibility: bool,
+
+    /// Allow page extraction.
+    pub allow_extract: bool,
+
+    /// Allow document assembly.
+    pub allow_assemble: bool,
+
+    /// Allow commenting and form filling.
+    pub allow_annotate_and_form: bool,
+
+    /// Allow form field fill-in or signing.
+    pub allow_form_filling: bool,
+
+    /// Allow other modifications.
+    pub allow_modify_other: bool,
+
+    /// Allow printing.
+    pub allow_print: PrintPermission,
+
+    /// Encrypt metadata.
+    pub encrypt_metadata: bool,
+}
+
+impl Default for RecipientParams {
+    fn default() -> Self {
+        Self {
+            certificates: vec![],
+            allow_accessibility: true,
+            allow_extract: true,
+            allow_assemble: false,
+            allow_annotate_and_form: true,
+            allow_form_filling: false,
+            allow_modify_other: false,
+            allow_print: PrintPermission::Full,
+            encrypt_metadata: true,
+        }
+    }
+}
+
+impl RecipientParams {
+    /// Returns the permission flags, i.e. the `P` entry of the standard security handler, which
+    /// the public-key security handler embeds in the enveloped data.
+    fn permissions(&self) -> u32 {
+        // Bits 7, 8, and 13 to 32 are reserved and must be set.
+        let mut p = 0xFFFF_F0C0;
+        for (allowed, bit) in [
+            (self.allow_modify_other, 4),
+            (self.allow_extract, 5),
+            (self.allow_annotate_and_form, 6),
+            (self.allow_form_filling, 9),
+            (self.allow_accessibility, 10),
+            (self.allow_assemble, 11),
+        ] {
+            if allowed {
+                p |= 1 << (bit - 1);
+            }
+        }
+        match self.allow_print {
+            PrintPermission::Full => p |= (1 << 2) | (1 << 11),
+            PrintPermission::Low => p |= 1 << 2,
+            PrintPermission::None => {}
+        }
+        p
+    }
+}
+
+/// Encrypts a PDF file to recipient certificates, instead of passwords, with the public-key
+/// security handler, i.e. `Adobe.PubSec` with `adbe.pkcs7.s5` and AES-256.
+///
+/// The file encryption key is enveloped in CMS to the certificates of `params.certificates`, so
+/// that only the holders of their private keys can open the document. The output file must be
+/// different from the input file, and the input file must not be encrypted.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file.
+/// - `params` - [`RecipientParams`] to encrypt with.
+///
+/// # Example
+///
+/// Following is an example of how to use the `encrypt_for_recipients` function:
+///
+/// ```no_run
+/// typster::encrypt_for_recipients(
+///     std::path::PathBuf::from("contract.pdf"),
+///     std::path::PathBuf::from("contract-encrypted.pdf"),
+///     &typster::RecipientParams {
+///         certificates: vec![std::fs::read("recipient.pem").unwrap()],
+///         allow_print: typster::PrintPermission::None,
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+/// ```
+#[instrument(skip_all, fields(input = %input.display(), output = %output.display()))]
+pub fn encrypt_for_recipients(
+    input: PathBuf,
+    output: PathBuf,
+    params: &RecipientParams,
+) -> Result<(), Box<dyn Error>> {
+    if output.exists() && input.canonicalize()? == output.canonicalize()? {
+        return Err("in-place update is not possible".into());
+    }
+
+    let mut certificates = Stack::new()?;
+    for certificate in &params.certificates {
+        let parsed = X509::stack_from_pem(certificate)
+            .ok()
+            .filter(|certs| !certs.is_empty())
+            .map_or_else(|| X509::from_der(certificate).map(|cert| vec![cert]), Ok)?;
+        for cert in parsed {
+            certificates.push(cert)?;
+        }
+    }
+    if certificates.is_empty() {
+        return Err("no recipient certificate is given".into());
+    }
+
+    let mut doc = Document::load(input)?;
+    if doc.is_encrypted() {
+        return Err("the input file is already encrypted".into());
+    }
+
+    // The enveloped data is a 20-byte seed followed by the permissions, most significant byte
+    // first, and the file encryption key is derived from both the seed and the enveloped data.
+    let mut seed = [0; 20];
+    rand_bytes(&mut seed)?;
+    let envelope = [&seed[..], &params.permissions().to_be_bytes()].concat();
+    let recipients = CmsContentInfo::encrypt(
+        &certificates,
+        &envelope,
+        Cipher::aes_256_cbc(),
+        CMSOptions::BINARY,
+    )?
+    .to_der()?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(&seed);
+    hasher.update(&recipients);
+    if !params.encrypt_metadata {
+        hasher.update(&[0xFF; 4]);
+    }
+    let key = hasher.finish();
+
+    for object in doc.objects.values_mut() {
+        if object.type_name().is_ok_and(|name| name == "Metadata") && !params.encrypt_metadata {
+            continue;
+        }
+        encrypt_object(&key, object)?;
+    }
+
+    let crypt_filter = dictionary! {
+        "CFM" => "AESV3",
+        "AuthEvent" => "DocOpen",
+        "Length" => 32,
+        "Recipients" => vec![Object::String(recipients, Hexadecimal)],
+        "EncryptMetadata" => params.encrypt_metadata,
+    };
+    let encrypt = doc.add_object(dictionary! {
+        "Filter" => "Adobe.PubSec",
+        "SubFilter" => "adbe.pkcs7.s5",
+        "V" => 5,
+        "Length" => 256,
+        "CF" => dictionary! { "DefaultCryptFilter" => crypt_filter },
+        "StmF" => "DefaultCryptFilter",
+        "StrF" => "DefaultCryptFilter",
+        "EncryptMetadata" => params.encrypt_metadata,
+    });
+    doc.trailer.set("Encrypt", encrypt);
+
+    // AES-256 is an extension level 3 feature of PDF 1.7.
+    if doc.version.as_str() < "1.7" {
+        doc.version = "1.7".to_string();
+    }
+    let extensions = dictionary! {
+        "ADBE" => dictionary! { "BaseVersion" => "1.7", "ExtensionLevel" => 3 },
+    };
+    doc.catalog_mut()?.set("Extensions", extensions);
+
+    doc.save(output)?;
+    Ok(())
+}
+
+/// Encrypts strings and streams in the object in place with AES-256.
+fn encrypt_object(key: &[u8], object: &mut Object) -> Result<(), ErrorStack> {
+    match object {
+        Object::String(bytes, format) => {
+            *bytes = encrypt_bytes(key, bytes)?;
+            *format = Hexadecimal;
+        }
+        Object::Array(array) => {
+            for object in array {
+                encrypt_object(key, object)?;
+            }
+        }
+        Object::Dictionary(dict) => encrypt_dictionary(key, dict)?,
+        Object::Stream(stream) => {
+            encrypt_dictionary(key, &mut stream.dict)?;
+            let content = encrypt_bytes(key, &stream.content)?;
+            stream.set_content(content);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn encrypt_dictionary(key: &[u8], dict: &mut Dictionary) -> Result<(), ErrorStack> {
+    for (_, object) in dict.iter_mut() {
+        encrypt_object(key, object)?;
+    }
+    Ok(())
+}
+
+/// Encrypts the bytes with AES-256 in CBC mode, prepending the random initialization vector.
+fn encrypt_bytes(key: &[u8], bytes: &[u8]) -> Result<Vec<u8>, ErrorStack> {
+    let mut iv = [0; 16];
+    rand_bytes(&mut iv)?;
+    Ok([&iv[..], &encrypt(Cipher::aes_256_cbc(), key, Some(&iv), bytes)?].concat())
+}
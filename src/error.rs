@@ -0,0 +1,237 @@
+#[cfg(any(feature = "compile", feature = "wasm"))]
+use std::fmt;
+use std::io;
+
+#[cfg(any(feature = "compile", feature = "wasm"))]
+use ecow::EcoVec;
+#[cfg(any(feature = "server", feature = "watch"))]
+use serde::Serialize;
+use thiserror::Error;
+#[cfg(any(feature = "compile", feature = "wasm"))]
+use typst::diag::SourceDiagnostic;
+#[cfg(any(feature = "server", feature = "watch"))]
+use typst::{diag::Severity, World};
+
+#[cfg(any(feature = "server", feature = "watch"))]
+use crate::{world::SystemWorld, SourceLocation};
+
+/// Errors returned by [`compile()`](crate::compile()), [`format()`](crate::format()),
+/// [`update_metadata()`](crate::update_metadata()), [`set_permission()`](crate::set_permission()),
+/// and [`remove_permission()`](crate::remove_permission()), along with their in-memory variants,
+/// and by [`diff()`](crate::diff()), `diff_pdfs()`, [`preflight()`](crate::preflight()),
+/// [`size_breakdown()`](crate::size_breakdown()), and [`extract_notes()`](crate::extract_notes()).
+///
+/// It converts into [`Box<dyn std::error::Error>`] with `?`, so it can be propagated the same way
+/// as the errors of the other functions. Other functions which compile a document to extract or
+/// query something from it, e.g. [`query()`](crate::query()), still return a boxed error, since most of
+/// their failures are specific to them, but a failed compilation is boxed as a `TypsterError`,
+/// which can be recovered with [`downcast_ref()`](std::error::Error::downcast_ref()). Variants may
+/// be added in any release.
+///
+/// # Example
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("main.typ").build();
+/// match typster::compile(&params) {
+///     Ok(duration) => println!("compiled in {duration:?}"),
+///     Err(typster::TypsterError::Diagnostics(diagnostics)) => eprintln!("{diagnostics}"),
+///     Err(why) => eprintln!("failed to compile: {why}"),
+/// }
+/// ```
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum TypsterError {
+    /// The input couldn't be set up for compilation, e.g. the input file doesn't exist.
+    #[error("{0}")]
+    World(String),
+
+    /// The document failed to compile, with the warnings and errors of the compilation.
+    #[cfg(any(feature = "compile", feature = "wasm"))]
+    #[error(transparent)]
+    Diagnostics(#[from] Diagnostics),
+
+    /// Reading or writing a file failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    /// A PDF file couldn't be read, modified, or written, e.g. it's broken or encrypted.
+    #[error("failed to process PDF: {0}")]
+    Pdf(String),
+
+    /// Fonts couldn't be loaded, e.g. a font given in memory isn't a valid font file.
+    #[error("{0}")]
+    Font(String),
+
+    /// The parameters are invalid, e.g. an in-place update which is not allowed.
+    #[error("invalid parameters: {0}")]
+    Params(String),
+
+    /// The compilation exceeded a limit of [`CompileParams::limits`](crate::CompileParams::limits),
+    /// e.g. it took too long or read too large a file.
+    #[error("limit exceeded: {0}")]
+    LimitExceeded(String),
+
+    /// The compilation took longer than [`CompileParams::timeout`](crate::CompileParams::timeout).
+    /// It may still be running on a background thread.
+    #[error("compilation timed out after {0:?}")]
+    TimedOut(std::time::Duration),
+
+    /// A page couldn't be rasterized, or an image couldn't be encoded or decoded, e.g. by
+    /// [`diff()`](crate::diff()).
+    #[error("failed to process image: {0}")]
+    Image(String),
+}
+
+/// Warnings and errors of a failed compilation, as reported by Typst, e.g. to count them or
+/// convert them into machine-readable output. They are formatted only when displayed, one per
+/// line with their hints.
+///
+/// # Example
+///
+/// Following is an example of how to use the `Diagnostics`:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("main.typ").build();
+/// if let Err(typster::TypsterError::Diagnostics(diagnostics)) = typster::compile(&params) {
+///     eprintln!("{} errors, {} warnings", diagnostics.errors().len(), diagnostics.warnings().len());
+///     for error in diagnostics.errors() {
+///         eprintln!("{}", error.message);
+///     }
+/// }
+/// ```
+#[cfg(any(feature = "compile", feature = "wasm"))]
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+    warnings: EcoVec<SourceDiagnostic>,
+    errors: EcoVec<SourceDiagnostic>,
+}
+
+#[cfg(any(feature = "compile", feature = "wasm"))]
+impl Diagnostics {
+    /// Keeps the warnings and errors of a compilation.
+    pub(crate) fn new(
+        warnings: EcoVec<SourceDiagnostic>,
+        errors: EcoVec<SourceDiagnostic>,
+    ) -> Self {
+        Self { warnings, errors }
+    }
+
+    /// Returns the warnings of the compilation.
+    pub fn warnings(&self) -> &[SourceDiagnostic] {
+        &self.warnings
+    }
+
+    /// Returns the errors of the compilation.
+    pub fn errors(&self) -> &[SourceDiagnostic] {
+        &self.errors
+    }
+
+    /// Returns the warnings, then the errors of the compilation.
+    pub fn iter(&self) -> impl Iterator<Item = &SourceDiagnostic> {
+        self.warnings.iter().chain(&self.errors)
+    }
+}
+
+#[cfg(any(feature = "compile", feature = "wasm"))]
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diagnostic) in self.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "{:?}: {}", diagnostic.severity, diagnostic.message)?;
+            for (j, hint) in diagnostic.hints.iter().enumerate() {
+                if j > 0 {
+                    writeln!(f)?;
+                }
+                write!(f, "hint: {hint}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "compile", feature = "wasm"))]
+impl std::error::Error for Diagnostics {}
+
+/// A warning or an error of a compilation with its location, sent as is to the clients of
+/// [`Server`](crate::Server) and [`watch()`](crate::watch()).
+#[cfg(any(feature = "server", feature = "watch"))]
+#[derive(Debug, Serialize)]
+pub(crate) struct Diagnostic {
+    severity: &'static str,
+    message: String,
+    hints: Vec<String>,
+    location: Option<SourceLocation>,
+}
+
+#[cfg(any(feature = "server", feature = "watch"))]
+impl Diagnostic {
+    /// Locates a warning or an error of a compilation in the sources of the world.
+    pub(crate) fn new(world: &SystemWorld, diagnostic: &SourceDiagnostic) -> Self {
+        let location = diagnostic.span.id().and_then(|id| {
+            let source = world.source(id).ok()?;
+            let range = source.range(diagnostic.span)?;
+            Some(SourceLocation::at(&source, range.start))
+        });
+        Self {
+            severity: match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            },
+            message: diagnostic.message.to_string(),
+            hints: diagnostic.hints.iter().map(|hint| hint.to_string()).collect(),
+            location,
+        }
+    }
+
+    /// Returns an error without location, e.g. for a compilation which failed before Typst
+    /// reported anything.
+    #[cfg(feature = "watch")]
+    pub(crate) fn error(message: String) -> Self {
+        Self {
+            severity: "error",
+            message,
+            hints: vec![],
+            location: None,
+        }
+    }
+}
+
+#[cfg(feature = "compile")]
+impl From<crate::world::WorldCreationError> for TypsterError {
+    fn from(err: crate::world::WorldCreationError) -> Self {
+        match err {
+            crate::world::WorldCreationError::Io(err) => Self::Io(err),
+            _ => Self::World(err.to_string()),
+        }
+    }
+}
+
+#[cfg(any(feature = "pdf_edit", feature = "pdf_metadata", feature = "pdf_permission"))]
+impl From<lopdf::Error> for TypsterError {
+    fn from(err: lopdf::Error) -> Self {
+        Self::Pdf(err.to_string())
+    }
+}
+
+#[cfg(feature = "diff")]
+impl From<image::ImageError> for TypsterError {
+    fn from(err: image::ImageError) -> Self {
+        Self::Image(err.to_string())
+    }
+}
+
+#[cfg(any(feature = "pdf_metadata", feature = "pdf_permission"))]
+impl From<xmp_toolkit::XmpError> for TypsterError {
+    fn from(err: xmp_toolkit::XmpError) -> Self {
+        Self::Pdf(format!("XMP: {err}"))
+    }
+}
+
+#[cfg(feature = "pdf_permission")]
+impl From<qpdf::QPdfError> for TypsterError {
+    fn from(err: qpdf::QPdfError) -> Self {
+        Self::Pdf(err.to_string())
+    }
+}
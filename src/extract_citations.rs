@@ -0,0 +1,172 @@
+use std::{error::Error, path::Path};
+
+use hayagriva::{Entry, Library};
+use serde::{Deserialize, Serialize};
+use typst::{
+    foundations::{IntoValue, NativeElement, Value},
+    model::{BibliographyElem, CiteElem, Document},
+    World,
+};
+
+use crate::{compile::compile_document, world::SystemWorld, CompileParams, TypsterError};
+
+/// A work cited in a compiled document, resolved from its bibliography.
+///
+/// See also [`extract_citations()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CitedWork {
+    /// Key of the entry, e.g. `knuth` for `@knuth`.
+    pub key: String,
+
+    /// 1-based page numbers the entry is cited on, in ascending order.
+    pub pages: Vec<usize>,
+
+    /// Type of the entry, i.e. the lowercased hayagriva entry type: `article`, `chapter`,
+    /// `entry`, `anthos`, `report`, `thesis`, `web`, `scene`, `artwork`, `patent`, `case`,
+    /// `newspaper`, `legislation`, `manuscript`, `post`, `misc`, `performance`, `periodical`,
+    /// `proceedings`, `book`, `blog`, `reference`, `conference`, `anthology`, `repository`,
+    /// `thread`, `video`, `audio`, `exhibition`, or `original`.
+    pub entry_type: String,
+
+    /// Title of the entry, if any.
+    pub title: Option<String>,
+
+    /// Authors of the entry, given names first, e.g. `Donald E. Knuth`.
+    pub authors: Vec<String>,
+
+    /// Year of publication, if any.
+    pub year: Option<i32>,
+
+    /// URL of the entry, if any.
+    pub url: Option<String>,
+
+    /// DOI of the entry, if any.
+    pub doi: Option<String>,
+}
+
+/// Extracts the works cited in a Typst document with the entries of its bibliography, in the order
+/// they are first cited, e.g. to audit citations or to export a "works cited" JSON file alongside
+/// the PDF.
+///
+/// Both `@key` references and `cite()` calls are extracted, while entries listed only because of
+/// `bibliography(full: true)` are not. Bibliography files in both the Hayagriva YAML and BibLaTeX
+/// formats are supported. The output file of `params` is not written.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`] of the document.
+///
+/// # Returns
+///
+/// Result containing the [`CitedWork`]s of the document, which is empty if the document has no
+/// bibliography.
+///
+/// # Example
+///
+/// Following is an example of how to use the `extract_citations` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("paper.typ").build();
+/// for work in typster::extract_citations(&params).unwrap() {
+///     println!("{}: {:?} (pages {:?})", work.key, work.title, work.pages);
+/// }
+/// ```
+pub fn extract_citations(params: &CompileParams) -> Result<Vec<CitedWork>, Box<dyn Error>> {
+    let world = SystemWorld::new(params).map_err(TypsterError::from)?;
+    let document = compile_document(&world)?;
+    let libraries = load_libraries(&world, &document)?;
+
+    let mut works: Vec<CitedWork> = vec![];
+    for element in document.introspector.query(&CiteElem::elem().select()) {
+        let (Some(cite), Some(location)) = (element.to_packed::<CiteElem>(), element.location())
+        else {
+            continue;
+        };
+        let key = cite.key.as_str();
+        let page = document.introspector.page(location).get();
+
+        if let Some(work) = works.iter_mut().find(|work| work.key == key) {
+            if !work.pages.contains(&page) {
+                work.pages.push(page);
+            }
+            continue;
+        }
+        // Typst fails to compile a citation of an unknown key, so the entry always exists.
+        let Some(entry) = libraries.iter().find_map(|library| library.get(key)) else {
+            continue;
+        };
+        works.push(CitedWork { pages: vec![page], ..work(entry) });
+    }
+    for work in &mut works {
+        work.pages.sort_unstable();
+    }
+
+    Ok(works)
+}
+
+/// Loads the bibliography files of the `bibliography()` elements of a compiled document.
+fn load_libraries(
+    world: &SystemWorld,
+    document: &Document,
+) -> Result<Vec<Library>, Box<dyn Error>> {
+    let mut libraries = vec![];
+    for element in document.introspector.query(&BibliographyElem::elem().select()) {
+        let Some(bibliography) = element.to_packed::<BibliographyElem>() else {
+            continue;
+        };
+        // Paths are relative to the file calling `bibliography()`.
+        let Some(id) = element.span().id() else {
+            continue;
+        };
+        let Value::Array(paths) = bibliography.path.clone().into_value() else {
+            continue;
+        };
+        for path in paths.iter().filter_map(|path| path.clone().cast::<String>().ok()) {
+            let data = world.file(id.join(&path)).map_err(|err| format!("{path}: {err}"))?;
+            let text = std::str::from_utf8(&data)?;
+            let library = match Path::new(&path).extension().and_then(|ext| ext.to_str()) {
+                Some("yml" | "yaml") => hayagriva::io::from_yaml_str(text)?,
+                Some("bib") => hayagriva::io::from_biblatex_str(text)
+                    .map_err(|_| format!("{path}: failed to parse BibLaTeX file"))?,
+                _ => return Err(format!("{path}: unknown bibliography format").into()),
+            };
+            libraries.push(library);
+        }
+    }
+    Ok(libraries)
+}
+
+/// Converts an entry of a bibliography into a [`CitedWork`] without pages.
+fn work(entry: &Entry) -> CitedWork {
+    CitedWork {
+        key: entry.key().to_string(),
+        pages: vec![],
+        entry_type: format!("{:?}", entry.entry_type()).to_lowercase(),
+        title: entry.title().map(|title| title.value.to_string()),
+        authors: entry
+            .authors()
+            .unwrap_or_default()
+            .iter()
+            .map(|person| {
+                let mut name = [&person.given_name, &person.prefix]
+                    .into_iter()
+                    .flatten()
+                    .chain(std::iter::once(&person.name))
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if let Some(suffix) = &person.suffix {
+                    name.push_str(", ");
+                    name.push_str(suffix);
+                }
+                name
+            })
+            .collect(),
+        year: entry.date().map(|date| date.year),
+        url: entry.url().map(|url| url.value.to_string()),
+        doi: entry
+            .serial_number()
+            .and_then(|numbers| numbers.0.get("doi"))
+            .cloned(),
+    }
+}
@@ -0,0 +1,156 @@
+use std::{error::Error, fs::read_to_string, path::PathBuf};
+
+use ecow::eco_vec;
+use serde::{Deserialize, Serialize};
+use typst::{
+    foundations::{Label, NativeElement, Selector, Smart, StyleChain, Value},
+    introspection::MetadataElem,
+    math::EquationElem,
+    model::{FigureElem, FigureKind},
+};
+
+use crate::{compile::compile_document, world::SystemWorld, CompileParams, TypsterError};
+
+/// A figure, table, or block equation of a compiled document.
+///
+/// See also [`extract_figures()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Figure {
+    /// Kind of the figure, e.g. `image`, `table`, `raw`, or a custom kind, or `equation` for an
+    /// equation.
+    pub kind: String,
+
+    /// Number of the figure as displayed, e.g. `1` or `2.1` with a custom numbering, or [`None`]
+    /// if it is not numbered.
+    pub number: Option<String>,
+
+    /// Plain text of the caption, if any.
+    pub caption: Option<String>,
+
+    /// Label of the figure without the angle brackets, if any.
+    pub label: Option<String>,
+
+    /// 1-based page number the figure is on.
+    pub page: usize,
+}
+
+/// Label of the metadata holding the numbers of figures and equations computed by Typst.
+const NUMBERS_LABEL: &str = "typster-figure-numbers";
+
+/// Appended to the main file to let Typst resolve the counters, which may be reset or updated by
+/// the document, e.g. per chapter, and format them with their numbering.
+const NUMBERS_QUERY: &str = r#"
+#context metadata((
+  figure: query(figure).map(it => if it.numbering != none {
+    numbering(it.numbering, ..it.counter.at(it.location()))
+  }),
+  equation: query(math.equation.where(block: true)).map(it => if it.numbering != none {
+    numbering(it.numbering, ..counter(math.equation).at(it.location()))
+  }),
+)) <typster-figure-numbers>
+"#;
+
+/// Extracts the figures, tables, and block equations of a Typst document, in the order of the
+/// document, e.g. to generate "List of Figures" data or an index across documents.
+///
+/// Numbers are the ones displayed in the document, taking custom numberings and counter updates
+/// into account. The output file of `params` is not written.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`] of the document.
+///
+/// # Returns
+///
+/// Result containing the [`Figure`]s of the document.
+///
+/// # Example
+///
+/// Following is an example of how to use the `extract_figures` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("report.typ").build();
+/// for figure in typster::extract_figures(&params).unwrap() {
+///     if let (Some(number), Some(caption)) = (figure.number, figure.caption) {
+///         println!("{} {number}: {caption} ({})", figure.kind, figure.page);
+///     }
+/// }
+/// ```
+pub fn extract_figures(params: &CompileParams) -> Result<Vec<Figure>, Box<dyn Error>> {
+    // Shadow the main file with the query appended, unless it is already shadowed.
+    let name = PathBuf::from(params.input.file_name().ok_or("input is not a file")?);
+    let mut params = params.clone();
+    let main = match params.shadow_files.remove(&name) {
+        Some(main) => main,
+        None => read_to_string(&params.input)?,
+    };
+    params.shadow_files.insert(name, main + NUMBERS_QUERY);
+
+    let world = SystemWorld::new(&params).map_err(TypsterError::from)?;
+    let document = compile_document(&world)?;
+    let introspector = &document.introspector;
+
+    let numbers = |key: &str| -> Vec<Option<String>> {
+        let metadata = introspector.query_label(Label::new(NUMBERS_LABEL)).ok();
+        let Some(Value::Dict(dict)) = metadata
+            .and_then(|element| element.to_packed::<MetadataElem>())
+            .map(|m| &m.value)
+        else {
+            return vec![];
+        };
+        let Ok(Value::Array(numbers)) = dict.get(key) else {
+            return vec![];
+        };
+        numbers
+            .iter()
+            .map(|number| match number {
+                Value::Str(number) => Some(number.to_string()),
+                _ => None,
+            })
+            .collect()
+    };
+    let mut figure_numbers = numbers("figure").into_iter();
+    let mut equation_numbers = numbers("equation").into_iter();
+
+    let selector =
+        Selector::Or(eco_vec![FigureElem::elem().select(), EquationElem::elem().select()]);
+    let mut figures = vec![];
+    for element in introspector.query(&selector) {
+        let Some(location) = element.location() else {
+            continue;
+        };
+        let label = element.label().map(|label| label.as_str().to_string());
+        let page = introspector.page(location).get();
+
+        if let Some(figure) = element.to_packed::<FigureElem>() {
+            let kind = match figure.kind(StyleChain::default()) {
+                Smart::Custom(FigureKind::Elem(elem)) => elem.name().to_string(),
+                Smart::Custom(FigureKind::Name(name)) => name.to_string(),
+                Smart::Auto => String::new(),
+            };
+            figures.push(Figure {
+                kind,
+                number: figure_numbers.next().flatten(),
+                caption: figure
+                    .caption(StyleChain::default())
+                    .as_ref()
+                    .map(|caption| caption.body.plain_text().to_string()),
+                label,
+                page,
+            });
+        } else if let Some(equation) = element.to_packed::<EquationElem>() {
+            if !equation.block(StyleChain::default()) {
+                continue;
+            }
+            figures.push(Figure {
+                kind: "equation".to_string(),
+                number: equation_numbers.next().flatten(),
+                caption: None,
+                label,
+                page,
+            });
+        }
+    }
+
+    Ok(figures)
+}
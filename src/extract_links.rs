@@ -0,0 +1,153 @@
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+use typst::{
+    foundations::Selector,
+    layout::{Frame, FrameItem, Point, Size},
+    model::{Destination, Document},
+};
+
+use crate::{compile::compile_document, world::SystemWorld, CompileParams, TypsterError};
+
+/// A hyperlink of a compiled document.
+///
+/// See also [`extract_links()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hyperlink {
+    /// 1-based page number the link is on.
+    pub page: usize,
+
+    /// Text of the link as laid out, e.g. `Section 2` for `@results`. Empty if the link contains
+    /// no text, e.g. an image.
+    pub text: String,
+
+    /// Where the link goes.
+    pub target: HyperlinkTarget,
+}
+
+/// Target of a [`Hyperlink`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HyperlinkTarget {
+    /// External URL, e.g. `https://typst.app`.
+    Url(String),
+    /// Position in the same document, e.g. a referenced heading.
+    Internal {
+        /// 1-based page number of the target.
+        page: usize,
+
+        /// Label of the target without the angle brackets, if any.
+        label: Option<String>,
+    },
+}
+
+/// Extracts the hyperlinks of a Typst document, in the order they are laid out, e.g. to validate
+/// every external URL with a link checker.
+///
+/// Both links created with the `link()` function and references such as `@intro` are extracted.
+/// A link broken across lines is returned once, with the text of each line joined by a space. The
+/// output file of `params` is not written.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`] of the document.
+///
+/// # Returns
+///
+/// Result containing the [`Hyperlink`]s of the document.
+///
+/// # Example
+///
+/// Following is an example of how to use the `extract_links` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("report.typ").build();
+/// for link in typster::extract_links(&params).unwrap() {
+///     if let typster::HyperlinkTarget::Url(url) = link.target {
+///         println!("{url} on page {}", link.page);
+///     }
+/// }
+/// ```
+pub fn extract_links(params: &CompileParams) -> Result<Vec<Hyperlink>, Box<dyn Error>> {
+    let world = SystemWorld::new(params).map_err(TypsterError::from)?;
+    let document = compile_document(&world)?;
+
+    let mut links: Vec<(usize, &Destination, String)> = vec![];
+    for (i, page) in document.pages.iter().enumerate() {
+        let mut areas = vec![];
+        let mut texts = vec![];
+        collect_items(&page.frame, Point::zero(), &mut areas, &mut texts);
+
+        for (origin, size, destination) in areas {
+            let text = texts
+                .iter()
+                .filter(|(point, _)| {
+                    point.x >= origin.x
+                        && point.x < origin.x + size.x
+                        && point.y >= origin.y
+                        && point.y <= origin.y + size.y
+                })
+                .map(|(_, text)| *text)
+                .collect::<String>();
+            // A link broken across lines is laid out as an area per line.
+            match links.last_mut() {
+                Some((last_page, last, last_text))
+                    if *last_page == i + 1 && *last == destination =>
+                {
+                    if !text.is_empty() {
+                        last_text.push(' ');
+                        last_text.push_str(&text);
+                    }
+                }
+                _ => links.push((i + 1, destination, text)),
+            }
+        }
+    }
+
+    Ok(links
+        .into_iter()
+        .map(|(page, destination, text)| Hyperlink {
+            page,
+            // Collapse whitespace, including the non-breaking space of references.
+            text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+            target: target(&document, destination),
+        })
+        .collect())
+}
+
+/// Collects the link areas and the text of a frame and its groups, with their positions on the
+/// page, in the order they are drawn.
+fn collect_items<'a>(
+    frame: &'a Frame,
+    offset: Point,
+    areas: &mut Vec<(Point, Size, &'a Destination)>,
+    texts: &mut Vec<(Point, &'a str)>,
+) {
+    for (position, item) in frame.items() {
+        let position = offset + *position;
+        match item {
+            FrameItem::Group(group) => collect_items(&group.frame, position, areas, texts),
+            FrameItem::Text(text) => texts.push((position, text.text.as_str())),
+            FrameItem::Link(destination, size) => areas.push((position, *size, destination)),
+            _ => {}
+        }
+    }
+}
+
+/// Resolves the destination of a link.
+fn target(document: &Document, destination: &Destination) -> HyperlinkTarget {
+    match destination {
+        Destination::Url(url) => HyperlinkTarget::Url(url.as_str().to_string()),
+        Destination::Position(position) => {
+            HyperlinkTarget::Internal { page: position.page.get(), label: None }
+        }
+        Destination::Location(location) => HyperlinkTarget::Internal {
+            page: document.introspector.page(*location).get(),
+            label: document
+                .introspector
+                .query_first(&Selector::Location(*location))
+                .and_then(|element| element.label())
+                .map(|label| label.as_str().to_string()),
+        },
+    }
+}
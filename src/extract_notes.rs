@@ -0,0 +1,96 @@
+use serde::{Deserialize, Serialize};
+use typst::{
+    foundations::{Label, Repr, Selector, Value},
+    introspection::MetadataElem,
+};
+
+use crate::{compile::compile_document, world::SystemWorld, CompileParams, TypsterError};
+
+/// Speaker notes of a page of a compiled document.
+///
+/// See also [`extract_notes()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageNotes {
+    /// 1-based page number.
+    pub page: usize,
+
+    /// Notes of the page, separated by blank lines if there are several, or empty if there are
+    /// none.
+    pub text: String,
+}
+
+/// Extracts the speaker notes of a slide deck, or of any Typst document, page by page, e.g. to
+/// generate a presenter view or a printed notes handout.
+///
+/// Notes are `metadata` elements with the label, e.g. `#metadata[Greet the audience] <notes>`,
+/// placed on the slide they belong to. Their value is a string or content, whose plain text is
+/// taken, or an array of them, one per line. Other values are taken as their representation. The
+/// output file of `params` is not written.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`] of the document.
+/// - `label` - Label of the notes, without angle brackets, e.g. `notes`.
+///
+/// # Returns
+///
+/// Result containing the [`PageNotes`] of every page of the document, in page order, or a
+/// [`TypsterError`] telling why it failed, e.g. [`TypsterError::Diagnostics`] if the document has
+/// errors.
+///
+/// # Example
+///
+/// Following is an example of how to use the `extract_notes` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("slides.typ").build();
+/// for notes in typster::extract_notes(&params, "notes").unwrap() {
+///     println!("--- slide {} ---\n{}", notes.page, notes.text);
+/// }
+/// ```
+pub fn extract_notes(params: &CompileParams, label: &str) -> Result<Vec<PageNotes>, TypsterError> {
+    let world = SystemWorld::new(params)?;
+    let document = compile_document(&world)?;
+    let introspector = &document.introspector;
+
+    let mut pages = (1..=document.pages.len())
+        .map(|page| PageNotes { page, text: String::new() })
+        .collect::<Vec<_>>();
+    for element in introspector.query(&Selector::Label(Label::new(label))) {
+        let (Some(location), Some(metadata)) =
+            (element.location(), element.to_packed::<MetadataElem>())
+        else {
+            continue;
+        };
+        let Some(notes) = pages.get_mut(introspector.page(location).get() - 1) else {
+            continue;
+        };
+        let text = note_text(&metadata.value);
+        if text.is_empty() {
+            continue;
+        }
+        if !notes.text.is_empty() {
+            notes.text.push_str("\n\n");
+        }
+        notes.text.push_str(&text);
+    }
+
+    Ok(pages)
+}
+
+/// Returns the text of the value of a note.
+fn note_text(value: &Value) -> String {
+    let text = match value {
+        Value::None => String::new(),
+        Value::Str(text) => text.to_string(),
+        Value::Content(content) => content.plain_text().to_string(),
+        Value::Array(items) => items
+            .iter()
+            .map(note_text)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        value => value.repr().to_string(),
+    };
+    text.trim().to_string()
+}
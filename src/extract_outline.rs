@@ -0,0 +1,85 @@
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+use typst::{
+    foundations::{NativeElement, StyleChain},
+    introspection::Location,
+    model::{Document, HeadingElem},
+};
+
+use crate::{compile::compile_document, world::SystemWorld, CompileParams, TypsterError};
+
+/// A heading of a compiled document.
+///
+/// See also [`extract_outline()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heading {
+    /// 1-based level of the heading, i.e. `1` for `= Introduction`.
+    pub level: usize,
+
+    /// Plain text of the heading, without its numbering.
+    pub text: String,
+
+    /// 1-based page number the heading is on.
+    pub page: usize,
+
+    /// Label of the heading without the angle brackets, e.g. `intro` for `= Introduction <intro>`,
+    /// if any.
+    pub label: Option<String>,
+}
+
+/// Extracts the headings of a Typst document, in the order of the document, e.g. to build a table
+/// of contents sidebar of a static site without parsing the PDF file.
+///
+/// Like the `outline()` function of Typst, headings with `outlined: false` are skipped. The
+/// output file of `params` is not written.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`] of the document.
+///
+/// # Returns
+///
+/// Result containing the [`Heading`]s of the document.
+///
+/// # Example
+///
+/// Following is an example of how to use the `extract_outline` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("report.typ").build();
+/// for heading in typster::extract_outline(&params).unwrap() {
+///     println!("{}{} ({})", "  ".repeat(heading.level - 1), heading.text, heading.page);
+/// }
+/// ```
+pub fn extract_outline(params: &CompileParams) -> Result<Vec<Heading>, Box<dyn Error>> {
+    let world = SystemWorld::new(params).map_err(TypsterError::from)?;
+    let document = compile_document(&world)?;
+
+    Ok(headings(&document).into_iter().map(|(heading, _)| heading).collect())
+}
+
+/// Collects the outlined headings of a compiled document with their locations.
+pub(crate) fn headings(document: &Document) -> Vec<(Heading, Location)> {
+    let mut headings = vec![];
+    for element in document.introspector.query(&HeadingElem::elem().select()) {
+        let Some(heading) = element.to_packed::<HeadingElem>() else {
+            continue;
+        };
+        // Elements are materialized once laid out, so the default styles resolve their fields.
+        if !heading.outlined(StyleChain::default()) {
+            continue;
+        }
+        let Some(location) = element.location() else {
+            continue;
+        };
+        let heading = Heading {
+            level: heading.resolve_level(StyleChain::default()).get(),
+            text: heading.body.plain_text().to_string(),
+            page: document.introspector.page(location).get(),
+            label: element.label().map(|label| label.as_str().to_string()),
+        };
+        headings.push((heading, location));
+    }
+    headings
+}
@@ -0,0 +1,47 @@
+use std::{collections::BTreeSet, error::Error, path::Path};
+
+use lopdf::Document;
+
+/// Extracts the text of pages of a PDF file, e.g. to verify the content of a compiled document in
+/// tests, or to index it for search, without external tools like `pdftotext`.
+///
+/// The text is extracted in the order it's drawn, with a line break after each text object, so
+/// it's not necessarily in the reading order, nor laid out as on the page. Text of fonts without a
+/// known encoding or `ToUnicode` map, and text in form XObjects, is not extracted.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file.
+/// - `pages` - 1-based page numbers to extract the text of, in ascending order regardless of the
+///   order given, with duplicates removed. Empty to extract the text of all pages.
+///
+/// # Returns
+///
+/// Result containing the text of each page.
+///
+/// # Example
+///
+/// Following is an example of how to use the `extract_pdf_text` function:
+///
+/// ```no_run
+/// let text = typster::extract_pdf_text(std::path::Path::new("sample.pdf"), &[1]).unwrap();
+/// assert!(text[0].contains("Typster"));
+/// ```
+pub fn extract_pdf_text(path: &Path, pages: &[u32]) -> Result<Vec<String>, Box<dyn Error>> {
+    let doc = Document::load(path)?;
+    let count = doc.get_pages().len() as u32;
+
+    let pages = if pages.is_empty() {
+        (1..=count).collect::<BTreeSet<_>>()
+    } else {
+        pages.iter().copied().collect()
+    };
+    if let Some(page) = pages.iter().find(|&&page| page == 0 || page > count) {
+        return Err(format!("page {page} does not exist").into());
+    }
+
+    pages
+        .into_iter()
+        .map(|page| doc.extract_text(&[page]).map_err(|e| e.into()))
+        .collect()
+}
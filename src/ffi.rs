@@ -0,0 +1,245 @@
+use std::{
+    ffi::{c_char, CStr, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::PathBuf,
+    ptr,
+};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    compile, format, set_permission, update_metadata, CompileParams, FormatParams, PdfMetadata,
+    PermissionParams, TypsterError,
+};
+
+/// Status code returned by the C-compatible functions, e.g. [`typster_compile()`]. Values are
+/// stable across releases.
+///
+/// Parameters of the functions are passed as JSON of the corresponding Rust structs, so that their
+/// signatures stay the same when fields are added. On failure, a function stores an error message
+/// into its `error` argument, if not null, which must be freed with [`typster_string_free()`]. On
+/// success, `error` is set to null.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TypsterStatus {
+    /// The function succeeded.
+    Ok = 0,
+    /// An argument is null, not UTF-8, or not valid JSON of the expected parameters.
+    InvalidArgument = 1,
+    /// See [`TypsterError::World`].
+    World = 2,
+    /// See [`TypsterError::Diagnostics`].
+    Diagnostics = 3,
+    /// See [`TypsterError::Io`].
+    Io = 4,
+    /// See [`TypsterError::Pdf`].
+    Pdf = 5,
+    /// See [`TypsterError::Font`].
+    Font = 6,
+    /// See [`TypsterError::Params`].
+    Params = 7,
+    /// See [`TypsterError::LimitExceeded`].
+    LimitExceeded = 8,
+    /// See [`TypsterError::TimedOut`].
+    TimedOut = 9,
+    /// See [`TypsterError::Image`].
+    Image = 10,
+    /// The function panicked, or failed for any other reason.
+    Unknown = 99,
+}
+
+impl From<&TypsterError> for TypsterStatus {
+    fn from(err: &TypsterError) -> Self {
+        match err {
+            TypsterError::World(_) => Self::World,
+            TypsterError::Diagnostics(_) => Self::Diagnostics,
+            TypsterError::Io(_) => Self::Io,
+            TypsterError::Pdf(_) => Self::Pdf,
+            TypsterError::Font(_) => Self::Font,
+            TypsterError::Params(_) => Self::Params,
+            TypsterError::LimitExceeded(_) => Self::LimitExceeded,
+            TypsterError::TimedOut(_) => Self::TimedOut,
+            TypsterError::Image(_) => Self::Image,
+        }
+    }
+}
+
+/// A failure of a C-compatible function, before it's split into a status and a message.
+struct Failure(TypsterStatus, String);
+
+impl From<TypsterError> for Failure {
+    fn from(err: TypsterError) -> Self {
+        Self((&err).into(), err.to_string())
+    }
+}
+
+/// Compiles a Typst document, the same way as [`compile()`].
+///
+/// # Arguments
+///
+/// - `params` - JSON of [`CompileParams`], e.g. `{"input": "main.typ", "output": "main.pdf"}`. An
+///   omitted `output` defaults to the input with the `pdf` extension.
+/// - `error` - Where to store the error message on failure. May be null.
+///
+/// # Safety
+///
+/// `params` must be a valid pointer to a null-terminated string, and `error` must be null or a
+/// valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn typster_compile(
+    params: *const c_char,
+    error: *mut *mut c_char,
+) -> TypsterStatus {
+    run(error, || {
+        let mut params: CompileParams = json(params)?;
+        if params.output.as_os_str().is_empty() {
+            params.output = params.input.with_extension("pdf");
+        }
+        compile(&params)?;
+        Ok(())
+    })
+}
+
+/// Formats a Typst file, the same way as [`format()`].
+///
+/// # Arguments
+///
+/// - `params` - JSON of [`FormatParams`], e.g. `{"input": "main.typ", "column": 80}`.
+/// - `output` - Where to store the formatted text on success, which must be freed with
+///   [`typster_string_free()`].
+/// - `error` - Where to store the error message on failure. May be null.
+///
+/// # Safety
+///
+/// `params` must be a valid pointer to a null-terminated string, `output` must be a valid pointer
+/// to write to, and `error` must be null or a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn typster_format(
+    params: *const c_char,
+    output: *mut *mut c_char,
+    error: *mut *mut c_char,
+) -> TypsterStatus {
+    run(error, || {
+        if output.is_null() {
+            return Err(Failure(TypsterStatus::InvalidArgument, "output is null".to_string()));
+        }
+        let params: FormatParams = json(params)?;
+        let formatted = to_c_string(format(&params)?)?;
+        *output = formatted;
+        Ok(())
+    })
+}
+
+/// Updates the metadata of a PDF file in place, the same way as [`update_metadata()`].
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file.
+/// - `metadata` - JSON of [`PdfMetadata`], e.g. `{"title": "Title", "author": "Author"}`.
+/// - `error` - Where to store the error message on failure. May be null.
+///
+/// # Safety
+///
+/// `path` and `metadata` must be valid pointers to null-terminated strings, and `error` must be
+/// null or a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn typster_update_metadata(
+    path: *const c_char,
+    metadata: *const c_char,
+    error: *mut *mut c_char,
+) -> TypsterStatus {
+    run(error, || {
+        let path = PathBuf::from(string(path)?);
+        let metadata: PdfMetadata = json(metadata)?;
+        update_metadata(&path, &metadata)?;
+        Ok(())
+    })
+}
+
+/// Sets the permission of a PDF file, the same way as [`set_permission()`].
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file.
+/// - `params` - JSON of [`PermissionParams`], e.g. `{"owner_password": "owner"}`.
+/// - `error` - Where to store the error message on failure. May be null.
+///
+/// # Safety
+///
+/// `input`, `output`, and `params` must be valid pointers to null-terminated strings, and `error`
+/// must be null or a valid pointer to write to.
+#[no_mangle]
+pub unsafe extern "C" fn typster_set_permission(
+    input: *const c_char,
+    output: *const c_char,
+    params: *const c_char,
+    error: *mut *mut c_char,
+) -> TypsterStatus {
+    run(error, || {
+        let input = PathBuf::from(string(input)?);
+        let output = PathBuf::from(string(output)?);
+        let params: PermissionParams = json(params)?;
+        set_permission(input, output, &params)?;
+        Ok(())
+    })
+}
+
+/// Frees a string returned by the other functions. Does nothing if `s` is null.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by the other functions, which has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn typster_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Runs a function, catching panics, and stores its error message, if any, into `error`.
+unsafe fn run(error: *mut *mut c_char, f: impl FnOnce() -> Result<(), Failure>) -> TypsterStatus {
+    let result = catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panicked".to_string());
+        Err(Failure(TypsterStatus::Unknown, message))
+    });
+
+    let (status, message) = match result {
+        Ok(()) => (TypsterStatus::Ok, None),
+        Err(Failure(status, message)) => (status, Some(message)),
+    };
+    if !error.is_null() {
+        // Interior null bytes are replaced, so that the message is never lost.
+        *error = message
+            .and_then(|message| CString::new(message.replace('\0', " ")).ok())
+            .map_or(ptr::null_mut(), CString::into_raw);
+    }
+    status
+}
+
+/// Reads a null-terminated UTF-8 string.
+unsafe fn string<'a>(s: *const c_char) -> Result<&'a str, Failure> {
+    if s.is_null() {
+        return Err(Failure(TypsterStatus::InvalidArgument, "argument is null".to_string()));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|err| Failure(TypsterStatus::InvalidArgument, err.to_string()))
+}
+
+/// Reads a null-terminated string as JSON of `T`.
+unsafe fn json<T: DeserializeOwned>(s: *const c_char) -> Result<T, Failure> {
+    serde_json::from_str(string(s)?)
+        .map_err(|err| Failure(TypsterStatus::InvalidArgument, format!("invalid JSON: {err}")))
+}
+
+/// Converts a string into one to be returned to the caller.
+fn to_c_string(s: String) -> Result<*mut c_char, Failure> {
+    CString::new(s)
+        .map(CString::into_raw)
+        .map_err(|err| Failure(TypsterStatus::Unknown, err.to_string()))
+}
@@ -0,0 +1,392 @@
+use std::{collections::HashMap, error::Error, path::Path};
+
+use lopdf::{decode_text_string, text_string, Dictionary, Document, Object, ObjectId, Stream};
+
+/// Field flag of radio buttons, i.e. bit position 16.
+pub(crate) const RADIO: i64 = 1 << 15;
+/// Field flag of push buttons, i.e. bit position 17.
+pub(crate) const PUSH_BUTTON: i64 = 1 << 16;
+
+/// Fills in form fields, i.e. AcroForm fields, of a PDF file, e.g. a government form.
+///
+/// Values of text fields and choice fields are set as is. Check boxes are checked with `true`,
+/// `yes`, `on`, or the name of their on state, and unchecked with `false`, `no`, `off`, or an empty
+/// string. Radio buttons are selected with the name of the button's on state, e.g. `Choice1`.
+///
+/// Appearances of text fields are generated with the default appearance of the field for ASCII
+/// values, and left to the viewer otherwise.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file, which may be the same as the input file.
+/// - `values` - Values keyed by the fully qualified field names, e.g. `applicant.name`.
+///
+/// # Example
+///
+/// Following is an example of how to use the `fill_form` function:
+///
+/// ```no_run
+/// typster::fill_form(
+///     std::path::Path::new("form.pdf"),
+///     std::path::Path::new("form-filled.pdf"),
+///     &std::collections::HashMap::from([
+///         ("applicant.name".to_string(), "Jane Doe".to_string()),
+///         ("agree".to_string(), "true".to_string()),
+///     ]),
+/// )
+/// .unwrap();
+/// ```
+pub fn fill_form(
+    input: &Path,
+    output: &Path,
+    values: &HashMap<String, String>,
+) -> Result<(), Box<dyn Error>> {
+    let mut doc = Document::load(input)?;
+    let fields = terminal_fields(&doc)?.into_iter().collect::<HashMap<_, _>>();
+
+    // Fail before any change if a field doesn't exist, e.g. because of a typo.
+    let mut unknown = values
+        .keys()
+        .filter(|name| !fields.contains_key(*name))
+        .collect::<Vec<_>>();
+    if !unknown.is_empty() {
+        unknown.sort();
+        return Err(format!("unknown form fields: {unknown:?}").into());
+    }
+
+    let form_da = doc
+        .catalog()?
+        .get_deref(b"AcroForm", &doc)
+        .and_then(Object::as_dict)
+        .and_then(|form| form.get(b"DA"))
+        .and_then(Object::as_str)
+        .map(|da| da.to_vec())
+        .ok();
+    let mut need_appearances = false;
+
+    for (name, value) in values {
+        let field = fields[name];
+        let field_type =
+            inherited(&doc, field, b"FT").and_then(|ft| ft.as_name().ok().map(<[u8]>::to_vec));
+        let flags = inherited(&doc, field, b"Ff")
+            .and_then(|ff| ff.as_i64().ok())
+            .unwrap_or(0);
+        let widgets = widgets(&doc, field)?;
+
+        match field_type.as_deref() {
+            Some(b"Tx") | Some(b"Ch") => {
+                doc.get_dictionary_mut(field)?.set("V", text_string(value));
+                let da = inherited(&doc, field, b"DA")
+                    .and_then(|da| da.as_str().ok().map(<[u8]>::to_vec))
+                    .or_else(|| form_da.clone());
+                for &widget in &widgets {
+                    match &da {
+                        Some(da) if value.chars().all(|c| matches!(c, ' '..='~')) => {
+                            text_appearance(&mut doc, widget, da, value)?
+                        }
+                        _ => {
+                            doc.get_dictionary_mut(widget)?.remove(b"AP");
+                            need_appearances = true;
+                        }
+                    }
+                }
+            }
+            Some(b"Btn") if flags & PUSH_BUTTON != 0 => {
+                return Err(format!("push button {name} has no value to fill in").into());
+            }
+            Some(b"Btn") => {
+                let radio = flags & RADIO != 0;
+                let states = widgets
+                    .iter()
+                    .map(|&widget| on_state(&doc, widget))
+                    .collect::<Vec<_>>();
+                let selected = match value.to_lowercase().as_str() {
+                    "false" | "no" | "off" | "" => None,
+                    "true" | "yes" | "on" if !radio => {
+                        Some(states.iter().flatten().next().ok_or("no on state")?.clone())
+                    }
+                    _ if states.iter().flatten().any(|state| state == value.as_bytes()) => {
+                        Some(value.as_bytes().to_vec())
+                    }
+                    _ => return Err(format!("invalid value for {name}: {value}").into()),
+                };
+
+                let state = selected.clone().unwrap_or(b"Off".to_vec());
+                doc.get_dictionary_mut(field)?.set("V", Object::Name(state));
+                for (&widget, on) in widgets.iter().zip(states) {
+                    let state = match (on, &selected) {
+                        (Some(on), Some(selected)) if on == *selected => on,
+                        _ => b"Off".to_vec(),
+                    };
+                    doc.get_dictionary_mut(widget)?.set("AS", Object::Name(state));
+                }
+            }
+            _ => return Err(format!("unsupported type of form field {name}").into()),
+        }
+    }
+
+    if need_appearances {
+        let form = doc.catalog()?.get(b"AcroForm")?.clone();
+        match form {
+            Object::Reference(id) => doc.get_dictionary_mut(id)?.set("NeedAppearances", true),
+            _ => doc
+                .catalog_mut()?
+                .get_mut(b"AcroForm")?
+                .as_dict_mut()?
+                .set("NeedAppearances", true),
+        }
+    }
+
+    doc.prune_objects();
+    doc.save(output)?;
+
+    Ok(())
+}
+
+/// Queries the values to fill in a form with [`fill_form()`] from a Typst document, i.e. the
+/// dictionary of the `metadata` element with the label, e.g.
+/// `#metadata((name: "Jane Doe", agree: true)) <form>`.
+///
+/// Strings are used as is, `none` as an empty string, and other values as their Typst
+/// representation, e.g. `true` or `42`.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`](crate::CompileParams) of the Typst document. The output is
+///   ignored.
+/// - `label` - Label of the `metadata` element, without angle brackets, e.g. `form`.
+///
+/// # Returns
+///
+/// Result containing the values keyed by the field names.
+///
+/// # Example
+///
+/// Following is an example of how to use the `query_form_values` function:
+///
+/// ```no_run
+/// let values = typster::query_form_values(
+///     &typster::CompileParams::builder().input("application.typ").build(),
+///     "form",
+/// )
+/// .unwrap();
+/// typster::fill_form(
+///     std::path::Path::new("form.pdf"),
+///     std::path::Path::new("form-filled.pdf"),
+///     &values,
+/// )
+/// .unwrap();
+/// ```
+#[cfg(feature = "compile")]
+pub fn query_form_values(
+    params: &crate::CompileParams,
+    label: &str,
+) -> Result<HashMap<String, String>, Box<dyn Error>> {
+    use typst::{
+        foundations::{Label, Repr, Selector, Value},
+        introspection::MetadataElem,
+    };
+
+    use crate::{compile::compile_document, world::SystemWorld, TypsterError};
+
+    let world = SystemWorld::new(params).map_err(TypsterError::from)?;
+    let document = compile_document(&world)?;
+
+    let elements = document.introspector.query(&Selector::Label(Label::new(label)));
+    if elements.is_empty() {
+        return Err(format!("no element labeled <{label}>").into());
+    }
+    let mut values = HashMap::new();
+    for element in elements {
+        let Some(Value::Dict(dict)) =
+            element.to_packed::<MetadataElem>().map(|metadata| &metadata.value)
+        else {
+            return Err(format!("<{label}> is not metadata of a dictionary").into());
+        };
+        for (key, value) in dict.iter() {
+            let value = match value {
+                Value::Str(value) => value.as_str().to_string(),
+                Value::None => String::new(),
+                value => value.repr().to_string(),
+            };
+            values.insert(key.as_str().to_string(), value);
+        }
+    }
+    Ok(values)
+}
+
+/// Returns the terminal fields of the form, i.e. the ones which hold values, with their fully
+/// qualified names, in the order of the form.
+pub(crate) fn terminal_fields(doc: &Document) -> Result<Vec<(String, ObjectId)>, Box<dyn Error>> {
+    let mut fields = vec![];
+    if let Ok(roots) = doc
+        .catalog()?
+        .get_deref(b"AcroForm", doc)
+        .and_then(Object::as_dict)
+        .and_then(|form| form.get_deref(b"Fields", doc))
+        .and_then(Object::as_array)
+    {
+        collect_fields(doc, roots, "", &mut fields, 0);
+    }
+    Ok(fields)
+}
+
+/// Collects the terminal fields, descending into the child fields of `Kids`.
+fn collect_fields(
+    doc: &Document,
+    kids: &[Object],
+    parent: &str,
+    fields: &mut Vec<(String, ObjectId)>,
+    depth: usize,
+) {
+    // Guard against cycles of malformed files.
+    if depth > 32 {
+        return;
+    }
+    for kid in kids {
+        let Ok(id) = kid.as_reference() else {
+            continue;
+        };
+        let Ok(field) = doc.get_dictionary(id) else {
+            continue;
+        };
+        let partial = field
+            .get(b"T")
+            .ok()
+            .and_then(|name| decode_text_string(name).ok())
+            .unwrap_or_default();
+        let name = match (parent.is_empty(), partial.is_empty()) {
+            (_, true) => parent.to_string(),
+            (true, false) => partial,
+            (false, false) => format!("{parent}.{partial}"),
+        };
+        // Kids with names are child fields, while the others are widgets of this field.
+        let children = field
+            .get_deref(b"Kids", doc)
+            .and_then(Object::as_array)
+            .map(|kids| {
+                kids.iter()
+                    .filter(|kid| {
+                        doc.dereference(kid)
+                            .and_then(|(_, kid)| kid.as_dict())
+                            .is_ok_and(|kid| kid.has(b"T"))
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        if children.is_empty() {
+            fields.push((name, id));
+        } else {
+            collect_fields(doc, &children, &name, fields, depth + 1);
+        }
+    }
+}
+
+/// Returns the attribute of the field, which may be inherited from its ancestors.
+pub(crate) fn inherited<'a>(doc: &'a Document, field: ObjectId, key: &[u8]) -> Option<&'a Object> {
+    let mut node = doc.get_dictionary(field).ok()?;
+    // Guard against cycles of malformed files.
+    for _ in 0..32 {
+        if let Ok(value) = node.get_deref(key, doc) {
+            return Some(value);
+        }
+        node = node.get_deref(b"Parent", doc).and_then(Object::as_dict).ok()?;
+    }
+    None
+}
+
+/// Returns the widget annotations of the terminal field, which may be the field itself.
+pub(crate) fn widgets(doc: &Document, field: ObjectId) -> Result<Vec<ObjectId>, Box<dyn Error>> {
+    match doc.get_dictionary(field)?.get_deref(b"Kids", doc) {
+        Ok(Object::Array(kids)) => {
+            Ok(kids.iter().filter_map(|kid| kid.as_reference().ok()).collect())
+        }
+        _ => Ok(vec![field]),
+    }
+}
+
+/// Returns the name of the on state of a check box or radio button widget, i.e. its normal
+/// appearance other than `Off`.
+pub(crate) fn on_state(doc: &Document, widget: ObjectId) -> Option<Vec<u8>> {
+    doc.get_dictionary(widget)
+        .ok()?
+        .get_deref(b"AP", doc)
+        .and_then(Object::as_dict)
+        .and_then(|ap| ap.get_deref(b"N", doc))
+        .and_then(Object::as_dict)
+        .ok()?
+        .iter()
+        .map(|(state, _)| state)
+        .find(|state| state.as_slice() != b"Off")
+        .cloned()
+}
+
+/// Generates the normal appearance of a single-line text field widget with the value, using the
+/// default appearance, e.g. `/Helv 0 Tf 0 g`, where the font size 0 means auto.
+fn text_appearance(
+    doc: &mut Document,
+    widget: ObjectId,
+    da: &[u8],
+    value: &str,
+) -> Result<(), Box<dyn Error>> {
+    let rect = doc
+        .get_dictionary(widget)?
+        .get(b"Rect")
+        .and_then(Object::as_array)?
+        .iter()
+        .map(|value| value.as_float().ok())
+        .collect::<Option<Vec<_>>>()
+        .filter(|rect| rect.len() == 4)
+        .ok_or("invalid widget rectangle")?;
+    let (width, height) = ((rect[2] - rect[0]).abs(), (rect[3] - rect[1]).abs());
+
+    // Replace the font size of the default appearance, if auto.
+    let da = String::from_utf8_lossy(da);
+    let tokens = da.split_whitespace().collect::<Vec<_>>();
+    let size = match tokens.iter().position(|&token| token == "Tf") {
+        Some(i) if i >= 1 => tokens[i - 1].parse::<f32>().unwrap_or(0.0),
+        _ => 0.0,
+    };
+    let size = if size > 0.0 { size } else { ((height - 4.0) * 0.7).clamp(4.0, 12.0) };
+    let da = match tokens.iter().position(|&token| token == "Tf") {
+        Some(i) if i >= 1 => {
+            let mut tokens = tokens.iter().map(|token| token.to_string()).collect::<Vec<_>>();
+            tokens[i - 1] = size.to_string();
+            tokens.join(" ")
+        }
+        _ => da.to_string(),
+    };
+
+    let escaped = value.replace('\\', "\\\\").replace('(', "\\(").replace(')', "\\)");
+    let baseline = (height - size) / 2.0 + size * 0.22;
+    let content = format!(
+        "/Tx BMC q 1 1 {} {} re W n BT {da} 2 {baseline} Td ({escaped}) Tj ET Q EMC",
+        width - 2.0,
+        height - 2.0
+    );
+
+    let mut appearance = Dictionary::new();
+    appearance.set("Type", Object::Name(b"XObject".to_vec()));
+    appearance.set("Subtype", Object::Name(b"Form".to_vec()));
+    appearance.set(
+        "BBox",
+        vec![Object::Integer(0), Object::Integer(0), Object::Real(width), Object::Real(height)],
+    );
+    // The font of the default appearance is one of the default resources of the form.
+    if let Ok(resources) = doc
+        .catalog()?
+        .get_deref(b"AcroForm", doc)
+        .and_then(Object::as_dict)
+        .and_then(|form| form.get(b"DR"))
+    {
+        appearance.set("Resources", resources.clone());
+    }
+    let appearance = doc.add_object(Stream::new(appearance, content.into_bytes()));
+
+    let mut ap = Dictionary::new();
+    ap.set("N", appearance);
+    doc.get_dictionary_mut(widget)?.set("AP", ap);
+    Ok(())
+}
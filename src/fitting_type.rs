@@ -0,0 +1,48 @@
+use std::fmt::Display;
+
+/// Fitting type for the PDF output, used by the live preview of `watch()` (Google Chrome only,
+/// maybe) and by the open action of `set_viewer_preferences()`.
+#[derive(Debug, Clone, Default)]
+pub enum FittingType {
+    /// Fit to page
+    Page,
+    /// Fit to width
+    #[default]
+    Width,
+    /// Fit to height
+    Height,
+}
+
+impl FittingType {
+    /// Name of the corresponding PDF destination type.
+    #[cfg(feature = "pdf_edit")]
+    pub(crate) fn destination(&self) -> &'static [u8] {
+        match self {
+            FittingType::Page => b"Fit",
+            FittingType::Width => b"FitH",
+            FittingType::Height => b"FitV",
+        }
+    }
+}
+
+impl From<&str> for FittingType {
+    fn from(value: &str) -> Self {
+        match value {
+            "page" => FittingType::Page,
+            "width" => FittingType::Width,
+            "height" => FittingType::Height,
+            _ => FittingType::Page,
+        }
+    }
+}
+
+impl Display for FittingType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // https://chromium.googlesource.com/chromium/src/+/6363f8da6aae63abedc87f60b629585f10bd8940/chrome/browser/resources/pdf/open_pdf_params_parser.js#61
+        match self {
+            FittingType::Page => write!(f, "fit"),
+            FittingType::Width => write!(f, "fith"),
+            FittingType::Height => write!(f, "fitv"),
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::{error::Error, fmt::Write, path::Path};
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+use crate::{merge_pdfs::inline_inherited_attributes, overlay::overlay};
+
+/// Annotation flag of hidden annotations, i.e. bit position 2.
+const HIDDEN: i64 = 1 << 1;
+/// Annotation flag of annotations which are not displayed, i.e. bit position 6.
+const NO_VIEW: i64 = 1 << 5;
+
+/// Form XObject of an appearance, with the transformation matrix which places it on the page.
+type Placement = (ObjectId, [f32; 6]);
+
+/// Flattens form fields and annotations of a PDF file, i.e. burns their appearances into the page
+/// contents, e.g. to produce a non-editable final document after filling in a form with
+/// [`fill_form()`](crate::fill_form()).
+///
+/// Links are kept as they are. Hidden annotations, and those without an appearance, e.g. form
+/// fields whose appearance is left to the viewer, are removed without a trace, as is the form
+/// itself.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file, which may be the same as the input file.
+///
+/// # Example
+///
+/// Following is an example of how to use the `flatten` function:
+///
+/// ```no_run
+/// typster::flatten(
+///     std::path::Path::new("form-filled.pdf"),
+///     std::path::Path::new("form-final.pdf"),
+/// )
+/// .unwrap();
+/// ```
+pub fn flatten(input: &Path, output: &Path) -> Result<(), Box<dyn Error>> {
+    let mut doc = Document::load(input)?;
+    let pages = doc.get_pages().into_values().collect::<Vec<_>>();
+    inline_inherited_attributes(&mut doc, &pages)?;
+
+    for page in pages {
+        let annots = match doc.get_dictionary(page)?.get(b"Annots") {
+            Ok(annots) => doc.dereference(annots)?.1.as_array()?.clone(),
+            Err(_) => continue,
+        };
+
+        let mut links = vec![];
+        let mut xobjects = Dictionary::new();
+        let mut content = String::new();
+        for annot in annots {
+            let Ok((_, Object::Dictionary(dict))) = doc.dereference(&annot) else {
+                continue;
+            };
+            if dict
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .is_ok_and(|subtype| subtype == b"Link")
+            {
+                links.push(annot);
+                continue;
+            }
+            if dict.get(b"F").and_then(Object::as_i64).unwrap_or(0) & (HIDDEN | NO_VIEW) != 0 {
+                continue;
+            }
+            let Some((appearance, [a, b, c, d, e, f])) = appearance(&mut doc, &annot)? else {
+                continue;
+            };
+            let name = format!("A{}", xobjects.len() + 1);
+            writeln!(content, "q {a} {b} {c} {d} {e} {f} cm /{name} Do Q")?;
+            xobjects.set(name.as_str(), appearance);
+        }
+
+        if !xobjects.is_empty() {
+            let mut resources = Dictionary::new();
+            resources.set("XObject", xobjects);
+            let mut form = Dictionary::new();
+            form.set("Type", Object::Name(b"XObject".to_vec()));
+            form.set("Subtype", Object::Name(b"Form".to_vec()));
+            form.set("BBox", doc.get_dictionary(page)?.get(b"MediaBox")?.clone());
+            form.set("Resources", resources);
+            let form = doc.add_object(Stream::new(form, content.into_bytes()));
+            // Annotation rectangles are in the default user space of the page.
+            overlay(&mut doc, page, form, [1.0, 0.0, 0.0, 1.0, 0.0, 0.0])?;
+        }
+
+        let page = doc.get_dictionary_mut(page)?;
+        if links.is_empty() {
+            page.remove(b"Annots");
+        } else {
+            page.set("Annots", links);
+        }
+    }
+
+    doc.catalog_mut()?.remove(b"AcroForm");
+    doc.prune_objects();
+    doc.save(output)?;
+
+    Ok(())
+}
+
+/// Returns the normal appearance of the annotation, in its current state for check boxes and radio
+/// buttons, with the transformation matrix which maps it onto the annotation rectangle.
+fn appearance(doc: &mut Document, annot: &Object) -> Result<Option<Placement>, Box<dyn Error>> {
+    let annot = doc.dereference(annot)?.1.as_dict()?;
+    let Ok(normal) = annot
+        .get_deref(b"AP", doc)
+        .and_then(Object::as_dict)
+        .and_then(|ap| ap.get(b"N"))
+    else {
+        return Ok(None);
+    };
+    let normal = match doc.dereference(normal)? {
+        (_, Object::Dictionary(states)) => {
+            match annot
+                .get(b"AS")
+                .and_then(Object::as_name)
+                .and_then(|state| states.get(state))
+            {
+                Ok(normal) => normal.clone(),
+                Err(_) => return Ok(None),
+            }
+        }
+        _ => normal.clone(),
+    };
+    let Some(rect) = floats(annot.get(b"Rect").ok()) else {
+        return Ok(None);
+    };
+
+    let (id, stream) = match normal {
+        Object::Reference(id) => (id, doc.get_object(id)?.as_stream()?.clone()),
+        Object::Stream(stream) => (doc.add_object(stream.clone()), stream),
+        _ => return Ok(None),
+    };
+    let Some(bbox) = floats(stream.dict.get(b"BBox").ok()) else {
+        return Ok(None);
+    };
+    let [a, b, c, d, e, f] = floats(stream.dict.get(b"Matrix").ok())
+        .and_then(|matrix| <[f32; 6]>::try_from(matrix).ok())
+        .unwrap_or([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    // Fit the bounding box, transformed by the matrix of the form, to the annotation rectangle.
+    let corners = [(bbox[0], bbox[1]), (bbox[0], bbox[3]), (bbox[2], bbox[1]), (bbox[2], bbox[3])]
+        .map(|(x, y)| (a * x + c * y + e, b * x + d * y + f));
+    let (x0, x1) = corners
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), &(x, _)| (min.min(x), max.max(x)));
+    let (y0, y1) = corners
+        .iter()
+        .fold((f32::MAX, f32::MIN), |(min, max), &(_, y)| (min.min(y), max.max(y)));
+    if x1 - x0 <= 0.0 || y1 - y0 <= 0.0 {
+        return Ok(None);
+    }
+    let (left, bottom) = (rect[0].min(rect[2]), rect[1].min(rect[3]));
+    let sx = (rect[2] - rect[0]).abs() / (x1 - x0);
+    let sy = (rect[3] - rect[1]).abs() / (y1 - y0);
+
+    // Appearance streams are form XObjects, even if not marked as such.
+    let dict = &mut doc.get_object_mut(id)?.as_stream_mut()?.dict;
+    dict.set("Type", Object::Name(b"XObject".to_vec()));
+    dict.set("Subtype", Object::Name(b"Form".to_vec()));
+
+    Ok(Some((id, [sx, 0.0, 0.0, sy, left - x0 * sx, bottom - y0 * sy])))
+}
+
+/// Returns the numbers of the array, e.g. a rectangle.
+fn floats(array: Option<&Object>) -> Option<Vec<f32>> {
+    array?
+        .as_array()
+        .ok()?
+        .iter()
+        .map(|value| value.as_float().ok())
+        .collect::<Option<Vec<_>>>()
+        .filter(|values| values.len() >= 4)
+}
@@ -1,6 +1,18 @@
-use std::{collections::HashMap, fs, path::PathBuf, sync::OnceLock};
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, OnceLock,
+    },
+    thread::{available_parallelism, scope},
+    time::Instant,
+};
 
 use fontdb::{Database, Source};
+use tracing::{debug, debug_span, warn};
 use typst::text::{Font, FontBook, FontInfo};
 
 /// Searches for fonts.
@@ -41,34 +53,35 @@ impl FontSearcher {
     }
 
     /// Search everything that is available.
+    ///
+    /// Font files are found first, then their faces are read in parallel, since reading the
+    /// metadata of each face dominates for large font directories. The time taken by each step is
+    /// reported as a `tracing` event. Font paths which aren't directories are skipped with a
+    /// warning.
     pub fn search(&mut self, font_paths: &[PathBuf]) {
-        let mut db = Database::new();
+        let start = Instant::now();
 
         // Font paths have highest priority.
-        for path in font_paths {
-            db.load_fonts_dir(path);
-        }
-
-        for face in db.faces() {
-            let path = match &face.source {
-                Source::File(path) | Source::SharedFile(path, _) => path,
-                // We never add binary sources to the database, so there
-                // shouln't be any.
-                Source::Binary(_) => continue,
-            };
+        let files = debug_span!("walk").in_scope(|| {
+            let mut seen = HashSet::new();
+            let mut files = vec![];
+            for path in font_paths {
+                if !path.is_dir() {
+                    warn!("font directory not found (searched at {})", path.display());
+                }
+                walk(path, &mut seen, &mut files);
+            }
+            files
+        });
+        let walked = start.elapsed();
 
-            let info = db
-                .with_face_data(face.id, FontInfo::new)
-                .expect("database must contain this font");
+        let faces = debug_span!("scan", files = files.len()).in_scope(|| scan(&files));
+        let scanned = start.elapsed() - walked;
+        debug!(files = files.len(), faces = faces.len(), ?walked, ?scanned, "searched fonts");
 
-            if let Some(info) = info {
-                self.book.push(info);
-                self.fonts.push(FontSlot {
-                    path: path.clone(),
-                    index: face.index,
-                    font: OnceLock::new(),
-                });
-            }
+        for (path, index, info) in faces {
+            self.book.push(info);
+            self.fonts.push(FontSlot { path, index, font: OnceLock::new() });
         }
 
         self.add_embedded();
@@ -166,6 +179,73 @@ impl FontSearcher {
     }
 }
 
+/// Collects the font files under a directory, recursively, following symlinks but visiting each
+/// directory once.
+fn walk(dir: &Path, seen: &mut HashSet<PathBuf>, files: &mut Vec<PathBuf>) {
+    if !seen.insert(dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf())) {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        if metadata.is_dir() {
+            walk(&path, seen, files);
+        } else if metadata.is_file() && is_font(&path) {
+            files.push(path);
+        }
+    }
+}
+
+/// Whether a path has the extension of a font file or a font collection.
+fn is_font(path: &Path) -> bool {
+    path.extension().and_then(OsStr::to_str).is_some_and(|ext| {
+        ["ttf", "ttc", "otf", "otc"]
+            .iter()
+            .any(|e| ext.eq_ignore_ascii_case(e))
+    })
+}
+
+/// Reads the faces of font files in parallel, in the order of the files and then of the faces in
+/// each file. Files which are not valid fonts are skipped.
+fn scan(files: &[PathBuf]) -> Vec<(PathBuf, u32, FontInfo)> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(files.len()));
+    let workers = available_parallelism().map(|n| n.get()).unwrap_or(1).min(files.len());
+
+    scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(path) = files.get(i) else {
+                    break;
+                };
+                let mut db = Database::new();
+                if db.load_font_file(path).is_err() {
+                    continue;
+                }
+                let faces = db
+                    .faces()
+                    .filter(|face| matches!(face.source, Source::File(_) | Source::SharedFile(..)))
+                    .filter_map(|face| {
+                        let info = db.with_face_data(face.id, FontInfo::new)??;
+                        Some((path.clone(), face.index, info))
+                    })
+                    .collect::<Vec<_>>();
+                results.lock().unwrap().push((i, faces));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().flat_map(|(_, faces)| faces).collect()
+}
+
 #[allow(unused_imports)]
 use crate::CompileParams; // For documentation purposes.
 
@@ -191,19 +271,19 @@ use crate::CompileParams; // For documentation purposes.
 /// Following is an example of how to use the `list_fonts` function:
 ///
 /// ```rust
-/// let params = typster::CompileParams {
-///     input: std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-///         .join("examples")
-///         .join("sample.typ"),
-///     output: std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-///         .join("examples")
-///         .join("sample.pdf"),
-///     font_paths: vec![],
-///     dict: vec![("input".to_string(), "value".to_string())],
-///     ppi: None,
-///     package_path: None,
-///     package_cache_path: None,
-/// };
+/// let params = typster::CompileParams::builder()
+///     .input(
+///         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///             .join("examples")
+///             .join("sample.typ"),
+///     )
+///     .output(
+///         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///             .join("examples")
+///             .join("sample.pdf"),
+///     )
+///     .dict("input", "value")
+///     .build();
 ///
 /// typster::list_fonts(&params.font_paths)
 ///     .iter()
@@ -1,12 +1,16 @@
 use std::{fs::read_to_string, path::PathBuf};
 
+use serde::{Deserialize, Serialize};
 use typst_syntax::parse;
 use typstyle_core::{strip_trailing_whitespace, AttrStore, PrettyPrinter, PrinterConfig};
 
+use crate::TypsterError;
+
 /// Parameters for a formatting operation.
 ///
 /// See also [`format()`].
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct FormatParams {
     /// Path to the input Typst file.
     pub input: PathBuf,
@@ -23,7 +27,7 @@ pub struct FormatParams {
 ///
 /// # Returns
 ///
-/// String containing the formatted Typst file.
+/// Result containing the formatted Typst file, or [`TypsterError::Io`] if the input can't be read.
 ///
 /// # Example
 ///
@@ -40,12 +44,17 @@ pub struct FormatParams {
 /// println!("{}", typster::format(&params).map_or_else(|why| why.to_string(), |s| s));
 /// ```
 
-pub fn format(params: &FormatParams) -> Result<String, Box<dyn std::error::Error>> {
-    let root = parse(&read_to_string(&params.input)?);
-    let config = PrinterConfig { max_width: params.column, ..Default::default() };
+pub fn format(params: &FormatParams) -> Result<String, TypsterError> {
+    Ok(format_text(&read_to_string(&params.input)?, params.column))
+}
+
+/// Formats a Typst source held in memory, e.g. an unsaved editor buffer.
+pub(crate) fn format_text(text: &str, column: usize) -> String {
+    let root = parse(text);
+    let config = PrinterConfig { max_width: column, ..Default::default() };
     let attr_store = AttrStore::new(&root);
     let markup = root.cast().unwrap();
     let printer = PrettyPrinter::new(config, attr_store);
     let doc = printer.convert_markup(markup);
-    Ok(strip_trailing_whitespace(&doc.pretty(params.column).to_string()))
+    strip_trailing_whitespace(&doc.pretty(column).to_string())
 }
@@ -0,0 +1,241 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use chrono::DateTime;
+use lopdf::{decode_text_string, Dictionary, Document, Object};
+use xmp_toolkit::{
+    xmp_ns::{DC, PDF, XMP, XMP_RIGHTS},
+    XmpMeta,
+};
+
+use crate::{
+    update_metadata::from_xmp_date, xmp::read_xmp, DocumentId, InfoValue, LocalizedText,
+    PdfMetadata,
+};
+
+/// Keys of the document information dictionary which are mapped to [`PdfMetadata`] fields, or
+/// managed by [`update_metadata()`](crate::update_metadata()), rather than custom properties.
+const STANDARD_INFO_KEYS: [&str; 9] = [
+    "Title",
+    "Author",
+    "Subject",
+    "Keywords",
+    "Creator",
+    "Producer",
+    "CreationDate",
+    "ModDate",
+    "Trapped",
+];
+
+/// Reads the metadata of a PDF file.
+///
+/// Values are taken from the document information dictionary, falling back to the XMP metadata.
+/// Keywords are taken from the XMP `dc:subject` array first, so that they aren't split at commas.
+/// Title and subject are taken from the XMP metadata first if they have language alternatives.
+/// Custom XMP namespaces are not read, since they can't be told apart from the standard ones.
+/// Language is taken from the `Lang` of the document catalog, which Typst writes, falling back to
+/// the XMP metadata. Copyright status, copyright notice, dates, and the extended Dublin Core
+/// fields, e.g. publisher, are only available in the XMP metadata.
+/// Missing values are left empty.
+///
+/// # Argument
+///
+/// - `path` - Path to the PDF file.
+///
+/// # Returns
+///
+/// Result containing the [`PdfMetadata`] of the file.
+///
+/// # Example
+///
+/// Following is an example of how to use the `get_metadata` function:
+///
+/// ```rust
+/// let output = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///     .join("examples")
+///     .join("sample.pdf");
+///
+/// // Compile a document first
+/// let params = typster::CompileParams::builder()
+///     .input(
+///         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///             .join("examples")
+///             .join("sample.typ"),
+///     )
+///     .output(output.clone())
+///     .font_path("assets")
+///     .dict("input", "value")
+///     .build();
+/// match typster::compile(&params) {
+///     Ok(duration) => println!("Compilation succeeded in {duration:?}"),
+///     Err(why) => eprintln!("{why}"),
+/// }
+///
+/// // Then read metadata
+/// let metadata = typster::get_metadata(&output).unwrap();
+/// println!("{}", metadata.title);
+/// ```
+pub fn get_metadata(path: &Path) -> Result<PdfMetadata, Box<dyn std::error::Error>> {
+    let doc = Document::load(path)?;
+    let xmp = match read_xmp(&doc) {
+        Some(xmp) => xmp,
+        None => XmpMeta::new()?,
+    };
+    let info = doc
+        .trailer
+        .get_deref(b"Info", &doc)
+        .and_then(|info| info.as_dict())
+        .cloned()
+        .unwrap_or_default();
+
+    let text = |key: &str| {
+        info.get(key.as_bytes())
+            .ok()
+            .and_then(|value| decode_text_string(value).ok())
+    };
+    let localized = |ns: &str, name: &str| {
+        xmp.localized_text(ns, name, None, "x-default")
+            .map(|(value, _)| value.value)
+    };
+
+    Ok(PdfMetadata {
+        title: alternatives(&xmp, DC, "title")
+            .or_else(|| text("Title").or_else(|| localized(DC, "title")).map(Into::into))
+            .unwrap_or_default(),
+        author: text("Author")
+            .or_else(|| xmp.array_item(DC, "creator", 1).map(|value| value.value))
+            .unwrap_or_default(),
+        application: text("Creator")
+            .or_else(|| xmp.property(XMP, "CreatorTool").map(|value| value.value))
+            .or_else(|| text("Producer"))
+            .unwrap_or_default(),
+        subject: alternatives(&xmp, DC, "description")
+            .or_else(|| {
+                text("Subject")
+                    .or_else(|| localized(DC, "description"))
+                    .map(Into::into)
+            })
+            .unwrap_or_default(),
+        copyright_status: xmp
+            .property_bool(XMP_RIGHTS, "Marked")
+            .map(|value| value.value)
+            .unwrap_or_default(),
+        copyright_notice: localized(DC, "rights").unwrap_or_default(),
+        keywords: Some(
+            xmp.property_array(DC, "subject")
+                .map(|value| value.value)
+                .collect::<Vec<_>>(),
+        )
+        .filter(|keywords| !keywords.is_empty())
+        .or_else(|| {
+            text("Keywords").map(|keywords| {
+                keywords
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|keyword| !keyword.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+        })
+        .unwrap_or_default(),
+        language: doc
+            .catalog()
+            .and_then(|catalog| catalog.get(b"Lang"))
+            .ok()
+            .and_then(|lang| decode_text_string(lang).ok())
+            .filter(|lang| !lang.is_empty())
+            .or_else(|| xmp.array_item(DC, "language", 1).map(|value| value.value))
+            .unwrap_or_default(),
+        custom_properties: custom_properties(&info),
+        custom_namespaces: vec![],
+        creation_date: xmp
+            .property_date(XMP, "CreateDate")
+            .and_then(|date| from_xmp_date(&date.value)),
+        modification_date: xmp
+            .property_date(XMP, "ModifyDate")
+            .and_then(|date| from_xmp_date(&date.value)),
+        preserve_dates: false,
+        publisher: xmp
+            .array_item(DC, "publisher", 1)
+            .map(|value| value.value)
+            .unwrap_or_default(),
+        contributors: xmp
+            .property_array(DC, "contributor")
+            .map(|value| value.value)
+            .collect(),
+        date: xmp
+            .array_item(DC, "date", 1)
+            .and_then(|value| DateTime::parse_from_rfc3339(&value.value).ok()),
+        identifier: xmp
+            .property(DC, "identifier")
+            .map(|value| value.value)
+            .unwrap_or_default(),
+        document_type: xmp
+            .array_item(DC, "type", 1)
+            .map(|value| value.value)
+            .unwrap_or_default(),
+        trapped: info
+            .get(b"Trapped")
+            .and_then(Object::as_name)
+            .ok()
+            .map(|name| String::from_utf8_lossy(name).to_string())
+            .or_else(|| xmp.property(PDF, "Trapped").map(|value| value.value))
+            .and_then(|trapped| trapped.parse().ok()),
+        info_entries: info_entries(&info),
+        record_history: false,
+        document_id: DocumentId::Preserve,
+    })
+}
+
+/// Reads all language alternatives of an XMP property, or [`None`] if there are none besides
+/// `x-default`.
+fn alternatives(xmp: &XmpMeta, ns: &str, name: &str) -> Option<LocalizedText> {
+    let texts = (1..=xmp.array_len(ns, name) as i32)
+        .filter_map(|i| {
+            let lang = xmp.property(ns, &format!("{name}[{i}]/?xml:lang"))?.value;
+            let text = xmp.array_item(ns, name, i)?.value;
+            Some((lang, text))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    texts
+        .keys()
+        .any(|lang| lang != "x-default")
+        .then_some(LocalizedText::Alternatives(texts))
+}
+
+/// Collects the non-standard entries of the document information dictionary with other values than
+/// text strings, which are collected by [`custom_properties()`].
+fn info_entries(info: &Dictionary) -> BTreeMap<String, InfoValue> {
+    info.iter()
+        .filter_map(|(key, value)| {
+            let key = String::from_utf8_lossy(key).to_string();
+            if STANDARD_INFO_KEYS.contains(&key.as_str()) {
+                return None;
+            }
+            let value = match value {
+                Object::Name(name) => InfoValue::Name(String::from_utf8_lossy(name).to_string()),
+                Object::Integer(integer) => InfoValue::Integer(*integer),
+                Object::Real(real) => InfoValue::Real(*real),
+                Object::Boolean(boolean) => InfoValue::Boolean(*boolean),
+                _ => return None,
+            };
+            Some((key, value))
+        })
+        .collect()
+}
+
+/// Collects the non-standard entries of the document information dictionary.
+fn custom_properties(info: &Dictionary) -> HashMap<String, String> {
+    info.iter()
+        .filter_map(|(key, value)| {
+            let key = String::from_utf8_lossy(key).to_string();
+            if STANDARD_INFO_KEYS.contains(&key.as_str()) {
+                return None;
+            }
+            decode_text_string(value).ok().map(|value| (key, value))
+        })
+        .collect()
+}
@@ -0,0 +1,107 @@
+use std::{error::Error, path::PathBuf};
+
+use qpdf::{QPdf, QPdfDictionary, QPdfScalar};
+use serde::{Deserialize, Serialize};
+
+use crate::PrintPermission;
+
+/// Encryption and permission information of a PDF file.
+///
+/// See also [`get_permission()`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionInfo {
+    /// Whether the document is encrypted.
+    pub encrypted: bool,
+
+    /// Revision of the standard security handler, e.g. `6` for AES-256. [`None`] if not encrypted.
+    pub revision: Option<i32>,
+
+    /// Length of the encryption key in bits, e.g. `256` for AES-256. [`None`] if not encrypted or
+    /// not specified.
+    pub key_length: Option<i32>,
+
+    /// Content copying for accessibility is allowed.
+    pub allow_accessibility: bool,
+
+    /// Page extraction is allowed.
+    pub allow_extract: bool,
+
+    /// Document assembly is allowed.
+    pub allow_assemble: bool,
+
+    /// Commenting and form filling are allowed.
+    pub allow_annotate_and_form: bool,
+
+    /// Form field fill-in or signing is allowed.
+    pub allow_form_filling: bool,
+
+    /// Other modifications are allowed.
+    pub allow_modify_other: bool,
+
+    /// Printing is allowed.
+    pub allow_print: PrintPermission,
+}
+
+/// Reads encryption and permission information of a PDF file.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file.
+/// - `password` - User password of the PDF file, if required to open it.
+///
+/// # Returns
+///
+/// Result containing the [`EncryptionInfo`] of the file. All operations are allowed if the file is
+/// not encrypted.
+///
+/// # Example
+///
+/// Following is an example of how to use the `get_permission` function:
+///
+/// ```no_run
+/// let info =
+///     typster::get_permission(std::path::PathBuf::from("sample-protected.pdf"), None).unwrap();
+/// println!("encrypted: {}, print: {}", info.encrypted, info.allow_print);
+/// ```
+pub fn get_permission(
+    path: PathBuf,
+    password: Option<&str>,
+) -> Result<EncryptionInfo, Box<dyn Error>> {
+    let pdf = match password {
+        Some(password) => QPdf::read_encrypted(path, password)?,
+        None => QPdf::read(path)?,
+    };
+
+    let encrypt = pdf
+        .get_trailer()
+        .and_then(|trailer| trailer.get("/Encrypt"))
+        .map(QPdfDictionary::from);
+    let integer = |key: &str| {
+        encrypt
+            .as_ref()
+            .and_then(|encrypt| encrypt.get(key))
+            .map(|value| QPdfScalar::from(value).as_i32())
+    };
+
+    // The permission flags of the standard security handler, with all bits set, i.e. everything
+    // allowed, if the document is not encrypted.
+    let permissions = integer("/P").unwrap_or(-1);
+    let allowed = |bit: u32| permissions & (1 << (bit - 1)) != 0;
+
+    Ok(EncryptionInfo {
+        encrypted: pdf.is_encrypted(),
+        revision: integer("/R"),
+        key_length: integer("/Length"),
+        allow_accessibility: allowed(10),
+        allow_extract: allowed(5),
+        allow_assemble: allowed(11),
+        allow_annotate_and_form: allowed(6),
+        allow_form_filling: allowed(9),
+        allow_modify_other: allowed(4),
+        allow_print: match (allowed(3), allowed(12)) {
+            (true, true) => PrintPermission::Full,
+            (true, false) => PrintPermission::Low,
+            _ => PrintPermission::None,
+        },
+    })
+}
@@ -0,0 +1,172 @@
+use std::{error::Error, fmt::Write, path::Path};
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+use crate::{merge_pdfs::inline_inherited_attributes, overlay::display_box};
+
+/// Arrangement of pages on sheets for [`impose()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Imposition {
+    /// Two pages side by side on each side of a sheet, ordered so that the sheets printed
+    /// double-sided, flipped on the short edge, and folded in the middle make a booklet. Blank
+    /// pages are added to make the number of pages a multiple of four.
+    Booklet,
+    /// The given number of pages, either 2 or 4, on each sheet, from left to right, then top to
+    /// bottom.
+    NUp(u32),
+}
+
+/// Rearranges and scales pages of a PDF file onto sheets of the same size as the first page, e.g.
+/// to print a compiled A4 document as an A5 booklet on A4 paper.
+///
+/// Each page is scaled to fit its cell of the sheet, keeping the aspect ratio. The outline, page
+/// labels, links, form fields, and other annotations are dropped, as they refer to the original
+/// pages.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file, which may be the same as the input file.
+/// - `imposition` - [`Imposition`] to arrange pages with.
+///
+/// # Example
+///
+/// Following is an example of how to use the `impose` function:
+///
+/// ```no_run
+/// typster::impose(
+///     std::path::Path::new("sample.pdf"),
+///     std::path::Path::new("booklet.pdf"),
+///     typster::Imposition::Booklet,
+/// )
+/// .unwrap();
+/// ```
+pub fn impose(input: &Path, output: &Path, imposition: Imposition) -> Result<(), Box<dyn Error>> {
+    if !matches!(imposition, Imposition::Booklet | Imposition::NUp(2 | 4)) {
+        return Err("only 2 or 4 pages per sheet are supported".into());
+    }
+
+    let mut doc = Document::load(input)?;
+    let pages = doc.get_pages().into_values().collect::<Vec<_>>();
+    if pages.is_empty() {
+        return Err("the PDF file has no pages".into());
+    }
+    inline_inherited_attributes(&mut doc, &pages)?;
+
+    let mut forms = Vec::with_capacity(pages.len());
+    for &page in &pages {
+        forms.push(page_to_form(&mut doc, page)?);
+    }
+
+    // Order of the pages on the sheets, where `None` is a blank page.
+    let (order, per_sheet) = match imposition {
+        Imposition::Booklet => {
+            let count = forms.len().div_ceil(4) * 4;
+            let page = |i: usize| forms.get(i).copied();
+            let order = (0..count / 2)
+                .step_by(2)
+                .flat_map(|i| [page(count - 1 - i), page(i), page(i + 1), page(count - 2 - i)])
+                .collect::<Vec<_>>();
+            (order, 2)
+        }
+        Imposition::NUp(n) => (forms.into_iter().map(Some).collect(), n as usize),
+    };
+
+    // Sheets have the size of the first page, turned for 2 pages per sheet.
+    let first = &order.iter().flatten().next().ok_or("the PDF file has no pages")?.1;
+    let (width, height) =
+        if per_sheet == 2 { (first.height, first.width) } else { (first.width, first.height) };
+    let (columns, rows) = match per_sheet {
+        2 if width >= height => (2, 1),
+        2 => (1, 2),
+        _ => (2, 2),
+    };
+    let (cell_width, cell_height) = (width / columns as f32, height / rows as f32);
+
+    let root = doc.catalog()?.get(b"Pages").and_then(Object::as_reference)?;
+    let mut sheets = vec![];
+    for cells in order.chunks(per_sheet) {
+        let mut xobjects = Dictionary::new();
+        let mut content = String::new();
+        for (i, (form, size)) in cells
+            .iter()
+            .enumerate()
+            .filter_map(|(i, cell)| Some((i, cell.as_ref()?)))
+        {
+            let (column, row) = (i % columns, i / columns);
+            let scale = (cell_width / size.width).min(cell_height / size.height);
+            let x = cell_width * column as f32 + (cell_width - size.width * scale) / 2.0;
+            let y =
+                height - cell_height * (row + 1) as f32 + (cell_height - size.height * scale) / 2.0;
+            let name = format!("P{}", i + 1);
+            writeln!(content, "q {scale} 0 0 {scale} {x} {y} cm /{name} Do Q")?;
+            xobjects.set(name.as_str(), *form);
+        }
+
+        let mut resources = Dictionary::new();
+        resources.set("XObject", xobjects);
+        let contents = doc.add_object(Stream::new(Dictionary::new(), content.into_bytes()));
+        let mut sheet = Dictionary::new();
+        sheet.set("Type", Object::Name(b"Page".to_vec()));
+        sheet.set("Parent", root);
+        sheet.set(
+            "MediaBox",
+            vec![Object::Integer(0), Object::Integer(0), Object::Real(width), Object::Real(height)],
+        );
+        sheet.set("Resources", resources);
+        sheet.set("Contents", contents);
+        sheets.push(Object::Reference(doc.add_object(sheet)));
+    }
+
+    let root = doc.get_dictionary_mut(root)?;
+    root.set("Count", sheets.len() as i64);
+    root.set("Kids", sheets);
+    let catalog = doc.catalog_mut()?;
+    for key in [b"Outlines".as_slice(), b"PageLabels", b"Dests", b"OpenAction", b"AcroForm"] {
+        catalog.remove(key);
+    }
+
+    doc.prune_objects();
+    doc.save(output)?;
+
+    Ok(())
+}
+
+/// Size of a page as displayed, in points.
+#[derive(Clone, Copy)]
+struct Size {
+    width: f32,
+    height: f32,
+}
+
+/// Turns the page into a form XObject, which draws the page as displayed, i.e. upright, with the
+/// origin at its bottom-left corner.
+fn page_to_form(doc: &mut Document, page: ObjectId) -> Result<(ObjectId, Size), Box<dyn Error>> {
+    let content = doc.get_page_content(page)?;
+    let page = doc.get_dictionary(page)?;
+    let display = display_box(page)?;
+
+    let mut form = Dictionary::new();
+    form.set("Type", Object::Name(b"XObject".to_vec()));
+    form.set("Subtype", Object::Name(b"Form".to_vec()));
+    form.set("BBox", page.get(b"CropBox").or_else(|_| page.get(b"MediaBox"))?.clone());
+    form.set("Matrix", invert(display.matrix)?.map(Object::Real).to_vec());
+    for key in [b"Resources".as_slice(), b"Group"] {
+        if let Ok(value) = page.get(key) {
+            form.set(key, value.clone());
+        }
+    }
+    let mut form = Stream::new(form, content);
+    let _ = form.compress();
+
+    Ok((doc.add_object(form), Size { width: display.width, height: display.height }))
+}
+
+/// Inverts the transformation matrix.
+fn invert([a, b, c, d, e, f]: [f32; 6]) -> Result<[f32; 6], Box<dyn Error>> {
+    let det = a * d - b * c;
+    if det == 0.0 {
+        return Err("singular transformation matrix".into());
+    }
+    Ok([d / det, -b / det, -c / det, a / det, (c * f - d * e) / det, (b * e - a * f) / det])
+}
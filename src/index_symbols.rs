@@ -0,0 +1,275 @@
+use std::{
+    error::Error,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use typst_syntax::{
+    ast::{self, AstNode},
+    FileId, LinkedNode, Source, SyntaxKind, VirtualPath,
+};
+
+use crate::SourceLocation;
+
+/// A symbol defined in a Typst project.
+///
+/// See also [`index_symbols()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Symbol {
+    /// Name of the symbol, e.g. `template` for `#let template(title, body) = ...`, `intro` for
+    /// `<intro>`, or `knuth` for a bibliography entry.
+    pub name: String,
+
+    /// Kind of the symbol.
+    pub kind: SymbolKind,
+
+    /// Where the symbol is defined.
+    pub location: SourceLocation,
+
+    /// Parameters of a function as written, e.g. `(title, body)`, or [`None`] for other kinds.
+    pub signature: Option<String>,
+}
+
+/// Kind of a [`Symbol`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SymbolKind {
+    /// Function defined with `#let name(..) = ...`.
+    Function,
+    /// Variable defined with `#let`, including each variable of a destructuring pattern.
+    #[default]
+    Variable,
+    /// Label attached to content, e.g. `<intro>`.
+    Label,
+    /// Key of an entry of a bibliography file loaded with `bibliography()`.
+    BibliographyKey,
+}
+
+/// Scans the Typst files of a project and returns the symbols they define, e.g. to provide an
+/// editor with go-to-definition and completion data.
+///
+/// Functions and variables are collected from the `let` bindings at the top level of each file,
+/// i.e. those other files can import, and labels from the whole files. Bibliography keys are
+/// collected from the Hayagriva YAML and BibLaTeX files passed to `bibliography()` as string
+/// literals. Hidden files and directories are skipped. Files are only parsed, not compiled, so a
+/// file with syntax errors still yields the symbols of its valid parts.
+///
+/// # Arguments
+///
+/// - `root` - Path to the root directory of the project.
+///
+/// # Returns
+///
+/// Result containing the [`Symbol`]s of the project, sorted by location.
+///
+/// # Example
+///
+/// Following is an example of how to use the `index_symbols` function:
+///
+/// ```no_run
+/// let symbols = typster::index_symbols(std::path::Path::new("thesis")).unwrap();
+/// for symbol in symbols.iter().filter(|symbol| symbol.kind == typster::SymbolKind::Function) {
+///     let location = &symbol.location;
+///     println!("{}:{}: {}", location.path.display(), location.line, symbol.name);
+/// }
+/// ```
+pub fn index_symbols(root: &Path) -> Result<Vec<Symbol>, Box<dyn Error>> {
+    let mut files = vec![];
+    collect_files(root, Path::new(""), &mut files)?;
+
+    let mut symbols = vec![];
+    let mut bibliographies = vec![];
+    for path in files
+        .iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "typ"))
+    {
+        let id = FileId::new(None, VirtualPath::new(path));
+        let source = Source::new(id, read_to_string(root.join(path))?);
+        let mut indexer = Indexer {
+            source: &source,
+            symbols: &mut symbols,
+            bibliographies: &mut bibliographies,
+        };
+        indexer.visit(&LinkedNode::new(source.root()), true);
+    }
+
+    bibliographies.sort();
+    bibliographies.dedup();
+    for path in bibliographies {
+        // A missing file is reported by the compiler, not by the index.
+        let Ok(text) = read_to_string(root.join(&path)) else {
+            continue;
+        };
+        let keys = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("bib") => biblatex_keys(&text),
+            Some("yml" | "yaml") => yaml_keys(&text),
+            _ => continue,
+        };
+        symbols.extend(keys.into_iter().map(|(name, line, column)| Symbol {
+            name,
+            kind: SymbolKind::BibliographyKey,
+            location: SourceLocation { path: path.clone(), line, column },
+            signature: None,
+        }));
+    }
+
+    symbols.sort_by(|a, b| a.location.cmp(&b.location));
+    Ok(symbols)
+}
+
+/// Collects the paths of the files under a directory relative to the root, skipping hidden ones.
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), Box<dyn Error>> {
+    for entry in read_dir(root.join(dir))? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = dir.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Walks the syntax tree of a source to collect symbols.
+struct Indexer<'a> {
+    source: &'a Source,
+    symbols: &'a mut Vec<Symbol>,
+    /// Paths of the bibliography files relative to the root.
+    bibliographies: &'a mut Vec<PathBuf>,
+}
+
+impl Indexer<'_> {
+    fn visit(&mut self, node: &LinkedNode, top_level: bool) {
+        match node.kind() {
+            SyntaxKind::LetBinding if top_level => self.let_binding(node),
+            SyntaxKind::Label if node.parent_kind() == Some(SyntaxKind::Markup) => {
+                if let Some(label) = node.cast::<ast::Label>() {
+                    self.push(label.get(), SymbolKind::Label, node.offset(), None);
+                }
+            }
+            SyntaxKind::FuncCall => self.bibliography(node),
+            _ => {}
+        }
+        // Bindings in blocks and function bodies are local.
+        let top_level = top_level
+            && !matches!(
+                node.kind(),
+                SyntaxKind::CodeBlock | SyntaxKind::ContentBlock | SyntaxKind::Closure
+            );
+        for child in node.children() {
+            self.visit(&child, top_level);
+        }
+    }
+
+    fn let_binding(&mut self, node: &LinkedNode) {
+        let Some(binding) = node.cast::<ast::LetBinding>() else {
+            return;
+        };
+        match binding.kind() {
+            ast::LetBindingKind::Closure(name) => {
+                let signature = node
+                    .children()
+                    .find(|child| child.kind() == SyntaxKind::Closure)
+                    .and_then(|closure| {
+                        closure.children().find(|child| child.kind() == SyntaxKind::Params)
+                    })
+                    .map(|params| params.get().clone().into_text().to_string());
+                self.push(name.get(), SymbolKind::Function, self.offset(name), signature);
+            }
+            ast::LetBindingKind::Normal(pattern) => {
+                for name in pattern.bindings() {
+                    self.push(name.get(), SymbolKind::Variable, self.offset(name), None);
+                }
+            }
+        }
+    }
+
+    /// Records the paths of a `bibliography()` call given as string literals.
+    fn bibliography(&mut self, node: &LinkedNode) {
+        let Some(call) = node.cast::<ast::FuncCall>() else {
+            return;
+        };
+        if !matches!(call.callee(), ast::Expr::Ident(ident) if ident.get() == "bibliography") {
+            return;
+        }
+        let Some(ast::Arg::Pos(arg)) = call.args().items().next() else {
+            return;
+        };
+        let paths = match arg {
+            ast::Expr::Str(path) => vec![path.get()],
+            ast::Expr::Array(array) => array
+                .items()
+                .filter_map(|item| match item {
+                    ast::ArrayItem::Pos(ast::Expr::Str(path)) => Some(path.get()),
+                    _ => None,
+                })
+                .collect(),
+            _ => return,
+        };
+        for path in paths {
+            let id = self.source.id().join(&path);
+            self.bibliographies.push(id.vpath().as_rootless_path().to_path_buf());
+        }
+    }
+
+    /// Returns the byte offset of a node in the source.
+    fn offset<'a>(&self, node: impl AstNode<'a>) -> usize {
+        self.source.range(node.span()).map_or(0, |range| range.start)
+    }
+
+    fn push(&mut self, name: &str, kind: SymbolKind, offset: usize, signature: Option<String>) {
+        self.symbols.push(Symbol {
+            name: name.to_string(),
+            kind,
+            location: SourceLocation::at(self.source, offset),
+            signature,
+        });
+    }
+}
+
+/// Returns the keys of the entries of a BibLaTeX file with their 1-based lines and columns.
+fn biblatex_keys(text: &str) -> Vec<(String, usize, usize)> {
+    let mut keys = vec![];
+    for (i, line) in text.lines().enumerate() {
+        let Some(rest) = line.trim_start().strip_prefix('@') else {
+            continue;
+        };
+        let Some((entry_type, rest)) = rest.split_once(['{', '(']) else {
+            continue;
+        };
+        if ["comment", "preamble", "string"].contains(&entry_type.trim().to_lowercase().as_str()) {
+            continue;
+        }
+        let key = rest.split(',').next().unwrap_or_default().trim();
+        if key.is_empty() {
+            continue;
+        }
+        let column = line[..line.len() - rest.len()].chars().count()
+            + rest.chars().take_while(|c| c.is_whitespace()).count();
+        keys.push((key.to_string(), i + 1, column + 1));
+    }
+    keys
+}
+
+/// Returns the top-level keys of a Hayagriva YAML file with their 1-based lines and columns.
+fn yaml_keys(text: &str) -> Vec<(String, usize, usize)> {
+    let mut keys = vec![];
+    for (i, line) in text.lines().enumerate() {
+        if line.starts_with([' ', '\t', '#', '-']) {
+            continue;
+        }
+        let Some((key, _)) = line.split_once(':') else {
+            continue;
+        };
+        let key = key.trim().trim_matches(['"', '\'']);
+        if !key.is_empty() {
+            keys.push((key.to_string(), i + 1, 1));
+        }
+    }
+    keys
+}
@@ -0,0 +1,36 @@
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+/// JSON-RPC error codes of the specification.
+pub(crate) const SERVER_ERROR: i64 = -32000;
+pub(crate) const PARSE_ERROR: i64 = -32700;
+pub(crate) const METHOD_NOT_FOUND: i64 = -32601;
+pub(crate) const INVALID_PARAMS: i64 = -32602;
+
+/// A JSON-RPC request or notification.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Request {
+    /// Identifier of the request, or [`None`] for a notification.
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// An error code and message of a JSON-RPC response.
+pub(crate) struct RpcError(pub i64, pub String);
+
+/// Deserializes the parameters of a method.
+pub(crate) fn params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|err| RpcError(INVALID_PARAMS, err.to_string()))
+}
+
+/// Creates a response to a request.
+pub(crate) fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(RpcError(code, message)) => {
+            json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+        }
+    }
+}
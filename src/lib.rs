@@ -5,9 +5,81 @@
 //! You can use this library to:
 //!
 //! - [compile](compile()) a Typst file to a PDF or PNG file
+//! - [export all pages](CompileParams::strip) of a Typst file as one tall PNG or WebP image
+//! - [export the pages](CompileParams::zip_entry) of a Typst file as PNG or SVG images in a zip
+//!   archive
+//! - [recompile](CompilerSession) a Typst file repeatedly, searching fonts only once and reading
+//!   only the changed files
+//! - [compile](compile_to_writer()) a Typst file to a PDF written to any writer, e.g. a socket
+//! - [profile](TimingSession) compilations of a Typst file, and write the trace in the Chrome
+//!   trace format
+//! - [compile](compile_archive()) a Typst project packed in a zip or tar archive
+//! - [compile](compile_in_memory()) a Typst project held in memory, e.g. in a browser with
+//!   WebAssembly
+//! - [extract headings](extract_outline()) of a Typst document
+//! - [count words](count()) and characters of a Typst document
+//! - [extract hyperlinks](extract_links()) of a Typst document
+//! - [extract figures](extract_figures()), tables, and equations of a Typst document
+//! - [extract speaker notes](extract_notes()) of the slides of a Typst document
+//! - [check labels and references](analyze_refs()) of a Typst document
+//! - [compare](diff()) the rendered pages of two Typst documents, or of
+//!   [two PDF files](diff_pdfs()), and highlight the differences
+//! - [query elements](query()) with a label of a Typst document, like `typst query`
+//! - [read document metadata](document_info()) set in a Typst document
+//! - [extract cited works](extract_citations()) of a Typst document
 //! - [format](format()) a Typst file
-//! - [update metadata](update_metadata()) of a PDF file
-//! - [set permission](set_permission()) of a PDF file
+//! - [parse](parse()) a Typst file into a syntax tree
+//! - [index symbols](index_symbols()) defined in a Typst project
+//! - [update metadata](update_metadata()) of a PDF file, or [in memory](update_metadata_bytes())
+//! - [get metadata](get_metadata()) of a PDF file
+//! - [strip metadata](strip_metadata()) from a PDF file
+//! - [export](export_xmp()) or [import](import_xmp()) XMP metadata of a PDF file as a sidecar file
+//! - [set](set_outline()) or [get](get_outline()) outline, a.k.a. bookmarks, of a PDF file
+//! - [set page labels](set_page_labels()) of a PDF file
+//! - [merge](merge_pdfs()) PDF files into one
+//! - [extract and reorder pages](select_pages()) of a PDF file
+//! - [rotate pages](rotate_pages()) of a PDF file
+//! - [number pages](number_pages()), e.g. with Bates numbers, of a PDF file
+//! - [impose](impose()) pages of a PDF file for booklet or N-up printing
+//! - [fill in form fields](fill_form()) of a PDF file, optionally with
+//!   [values queried from a Typst document](query_form_values())
+//! - [read form fields](read_form()) of a PDF file
+//! - [flatten](flatten()) form fields and annotations of a PDF file into its pages
+//! - [extract text](extract_pdf_text()) of a PDF file
+//! - [compare](pdf_equivalent()) PDF files by their content, ignoring identifiers and timestamps
+//! - [audit accessibility](audit_accessibility()), e.g. the language, tagging, and alternative
+//!   text, of a PDF file
+//! - [preflight](preflight()) a PDF file for print, e.g. embedded fonts and image resolution
+//! - [break down the size](size_breakdown()) of a PDF file into fonts, images, and content
+//! - [add links](add_links()) to pages of a PDF file
+//! - [redact](redact()) regions of pages of a PDF file
+//! - [attach files](attach_files()) to a PDF file
+//! - [embed a Factur-X / ZUGFeRD e-invoice](embed_e_invoice()) into a PDF file
+//! - [convert](convert_to_pdfa()) a PDF file toward PDF/A for archiving
+//! - [set viewer preferences](set_viewer_preferences()), i.e. the initial view, of a PDF file
+//! - [linearize](linearize()), a.k.a. fast web view, a PDF file, or
+//!   [in memory](linearize_bytes())
+//! - [optimize](optimize_pdf()) a PDF file to reduce its size
+//! - [render](render_pdf()) pages of an existing PDF file to PNG images
+//! - [stamp](stamp_pdf()) every page of a PDF file with a Typst template, e.g. a watermark
+//! - [set permission](set_permission()) of a PDF file, or [in memory](set_permission_bytes())
+//! - [set permission of many PDF files](set_permission_dir()) in parallel
+//! - [remove permission](remove_permission()) of a PDF file, i.e. decrypt it
+//! - [encrypt](encrypt_for_recipients()) a PDF file to recipient certificates instead of
+//!   passwords
+//! - [get permission](get_permission()), i.e. encryption and allowed operations, of a PDF file
+//! - [compile, update metadata, and set permission](Pipeline) of a Typst file in one call
+//! - [build all documents](Project) of a project described by a `typster.toml` manifest
+//! - [sign](sign_pdf()) a PDF file with a PKCS#12 certificate, and [verify](verify_signatures())
+//!   its signatures
+//! - [call](typster_compile()) the functions above from C, Swift, C#, or C++
+//! - [serve](Server) compile, format, and query requests over JSON-RPC, keeping the fonts and
+//!   files warm between them
+//! - [serve](LanguageServer) diagnostics, formatting, and symbols to editors over the Language
+//!   Server Protocol
+//! - [assert](assert_compiles_like()) that a Typst document renders like its golden images in
+//!   tests
+//! - run the functions above from shell scripts with the `typster` binary of the `cli` feature
 //! - [watch](watch()) for changes in the input Typst file along with its dependencies and recompile
 //!   it when a change is detected
 //!
@@ -25,11 +97,68 @@
 //!
 //! ## Capabilities
 //!
-//! - `compile`: Enables the [`compile()`] and [`list_fonts()`] functions.
+//! - `compile`: Enables the [`compile()`], [`compile_to_writer()`], [`extract_outline()`],
+//!   [`count()`], [`extract_links()`], [`extract_figures()`], [`extract_notes()`],
+//!   [`analyze_refs()`], [`query()`], [`document_info()`], and [`list_fonts()`] functions, and the
+//!   [`CompilerSession`] and [`TimingSession`] structs. This feature also enables the `syntax`
+//!   feature.
+//! - `archive`: Enables the [`compile_archive()`] function. This feature also enables the `compile`
+//!   feature.
+//! - `bibliography`: Enables the [`extract_citations()`] function. This feature also enables the
+//!   `compile` feature.
+//! - `cli`: Builds the `typster` binary, whose subcommands map to the library functions. This
+//!   feature also enables the `format`, `pdf_metadata`, `pdf_permission`, and `watch` features.
+//! - `diff`: Enables the [`diff()`] function, and with the `pdf_render` feature, the
+//!   [`diff_pdfs()`] function. This feature also enables the `compile` feature.
+//! - `e_invoice`: Enables the [`embed_e_invoice()`] function. This feature also enables the
+//!   `pdf_edit` and `pdf_metadata` features.
+//! - `ffi`: Enables the C-compatible [`typster_compile()`], [`typster_format()`],
+//!   [`typster_update_metadata()`], [`typster_set_permission()`], and [`typster_string_free()`]
+//!   functions, declared in `include/typster.h`. This feature also enables the `compile`, `format`,
+//!   `pdf_metadata`, and `pdf_permission` features.
 //! - `format`: Enables the [`format()`] function.
-//! - `pdf_metadata`: Enables the [`update_metadata()`] function.
-//! - `pdf_permission`: Enables the [`set_permission()`] function.
-//! - `watch`: Enables the [`watch()`] function. This feature also enables the `compile` feature.
+//! - `lsp`: Enables the [`LanguageServer`] struct. This feature also enables the `compile` and
+//!   `format` features.
+//! - `pdf_edit`: Enables the [`set_outline()`], [`get_outline()`], [`set_page_labels()`],
+//!   [`merge_pdfs()`], [`select_pages()`], [`rotate_pages()`], [`number_pages()`], [`impose()`],
+//!   [`fill_form()`], [`read_form()`], [`flatten()`], [`extract_pdf_text()`], [`pdf_equivalent()`],
+//!   [`audit_accessibility()`], [`preflight()`], [`size_breakdown()`], [`add_links()`],
+//!   [`redact()`], [`attach_files()`], and [`set_viewer_preferences()`] functions. With the
+//!   `compile` feature, it also enables the [`query_form_values()`] function.
+//! - `pdf_linearize`: Enables the [`linearize()`] and [`linearize_bytes()`] functions, which
+//!   work on any PDF file, encrypted or not, independently of `set_permission()`.
+//! - `pdf_metadata`: Enables the [`update_metadata()`], [`update_metadata_bytes()`],
+//!   [`get_metadata()`], [`strip_metadata()`], [`export_xmp()`], and [`import_xmp()`]
+//!   functions.
+//! - `pdf_optimize`: Enables the [`optimize_pdf()`] function.
+//! - `pdf_permission`: Enables the [`set_permission()`], [`set_permission_bytes()`],
+//!   [`set_permission_dir()`], [`remove_permission()`], and [`get_permission()`] functions.
+//! - `pdf_public_key`: Enables the [`encrypt_for_recipients()`] function. This feature also enables
+//!   the `pdf_permission` feature.
+//! - `pdf_render`: Enables the [`render_pdf()`] function. The PDFium library must be available at
+//!   runtime.
+//! - `pdf_sign`: Enables the [`sign_pdf()`] and [`verify_signatures()`] functions.
+//! - `pdf_stamp`: Enables the [`stamp_pdf()`] function. This feature also enables the `compile`
+//!   and `pdf_edit` features.
+//! - `pdfa`: Enables the [`convert_to_pdfa()`] function. This feature also enables the `pdf_edit`
+//!   and `pdf_metadata` features.
+//! - `pipeline`: Enables the [`Pipeline`] struct. This feature also enables the `compile`,
+//!   `pdf_metadata`, and `pdf_permission` features.
+//! - `project`: Enables the [`Project`] struct. This feature also enables the `pipeline` feature.
+//! - `server`: Enables the [`Server`] struct. This feature also enables the `compile` and `format`
+//!   features.
+//! - `strip`: Enables the [`CompileParams::strip`] field and the [`Strip`] struct, to export
+//!   all pages as one tall PNG or WebP image. This feature also enables the `compile` feature.
+//! - `syntax`: Enables the [`parse()`] and [`index_symbols()`] functions.
+//! - `testing`: Enables the [`assert_compiles_like()`] and [`assert_compiles_like_with()`]
+//!   functions for snapshot tests. This feature also enables the `diff` feature.
+//! - `wasm`: Enables the [`compile_in_memory()`] function, which builds on
+//!   `wasm32-unknown-unknown` since this feature doesn't depend on the filesystem, the network, or
+//!   qpdf.
+//! - `watch`: Enables the [`watch()`] and [`spawn_watch()`] functions, and the [`WatchHandle`]
+//!   struct. This feature also enables the `compile` feature.
+//! - `zip_export`: Enables the zip output of [`compile()`], and the [`CompileParams::zip_entry`]
+//!   field. This feature also enables the `compile` feature.
 //!
 //! ## Fonts Embedding
 //!
@@ -48,38 +177,317 @@
 //! - The crate won’t search system fonts to ensure the reproducibility. All fonts you need should
 //!   be explicitly added via [`CompileParams::font_paths`].
 
+#[cfg(feature = "pdf_edit")]
+pub use add_links::{add_links, Link, LinkTarget};
 #[cfg(feature = "compile")]
-pub use compile::{compile, CompileParams};
+pub use analyze_refs::{analyze_refs, LabelDefinition, RefFinding, RefReport, Reference};
+#[cfg(feature = "archive")]
+pub use archive::{compile_archive, ProjectArchive};
+#[cfg(feature = "pdf_edit")]
+pub use attach_files::{attach_files, Attachment, AttachmentRelationship};
+#[cfg(feature = "pdf_edit")]
+pub use audit_accessibility::{audit_accessibility, AccessibilityFinding, AccessibilityIssue};
+#[cfg(feature = "compile")]
+pub use compile::{
+    compile, compile_to_writer, CompileParams, CompileParamsBuilder, CompileReport, FileAccess,
+    FileAccessHook, Limits, PdfStandard, Sandbox,
+};
+#[cfg(feature = "wasm")]
+pub use compile_in_memory::{compile_in_memory, InMemoryParams};
+#[cfg(feature = "compile")]
+pub use compiler_session::CompilerSession;
+#[cfg(feature = "pdfa")]
+pub use convert_to_pdfa::{convert_to_pdfa, PdfaLevel, PdfaReport};
+#[cfg(feature = "compile")]
+pub use count::{count, SectionStats, TextStats};
+#[cfg(all(feature = "diff", feature = "pdf_render"))]
+pub use diff::diff_pdfs;
+#[cfg(feature = "diff")]
+pub use diff::{diff, PageDiff, PageStatus};
+#[cfg(any(feature = "pdf_metadata", feature = "pdf_permission"))]
+pub use document_id::DocumentId;
+#[cfg(feature = "compile")]
+pub use document_info::{document_info, DocumentInfo};
+#[cfg(feature = "e_invoice")]
+pub use e_invoice::{embed_e_invoice, ConformanceLevel, EInvoiceParams};
+#[cfg(feature = "pdf_public_key")]
+pub use encrypt_for_recipients::{encrypt_for_recipients, RecipientParams};
+#[cfg(any(feature = "compile", feature = "wasm"))]
+pub use error::Diagnostics;
+#[cfg(any(
+    feature = "compile",
+    feature = "format",
+    feature = "pdf_edit",
+    feature = "pdf_metadata",
+    feature = "pdf_permission",
+    feature = "wasm"
+))]
+pub use error::TypsterError;
+#[cfg(feature = "bibliography")]
+pub use extract_citations::{extract_citations, CitedWork};
+#[cfg(feature = "compile")]
+pub use extract_figures::{extract_figures, Figure};
+#[cfg(feature = "compile")]
+pub use extract_links::{extract_links, Hyperlink, HyperlinkTarget};
+#[cfg(feature = "compile")]
+pub use extract_notes::{extract_notes, PageNotes};
+#[cfg(feature = "compile")]
+pub use extract_outline::{extract_outline, Heading};
+#[cfg(feature = "pdf_edit")]
+pub use extract_pdf_text::extract_pdf_text;
+#[cfg(feature = "ffi")]
+pub use ffi::{
+    typster_compile, typster_format, typster_set_permission, typster_string_free,
+    typster_update_metadata, TypsterStatus,
+};
+#[cfg(feature = "pdf_edit")]
+pub use fill_form::fill_form;
+#[cfg(all(feature = "compile", feature = "pdf_edit"))]
+pub use fill_form::query_form_values;
+#[cfg(any(feature = "pdf_edit", feature = "watch"))]
+pub use fitting_type::FittingType;
+#[cfg(feature = "pdf_edit")]
+pub use flatten::flatten;
 #[cfg(feature = "compile")]
 pub use fonts::list_fonts;
 #[cfg(feature = "format")]
 pub use format::{format, FormatParams};
+#[cfg(feature = "pdf_metadata")]
+pub use get_metadata::get_metadata;
 #[cfg(feature = "pdf_permission")]
-pub use set_permission::{set_permission, PermissionParams, PrintPermission};
+pub use get_permission::{get_permission, EncryptionInfo};
+#[cfg(feature = "pdf_edit")]
+pub use impose::{impose, Imposition};
+#[cfg(feature = "syntax")]
+pub use index_symbols::{index_symbols, Symbol, SymbolKind};
+#[cfg(feature = "pdf_linearize")]
+pub use linearize::{linearize, linearize_bytes};
+#[cfg(feature = "lsp")]
+pub use lsp::LanguageServer;
+#[cfg(feature = "pdf_edit")]
+pub use merge_pdfs::merge_pdfs;
+#[cfg(feature = "pdf_edit")]
+pub use number_pages::{number_pages, NumberPosition, NumberingParams};
+#[cfg(feature = "pdf_optimize")]
+pub use optimize_pdf::{optimize_pdf, OptimizeParams, OptimizeReport};
+#[cfg(feature = "pdf_edit")]
+pub use outline::{get_outline, set_outline, OutlineEntry};
+#[cfg(feature = "pdf_edit")]
+pub use page_labels::{set_page_labels, PageLabel, PageLabelStyle};
+#[cfg(feature = "syntax")]
+pub use parse::{parse, SourceLocation, SyntaxError, SyntaxNode, SyntaxTree};
+#[cfg(feature = "pdf_edit")]
+pub use pdf_equivalent::pdf_equivalent;
+#[cfg(feature = "pipeline")]
+pub use pipeline::Pipeline;
+#[cfg(feature = "pdf_edit")]
+pub use preflight::{preflight, PreflightCheck, PreflightFinding, PreflightProfile};
+#[cfg(feature = "project")]
+pub use project::Project;
+#[cfg(feature = "compile")]
+pub use query::query;
+#[cfg(feature = "pdf_edit")]
+pub use read_form::{read_form, FormField, FormFieldType};
+#[cfg(feature = "pdf_edit")]
+pub use redact::{redact, Redaction};
+#[cfg(feature = "pdf_permission")]
+pub use remove_permission::remove_permission;
+#[cfg(feature = "pdf_render")]
+pub use render_pdf::render_pdf;
+#[cfg(feature = "pdf_edit")]
+pub use rotate_pages::rotate_pages;
+#[cfg(feature = "pdf_edit")]
+pub use select_pages::select_pages;
+#[cfg(feature = "server")]
+pub use server::Server;
+#[cfg(feature = "pdf_permission")]
+pub use set_permission::{
+    set_permission, set_permission_bytes, EncryptionLevel, PermissionParams, PrintPermission,
+};
+#[cfg(feature = "pdf_permission")]
+pub use set_permission_dir::{set_permission_dir, PermissionReport};
+#[cfg(feature = "pdf_sign")]
+pub use sign_pdf::{sign_pdf, SigningParams};
+#[cfg(feature = "pdf_edit")]
+pub use size_breakdown::{size_breakdown, FontSize, ImageSize, SizeReport};
+#[cfg(feature = "pdf_stamp")]
+pub use stamp_pdf::{stamp_pdf, StampParams};
+#[cfg(feature = "strip")]
+pub use strip::Strip;
 #[cfg(feature = "pdf_metadata")]
-pub use update_metadata::{update_metadata, PdfMetadata};
+pub use strip_metadata::strip_metadata;
+#[cfg(feature = "testing")]
+pub use testing::{assert_compiles_like, assert_compiles_like_with, Tolerance, BLESS_ENV};
+#[cfg(feature = "compile")]
+pub use timing_session::TimingSession;
+#[cfg(feature = "pdf_metadata")]
+pub use update_metadata::{
+    update_metadata, update_metadata_bytes, InfoValue, LocalizedText, PdfMetadata, Trapped,
+    XmpNamespace,
+};
+#[cfg(feature = "pdf_sign")]
+pub use verify_signatures::{verify_signatures, SignatureReport};
 pub use version::{typst_version, version};
+#[cfg(feature = "pdf_edit")]
+pub use viewer_preferences::{set_viewer_preferences, PageLayout, ViewerPreferences};
 #[cfg(feature = "watch")]
-pub use watch::watch;
-#[cfg(feature = "watch")]
-pub use watch::FittingType;
+pub use watch::{spawn_watch, watch, WatchHandle};
+#[cfg(feature = "pdf_metadata")]
+pub use xmp_sidecar::{export_xmp, import_xmp};
 
+#[cfg(feature = "pdf_edit")]
+mod add_links;
+#[cfg(feature = "compile")]
+mod analyze_refs;
+#[cfg(feature = "archive")]
+mod archive;
+#[cfg(feature = "pdf_edit")]
+mod attach_files;
+#[cfg(feature = "pdf_edit")]
+mod audit_accessibility;
 #[cfg(feature = "compile")]
 mod compile;
+#[cfg(feature = "wasm")]
+mod compile_in_memory;
+#[cfg(feature = "compile")]
+mod compiler_session;
+#[cfg(feature = "pdfa")]
+mod convert_to_pdfa;
+#[cfg(feature = "compile")]
+mod count;
+#[cfg(feature = "diff")]
+mod diff;
+#[cfg(any(feature = "pdf_metadata", feature = "pdf_permission"))]
+mod document_id;
+#[cfg(feature = "compile")]
+mod document_info;
 #[cfg(feature = "compile")]
 mod download;
+#[cfg(feature = "e_invoice")]
+mod e_invoice;
+#[cfg(feature = "pdf_public_key")]
+mod encrypt_for_recipients;
+#[cfg(any(
+    feature = "compile",
+    feature = "format",
+    feature = "pdf_edit",
+    feature = "pdf_metadata",
+    feature = "pdf_permission",
+    feature = "wasm"
+))]
+mod error;
+#[cfg(feature = "bibliography")]
+mod extract_citations;
+#[cfg(feature = "compile")]
+mod extract_figures;
+#[cfg(feature = "compile")]
+mod extract_links;
+#[cfg(feature = "compile")]
+mod extract_notes;
+#[cfg(feature = "compile")]
+mod extract_outline;
+#[cfg(feature = "pdf_edit")]
+mod extract_pdf_text;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "pdf_edit")]
+mod fill_form;
+#[cfg(any(feature = "pdf_edit", feature = "watch"))]
+mod fitting_type;
+#[cfg(feature = "pdf_edit")]
+mod flatten;
 #[cfg(feature = "compile")]
 mod fonts;
 #[cfg(feature = "format")]
 mod format;
+#[cfg(feature = "pdf_metadata")]
+mod get_metadata;
+#[cfg(feature = "pdf_permission")]
+mod get_permission;
+#[cfg(feature = "pdf_edit")]
+mod impose;
+#[cfg(feature = "syntax")]
+mod index_symbols;
+#[cfg(any(feature = "lsp", feature = "server"))]
+mod jsonrpc;
+#[cfg(feature = "pdf_linearize")]
+mod linearize;
+#[cfg(feature = "lsp")]
+mod lsp;
+#[cfg(feature = "pdf_edit")]
+mod merge_pdfs;
+#[cfg(feature = "pdf_edit")]
+mod number_pages;
+#[cfg(feature = "pdf_optimize")]
+mod optimize_pdf;
+#[cfg(feature = "pdf_edit")]
+mod outline;
+#[cfg(feature = "pdf_edit")]
+mod overlay;
 #[cfg(feature = "compile")]
 mod package;
+#[cfg(feature = "pdf_edit")]
+mod page_labels;
+#[cfg(feature = "syntax")]
+mod parse;
+#[cfg(feature = "pdf_edit")]
+mod pdf_equivalent;
+#[cfg(feature = "pipeline")]
+mod pipeline;
+#[cfg(feature = "pdf_edit")]
+mod preflight;
+#[cfg(feature = "project")]
+mod project;
+#[cfg(feature = "compile")]
+mod query;
+#[cfg(feature = "pdf_edit")]
+mod read_form;
+#[cfg(feature = "pdf_edit")]
+mod redact;
+#[cfg(feature = "pdf_permission")]
+mod remove_permission;
+#[cfg(feature = "pdf_render")]
+mod render_pdf;
+#[cfg(feature = "pdf_edit")]
+mod rotate_pages;
+#[cfg(feature = "pdf_edit")]
+mod select_pages;
+#[cfg(feature = "server")]
+mod server;
 #[cfg(feature = "pdf_permission")]
 mod set_permission;
+#[cfg(feature = "pdf_permission")]
+mod set_permission_dir;
+#[cfg(feature = "pdf_sign")]
+mod sign_pdf;
+#[cfg(feature = "pdf_edit")]
+mod size_breakdown;
+#[cfg(feature = "pdf_stamp")]
+mod stamp_pdf;
+#[cfg(feature = "strip")]
+mod strip;
+#[cfg(feature = "pdf_metadata")]
+mod strip_metadata;
+#[cfg(feature = "testing")]
+mod testing;
+#[cfg(feature = "pdf_sign")]
+mod timestamp;
+#[cfg(feature = "compile")]
+mod timing_session;
 #[cfg(feature = "pdf_metadata")]
 mod update_metadata;
+#[cfg(feature = "pdf_sign")]
+mod verify_signatures;
 mod version;
+#[cfg(feature = "pdf_edit")]
+mod viewer_preferences;
 #[cfg(feature = "watch")]
 mod watch;
 #[cfg(feature = "compile")]
 mod world;
+#[cfg(any(feature = "pdf_metadata", feature = "pdf_permission"))]
+mod xmp;
+#[cfg(feature = "pdf_metadata")]
+mod xmp_sidecar;
+#[cfg(feature = "zip_export")]
+mod zip_export;
@@ -0,0 +1,67 @@
+use std::{error::Error, path::PathBuf};
+
+use tracing::instrument;
+
+/// Linearizes a PDF file, a.k.a. fast web view, so that viewers can start rendering the first page
+/// before the whole file is downloaded, e.g. when a large document is served over HTTP.
+///
+/// Note that in-place update is not possible, so the output file must be different from the input
+/// file.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file.
+///
+/// # Example
+///
+/// Following is an example of how to use the `linearize` function:
+///
+/// ```no_run
+/// typster::linearize(
+///     std::path::PathBuf::from("sample.pdf"),
+///     std::path::PathBuf::from("sample-linearized.pdf"),
+/// )
+/// .unwrap();
+/// ```
+#[instrument(skip_all, fields(input = %input.display(), output = %output.display()))]
+pub fn linearize(input: PathBuf, output: PathBuf) -> Result<(), Box<dyn Error>> {
+    if output.exists() && input.canonicalize()? == output.canonicalize()? {
+        return Err("in-place update is not possible".into());
+    }
+    qpdf::QPdf::read(input)?
+        .writer()
+        .linearize(true)
+        .write(output)
+        .map_err(|e| e.into())
+}
+
+/// Linearizes a PDF document in memory, e.g. to serve a freshly compiled document from a web
+/// service without encrypting it or writing it to disk.
+///
+/// Works the same way as [`linearize()`].
+///
+/// # Arguments
+///
+/// - `pdf` - Contents of the PDF file.
+///
+/// # Returns
+///
+/// Result containing the contents of the linearized PDF file.
+///
+/// # Example
+///
+/// Following is an example of how to use the `linearize_bytes` function:
+///
+/// ```no_run
+/// let pdf = std::fs::read("sample.pdf").unwrap();
+/// let linearized = typster::linearize_bytes(&pdf).unwrap();
+/// ```
+#[instrument(skip_all, fields(bytes = pdf.len()))]
+pub fn linearize_bytes(pdf: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    qpdf::QPdf::read_from_memory(pdf)?
+        .writer()
+        .linearize(true)
+        .write_to_memory()
+        .map_err(|e| e.into())
+}
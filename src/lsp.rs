@@ -0,0 +1,494 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use typst::{
+    diag::{Severity, SourceDiagnostic, Warned},
+    World,
+};
+use typst_syntax::Source;
+
+use crate::{
+    compile::typst_compile,
+    compiler_session::MAX_CACHE_AGE,
+    format::format_text,
+    index_symbols,
+    jsonrpc::{params, response, Request, RpcError, METHOD_NOT_FOUND, PARSE_ERROR, SERVER_ERROR},
+    world::SystemWorld,
+    CompileParams, SymbolKind,
+};
+
+/// Line width of formatted documents, unless set by the `column` initialization option.
+const DEFAULT_COLUMN: usize = 120;
+
+/// A minimal [Language Server](https://microsoft.github.io/language-server-protocol/) for Typst,
+/// so that lightweight editors get diagnostics, formatting, and symbols without a dedicated
+/// extension.
+///
+/// Messages are JSON-RPC 2.0 with `Content-Length` headers, as specified by the protocol. The
+/// following are supported:
+///
+/// - Diagnostics: the document is compiled when a file is opened or saved, and the warnings and
+///   errors are published with `textDocument/publishDiagnostics`. Files are read from disk, so
+///   unsaved changes are not taken into account. The world is kept warm between compilations.
+/// - `textDocument/formatting`: formats the open buffer like [`format()`](crate::format()).
+/// - `textDocument/documentSymbol` and `workspace/symbol`: return the symbols of
+///   [`index_symbols()`] of the workspace root.
+///
+/// The `initializationOptions` of the `initialize` request may set `main`, the path of the main
+/// file relative to the workspace root, which is compiled whatever file is saved, `fontPaths`,
+/// and `column`, the line width of formatted documents, `120` by default. Without `main`, the
+/// saved Typst file is compiled itself.
+///
+/// # Example
+///
+/// Following is an example of how to use the `LanguageServer`:
+///
+/// ```no_run
+/// // Run by the editor, e.g. as the `typst` language server command.
+/// typster::LanguageServer::new().serve_stdio().unwrap();
+/// ```
+#[derive(Default)]
+pub struct LanguageServer {
+    /// Workspace root, from the `initialize` request.
+    root: PathBuf,
+    /// Options from the `initialize` request.
+    options: InitializationOptions,
+    /// Text of the open documents, keyed by URI.
+    documents: HashMap<String, String>,
+    /// Worlds kept warm between compilations, keyed by their main file.
+    worlds: HashMap<PathBuf, SystemWorld>,
+    /// Files with published diagnostics, to clear them once fixed.
+    published: HashSet<PathBuf>,
+}
+
+impl LanguageServer {
+    /// Creates a language server, to be initialized by the client.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves the client on stdin and stdout, until it sends `exit` or closes stdin.
+    pub fn serve_stdio(&mut self) -> io::Result<()> {
+        self.serve_connection(io::stdin().lock(), io::stdout().lock())
+    }
+
+    /// Serves messages read from `reader`, and writes responses and notifications to `writer`,
+    /// until the client sends `exit` or `reader` reaches the end.
+    pub fn serve_connection(
+        &mut self,
+        mut reader: impl BufRead,
+        mut writer: impl Write,
+    ) -> io::Result<()> {
+        while let Some(body) = read_message(&mut reader)? {
+            let mut outgoing = vec![];
+            let exit = self.handle(&body, &mut outgoing);
+            for message in &outgoing {
+                let body = serde_json::to_vec(message)?;
+                write!(writer, "Content-Length: {}\r\n\r\n", body.len())?;
+                writer.write_all(&body)?;
+            }
+            writer.flush()?;
+            if exit {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles a message, and returns whether the client asked to exit.
+    fn handle(&mut self, body: &[u8], outgoing: &mut Vec<Value>) -> bool {
+        let request = match serde_json::from_slice::<Request>(body) {
+            Ok(request) => request,
+            Err(err) => {
+                outgoing.push(response(Value::Null, Err(RpcError(PARSE_ERROR, err.to_string()))));
+                return false;
+            }
+        };
+
+        let result = match request.method.as_str() {
+            "initialize" => params(request.params).map(|params| self.initialize(params)),
+            "shutdown" => Ok(Value::Null),
+            "exit" => return true,
+            "textDocument/didOpen" => params(request.params).map(|params: DidOpenParams| {
+                let TextDocumentItem { uri, text } = params.text_document;
+                self.documents.insert(uri.clone(), text);
+                self.diagnose(&uri, outgoing);
+                Value::Null
+            }),
+            "textDocument/didChange" => params(request.params).map(|params: DidChangeParams| {
+                // Changes are full texts, as declared in the capabilities.
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.documents.insert(params.text_document.uri, change.text);
+                }
+                Value::Null
+            }),
+            "textDocument/didSave" => params(request.params).map(|params: DocumentParams| {
+                self.diagnose(&params.text_document.uri, outgoing);
+                Value::Null
+            }),
+            "textDocument/didClose" => params(request.params).map(|params: DocumentParams| {
+                self.documents.remove(&params.text_document.uri);
+                Value::Null
+            }),
+            "textDocument/formatting" => {
+                params(request.params).and_then(|params: DocumentParams| self.format(params))
+            }
+            "textDocument/documentSymbol" => {
+                params(request.params).and_then(|params: DocumentParams| {
+                    let path = uri_to_path(&params.text_document.uri);
+                    self.symbols(|symbol_path, _| Some(symbol_path) == path.as_deref())
+                })
+            }
+            "workspace/symbol" => params(request.params).and_then(|params: QueryParams| {
+                let query = params.query.to_lowercase();
+                self.symbols(|_, name| name.to_lowercase().contains(&query))
+            }),
+            method => Err(RpcError(METHOD_NOT_FOUND, format!("unknown method: {method}"))),
+        };
+        if let Some(id) = request.id {
+            outgoing.push(response(id, result));
+        }
+        false
+    }
+
+    /// Keeps the workspace root and the options, and returns the capabilities of the server.
+    fn initialize(&mut self, params: InitializeParams) -> Value {
+        self.root = params
+            .root_uri
+            .as_deref()
+            .and_then(uri_to_path)
+            .or(params.root_path)
+            .unwrap_or_default();
+        self.options = params.initialization_options;
+        json!({
+            "capabilities": {
+                "textDocumentSync": {
+                    "openClose": true,
+                    "change": 1,
+                    "save": { "includeText": false },
+                },
+                "documentFormattingProvider": true,
+                "documentSymbolProvider": true,
+                "workspaceSymbolProvider": true,
+            },
+            "serverInfo": { "name": "typster", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    /// Compiles the main file, or the file of the URI, and publishes the diagnostics of every
+    /// file, clearing those of the files without any anymore.
+    fn diagnose(&mut self, uri: &str, outgoing: &mut Vec<Value>) {
+        let main = match &self.options.main {
+            Some(main) => self.root.join(main),
+            None => match uri_to_path(uri) {
+                Some(path) if path.extension().is_some_and(|ext| ext == "typ") => path,
+                _ => return,
+            },
+        };
+
+        // Paths of the diagnostics are relative to the root of the world, i.e. the directory of
+        // the main file.
+        let root = main
+            .canonicalize()
+            .ok()
+            .and_then(|main| Some(main.parent()?.to_path_buf()))
+            .unwrap_or_default();
+        let mut diagnostics = HashMap::<PathBuf, Vec<Value>>::new();
+        match self.warm_world(&main) {
+            Ok(world) => {
+                let Warned { output, warnings } = typst_compile(world);
+                let errors = output.err().unwrap_or_default();
+                for diagnostic in warnings.iter().chain(errors.iter()) {
+                    if let Some((path, diagnostic)) = lsp_diagnostic(world, &root, diagnostic) {
+                        diagnostics.entry(path).or_default().push(diagnostic);
+                    }
+                }
+                comemo::evict(MAX_CACHE_AGE);
+            }
+            Err(message) => {
+                let message = format!("failed to compile {}: {message}", main.display());
+                outgoing.push(json!({
+                    "jsonrpc": "2.0",
+                    "method": "window/showMessage",
+                    "params": { "type": 1, "message": message },
+                }));
+            }
+        }
+
+        let cleared = self
+            .published
+            .iter()
+            .filter(|path| !diagnostics.contains_key(*path))
+            .map(|path| (path.clone(), vec![]))
+            .collect::<Vec<_>>();
+        self.published = diagnostics.keys().cloned().collect();
+        for (path, diagnostics) in diagnostics.into_iter().chain(cleared) {
+            outgoing.push(json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": { "uri": path_to_uri(&path), "diagnostics": diagnostics },
+            }));
+        }
+    }
+
+    /// Returns the warm world of a main file, creating it on the first compilation, and resets it
+    /// so that changed files are read again.
+    fn warm_world(&mut self, main: &Path) -> Result<&SystemWorld, String> {
+        if !self.worlds.contains_key(main) {
+            let mut params = CompileParams::builder().input(main).build();
+            params.font_paths = self
+                .options
+                .font_paths
+                .iter()
+                .map(|path| self.root.join(path))
+                .collect();
+            let world = SystemWorld::new(&params).map_err(|err| err.to_string())?;
+            self.worlds.insert(main.to_path_buf(), world);
+        }
+        let world = self.worlds.get_mut(main).expect("inserted above");
+        world.reset();
+        Ok(world)
+    }
+
+    /// Formats an open document, and returns the edit replacing its whole text.
+    fn format(&self, params: DocumentParams) -> Result<Value, RpcError> {
+        let text = self
+            .documents
+            .get(&params.text_document.uri)
+            .ok_or_else(|| RpcError(SERVER_ERROR, "the document is not open".to_string()))?;
+        let column = self.options.column.unwrap_or(DEFAULT_COLUMN);
+        let formatted = format_text(text, column);
+        if formatted == *text {
+            return Ok(json!([]));
+        }
+        let last = text.rsplit('\n').next().unwrap_or_default();
+        let end = json!({
+            "line": text.matches('\n').count(),
+            "character": last.encode_utf16().count(),
+        });
+        Ok(json!([{
+            "range": { "start": { "line": 0, "character": 0 }, "end": end },
+            "newText": formatted,
+        }]))
+    }
+
+    /// Returns the symbols of the workspace whose absolute path and name match `filter`, as
+    /// `SymbolInformation`.
+    fn symbols(&self, filter: impl Fn(&Path, &str) -> bool) -> Result<Value, RpcError> {
+        let symbols =
+            index_symbols(&self.root).map_err(|err| RpcError(SERVER_ERROR, err.to_string()))?;
+        let symbols = symbols
+            .iter()
+            .filter_map(|symbol| {
+                let path = self.root.join(&symbol.location.path);
+                if !filter(&path, &symbol.name) {
+                    return None;
+                }
+                // Columns are in characters, which match UTF-16 code units outside of emojis and
+                // rare scripts.
+                let line = symbol.location.line.saturating_sub(1);
+                let start = symbol.location.column.saturating_sub(1);
+                let end = start + symbol.name.encode_utf16().count();
+                Some(json!({
+                    "name": symbol.name,
+                    "kind": match symbol.kind {
+                        SymbolKind::Function => 12,
+                        SymbolKind::Variable => 13,
+                        SymbolKind::Label => 20,
+                        SymbolKind::BibliographyKey => 15,
+                    },
+                    "location": {
+                        "uri": path_to_uri(&path),
+                        "range": {
+                            "start": { "line": line, "character": start },
+                            "end": { "line": line, "character": end },
+                        },
+                    },
+                }))
+            })
+            .collect::<Vec<_>>();
+        Ok(Value::Array(symbols))
+    }
+}
+
+/// Parameters of the `initialize` request.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeParams {
+    #[serde(default)]
+    root_uri: Option<String>,
+    #[serde(default)]
+    root_path: Option<PathBuf>,
+    #[serde(default)]
+    initialization_options: InitializationOptions,
+}
+
+/// Options of the server, set by the client in the `initialize` request.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct InitializationOptions {
+    /// Path of the main file relative to the workspace root.
+    main: Option<PathBuf>,
+    /// Directories to search for fonts, relative to the workspace root.
+    font_paths: Vec<PathBuf>,
+    /// Line width of formatted documents.
+    column: Option<usize>,
+}
+
+/// Parameters of the `textDocument/didOpen` notification.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidOpenParams {
+    text_document: TextDocumentItem,
+}
+
+/// An open document.
+#[derive(Debug, Deserialize)]
+struct TextDocumentItem {
+    uri: String,
+    text: String,
+}
+
+/// Parameters of the `textDocument/didChange` notification.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DidChangeParams {
+    text_document: TextDocumentIdentifier,
+    content_changes: Vec<ContentChange>,
+}
+
+/// A change of a document, i.e. its full text.
+#[derive(Debug, Deserialize)]
+struct ContentChange {
+    text: String,
+}
+
+/// Parameters of the methods which only take a document.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DocumentParams {
+    text_document: TextDocumentIdentifier,
+}
+
+/// A document, by its URI.
+#[derive(Debug, Deserialize)]
+struct TextDocumentIdentifier {
+    uri: String,
+}
+
+/// Parameters of the `workspace/symbol` request.
+#[derive(Debug, Deserialize)]
+struct QueryParams {
+    query: String,
+}
+
+/// Reads the body of a message, or returns [`None`] at the end of the input.
+fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+/// Converts a warning or an error of a compilation into an LSP diagnostic, with the path of its
+/// file under the root of the world, unless it's in a package or has no location.
+fn lsp_diagnostic(
+    world: &SystemWorld,
+    root: &Path,
+    diagnostic: &SourceDiagnostic,
+) -> Option<(PathBuf, Value)> {
+    let id = diagnostic.span.id().filter(|id| id.package().is_none())?;
+    let source = world.source(id).ok()?;
+    let range = source.range(diagnostic.span)?;
+    let message = std::iter::once(diagnostic.message.as_str())
+        .chain(diagnostic.hints.iter().map(|hint| hint.as_str()))
+        .collect::<Vec<_>>()
+        .join("\nhint: ");
+    Some((
+        root.join(id.vpath().as_rootless_path()),
+        json!({
+            "range": {
+                "start": position(&source, range.start),
+                "end": position(&source, range.end),
+            },
+            "severity": match diagnostic.severity {
+                Severity::Error => 1,
+                Severity::Warning => 2,
+            },
+            "source": "typst",
+            "message": message,
+        }),
+    ))
+}
+
+/// Returns the LSP position of a byte offset, i.e. its line and its UTF-16 column.
+fn position(source: &Source, offset: usize) -> Value {
+    let line = source.byte_to_line(offset).unwrap_or(0);
+    let start = source.line_to_byte(line).unwrap_or(0);
+    let column =
+        source.byte_to_utf16(offset).unwrap_or(0) - source.byte_to_utf16(start).unwrap_or(0);
+    json!({ "line": line, "character": column })
+}
+
+/// Converts a `file` URI into a path, decoding percent-encoded bytes.
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let byte = match (encoded[i], encoded.get(i + 1..i + 3)) {
+            (b'%', Some(hex)) => {
+                let byte = u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()?;
+                i += 2;
+                byte
+            }
+            (byte, _) => byte,
+        };
+        decoded.push(byte);
+        i += 1;
+    }
+    let path = String::from_utf8(decoded).ok()?;
+    // Drop the slash before the drive letter of a Windows path, e.g. /C:/Users.
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if cfg!(windows) && drive.is_ascii_alphabetic() => &path[1..],
+        _ => path.as_str(),
+    };
+    Some(PathBuf::from(path))
+}
+
+/// Converts an absolute path into a `file` URI, percent-encoding bytes other than unreserved
+/// characters and separators.
+fn path_to_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from(if path.starts_with('/') { "file://" } else { "file:///" });
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    uri
+}
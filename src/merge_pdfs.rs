@@ -0,0 +1,171 @@
+use std::path::{Path, PathBuf};
+
+use lopdf::{Document, Object, ObjectId};
+
+use crate::{
+    outline::{read_outline, write_outline},
+    page_labels::{read_page_labels, write_page_labels},
+    OutlineEntry, PageLabel,
+};
+
+/// Page attributes which may be inherited from the page tree, and must be copied to the pages
+/// moved to another tree.
+const INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Merges PDF files into one, e.g. to attach a scanned annex to a compiled report.
+///
+/// The pages are concatenated in the order of the inputs, and so are the outlines and page labels.
+/// Other document-level properties, e.g. metadata and viewer preferences, are taken from the first
+/// input, while those of the other inputs, e.g. named destinations, form fields, and attachments,
+/// are dropped.
+///
+/// # Arguments
+///
+/// - `inputs` - Paths to the input PDF files.
+/// - `output` - Path to the output PDF file.
+///
+/// # Example
+///
+/// Following is an example of how to use the `merge_pdfs` function:
+///
+/// ```no_run
+/// typster::merge_pdfs(
+///     &[std::path::PathBuf::from("report.pdf"), std::path::PathBuf::from("annex.pdf")],
+///     std::path::Path::new("merged.pdf"),
+/// )
+/// .unwrap();
+/// ```
+pub fn merge_pdfs(inputs: &[PathBuf], output: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let (first, rest) = inputs.split_first().ok_or("no input PDF files")?;
+
+    let mut merged = Document::load(first)?;
+    let mut count = merged.get_pages().len() as u32;
+    let mut outline = offset_entries(read_outline(&merged)?, 0);
+    let mut labels = vec![(read_page_labels(&merged), 0, count)];
+    let root = merged.catalog()?.get(b"Pages").and_then(Object::as_reference)?;
+
+    for input in rest {
+        let mut doc = Document::load(input)?;
+        let pages = doc.get_pages().into_values().collect::<Vec<_>>();
+        inline_inherited_attributes(&mut doc, &pages)?;
+
+        outline.extend(offset_entries(read_outline(&doc)?, count));
+        labels.push((read_page_labels(&doc), count, pages.len() as u32));
+
+        // Move all objects to unused ids; the catalog and the page tree become unreferenced.
+        let offset = merged.max_id;
+        for (id, mut object) in std::mem::take(&mut doc.objects) {
+            offset_references(&mut object, offset);
+            merged.objects.insert((id.0 + offset, id.1), object);
+        }
+        merged.max_id += doc.max_id;
+
+        // The kids of the page tree root may be an indirect array.
+        let kids = match merged.get_dictionary(root)?.get(b"Kids")? {
+            Object::Reference(id) => *id,
+            _ => root,
+        };
+        for page in &pages {
+            let page = (page.0 + offset, page.1);
+            merged.get_dictionary_mut(page)?.set("Parent", root);
+            let kids = match merged.get_object_mut(kids)? {
+                Object::Dictionary(dict) => dict.get_mut(b"Kids")?,
+                kids => kids,
+            };
+            kids.as_array_mut()?.push(Object::Reference(page));
+        }
+        count += pages.len() as u32;
+        merged.get_dictionary_mut(root)?.set("Count", count as i64);
+    }
+
+    write_outline(&mut merged, &outline)?;
+    write_page_labels(&mut merged, merge_labels(labels))?;
+
+    merged.prune_objects();
+    merged.save(output)?;
+
+    Ok(())
+}
+
+/// Copies the attributes inherited from the page tree to the pages themselves.
+pub(crate) fn inline_inherited_attributes(
+    doc: &mut Document,
+    pages: &[ObjectId],
+) -> Result<(), Box<dyn std::error::Error>> {
+    for &page in pages {
+        for key in INHERITABLE {
+            if doc.get_dictionary(page)?.has(key) {
+                continue;
+            }
+            if let Some(value) = inherited(doc, page, key) {
+                doc.get_dictionary_mut(page)?.set(key, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Finds the attribute in the ancestors of the page.
+fn inherited(doc: &Document, page: ObjectId, key: &[u8]) -> Option<Object> {
+    let mut node = doc.get_dictionary(page).ok()?;
+    // Guard against cycles of malformed files.
+    for _ in 0..32 {
+        node = node.get_deref(b"Parent", doc).and_then(Object::as_dict).ok()?;
+        if let Ok(value) = node.get(key) {
+            return Some(value.clone());
+        }
+    }
+    None
+}
+
+/// Offsets the object numbers of the references in the object.
+pub(crate) fn offset_references(object: &mut Object, offset: u32) {
+    match object {
+        Object::Reference(id) => id.0 += offset,
+        Object::Array(array) => array.iter_mut().for_each(|item| offset_references(item, offset)),
+        Object::Dictionary(dict) => dict
+            .iter_mut()
+            .for_each(|(_, value)| offset_references(value, offset)),
+        Object::Stream(stream) => stream
+            .dict
+            .iter_mut()
+            .for_each(|(_, value)| offset_references(value, offset)),
+        _ => {}
+    }
+}
+
+/// Offsets the pages of the outline entries. Entries which can't be resolved to a page point to
+/// the first page of their document.
+fn offset_entries(entries: Vec<OutlineEntry>, offset: u32) -> Vec<OutlineEntry> {
+    entries
+        .into_iter()
+        .map(|entry| OutlineEntry {
+            page: entry.page.max(1) + offset,
+            children: offset_entries(entry.children, offset),
+            ..entry
+        })
+        .collect()
+}
+
+/// Concatenates the page labels of the documents, given with the number of preceding pages and
+/// of their own pages. Documents without page labels are numbered with decimal numbers from 1, as
+/// viewers show them on their own.
+fn merge_labels(labels: Vec<(Vec<PageLabel>, u32, u32)>) -> Vec<PageLabel> {
+    if labels.iter().all(|(labels, _, _)| labels.is_empty()) {
+        return vec![];
+    }
+    labels
+        .into_iter()
+        .filter(|(_, _, pages)| *pages > 0)
+        .flat_map(|(labels, offset, pages)| {
+            let labels = labels.into_iter().filter(|label| label.start_page <= pages);
+            let labels = match labels.clone().next() {
+                Some(label) if label.start_page == 1 => labels.collect::<Vec<_>>(),
+                _ => [PageLabel::default()].into_iter().chain(labels).collect(),
+            };
+            labels
+                .into_iter()
+                .map(move |label| PageLabel { start_page: label.start_page + offset, ..label })
+        })
+        .collect()
+}
@@ -0,0 +1,229 @@
+use std::{error::Error, path::Path};
+
+use lopdf::{Dictionary, Document, Object, Stream};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    merge_pdfs::inline_inherited_attributes,
+    overlay::{display_box, overlay},
+};
+
+/// Widths of the printable ASCII characters, from space to tilde, of the standard Helvetica font in
+/// WinAnsiEncoding, in 1/1000 of the font size.
+pub(crate) const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Parameters for numbering pages of a PDF file.
+///
+/// The number of each page is `prefix`, the counter zero-padded to `digits`, then `suffix`, e.g.
+/// `ACME000001`. Only printable ASCII characters are supported.
+///
+/// See also [`number_pages()`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NumberingParams {
+    /// Text before the counter, e.g. `ACME` for Bates numbers, or `Page ` for page numbers.
+    #[serde(default)]
+    pub prefix: String,
+
+    /// Text after the counter.
+    #[serde(default)]
+    pub suffix: String,
+
+    /// Counter of the first page.
+    pub start: u64,
+
+    /// Minimum number of digits of the counter, padded with zeros. Set to 0 for no padding.
+    pub digits: usize,
+
+    /// Position of the number on the page as displayed.
+    #[serde(default)]
+    pub position: NumberPosition,
+
+    /// Distance of the number from the edges of the page, in points.
+    pub margin: f32,
+
+    /// Font size of the number, in points.
+    pub font_size: f32,
+}
+
+impl Default for NumberingParams {
+    /// Bates numbers with six digits from 1, e.g. `000001`, at the bottom-right corner.
+    fn default() -> Self {
+        Self {
+            prefix: String::new(),
+            suffix: String::new(),
+            start: 1,
+            digits: 6,
+            position: NumberPosition::BottomRight,
+            margin: 18.0,
+            font_size: 10.0,
+        }
+    }
+}
+
+impl NumberingParams {
+    /// Plain page numbers from 1 at the bottom center, e.g. `1`, `2`, `3`.
+    pub fn page_numbers() -> Self {
+        Self {
+            digits: 0,
+            position: NumberPosition::BottomCenter,
+            ..Default::default()
+        }
+    }
+}
+
+/// Position of a page number for [`NumberingParams`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberPosition {
+    /// Top-left corner
+    TopLeft,
+    /// Top, centered
+    TopCenter,
+    /// Top-right corner
+    TopRight,
+    /// Bottom-left corner
+    BottomLeft,
+    /// Bottom, centered
+    BottomCenter,
+    /// Bottom-right corner
+    #[default]
+    BottomRight,
+}
+
+impl From<&str> for NumberPosition {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().replace('-', "_").as_str() {
+            "top_left" => NumberPosition::TopLeft,
+            "top_center" => NumberPosition::TopCenter,
+            "top_right" => NumberPosition::TopRight,
+            "bottom_left" => NumberPosition::BottomLeft,
+            "bottom_center" => NumberPosition::BottomCenter,
+            _ => NumberPosition::BottomRight,
+        }
+    }
+}
+
+/// Stamps sequential numbers on every page of a PDF file, e.g. Bates numbers for legal document
+/// production, or page numbers for a scanned document.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file, which may be the same as the input file.
+/// - `params` - [`NumberingParams`] to number with.
+///
+/// # Returns
+///
+/// Result containing the counter following the last page, to continue numbering with the next
+/// document of a set.
+///
+/// # Example
+///
+/// Following is an example of how to use the `number_pages` function:
+///
+/// ```no_run
+/// let mut start = 1;
+/// for name in ["exhibit-a", "exhibit-b"] {
+///     start = typster::number_pages(
+///         std::path::Path::new(&format!("{name}.pdf")),
+///         std::path::Path::new(&format!("{name}-numbered.pdf")),
+///         &typster::NumberingParams { prefix: "ACME".to_string(), start, ..Default::default() },
+///     )
+///     .unwrap();
+/// }
+/// ```
+pub fn number_pages(
+    input: &Path,
+    output: &Path,
+    params: &NumberingParams,
+) -> Result<u64, Box<dyn Error>> {
+    if !params
+        .prefix
+        .chars()
+        .chain(params.suffix.chars())
+        .all(|c| matches!(c, ' '..='~'))
+    {
+        return Err("only printable ASCII characters are supported in prefix and suffix".into());
+    }
+
+    let mut doc = Document::load(input)?;
+    let pages = doc.get_pages().into_values().collect::<Vec<_>>();
+    inline_inherited_attributes(&mut doc, &pages)?;
+
+    let mut font = Dictionary::new();
+    font.set("Type", Object::Name(b"Font".to_vec()));
+    font.set("Subtype", Object::Name(b"Type1".to_vec()));
+    font.set("BaseFont", Object::Name(b"Helvetica".to_vec()));
+    font.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
+    let font = doc.add_object(font);
+
+    let mut counter = params.start;
+    for page in pages {
+        let display = display_box(doc.get_dictionary(page)?)?;
+        let text = format!(
+            "{}{:0digits$}{}",
+            params.prefix,
+            counter,
+            params.suffix,
+            digits = params.digits
+        );
+        let width = text
+            .bytes()
+            .map(|byte| HELVETICA_WIDTHS[(byte - b' ') as usize] as f32)
+            .sum::<f32>()
+            * params.font_size
+            / 1000.0;
+
+        let (margin, size) = (params.margin, params.font_size);
+        let x = match params.position {
+            NumberPosition::TopLeft | NumberPosition::BottomLeft => margin,
+            NumberPosition::TopCenter | NumberPosition::BottomCenter => {
+                (display.width - width) / 2.0
+            }
+            NumberPosition::TopRight | NumberPosition::BottomRight => {
+                display.width - margin - width
+            }
+        };
+        let y = match params.position {
+            NumberPosition::TopLeft | NumberPosition::TopCenter | NumberPosition::TopRight => {
+                display.height - margin - size
+            }
+            _ => margin,
+        };
+
+        let mut form = Dictionary::new();
+        form.set("Type", Object::Name(b"XObject".to_vec()));
+        form.set("Subtype", Object::Name(b"Form".to_vec()));
+        form.set(
+            "BBox",
+            vec![
+                Object::Integer(0),
+                Object::Integer(0),
+                Object::Real(display.width),
+                Object::Real(display.height),
+            ],
+        );
+        let mut fonts = Dictionary::new();
+        fonts.set("F1", font);
+        let mut resources = Dictionary::new();
+        resources.set("Font", fonts);
+        form.set("Resources", resources);
+        // The text has no characters to escape other than these.
+        let escaped = text.replace('\\', "\\\\").replace('(', "\\(").replace(')', "\\)");
+        let content = format!("BT /F1 {size} Tf {x} {y} Td ({escaped}) Tj ET");
+        let form = doc.add_object(Stream::new(form, content.into_bytes()));
+
+        overlay(&mut doc, page, form, display.matrix)?;
+        counter += 1;
+    }
+
+    doc.save(output)?;
+
+    Ok(counter)
+}
@@ -0,0 +1,326 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::{read, write},
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use flate2::read::ZlibDecoder;
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageFormat};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+/// Upper limit of rounds of deduplication. Each round may reveal new duplicates, e.g. two font
+/// dictionaries which only differed by references to identical font files.
+const MAX_DEDUPLICATION_ROUNDS: usize = 8;
+
+/// Parameters for optimizing a PDF file.
+///
+/// See also [`optimize_pdf()`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizeParams {
+    /// Compress streams which are not compressed yet with Flate.
+    pub compress_streams: bool,
+
+    /// Merge identical objects, e.g. images or fonts embedded more than once.
+    pub deduplicate: bool,
+
+    /// Downsample RGB and grayscale images whose width or height exceeds this number of pixels,
+    /// keeping the aspect ratio. Set to [`None`] to keep images as they are.
+    #[serde(default)]
+    pub max_image_size: Option<u32>,
+
+    /// Quality, from 1 to 100, of downsampled JPEG images.
+    pub jpeg_quality: u8,
+}
+
+impl Default for OptimizeParams {
+    fn default() -> Self {
+        Self {
+            compress_streams: true,
+            deduplicate: true,
+            max_image_size: None,
+            jpeg_quality: 80,
+        }
+    }
+}
+
+/// Result of optimizing a PDF file with [`optimize_pdf()`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OptimizeReport {
+    /// Size of the input file, in bytes.
+    pub input_size: u64,
+
+    /// Size of the output file, in bytes.
+    pub output_size: u64,
+
+    /// Number of objects removed as duplicates.
+    pub deduplicated_objects: usize,
+
+    /// Number of images downsampled.
+    pub downsampled_images: usize,
+}
+
+impl OptimizeReport {
+    /// Bytes saved by the optimization, which is negative if the output file is larger.
+    pub fn saved(&self) -> i64 {
+        self.input_size as i64 - self.output_size as i64
+    }
+}
+
+/// Optimizes a PDF file to reduce its size, e.g. a compiled document with many photos.
+///
+/// If the optimized file is not smaller than the input file, e.g. because the input file uses
+/// object streams, which are not written back, the input file is written to the output as is.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file, which may be the same as the input file.
+/// - `params` - [`OptimizeParams`] to optimize with.
+///
+/// # Returns
+///
+/// Result containing an [`OptimizeReport`] with the sizes before and after the optimization.
+///
+/// # Example
+///
+/// Following is an example of how to use the `optimize_pdf` function:
+///
+/// ```no_run
+/// let report = typster::optimize_pdf(
+///     std::path::Path::new("photos.pdf"),
+///     std::path::Path::new("photos-optimized.pdf"),
+///     &typster::OptimizeParams { max_image_size: Some(2048), ..Default::default() },
+/// )
+/// .unwrap();
+/// println!("{} bytes saved", report.saved());
+/// ```
+#[instrument(skip_all, fields(input = %input.display(), output = %output.display()))]
+pub fn optimize_pdf(
+    input: &Path,
+    output: &Path,
+    params: &OptimizeParams,
+) -> Result<OptimizeReport, Box<dyn Error>> {
+    if !(1..=100).contains(&params.jpeg_quality) {
+        return Err(format!("jpeg_quality must be from 1 to 100: {}", params.jpeg_quality).into());
+    }
+
+    let original = read(input)?;
+    let mut report = OptimizeReport {
+        input_size: original.len() as u64,
+        ..Default::default()
+    };
+    let mut doc = Document::load_mem(&original)?;
+
+    if let Some(max) = params.max_image_size {
+        for object in doc.objects.values_mut() {
+            if let Object::Stream(stream) = object {
+                if downsample(stream, max, params.jpeg_quality)? {
+                    report.downsampled_images += 1;
+                }
+            }
+        }
+    }
+    if params.compress_streams {
+        doc.compress();
+    }
+    if params.deduplicate {
+        report.deduplicated_objects = deduplicate(&mut doc);
+    }
+
+    doc.prune_objects();
+    doc.renumber_objects();
+    let mut optimized = vec![];
+    doc.save_to(&mut optimized)?;
+
+    let pdf = if optimized.len() < original.len() { optimized } else { original };
+    report.output_size = pdf.len() as u64;
+    write(output, pdf)?;
+    Ok(report)
+}
+
+/// Downsamples the stream if it's an image larger than the maximum size. Returns whether it was
+/// downsampled.
+///
+/// Only 8-bit RGB and grayscale images, either JPEG or Flate compressed without predictors, are
+/// supported. Others, e.g. CMYK or indexed images, are left as they are.
+fn downsample(stream: &mut Stream, max: u32, quality: u8) -> Result<bool, Box<dyn Error>> {
+    let dict = &stream.dict;
+    if dict.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Image") {
+        return Ok(false);
+    }
+    let (Ok(width), Ok(height)) =
+        (dict.get(b"Width").and_then(Object::as_i64), dict.get(b"Height").and_then(Object::as_i64))
+    else {
+        return Ok(false);
+    };
+    if width <= max as i64 && height <= max as i64 {
+        return Ok(false);
+    }
+    let gray = match dict.get(b"ColorSpace").and_then(Object::as_name) {
+        Ok(b"DeviceRGB") => false,
+        Ok(b"DeviceGray") => true,
+        _ => return Ok(false),
+    };
+    if dict.get(b"BitsPerComponent").and_then(Object::as_i64).ok() != Some(8)
+        || dict.has(b"DecodeParms")
+        || dict.has(b"Decode")
+    {
+        return Ok(false);
+    }
+    let filter = match dict.get(b"Filter") {
+        Ok(Object::Name(name)) => name.clone(),
+        Ok(Object::Array(filters)) if filters.len() == 1 => filters[0].as_name()?.to_vec(),
+        _ => return Ok(false),
+    };
+
+    let (width, height) = (width as u32, height as u32);
+    let image = match filter.as_slice() {
+        b"FlateDecode" => {
+            let mut raw = vec![];
+            ZlibDecoder::new(stream.content.as_slice()).read_to_end(&mut raw)?;
+            if gray {
+                image::GrayImage::from_raw(width, height, raw).map(DynamicImage::ImageLuma8)
+            } else {
+                image::RgbImage::from_raw(width, height, raw).map(DynamicImage::ImageRgb8)
+            }
+            .ok_or("image data is shorter than its dimensions")?
+        }
+        b"DCTDecode" => image::load_from_memory_with_format(&stream.content, ImageFormat::Jpeg)?,
+        _ => return Ok(false),
+    };
+    let image = image.resize(max, max, FilterType::Triangle);
+    let image = if gray {
+        DynamicImage::ImageLuma8(image.to_luma8())
+    } else {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    };
+
+    stream.dict.set("Width", image.width() as i64);
+    stream.dict.set("Height", image.height() as i64);
+    if filter == b"FlateDecode" {
+        stream.set_plain_content(image.into_bytes());
+        stream.compress()?;
+    } else {
+        let mut jpeg = Cursor::new(vec![]);
+        image.write_with_encoder(JpegEncoder::new_with_quality(&mut jpeg, quality))?;
+        stream.set_content(jpeg.into_inner());
+    }
+    Ok(true)
+}
+
+/// Replaces references to identical objects with references to one of them, and removes the rest.
+/// Returns the number of removed objects.
+fn deduplicate(doc: &mut Document) -> usize {
+    let mut removed = 0;
+    for _ in 0..MAX_DEDUPLICATION_ROUNDS {
+        let mut buckets = HashMap::<u64, Vec<ObjectId>>::new();
+        let mut duplicates = HashMap::new();
+        for (&id, object) in &doc.objects {
+            if !can_share(object) {
+                continue;
+            }
+            let mut hasher = DefaultHasher::new();
+            fingerprint(object, &mut hasher);
+            let bucket = buckets.entry(hasher.finish()).or_default();
+            match bucket.iter().find(|&&other| same(&doc.objects[&other], object)) {
+                Some(&original) => {
+                    duplicates.insert(id, original);
+                }
+                None => bucket.push(id),
+            }
+        }
+        if duplicates.is_empty() {
+            break;
+        }
+
+        for object in doc.objects.values_mut() {
+            replace_references(object, &duplicates);
+        }
+        for (_, object) in doc.trailer.iter_mut() {
+            replace_references(object, &duplicates);
+        }
+        for id in duplicates.keys() {
+            doc.objects.remove(id);
+        }
+        removed += duplicates.len();
+    }
+    removed
+}
+
+/// Whether the object may be referred to from more than one place. Page tree nodes, for example,
+/// must be unique to keep the page count.
+fn can_share(object: &Object) -> bool {
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &stream.dict,
+        _ => return true,
+    };
+    !matches!(
+        dict.get(b"Type").and_then(Object::as_name),
+        Ok(b"Catalog" | b"Pages" | b"Page" | b"Annot" | b"Sig")
+    )
+}
+
+/// Hashes the object. Equal objects have the same hash, while the stream's position in the input
+/// file is ignored.
+fn fingerprint(object: &Object, hasher: &mut impl Hasher) {
+    std::mem::discriminant(object).hash(hasher);
+    match object {
+        Object::Null => {}
+        Object::Boolean(value) => value.hash(hasher),
+        Object::Integer(value) => value.hash(hasher),
+        Object::Real(value) => value.to_bits().hash(hasher),
+        Object::Name(name) => name.hash(hasher),
+        Object::String(text, _) => text.hash(hasher),
+        Object::Array(array) => array.iter().for_each(|item| fingerprint(item, hasher)),
+        Object::Dictionary(dict) => fingerprint_dict(dict, hasher),
+        Object::Stream(stream) => {
+            fingerprint_dict(&stream.dict, hasher);
+            stream.content.hash(hasher);
+        }
+        Object::Reference(id) => id.hash(hasher),
+    }
+}
+
+/// Hashes the dictionary regardless of the order of its entries, as does its equality.
+fn fingerprint_dict(dict: &Dictionary, hasher: &mut impl Hasher) {
+    let mut entries = dict.iter().collect::<Vec<_>>();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    for (key, value) in entries {
+        key.hash(hasher);
+        fingerprint(value, hasher);
+    }
+}
+
+/// Whether the objects are equal, ignoring the stream's position in the input file.
+fn same(a: &Object, b: &Object) -> bool {
+    match (a, b) {
+        (Object::Stream(a), Object::Stream(b)) => a.dict == b.dict && a.content == b.content,
+        _ => a == b,
+    }
+}
+
+/// Replaces references in the object according to the map.
+fn replace_references(object: &mut Object, map: &HashMap<ObjectId, ObjectId>) {
+    match object {
+        Object::Reference(id) => {
+            if let Some(&original) = map.get(id) {
+                *id = original;
+            }
+        }
+        Object::Array(array) => array.iter_mut().for_each(|item| replace_references(item, map)),
+        Object::Dictionary(dict) => {
+            dict.iter_mut().for_each(|(_, value)| replace_references(value, map))
+        }
+        Object::Stream(stream) => stream
+            .dict
+            .iter_mut()
+            .for_each(|(_, value)| replace_references(value, map)),
+        _ => {}
+    }
+}
@@ -0,0 +1,297 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+
+use lopdf::{decode_text_string, text_string, Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+
+/// An entry of the document outline, a.k.a. bookmarks.
+///
+/// See also [`set_outline()`] and [`get_outline()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutlineEntry {
+    /// Title of the entry.
+    pub title: String,
+
+    /// 1-based page number the entry jumps to. The page is fitted in the window.
+    pub page: u32,
+
+    /// Nested entries.
+    #[serde(default)]
+    pub children: Vec<OutlineEntry>,
+}
+
+/// Replaces the outline, a.k.a. bookmarks, of a PDF file.
+///
+/// All entries are expanded. Pass an empty list to remove the outline.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file, which will be updated in place.
+/// - `outline` - Top-level entries of the outline.
+///
+/// # Example
+///
+/// Following is an example of how to use the `set_outline` function:
+///
+/// ```no_run
+/// typster::set_outline(
+///     std::path::Path::new("sample.pdf"),
+///     vec![typster::OutlineEntry {
+///         title: "Introduction".to_string(),
+///         page: 1,
+///         children: vec![typster::OutlineEntry {
+///             title: "Background".to_string(),
+///             page: 2,
+///             ..Default::default()
+///         }],
+///     }],
+/// )
+/// .unwrap();
+/// ```
+pub fn set_outline(
+    path: &Path,
+    outline: Vec<OutlineEntry>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut doc = Document::load(path)?;
+    write_outline(&mut doc, &outline)?;
+
+    // Drop the previous outline, if any.
+    doc.prune_objects();
+    doc.save(path)?;
+
+    Ok(())
+}
+
+/// Reads the outline, a.k.a. bookmarks, of a PDF file.
+///
+/// Entries whose destination can't be resolved to a page, e.g. links to other files, are given the
+/// page `0`.
+///
+/// # Argument
+///
+/// - `path` - Path to the PDF file.
+///
+/// # Returns
+///
+/// Result containing the top-level entries of the outline, which is empty if the file has none.
+///
+/// # Example
+///
+/// Following is an example of how to use the `get_outline` function:
+///
+/// ```no_run
+/// for entry in typster::get_outline(std::path::Path::new("sample.pdf")).unwrap() {
+///     println!("{} ({})", entry.title, entry.page);
+/// }
+/// ```
+pub fn get_outline(path: &Path) -> Result<Vec<OutlineEntry>, Box<dyn std::error::Error>> {
+    read_outline(&Document::load(path)?)
+}
+
+/// Replaces the outline of a document loaded in memory. The previous outline objects are left
+/// unreferenced.
+pub(crate) fn write_outline(
+    doc: &mut Document,
+    outline: &[OutlineEntry],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pages = doc.get_pages();
+
+    doc.catalog_mut()?.remove(b"Outlines");
+    if !outline.is_empty() {
+        let root = doc.new_object_id();
+        let (first, last, count) = add_entries(doc, &pages, outline, root)?;
+
+        let mut dict = Dictionary::new();
+        dict.set("Type", Object::Name(b"Outlines".to_vec()));
+        dict.set("First", first);
+        dict.set("Last", last);
+        dict.set("Count", count);
+        doc.objects.insert(root, Object::Dictionary(dict));
+        doc.catalog_mut()?.set("Outlines", root);
+    }
+
+    Ok(())
+}
+
+/// Reads the outline of a document loaded in memory.
+pub(crate) fn read_outline(
+    doc: &Document,
+) -> Result<Vec<OutlineEntry>, Box<dyn std::error::Error>> {
+    let pages = doc
+        .get_pages()
+        .into_iter()
+        .map(|(number, id)| (id, number))
+        .collect::<BTreeMap<_, _>>();
+
+    let first = doc
+        .catalog()?
+        .get_deref(b"Outlines", doc)
+        .and_then(Object::as_dict)
+        .and_then(|outlines| outlines.get(b"First"))
+        .and_then(Object::as_reference);
+
+    Ok(match first {
+        Ok(first) => read_entries(doc, &pages, first, &mut HashSet::new()),
+        Err(_) => vec![],
+    })
+}
+
+/// Adds outline items for `entries` under `parent`, and returns the first and last item, and the
+/// number of visible descendants.
+fn add_entries(
+    doc: &mut Document,
+    pages: &BTreeMap<u32, ObjectId>,
+    entries: &[OutlineEntry],
+    parent: ObjectId,
+) -> Result<(ObjectId, ObjectId, i64), Box<dyn std::error::Error>> {
+    let ids = entries.iter().map(|_| doc.new_object_id()).collect::<Vec<_>>();
+    let mut count = 0;
+
+    for (i, entry) in entries.iter().enumerate() {
+        let page = pages
+            .get(&entry.page)
+            .ok_or_else(|| format!("page {} of \"{}\" does not exist", entry.page, entry.title))?;
+
+        let mut dict = Dictionary::new();
+        dict.set("Title", text_string(&entry.title));
+        dict.set("Parent", parent);
+        dict.set("Dest", vec![Object::Reference(*page), Object::Name(b"Fit".to_vec())]);
+        if i > 0 {
+            dict.set("Prev", ids[i - 1]);
+        }
+        if let Some(next) = ids.get(i + 1) {
+            dict.set("Next", *next);
+        }
+        if !entry.children.is_empty() {
+            let (first, last, descendants) = add_entries(doc, pages, &entry.children, ids[i])?;
+            dict.set("First", first);
+            dict.set("Last", last);
+            dict.set("Count", descendants);
+            count += descendants;
+        }
+        doc.objects.insert(ids[i], Object::Dictionary(dict));
+        count += 1;
+    }
+
+    Ok((ids[0], ids[ids.len() - 1], count))
+}
+
+/// Reads the outline item `id` and its following siblings. `visited` guards against malformed
+/// files whose items form a cycle.
+fn read_entries(
+    doc: &Document,
+    pages: &BTreeMap<ObjectId, u32>,
+    id: ObjectId,
+    visited: &mut HashSet<ObjectId>,
+) -> Vec<OutlineEntry> {
+    let mut entries = vec![];
+    let mut next = Some(id);
+
+    while let Some(id) = next.filter(|id| visited.insert(*id)) {
+        let Ok(dict) = doc.get_dictionary(id) else {
+            break;
+        };
+
+        let children = match dict.get(b"First").and_then(Object::as_reference) {
+            Ok(first) => read_entries(doc, pages, first, visited),
+            Err(_) => vec![],
+        };
+        entries.push(OutlineEntry {
+            title: dict
+                .get(b"Title")
+                .ok()
+                .and_then(|title| decode_text_string(title).ok())
+                .unwrap_or_default(),
+            page: destination(doc, dict)
+                .and_then(|page| pages.get(&page).copied())
+                .unwrap_or_default(),
+            children,
+        });
+        next = dict.get(b"Next").and_then(Object::as_reference).ok();
+    }
+
+    entries
+}
+
+/// Resolves the page object of the destination of an outline item, which is given either directly
+/// with `Dest`, or with a `GoTo` action.
+fn destination(doc: &Document, item: &Dictionary) -> Option<ObjectId> {
+    let dest = match item.get_deref(b"Dest", doc) {
+        Ok(dest) => dest,
+        Err(_) => {
+            let action = item.get_deref(b"A", doc).and_then(Object::as_dict).ok()?;
+            if action.get(b"S").and_then(Object::as_name).ok()? != b"GoTo" {
+                return None;
+            }
+            action.get_deref(b"D", doc).ok()?
+        }
+    };
+
+    let dest = match dest {
+        Object::Array(_) => dest,
+        Object::Name(name) | Object::String(name, _) => named_destination(doc, name)?,
+        _ => return None,
+    };
+    // A named destination may be wrapped in a dictionary with the `D` entry.
+    let dest = match dest {
+        Object::Dictionary(dict) => dict.get_deref(b"D", doc).ok()?,
+        _ => dest,
+    };
+
+    dest.as_array().ok()?.first()?.as_reference().ok()
+}
+
+/// Looks up a named destination, either in the `Dests` dictionary of the catalog (PDF 1.1), or in
+/// the `Dests` name tree of the `Names` dictionary.
+fn named_destination<'a>(doc: &'a Document, name: &[u8]) -> Option<&'a Object> {
+    let catalog = doc.catalog().ok()?;
+
+    if let Ok(dests) = catalog.get_deref(b"Dests", doc).and_then(Object::as_dict) {
+        if let Ok(dest) = dests.get_deref(name, doc) {
+            return Some(dest);
+        }
+    }
+
+    let tree = catalog
+        .get_deref(b"Names", doc)
+        .and_then(Object::as_dict)
+        .and_then(|names| names.get_deref(b"Dests", doc))
+        .and_then(Object::as_dict)
+        .ok()?;
+    lookup_name_tree(doc, tree, name, &mut HashSet::new())
+}
+
+/// Looks up a name tree node and its kids for `name`.
+fn lookup_name_tree<'a>(
+    doc: &'a Document,
+    node: &'a Dictionary,
+    name: &[u8],
+    visited: &mut HashSet<ObjectId>,
+) -> Option<&'a Object> {
+    if let Ok(names) = node.get_deref(b"Names", doc).and_then(Object::as_array) {
+        for pair in names.chunks(2) {
+            if let [Object::String(key, _), value] = pair {
+                if key == name {
+                    return doc.dereference(value).ok().map(|(_, value)| value);
+                }
+            }
+        }
+    }
+
+    let kids = node.get_deref(b"Kids", doc).and_then(Object::as_array).ok()?;
+    for kid in kids.iter().filter_map(|kid| kid.as_reference().ok()) {
+        if !visited.insert(kid) {
+            continue;
+        }
+        let Ok(kid) = doc.get_dictionary(kid) else {
+            continue;
+        };
+        if let Some(dest) = lookup_name_tree(doc, kid, name, visited) {
+            return Some(dest);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,117 @@
+use std::error::Error;
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+/// Visible area of a page as displayed, i.e. with its rotation applied.
+pub(crate) struct DisplayBox {
+    /// Width of the page as displayed, in points.
+    pub width: f32,
+
+    /// Height of the page as displayed, in points.
+    pub height: f32,
+
+    /// Transformation matrix which maps the coordinates of the page as displayed, with the origin
+    /// at its bottom-left corner, to the user space of the page.
+    pub matrix: [f32; 6],
+}
+
+/// Returns the visible area of the page, i.e. its crop box, or media box if not specified, as
+/// displayed. The attributes must not be inherited, e.g. inlined with
+/// [`inline_inherited_attributes()`](crate::merge_pdfs::inline_inherited_attributes).
+pub(crate) fn display_box(page: &Dictionary) -> Result<DisplayBox, Box<dyn Error>> {
+    let rect = page
+        .get(b"CropBox")
+        .or_else(|_| page.get(b"MediaBox"))
+        .and_then(Object::as_array)?
+        .iter()
+        .map(|value| value.as_float().ok())
+        .collect::<Option<Vec<_>>>()
+        .filter(|rect| rect.len() == 4)
+        .ok_or("invalid page box")?;
+    let (x, width) = (rect[0].min(rect[2]), (rect[2] - rect[0]).abs());
+    let (y, height) = (rect[1].min(rect[3]), (rect[3] - rect[1]).abs());
+
+    let rotation = page
+        .get(b"Rotate")
+        .and_then(Object::as_i64)
+        .unwrap_or(0)
+        .rem_euclid(360);
+    Ok(match rotation {
+        90 => DisplayBox {
+            width: height,
+            height: width,
+            matrix: [0.0, 1.0, -1.0, 0.0, x + width, y],
+        },
+        180 => DisplayBox {
+            width,
+            height,
+            matrix: [-1.0, 0.0, 0.0, -1.0, x + width, y + height],
+        },
+        270 => DisplayBox {
+            width: height,
+            height: width,
+            matrix: [0.0, -1.0, 1.0, 0.0, x, y + height],
+        },
+        _ => DisplayBox { width, height, matrix: [1.0, 0.0, 0.0, 1.0, x, y] },
+    })
+}
+
+/// Transforms the rectangle, i.e. `[left, bottom, right, top]`, with the matrix, returning the
+/// bounding box of the result.
+pub(crate) fn transform_rect([a, b, c, d, e, f]: [f32; 6], rect: [f32; 4]) -> [f32; 4] {
+    let [left, bottom, right, top] = rect;
+    let corners = [(left, bottom), (left, top), (right, bottom), (right, top)]
+        .map(|(x, y)| (a * x + c * y + e, b * x + d * y + f));
+    corners.iter().skip(1).fold(
+        [corners[0].0, corners[0].1, corners[0].0, corners[0].1],
+        |[x0, y0, x1, y1], &(x, y)| [x0.min(x), y0.min(y), x1.max(x), y1.max(y)],
+    )
+}
+
+/// Draws the form XObject over the page contents with the transformation matrix.
+pub(crate) fn overlay(
+    doc: &mut Document,
+    page: ObjectId,
+    form: ObjectId,
+    matrix: [f32; 6],
+) -> Result<(), Box<dyn Error>> {
+    // The resources may be shared with other pages, so they're copied to the page before adding
+    // the form.
+    let mut resources = match doc.get_dictionary(page)?.get(b"Resources") {
+        Ok(resources) => doc.dereference(resources)?.1.as_dict()?.clone(),
+        Err(_) => Dictionary::new(),
+    };
+    let mut xobjects = match resources.get(b"XObject") {
+        Ok(xobjects) => doc.dereference(xobjects)?.1.as_dict()?.clone(),
+        Err(_) => Dictionary::new(),
+    };
+    let name = (1..)
+        .map(|i| format!("TypsterStamp{i}"))
+        .find(|name| !xobjects.has(name.as_bytes()))
+        .unwrap_or_default();
+    xobjects.set(name.as_str(), form);
+    resources.set("XObject", xobjects);
+
+    // Isolate the graphics state of the original contents from the form.
+    let [a, b, c, d, e, f] = matrix;
+    let before = doc.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec()));
+    let after = doc.add_object(Stream::new(
+        Dictionary::new(),
+        format!("\nQ\nq {a} {b} {c} {d} {e} {f} cm /{name} Do Q\n").into_bytes(),
+    ));
+    let mut contents = vec![Object::Reference(before)];
+    match doc.get_dictionary(page)?.get(b"Contents") {
+        Ok(Object::Reference(id)) => match doc.get_object(*id)? {
+            Object::Array(array) => contents.extend(array.iter().cloned()),
+            _ => contents.push(Object::Reference(*id)),
+        },
+        Ok(Object::Array(array)) => contents.extend(array.iter().cloned()),
+        _ => {}
+    }
+    contents.push(Object::Reference(after));
+
+    let page = doc.get_dictionary_mut(page)?;
+    page.set("Resources", resources);
+    page.set("Contents", contents);
+    Ok(())
+}
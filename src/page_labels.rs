@@ -0,0 +1,245 @@
+use std::path::Path;
+
+use lopdf::{decode_text_string, text_string, Dictionary, Document, Object};
+use serde::{Deserialize, Serialize};
+
+/// A page label range, which applies to the pages from `start_page` until the next range.
+///
+/// For example, front matter numbered with lowercase roman numerals starting at page 1, followed by
+/// the body numbered with decimal numbers starting at page 5, and an appendix labeled `A-1`,
+/// `A-2`, ... starting at page 20:
+///
+/// ```rust
+/// use typster::{PageLabel, PageLabelStyle};
+///
+/// let labels = vec![
+///     PageLabel { start_page: 1, style: PageLabelStyle::LowerRoman, ..Default::default() },
+///     PageLabel { start_page: 5, ..Default::default() },
+///     PageLabel { start_page: 20, prefix: "A-".to_string(), ..Default::default() },
+/// ];
+/// ```
+///
+/// See also [`set_page_labels()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageLabel {
+    /// 1-based page number where the range starts.
+    pub start_page: u32,
+
+    /// Numbering style of the range.
+    #[serde(default)]
+    pub style: PageLabelStyle,
+
+    /// Prefix of each label in the range, e.g. `A-`.
+    #[serde(default)]
+    pub prefix: String,
+
+    /// Number of the first page in the range, which must be 1 or greater.
+    #[serde(default = "default_first_number")]
+    pub first_number: u32,
+}
+
+/// Numbering style of a [`PageLabel`] range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PageLabelStyle {
+    /// Decimal numbers, e.g. `1`, `2`, `3`.
+    #[default]
+    Decimal,
+    /// Uppercase roman numerals, e.g. `I`, `II`, `III`.
+    UpperRoman,
+    /// Lowercase roman numerals, e.g. `i`, `ii`, `iii`.
+    LowerRoman,
+    /// Uppercase letters, e.g. `A`, `B`, `C`, ..., `AA`, `BB`.
+    UpperAlpha,
+    /// Lowercase letters, e.g. `a`, `b`, `c`, ..., `aa`, `bb`.
+    LowerAlpha,
+    /// No numbers, i.e. the label consists of the prefix only.
+    None,
+}
+
+impl PageLabelStyle {
+    /// Name of the style in the page label dictionary, if any.
+    fn name(&self) -> Option<&'static [u8]> {
+        match self {
+            PageLabelStyle::Decimal => Some(b"D"),
+            PageLabelStyle::UpperRoman => Some(b"R"),
+            PageLabelStyle::LowerRoman => Some(b"r"),
+            PageLabelStyle::UpperAlpha => Some(b"A"),
+            PageLabelStyle::LowerAlpha => Some(b"a"),
+            PageLabelStyle::None => None,
+        }
+    }
+}
+
+impl Default for PageLabel {
+    fn default() -> Self {
+        Self {
+            start_page: 1,
+            style: PageLabelStyle::Decimal,
+            prefix: String::new(),
+            first_number: default_first_number(),
+        }
+    }
+}
+
+fn default_first_number() -> u32 {
+    1
+}
+
+/// Sets page labels of a PDF file, which viewers show instead of the physical page numbers.
+///
+/// The ranges may be given in any order, but one of them must start at page 1. Pass an empty list
+/// to remove the page labels.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file, which will be updated in place.
+/// - `labels` - [`PageLabel`] ranges to set.
+///
+/// # Example
+///
+/// Following is an example of how to use the `set_page_labels` function:
+///
+/// ```no_run
+/// typster::set_page_labels(
+///     std::path::Path::new("sample.pdf"),
+///     vec![
+///         typster::PageLabel {
+///             style: typster::PageLabelStyle::LowerRoman,
+///             ..Default::default()
+///         },
+///         typster::PageLabel { start_page: 5, ..Default::default() },
+///     ],
+/// )
+/// .unwrap();
+/// ```
+pub fn set_page_labels(
+    path: &Path,
+    labels: Vec<PageLabel>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut doc = Document::load(path)?;
+    write_page_labels(&mut doc, labels)?;
+
+    // Drop the previous page labels, if any.
+    doc.prune_objects();
+    doc.save(path)?;
+
+    Ok(())
+}
+
+/// Validates and replaces the page labels of a document loaded in memory. The previous page label
+/// objects are left unreferenced.
+pub(crate) fn write_page_labels(
+    doc: &mut Document,
+    mut labels: Vec<PageLabel>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let pages = doc.get_pages().len() as u32;
+
+    labels.sort_by_key(|label| label.start_page);
+    if let Some(label) = labels
+        .iter()
+        .find(|label| label.start_page == 0 || label.start_page > pages)
+    {
+        return Err(format!("page {} does not exist", label.start_page).into());
+    }
+    if labels.first().is_some_and(|label| label.start_page != 1) {
+        return Err("page labels must start at page 1".into());
+    }
+    if labels.windows(2).any(|pair| pair[0].start_page == pair[1].start_page) {
+        return Err("page label ranges must start at different pages".into());
+    }
+    if labels.iter().any(|label| label.first_number == 0) {
+        return Err("the first number of a page label range must be 1 or greater".into());
+    }
+
+    doc.catalog_mut()?.remove(b"PageLabels");
+    if !labels.is_empty() {
+        // The number tree consists of a single root node with 0-based page indices as keys.
+        let nums = labels
+            .iter()
+            .flat_map(|label| {
+                let mut dict = Dictionary::new();
+                if let Some(name) = label.style.name() {
+                    dict.set("S", Object::Name(name.to_vec()));
+                }
+                if !label.prefix.is_empty() {
+                    dict.set("P", text_string(&label.prefix));
+                }
+                if label.first_number != 1 {
+                    dict.set("St", label.first_number as i64);
+                }
+                [Object::Integer(label.start_page as i64 - 1), Object::Dictionary(dict)]
+            })
+            .collect::<Vec<_>>();
+
+        let mut tree = Dictionary::new();
+        tree.set("Nums", nums);
+        let id = doc.add_object(Object::Dictionary(tree));
+        doc.catalog_mut()?.set("PageLabels", id);
+    }
+
+    Ok(())
+}
+
+/// Reads the page labels of a document loaded in memory, sorted by the start page. Ranges which
+/// can't be parsed are skipped.
+pub(crate) fn read_page_labels(doc: &Document) -> Vec<PageLabel> {
+    let mut labels = vec![];
+    if let Ok(tree) = doc
+        .catalog()
+        .and_then(|catalog| catalog.get_deref(b"PageLabels", doc))
+    {
+        collect_labels(doc, tree, &mut labels, 0);
+    }
+    labels.sort_by_key(|label| label.start_page);
+    labels
+}
+
+/// Collects the page labels of a number tree node, descending into `Kids`.
+fn collect_labels(doc: &Document, node: &Object, labels: &mut Vec<PageLabel>, depth: usize) {
+    // Guard against cycles of malformed files.
+    if depth > 32 {
+        return;
+    }
+    let Ok(node) = node.as_dict() else {
+        return;
+    };
+    if let Ok(nums) = node.get_deref(b"Nums", doc).and_then(Object::as_array) {
+        for pair in nums.chunks_exact(2) {
+            let (Ok(index @ 0..), Ok(dict)) =
+                (pair[0].as_i64(), doc.dereference(&pair[1]).and_then(|(_, dict)| dict.as_dict()))
+            else {
+                continue;
+            };
+            let style = match dict.get(b"S").and_then(Object::as_name) {
+                Ok(b"D") => PageLabelStyle::Decimal,
+                Ok(b"R") => PageLabelStyle::UpperRoman,
+                Ok(b"r") => PageLabelStyle::LowerRoman,
+                Ok(b"A") => PageLabelStyle::UpperAlpha,
+                Ok(b"a") => PageLabelStyle::LowerAlpha,
+                _ => PageLabelStyle::None,
+            };
+            labels.push(PageLabel {
+                start_page: index as u32 + 1,
+                style,
+                prefix: dict
+                    .get(b"P")
+                    .ok()
+                    .and_then(|prefix| decode_text_string(prefix).ok())
+                    .unwrap_or_default(),
+                first_number: dict
+                    .get(b"St")
+                    .and_then(Object::as_i64)
+                    .map(|number| number.max(1) as u32)
+                    .unwrap_or(1),
+            });
+        }
+    }
+    if let Ok(kids) = node.get_deref(b"Kids", doc).and_then(Object::as_array) {
+        for kid in kids {
+            if let Ok((_, kid)) = doc.dereference(kid) {
+                collect_labels(doc, kid, labels, depth + 1);
+            }
+        }
+    }
+}
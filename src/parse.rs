@@ -0,0 +1,125 @@
+use std::{ops::Range, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use typst_syntax::{LinkedNode, Source};
+
+/// Syntax tree of a Typst source, returned by [`parse()`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyntaxTree {
+    /// Root node, whose kind is `Markup`.
+    pub root: SyntaxNode,
+
+    /// Syntax errors, in the order of the source.
+    pub errors: Vec<SyntaxError>,
+}
+
+/// A node of a [`SyntaxTree`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyntaxNode {
+    /// Kind of the node, e.g. `Heading`, `FuncCall`, or `Ident`, which is the name of the
+    /// corresponding variant of `typst_syntax::SyntaxKind`.
+    pub kind: String,
+
+    /// Byte range of the node in the source.
+    pub span: Range<usize>,
+
+    /// Text of the node if it is a non-empty leaf, e.g. `=` of a heading marker, or [`None`]
+    /// otherwise.
+    pub text: Option<String>,
+
+    /// Child nodes, in the order of the source. Concatenating the text of all leaves yields the
+    /// source.
+    pub children: Vec<SyntaxNode>,
+}
+
+/// A syntax error of a [`SyntaxTree`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyntaxError {
+    /// Error message, e.g. `unclosed delimiter`.
+    pub message: String,
+
+    /// Byte range of the error in the source.
+    pub span: Range<usize>,
+
+    /// Hints to fix the error.
+    pub hints: Vec<String>,
+}
+
+/// A position in a source file of a Typst project.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct SourceLocation {
+    /// Path of the file relative to the project root.
+    pub path: PathBuf,
+
+    /// 1-based line number.
+    pub line: usize,
+
+    /// 1-based column number, in characters.
+    pub column: usize,
+}
+
+impl SourceLocation {
+    /// Returns the location of a byte offset in a source.
+    pub(crate) fn at(source: &Source, offset: usize) -> Self {
+        Self {
+            path: source.id().vpath().as_rootless_path().to_path_buf(),
+            line: source.byte_to_line(offset).map_or(0, |line| line + 1),
+            column: source.byte_to_column(offset).map_or(0, |column| column + 1),
+        }
+    }
+}
+
+/// Parses a Typst source into a syntax tree, e.g. for linters, documentation generators, or
+/// refactoring tools.
+///
+/// The tree is lossless, i.e. it includes whitespace and comments, and parsing never fails;
+/// invalid syntax is represented by `Error` nodes and reported in [`SyntaxTree::errors`]. The
+/// representation is independent of the internals of `typst-syntax`, so it can be serialized,
+/// e.g. to JSON, and consumed without depending on them.
+///
+/// # Arguments
+///
+/// - `text` - Typst source to parse.
+///
+/// # Returns
+///
+/// [`SyntaxTree`] of the source.
+///
+/// # Example
+///
+/// Following is an example of how to use the `parse` function:
+///
+/// ```rust
+/// let tree = typster::parse("= Introduction\nHello, *world*!");
+/// assert_eq!(tree.root.kind, "Markup");
+/// assert_eq!(tree.root.children[0].kind, "Heading");
+/// assert!(tree.errors.is_empty());
+/// ```
+pub fn parse(text: &str) -> SyntaxTree {
+    let source = Source::detached(text);
+    let root = convert(&LinkedNode::new(source.root()));
+    let errors = source
+        .root()
+        .errors()
+        .into_iter()
+        .map(|error| SyntaxError {
+            message: error.message.to_string(),
+            span: source.range(error.span).unwrap_or_default(),
+            hints: error.hints.iter().map(|hint| hint.to_string()).collect(),
+        })
+        .collect();
+
+    SyntaxTree { root, errors }
+}
+
+/// Converts a node of `typst-syntax` and its descendants.
+fn convert(node: &LinkedNode) -> SyntaxNode {
+    // Only leaves have text.
+    let text = node.text();
+    SyntaxNode {
+        kind: format!("{:?}", node.kind()),
+        span: node.range(),
+        text: (!text.is_empty()).then(|| text.to_string()),
+        children: node.children().map(|child| convert(&child)).collect(),
+    }
+}
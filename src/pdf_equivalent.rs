@@ -0,0 +1,249 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    path::Path,
+};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use tracing::debug;
+
+/// Keys whose values change between two writes of the same document, or are compared separately.
+const IGNORED_KEYS: [&[u8]; 5] = [b"CreationDate", b"ID", b"Metadata", b"ModDate", b"Parent"];
+
+/// Keys of stream dictionaries which depend on how the stream is compressed.
+const ENCODING_KEYS: [&[u8]; 3] = [b"DecodeParms", b"Filter", b"Length"];
+
+/// Properties of the XMP metadata which change between two writes of the same document.
+const XMP_VOLATILE: [&str; 5] =
+    ["CreateDate", "DocumentID", "InstanceID", "MetadataDate", "ModifyDate"];
+
+/// Compares two PDF files by their content rather than their bytes, e.g. to check the output of a
+/// compilation in tests without breaking every time a dependency changes the byte layout.
+///
+/// Pages are compared by their content streams, resources, e.g. fonts and images, and
+/// annotations, with streams decompressed and references resolved, so object numbers,
+/// compression, and the order of objects don't matter. The document information, the catalog,
+/// and the XMP metadata are compared too, except the file identifiers, creation and modification
+/// dates, and XMP instance and document IDs.
+///
+/// # Arguments
+///
+/// - `a` - Path to the first PDF file.
+/// - `b` - Path to the second PDF file.
+///
+/// # Returns
+///
+/// Result containing whether the files are equivalent. The first difference found is logged at
+/// the debug level.
+///
+/// # Example
+///
+/// Following is an example of how to use the `pdf_equivalent` function:
+///
+/// ```no_run
+/// let expected = std::path::Path::new("tests/expected.pdf");
+/// let actual = std::path::Path::new("out/actual.pdf");
+/// assert!(typster::pdf_equivalent(expected, actual).unwrap());
+/// ```
+pub fn pdf_equivalent(a: &Path, b: &Path) -> Result<bool, Box<dyn Error>> {
+    let (a, b) = (Document::load(a)?, Document::load(b)?);
+    let (a, b) = (Canonicalizer::new(&a), Canonicalizer::new(&b));
+
+    let (pages_a, pages_b) = (a.pages(), b.pages());
+    if pages_a.len() != pages_b.len() {
+        debug!("page counts differ: {} and {}", pages_a.len(), pages_b.len());
+        return Ok(false);
+    }
+    if let Some(page) = pages_a.iter().zip(&pages_b).position(|(a, b)| a != b) {
+        debug!("page {} differs", page + 1);
+        return Ok(false);
+    }
+
+    for (part, a, b) in [
+        ("document information", a.info(), b.info()),
+        ("catalog", a.catalog(), b.catalog()),
+    ] {
+        if a != b {
+            debug!("{part} differs");
+            return Ok(false);
+        }
+    }
+    if a.xmp() != b.xmp() {
+        debug!("XMP metadata differs");
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// A PDF object with its references resolved, its streams decompressed, and the keys which
+/// don't affect the content removed.
+#[derive(Debug, PartialEq)]
+enum Canonical {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Real(u32),
+    Name(Vec<u8>),
+    String(Vec<u8>),
+    Array(Vec<Canonical>),
+    Dictionary(BTreeMap<Vec<u8>, Canonical>),
+    Stream(BTreeMap<Vec<u8>, Canonical>, Vec<u8>),
+    /// A reference to a page, by its 1-based number, e.g. the destination of a link.
+    Page(usize),
+    /// A reference to an object being canonicalized, e.g. an annotation pointing to its page.
+    Cycle,
+}
+
+/// Converts the objects of a document into [`Canonical`] ones.
+struct Canonicalizer<'a> {
+    doc: &'a Document,
+    /// Page numbers of the page objects.
+    pages: HashMap<ObjectId, usize>,
+}
+
+impl<'a> Canonicalizer<'a> {
+    fn new(doc: &'a Document) -> Self {
+        let pages = doc
+            .get_pages()
+            .into_iter()
+            .map(|(number, id)| (id, number as usize))
+            .collect();
+        Self { doc, pages }
+    }
+
+    /// Returns the canonical page dictionaries, in page order.
+    fn pages(&self) -> Vec<Canonical> {
+        self.doc
+            .get_pages()
+            .into_values()
+            .map(|id| match self.doc.get_object(id) {
+                Ok(object) => self.canonical(object, &mut vec![id]),
+                Err(_) => Canonical::Null,
+            })
+            .collect()
+    }
+
+    /// Returns the canonical document information dictionary.
+    fn info(&self) -> Canonical {
+        match self.doc.trailer.get(b"Info") {
+            Ok(info) => self.canonical(info, &mut vec![]),
+            Err(_) => Canonical::Null,
+        }
+    }
+
+    /// Returns the canonical catalog, without the page tree, which is compared page by page.
+    fn catalog(&self) -> Canonical {
+        match self.doc.catalog() {
+            Ok(catalog) => {
+                let mut catalog = catalog.clone();
+                catalog.remove(b"Pages");
+                self.dictionary(&catalog, &mut vec![], &[])
+            }
+            Err(_) => Canonical::Null,
+        }
+    }
+
+    /// Returns the XMP metadata of the catalog, with the volatile properties removed.
+    fn xmp(&self) -> Option<String> {
+        let id = self.doc.catalog().ok()?.get(b"Metadata").ok()?.as_reference().ok()?;
+        let stream = self.doc.get_object(id).ok()?.as_stream().ok()?;
+        let content = stream
+            .decompressed_content()
+            .unwrap_or_else(|_| stream.content.clone());
+        Some(
+            XMP_VOLATILE
+                .iter()
+                .fold(String::from_utf8_lossy(&content).into_owned(), |xmp, name| {
+                    mask_xmp_property(&xmp, name)
+                }),
+        )
+    }
+
+    /// Converts an object, resolving references which are not on the `stack` of the objects
+    /// being converted.
+    fn canonical(&self, object: &Object, stack: &mut Vec<ObjectId>) -> Canonical {
+        match object {
+            Object::Null => Canonical::Null,
+            Object::Boolean(value) => Canonical::Boolean(*value),
+            Object::Integer(value) => Canonical::Integer(*value),
+            Object::Real(value) => Canonical::Real(value.to_bits()),
+            Object::Name(name) => Canonical::Name(name.clone()),
+            Object::String(text, _) => Canonical::String(text.clone()),
+            Object::Array(items) => {
+                Canonical::Array(items.iter().map(|item| self.canonical(item, stack)).collect())
+            }
+            Object::Dictionary(dict) => self.dictionary(dict, stack, &[]),
+            Object::Stream(stream) => {
+                let Canonical::Dictionary(dict) =
+                    self.dictionary(&stream.dict, stack, &ENCODING_KEYS)
+                else {
+                    unreachable!("a dictionary is canonicalized into a dictionary");
+                };
+                let content = stream
+                    .decompressed_content()
+                    .unwrap_or_else(|_| stream.content.clone());
+                Canonical::Stream(dict, content)
+            }
+            Object::Reference(id) => {
+                if stack.contains(id) {
+                    return Canonical::Cycle;
+                }
+                if let Some(page) = self.pages.get(id) {
+                    return Canonical::Page(*page);
+                }
+                let Ok(object) = self.doc.get_object(*id) else {
+                    return Canonical::Null;
+                };
+                stack.push(*id);
+                let canonical = self.canonical(object, stack);
+                stack.pop();
+                canonical
+            }
+        }
+    }
+
+    /// Converts a dictionary, without the ignored keys and the `skipped` ones.
+    fn dictionary(
+        &self,
+        dict: &Dictionary,
+        stack: &mut Vec<ObjectId>,
+        skipped: &[&[u8]],
+    ) -> Canonical {
+        Canonical::Dictionary(
+            dict.iter()
+                .filter(|(key, _)| {
+                    !IGNORED_KEYS.contains(&key.as_slice()) && !skipped.contains(&key.as_slice())
+                })
+                .map(|(key, value)| (key.clone(), self.canonical(value, stack)))
+                .collect(),
+        )
+    }
+}
+
+/// Removes the values of an XMP property, in both the attribute form, e.g.
+/// `xmpMM:InstanceID="uuid:..."`, and the element form, e.g.
+/// `<xmpMM:InstanceID>uuid:...</xmpMM:InstanceID>`, whatever the namespace prefix.
+fn mask_xmp_property(xmp: &str, name: &str) -> String {
+    let mut masked = String::with_capacity(xmp.len());
+    let mut rest = xmp;
+    while let Some(start) = rest.find(name) {
+        let (before, after) = rest.split_at(start + name.len());
+        masked.push_str(before);
+        rest = after;
+        let closing_tag = before.rfind('<').is_some_and(|i| before[i..].starts_with("</"));
+        let close = match rest.chars().next() {
+            Some('=') => '"',
+            Some('>') if !closing_tag => '<',
+            _ => continue,
+        };
+        // Skip `="` of an attribute, or `>` of an element, then the value.
+        let open = if close == '"' { 2 } else { 1 };
+        let Some(end) = rest.get(open..).and_then(|value| value.find(close)) else {
+            continue;
+        };
+        masked.push_str(&rest[..open]);
+        rest = &rest[open + end..];
+    }
+    masked.push_str(rest);
+    masked
+}
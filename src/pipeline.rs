@@ -0,0 +1,100 @@
+use std::{error::Error, fs};
+
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::{
+    compile::{export_pdf_bytes, typst_compile},
+    error::Diagnostics,
+    set_permission_bytes, update_metadata_bytes,
+    world::SystemWorld,
+    CompileParams, PdfMetadata, PermissionParams,
+};
+
+/// Chains compilation, metadata update, and permission setting of a Typst document, passing the
+/// PDF between the steps in memory instead of through temporary files.
+///
+/// Steps run in the order of compilation, [metadata](Pipeline::metadata()) update, then
+/// [permission](Pipeline::permission()) setting, regardless of the order they are added, since
+/// the metadata can't be updated once the document is encrypted.
+///
+/// # Example
+///
+/// Following is an example of how to use the `Pipeline`:
+///
+/// ```no_run
+/// typster::Pipeline::new(typster::CompileParams::builder().input("sample.typ").build())
+///     .metadata(typster::PdfMetadata { title: "Title".into(), ..Default::default() })
+///     .permission(typster::PermissionParams {
+///         owner_password: Some("owner".to_string()),
+///         ..typster::PermissionParams::print_only()
+///     })
+///     .run()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Pipeline {
+    /// Parameters of the compilation. `output` is where [`run()`](Pipeline::run()) writes the
+    /// final PDF. Flattened when serialized, i.e. its fields sit next to `metadata` and
+    /// `permission`.
+    #[serde(flatten)]
+    pub compile: CompileParams,
+
+    /// Metadata to set after the compilation, if any.
+    pub metadata: Option<PdfMetadata>,
+
+    /// Permission to set at the end, if any.
+    pub permission: Option<PermissionParams>,
+}
+
+impl Pipeline {
+    /// Creates a pipeline which only compiles the document.
+    pub fn new(params: CompileParams) -> Self {
+        Self { compile: params, ..Default::default() }
+    }
+
+    /// Adds a step to update the metadata, the same way as
+    /// [`update_metadata()`](crate::update_metadata()).
+    pub fn metadata(mut self, metadata: PdfMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    /// Adds a step to set the permission, the same way as
+    /// [`set_permission()`](crate::set_permission()).
+    pub fn permission(mut self, params: PermissionParams) -> Self {
+        self.permission = Some(params);
+        self
+    }
+
+    /// Runs the steps and returns the final PDF in memory, without writing any file.
+    ///
+    /// # Returns
+    ///
+    /// Result containing the contents of the final PDF file, or the error of the first failed
+    /// step.
+    #[instrument(name = "pipeline", skip_all, fields(input = %self.compile.input.display()))]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Box<dyn Error>> {
+        let world = SystemWorld::new(&self.compile).map_err(|err| err.to_string())?;
+        let typst::diag::Warned { output, warnings } = typst_compile(&world);
+        let mut pdf = output
+            .and_then(|document| export_pdf_bytes(&document, &world, &self.compile))
+            .map_err(|errors| Diagnostics::new(warnings, errors))?;
+
+        if let Some(metadata) = &self.metadata {
+            pdf = update_metadata_bytes(&pdf, metadata)?;
+        }
+        if let Some(permission) = &self.permission {
+            pdf = set_permission_bytes(&pdf, permission)?;
+        }
+        Ok(pdf)
+    }
+
+    /// Runs the steps and writes the final PDF to the `output` of the compilation parameters,
+    /// which is always a PDF regardless of its extension. Nothing is written if any step fails.
+    pub fn run(&self) -> Result<(), Box<dyn Error>> {
+        fs::write(&self.compile.output, self.to_bytes()?)?;
+        Ok(())
+    }
+}
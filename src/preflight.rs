@@ -0,0 +1,487 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::Path,
+};
+
+use lopdf::{content::Content, Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+
+use crate::{fill_form::inherited, TypsterError};
+
+/// Identity transformation matrix.
+const IDENTITY: Matrix = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Maximum depth of nested form XObjects, to stop at cycles.
+const MAX_FORM_DEPTH: usize = 16;
+
+/// Tolerance, in points, when comparing page boxes.
+const BOX_TOLERANCE: f32 = 0.01;
+
+/// Transformation matrix `[a b c d e f]`, as in the `cm` operator.
+type Matrix = [f32; 6];
+
+/// Rectangle `[llx lly urx ury]`, normalized so that the lower-left corner comes first.
+type Rect = [f32; 4];
+
+/// Requirements of a print shop, for [`preflight()`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreflightProfile {
+    /// Whether every font used must be embedded. Defaults to `true`.
+    pub require_embedded_fonts: bool,
+    /// Minimum effective resolution of images as placed on the page, in pixels per inch, or
+    /// [`None`] to accept any resolution. Defaults to `300.0`.
+    pub min_image_ppi: Option<f32>,
+    /// Whether to check that the page boxes are valid, nested, i.e. the trim box within the bleed
+    /// box within the media box, and of the same size on every page. Defaults to `true`.
+    pub check_page_boxes: bool,
+    /// Whether transparency, i.e. soft masks, constant opacity below 1, blend modes, and
+    /// transparency groups, is accepted. Defaults to `true`. PDF/X-1a and PDF/X-3 workflows
+    /// require `false`.
+    pub allow_transparency: bool,
+}
+
+impl Default for PreflightProfile {
+    fn default() -> Self {
+        Self {
+            require_embedded_fonts: true,
+            min_image_ppi: Some(300.0),
+            check_page_boxes: true,
+            allow_transparency: true,
+        }
+    }
+}
+
+/// An issue found by [`preflight()`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PreflightFinding {
+    /// Check which failed.
+    pub check: PreflightCheck,
+    /// 1-based number of the page the issue is on, or the first page it's found on for a font.
+    pub page: u32,
+    /// Description of the issue.
+    pub message: String,
+}
+
+/// Kind of a [`PreflightFinding`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightCheck {
+    /// A font is used but not embedded.
+    FontNotEmbedded,
+    /// An image is placed below [`PreflightProfile::min_image_ppi`].
+    ImageResolution,
+    /// A page box is invalid, not nested in the enclosing box, or of a different size than on
+    /// the first page.
+    PageBoxes,
+    /// A page uses transparency, which the profile forbids.
+    Transparency,
+}
+
+/// Checks a PDF file against the requirements of a print shop before sending it, e.g. a document
+/// compiled with Typst and post-processed by other functions of this crate: embedded fonts, the
+/// effective resolution of images, consistent page boxes, and, if forbidden, transparency.
+///
+/// Only the content which is drawn is checked, i.e. the page content streams and the form
+/// XObjects they draw, not annotations or unused resources. This is a quick check rather than a
+/// full PDF/X validation.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file.
+/// - `profile` - [`PreflightProfile`] to check against.
+///
+/// # Returns
+///
+/// Result containing the issues found, in page order, or empty if the file passes, or a
+/// [`TypsterError`] telling why it failed, e.g. [`TypsterError::Pdf`] if the file is broken.
+///
+/// # Example
+///
+/// Following is an example of how to use the `preflight` function:
+///
+/// ```no_run
+/// let profile = typster::PreflightProfile {
+///     allow_transparency: false,
+///     ..Default::default()
+/// };
+/// let findings = typster::preflight(std::path::Path::new("flyer.pdf"), &profile).unwrap();
+/// for finding in &findings {
+///     println!("page {}: {:?}: {}", finding.page, finding.check, finding.message);
+/// }
+/// ```
+pub fn preflight(
+    path: &Path,
+    profile: &PreflightProfile,
+) -> Result<Vec<PreflightFinding>, TypsterError> {
+    let doc = Document::load(path)?;
+    let mut inspector = Inspector {
+        doc: &doc,
+        profile,
+        page: 0,
+        fonts: HashSet::new(),
+        images: HashSet::new(),
+        transparent: false,
+        findings: vec![],
+    };
+    let mut size = None;
+
+    for (number, id) in doc.get_pages() {
+        inspector.page = number;
+        inspector.images.clear();
+        inspector.transparent = false;
+
+        if profile.check_page_boxes {
+            inspector.check_boxes(id, &mut size);
+        }
+
+        let page = doc.get_dictionary(id)?;
+        if page
+            .get_deref(b"Group", &doc)
+            .and_then(Object::as_dict)
+            .is_ok_and(is_transparency_group)
+        {
+            inspector.transparency("the page is a transparency group");
+        }
+        let resources = inherited(&doc, id, b"Resources").and_then(|res| res.as_dict().ok());
+        if let Ok(content) = doc.get_page_content(id) {
+            inspector.content(&content, resources, IDENTITY, 0);
+        }
+    }
+    Ok(inspector.findings)
+}
+
+/// Returns whether the program of a font is embedded.
+pub(crate) fn is_embedded(doc: &Document, font: &Dictionary) -> bool {
+    font.get_deref(b"FontDescriptor", doc)
+        .and_then(Object::as_dict)
+        .is_ok_and(|descriptor| {
+            [b"FontFile".as_slice(), b"FontFile2", b"FontFile3"]
+                .iter()
+                .any(|key| descriptor.has(key))
+        })
+}
+
+/// Returns whether a group attributes dictionary is a transparency group.
+fn is_transparency_group(group: &Dictionary) -> bool {
+    group
+        .get(b"S")
+        .and_then(Object::as_name)
+        .is_ok_and(|name| name == b"Transparency")
+}
+
+/// Multiplies two matrices, i.e. applies `m` then `n`.
+fn multiply(m: Matrix, n: Matrix) -> Matrix {
+    [
+        m[0] * n[0] + m[1] * n[2],
+        m[0] * n[1] + m[1] * n[3],
+        m[2] * n[0] + m[3] * n[2],
+        m[2] * n[1] + m[3] * n[3],
+        m[4] * n[0] + m[5] * n[2] + n[4],
+        m[4] * n[1] + m[5] * n[3] + n[5],
+    ]
+}
+
+/// Reads a matrix from the operands of `cm` or the `Matrix` of a form XObject.
+fn matrix(values: &[Object]) -> Option<Matrix> {
+    let values = values
+        .iter()
+        .map(|value| value.as_float().ok())
+        .collect::<Option<Vec<_>>>()?;
+    values.try_into().ok()
+}
+
+/// Reads a rectangle, normalized.
+fn rect(value: &Object) -> Option<Rect> {
+    let values = value
+        .as_array()
+        .ok()?
+        .iter()
+        .map(|value| value.as_float().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let [x1, y1, x2, y2] = values.try_into().ok()?;
+    Some([x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2)])
+}
+
+/// Returns whether the rectangle `inner` is within `outer`.
+fn contains(outer: Rect, inner: Rect) -> bool {
+    inner[0] >= outer[0] - BOX_TOLERANCE
+        && inner[1] >= outer[1] - BOX_TOLERANCE
+        && inner[2] <= outer[2] + BOX_TOLERANCE
+        && inner[3] <= outer[3] + BOX_TOLERANCE
+}
+
+/// Walks the content of the pages, and collects the findings.
+struct Inspector<'a> {
+    doc: &'a Document,
+    profile: &'a PreflightProfile,
+    /// Number of the page being inspected.
+    page: u32,
+    /// Fonts already checked, on any page.
+    fonts: HashSet<ObjectId>,
+    /// Images already reported on the current page.
+    images: HashSet<ObjectId>,
+    /// Whether transparency has been reported on the current page.
+    transparent: bool,
+    findings: Vec<PreflightFinding>,
+}
+
+impl Inspector<'_> {
+    fn report(&mut self, check: PreflightCheck, message: String) {
+        self.findings
+            .push(PreflightFinding { check, page: self.page, message });
+    }
+
+    /// Reports transparency once per page.
+    fn transparency(&mut self, reason: &str) {
+        if !self.profile.allow_transparency && !self.transparent {
+            self.transparent = true;
+            self.report(PreflightCheck::Transparency, reason.to_string());
+        }
+    }
+
+    /// Checks the boxes of a page, and that its trim size matches the `size` of the first page.
+    fn check_boxes(&mut self, page: ObjectId, size: &mut Option<(f32, f32)>) {
+        let doc = self.doc;
+        let get = |key: &[u8]| inherited(doc, page, key).map(rect);
+        let Some(Some(media)) = get(b"MediaBox") else {
+            self.report(PreflightCheck::PageBoxes, "the media box is missing or invalid".into());
+            return;
+        };
+
+        let mut boxes = BTreeMap::new();
+        for (key, name) in [
+            (b"CropBox".as_slice(), "crop"),
+            (b"BleedBox", "bleed"),
+            (b"TrimBox", "trim"),
+            (b"ArtBox", "art"),
+        ] {
+            match get(key) {
+                Some(Some(rect)) if rect[2] - rect[0] > 0.0 && rect[3] - rect[1] > 0.0 => {
+                    boxes.insert(name, rect);
+                }
+                Some(_) => {
+                    self.report(PreflightCheck::PageBoxes, format!("the {name} box is invalid"))
+                }
+                None => {}
+            }
+        }
+
+        let bleed = boxes.get("bleed").copied().unwrap_or(media);
+        for (inner, outer, outer_name) in [
+            ("crop", media, "media"),
+            ("bleed", media, "media"),
+            ("trim", bleed, "bleed"),
+            ("art", bleed, "bleed"),
+        ] {
+            if boxes.get(inner).is_some_and(|rect| !contains(outer, *rect)) {
+                self.report(
+                    PreflightCheck::PageBoxes,
+                    format!("the {inner} box extends beyond the {outer_name} box"),
+                );
+            }
+        }
+
+        let trim = boxes.get("trim").copied().unwrap_or(media);
+        let current = (trim[2] - trim[0], trim[3] - trim[1]);
+        match *size {
+            None => *size = Some(current),
+            Some(first)
+                if (first.0 - current.0).abs() > BOX_TOLERANCE
+                    || (first.1 - current.1).abs() > BOX_TOLERANCE =>
+            {
+                self.report(
+                    PreflightCheck::PageBoxes,
+                    format!(
+                        "the trim size {:.1} x {:.1} pt differs from {:.1} x {:.1} pt of the \
+                         first page",
+                        current.0, current.1, first.0, first.1
+                    ),
+                );
+            }
+            Some(_) => {}
+        }
+    }
+
+    /// Inspects a content stream drawn with the transformation `ctm`, and the form XObjects it
+    /// draws, at the nesting `depth`.
+    fn content(
+        &mut self,
+        content: &[u8],
+        resources: Option<&Dictionary>,
+        ctm: Matrix,
+        depth: usize,
+    ) {
+        let Ok(content) = Content::decode(content) else {
+            return;
+        };
+        let doc = self.doc;
+        let resource = |category: &[u8], name: &Object| {
+            let name = name.as_name().ok()?;
+            let object = resources?
+                .get_deref(category, doc)
+                .and_then(Object::as_dict)
+                .ok()?
+                .get(name)
+                .ok()?;
+            doc.dereference(object).ok()
+        };
+
+        let mut stack = vec![];
+        let mut ctm = ctm;
+        for operation in &content.operations {
+            let operand = operation.operands.first();
+            match operation.operator.as_str() {
+                "q" => stack.push(ctm),
+                "Q" => ctm = stack.pop().unwrap_or(ctm),
+                "cm" => {
+                    if let Some(m) = matrix(&operation.operands) {
+                        ctm = multiply(m, ctm);
+                    }
+                }
+                "Tf" => {
+                    if let Some((id, font)) = operand.and_then(|name| resource(b"Font", name)) {
+                        if let Ok(font) = font.as_dict() {
+                            self.font(id, font);
+                        }
+                    }
+                }
+                "gs" => {
+                    if let Some(Ok(state)) = operand
+                        .and_then(|name| resource(b"ExtGState", name))
+                        .map(|(_, state)| state.as_dict())
+                    {
+                        self.graphics_state(state);
+                    }
+                }
+                "Do" => {
+                    if let Some((id, Ok(xobject))) = operand
+                        .and_then(|name| resource(b"XObject", name))
+                        .map(|(id, xobject)| (id, xobject.as_stream()))
+                    {
+                        let name = String::from_utf8_lossy(
+                            operand.and_then(|name| name.as_name().ok()).unwrap_or_default(),
+                        )
+                        .into_owned();
+                        match xobject.dict.get(b"Subtype").and_then(Object::as_name) {
+                            Ok(b"Image") => self.image(id, &name, &xobject.dict, ctm),
+                            Ok(b"Form") if depth < MAX_FORM_DEPTH => {
+                                if xobject
+                                    .dict
+                                    .get_deref(b"Group", doc)
+                                    .and_then(Object::as_dict)
+                                    .is_ok_and(is_transparency_group)
+                                {
+                                    self.transparency(&format!(
+                                        "the form {name} is a transparency group"
+                                    ));
+                                }
+                                let form = xobject
+                                    .dict
+                                    .get(b"Matrix")
+                                    .and_then(Object::as_array)
+                                    .ok()
+                                    .and_then(|m| matrix(m))
+                                    .unwrap_or(IDENTITY);
+                                let form_resources = xobject
+                                    .dict
+                                    .get_deref(b"Resources", doc)
+                                    .and_then(Object::as_dict)
+                                    .ok()
+                                    .or(resources);
+                                let content = xobject
+                                    .decompressed_content()
+                                    .unwrap_or_else(|_| xobject.content.clone());
+                                self.content(
+                                    &content,
+                                    form_resources,
+                                    multiply(form, ctm),
+                                    depth + 1,
+                                );
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks that a font is embedded, once per font.
+    fn font(&mut self, id: Option<ObjectId>, font: &Dictionary) {
+        if !self.profile.require_embedded_fonts || id.is_some_and(|id| !self.fonts.insert(id)) {
+            return;
+        }
+        let embedded = match font.get(b"Subtype").and_then(Object::as_name) {
+            Ok(b"Type3") => true,
+            Ok(b"Type0") => font
+                .get_deref(b"DescendantFonts", self.doc)
+                .and_then(Object::as_array)
+                .ok()
+                .and_then(|fonts| fonts.first())
+                .and_then(|font| self.doc.dereference(font).ok())
+                .and_then(|(_, font)| font.as_dict().ok())
+                .is_some_and(|font| is_embedded(self.doc, font)),
+            _ => is_embedded(self.doc, font),
+        };
+        if !embedded {
+            let base = font.get(b"BaseFont").and_then(Object::as_name).unwrap_or_default();
+            self.report(
+                PreflightCheck::FontNotEmbedded,
+                format!("the font {} is not embedded", String::from_utf8_lossy(base)),
+            );
+        }
+    }
+
+    /// Checks an extended graphics state for transparency.
+    fn graphics_state(&mut self, state: &Dictionary) {
+        let opacity = |key: &[u8]| state.get(key).and_then(Object::as_float).is_ok_and(|a| a < 1.0);
+        if state
+            .get(b"SMask")
+            .is_ok_and(|mask| mask.as_name().map_or(true, |name| name != b"None"))
+        {
+            self.transparency("a soft mask is used");
+        } else if opacity(b"CA") || opacity(b"ca") {
+            self.transparency("an opacity below 1 is used");
+        } else if state
+            .get(b"BM")
+            .and_then(Object::as_name)
+            .is_ok_and(|mode| mode != b"Normal" && mode != b"Compatible")
+        {
+            self.transparency("a blend mode is used");
+        }
+    }
+
+    /// Checks the resolution of an image placed with the transformation `ctm`, which maps the
+    /// unit square onto the page, and whether it has a soft mask.
+    fn image(&mut self, id: Option<ObjectId>, name: &str, image: &Dictionary, ctm: Matrix) {
+        if image.has(b"SMask")
+            || image.get(b"SMaskInData").and_then(Object::as_i64).unwrap_or(0) > 0
+        {
+            self.transparency(&format!("the image {name} has a soft mask"));
+        }
+
+        let Some(min) = self.profile.min_image_ppi else {
+            return;
+        };
+        if id.is_some_and(|id| !self.images.insert(id)) {
+            return;
+        }
+        let pixels = |key: &[u8]| image.get(key).and_then(Object::as_float).unwrap_or(0.0);
+        let (width, height) = (pixels(b"Width"), pixels(b"Height"));
+        // Size of the placed image in inches, along both of its axes.
+        let placed_width = ctm[0].hypot(ctm[1]) / 72.0;
+        let placed_height = ctm[2].hypot(ctm[3]) / 72.0;
+        if placed_width <= 0.0 || placed_height <= 0.0 {
+            return;
+        }
+        let ppi = (width / placed_width).min(height / placed_height);
+        if ppi < min {
+            self.report(
+                PreflightCheck::ImageResolution,
+                format!("the image {name} is placed at {ppi:.0} ppi, below {min:.0} ppi"),
+            );
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::Pipeline;
+
+/// File name of the manifest [`Project::load()`] reads.
+const MANIFEST: &str = "typster.toml";
+
+/// A Typst project described by a `typster.toml` manifest, which lists the documents to build
+/// along with their fonts, inputs, PDF standards, metadata, and permission.
+///
+/// See also [`Project::load()`].
+#[derive(Debug, Clone, Default)]
+pub struct Project {
+    /// Directory the manifest is in, which relative paths of the manifest are resolved against.
+    pub root: PathBuf,
+
+    /// Documents to build, in the order of the manifest, with the settings shared by all targets
+    /// already applied.
+    pub targets: Vec<Pipeline>,
+}
+
+/// Contents of a `typster.toml` manifest.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Manifest {
+    /// Font directories shared by all targets, searched before those of each target.
+    font_paths: Vec<PathBuf>,
+
+    /// `sys.inputs` shared by all targets. Those of a target take precedence.
+    dict: Vec<(String, String)>,
+
+    /// Path to local packages, unless set by a target.
+    package_path: Option<PathBuf>,
+
+    /// Path to the package cache, unless set by a target.
+    package_cache_path: Option<PathBuf>,
+
+    /// Documents to build.
+    #[serde(rename = "target")]
+    targets: Vec<Pipeline>,
+}
+
+impl Project {
+    /// Loads the `typster.toml` manifest of a project, e.g. to use typster as the engine of a
+    /// build tool.
+    ///
+    /// The top level of the manifest holds `font_paths`, `dict`, `package_path`, and
+    /// `package_cache_path` shared by all targets. Each `[[target]]` table takes the fields of
+    /// [`CompileParams`](crate::CompileParams), plus optional `metadata` and `permission` tables
+    /// of [`PdfMetadata`](crate::PdfMetadata) and [`PermissionParams`](crate::PermissionParams).
+    /// Relative paths are resolved against `dir`, and an omitted `output` defaults to the input
+    /// with the `pdf` extension.
+    ///
+    /// # Arguments
+    ///
+    /// - `dir` - Directory containing `typster.toml`.
+    ///
+    /// # Returns
+    ///
+    /// Result containing the loaded [`Project`].
+    ///
+    /// # Example
+    ///
+    /// Following is an example of how to use the `load` function, with `typster.toml` like:
+    ///
+    /// ```toml
+    /// font_paths = ["fonts"]
+    /// dict = [["version", "1.0"]]
+    ///
+    /// [[target]]
+    /// input = "report.typ"
+    /// output = "out/report.pdf"
+    /// pdf_standards = ["a-2b"]
+    ///
+    /// [target.metadata]
+    /// title = "Report"
+    /// author = "Author"
+    ///
+    /// [[target]]
+    /// input = "handout.typ"
+    ///
+    /// [target.permission]
+    /// owner_password = "owner"
+    /// allow_extract = false
+    /// ```
+    ///
+    /// ```no_run
+    /// let project = typster::Project::load(std::path::Path::new(".")).unwrap();
+    /// for output in project.build().unwrap() {
+    ///     println!("built {}", output.display());
+    /// }
+    /// ```
+    pub fn load(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let path = dir.join(MANIFEST);
+        let text = fs::read_to_string(&path)
+            .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+        let manifest: Manifest = toml::from_str(&text)
+            .map_err(|err| format!("failed to parse {}: {err}", path.display()))?;
+
+        let targets = manifest
+            .targets
+            .into_iter()
+            .map(|mut target| {
+                let params = &mut target.compile;
+                if params.input.as_os_str().is_empty() {
+                    return Err(format!("a target of {} has no input", path.display()));
+                }
+                if params.output.as_os_str().is_empty() {
+                    params.output = params.input.with_extension("pdf");
+                }
+                params.font_paths.splice(0..0, manifest.font_paths.iter().cloned());
+                params.dict.splice(0..0, manifest.dict.iter().cloned());
+                params.package_path = params.package_path.take().or(manifest.package_path.clone());
+                params.package_cache_path = params
+                    .package_cache_path
+                    .take()
+                    .or(manifest.package_cache_path.clone());
+                params.resolve_paths(dir);
+                Ok(target)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { root: dir.to_path_buf(), targets })
+    }
+
+    /// Builds all targets in the order of the manifest, creating the directories of their outputs
+    /// as needed. Stops at the first target which fails.
+    ///
+    /// # Returns
+    ///
+    /// Result containing the paths to the written PDF files, or the error of the failed target.
+    pub fn build(&self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let mut outputs = vec![];
+        for target in &self.targets {
+            let output = &target.compile.output;
+            if let Some(parent) = output.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            target.run().map_err(|err| {
+                format!("failed to build {}: {err}", target.compile.input.display())
+            })?;
+            outputs.push(output.clone());
+        }
+        Ok(outputs)
+    }
+}
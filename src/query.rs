@@ -0,0 +1,58 @@
+use std::error::Error;
+
+use typst::{
+    foundations::{IntoValue, Label, Selector, Value},
+    model::Document,
+};
+
+use crate::{compile::compile_document, world::SystemWorld, CompileParams, TypsterError};
+
+/// Queries the elements with a label of a Typst document, like `typst query`, e.g. to read
+/// metadata from a document in a build script.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`] of the document. The output file is not written.
+/// - `label` - Label of the elements, without angle brackets, e.g. `results`.
+/// - `field` - Field of the elements to return instead of the elements themselves, e.g. `value`
+///   of `metadata` elements. Elements without the field are skipped.
+///
+/// # Returns
+///
+/// Result containing the elements, or their field, as JSON in the order of the document.
+///
+/// # Example
+///
+/// Following is an example of how to use the `query` function:
+///
+/// ```no_run
+/// // #metadata("1.2.3") <version>
+/// let params = typster::CompileParams::builder().input("main.typ").build();
+/// let versions = typster::query(&params, "version", Some("value")).unwrap();
+/// assert_eq!(versions, vec![serde_json::json!("1.2.3")]);
+/// ```
+pub fn query(
+    params: &CompileParams,
+    label: &str,
+    field: Option<&str>,
+) -> Result<Vec<serde_json::Value>, Box<dyn Error>> {
+    let world = SystemWorld::new(params).map_err(TypsterError::from)?;
+    let document = compile_document(&world)?;
+    Ok(select(&document, label, field)
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<_, _>>()?)
+}
+
+/// Returns the elements with a label, or their field, of a compiled document.
+pub(crate) fn select(document: &Document, label: &str, field: Option<&str>) -> Vec<Value> {
+    document
+        .introspector
+        .query(&Selector::Label(Label::new(label)))
+        .into_iter()
+        .filter_map(|element| match field {
+            Some(field) => element.get_by_name(field).ok(),
+            None => Some(element.into_value()),
+        })
+        .collect()
+}
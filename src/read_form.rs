@@ -0,0 +1,161 @@
+use std::{error::Error, path::Path};
+
+use lopdf::{decode_text_string, Document, Object};
+use serde::{Deserialize, Serialize};
+
+use crate::fill_form::{inherited, on_state, terminal_fields, widgets, PUSH_BUTTON, RADIO};
+
+/// Field flag of read-only fields, i.e. bit position 1.
+const READ_ONLY: u32 = 1;
+/// Field flag of required fields, i.e. bit position 2.
+const REQUIRED: u32 = 1 << 1;
+/// Field flag of combo boxes, i.e. bit position 18.
+const COMBO: u32 = 1 << 17;
+
+/// Form field, i.e. AcroForm field, of a PDF file read with [`read_form()`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FormField {
+    /// Fully qualified name of the field, e.g. `applicant.name`, as taken by
+    /// [`fill_form()`](crate::fill_form()).
+    pub name: String,
+
+    /// Type of the field.
+    pub field_type: FormFieldType,
+
+    /// Current value of the field. Check boxes and radio buttons have the name of their selected
+    /// state, e.g. `Yes`, or `Off` if not selected. Multiple selections of a list box are separated
+    /// by newlines. Empty if not set.
+    pub value: String,
+
+    /// Options of the field, i.e. the export values of a combo box or list box, or the names of the
+    /// on states of check boxes and radio buttons. Empty for the other types.
+    pub options: Vec<String>,
+
+    /// Field flags (`Ff`) as defined by the PDF specification, e.g. `1` for read-only.
+    pub flags: u32,
+
+    /// Whether the field is read-only.
+    pub read_only: bool,
+
+    /// Whether the field is required to have a value when the form is submitted.
+    pub required: bool,
+}
+
+/// Type of a [`FormField`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormFieldType {
+    /// Text field
+    #[default]
+    Text,
+    /// Check box
+    CheckBox,
+    /// Radio button group
+    RadioButton,
+    /// Push button, which has no value
+    PushButton,
+    /// Combo box, i.e. drop-down list
+    ComboBox,
+    /// List box
+    ListBox,
+    /// Signature field
+    Signature,
+}
+
+/// Reads the form fields, i.e. AcroForm fields, of a PDF file, e.g. to inspect a form before
+/// filling it with [`fill_form()`](crate::fill_form()), or to export submitted data.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file.
+///
+/// # Returns
+///
+/// Result containing the terminal fields, i.e. the ones which hold values, in the order of the
+/// form. It's empty if the file has no form.
+///
+/// # Example
+///
+/// Following is an example of how to use the `read_form` function:
+///
+/// ```no_run
+/// for field in typster::read_form(std::path::Path::new("form.pdf")).unwrap() {
+///     println!("{} ({:?}): {}", field.name, field.field_type, field.value);
+/// }
+/// ```
+pub fn read_form(path: &Path) -> Result<Vec<FormField>, Box<dyn Error>> {
+    let doc = Document::load(path)?;
+
+    let mut fields = vec![];
+    for (name, field) in terminal_fields(&doc)? {
+        let flags = inherited(&doc, field, b"Ff")
+            .and_then(|ff| ff.as_i64().ok())
+            .unwrap_or(0) as u32;
+        let field_type = match inherited(&doc, field, b"FT").and_then(|ft| ft.as_name().ok()) {
+            Some(b"Btn") if flags & PUSH_BUTTON as u32 != 0 => FormFieldType::PushButton,
+            Some(b"Btn") if flags & RADIO as u32 != 0 => FormFieldType::RadioButton,
+            Some(b"Btn") => FormFieldType::CheckBox,
+            Some(b"Ch") if flags & COMBO != 0 => FormFieldType::ComboBox,
+            Some(b"Ch") => FormFieldType::ListBox,
+            Some(b"Sig") => FormFieldType::Signature,
+            _ => FormFieldType::Text,
+        };
+
+        let value = match inherited(&doc, field, b"V") {
+            Some(Object::Array(values)) => values
+                .iter()
+                .filter_map(|value| text(&doc, value))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Some(value) => text(&doc, value).unwrap_or_default(),
+            None => String::new(),
+        };
+
+        let mut options = vec![];
+        match field_type {
+            FormFieldType::CheckBox | FormFieldType::RadioButton => {
+                for widget in widgets(&doc, field)? {
+                    if let Some(state) = on_state(&doc, widget) {
+                        let state = String::from_utf8_lossy(&state).to_string();
+                        if !options.contains(&state) {
+                            options.push(state);
+                        }
+                    }
+                }
+            }
+            FormFieldType::ComboBox | FormFieldType::ListBox => {
+                if let Some(Object::Array(opts)) = inherited(&doc, field, b"Opt") {
+                    // Each option is either the value, or a pair of the export value and the text
+                    // to display.
+                    options.extend(opts.iter().filter_map(|opt| match doc.dereference(opt) {
+                        Ok((_, Object::Array(pair))) => pair.first().and_then(|v| text(&doc, v)),
+                        Ok((_, value)) => text(&doc, value),
+                        Err(_) => None,
+                    }));
+                }
+            }
+            _ => {}
+        }
+
+        fields.push(FormField {
+            name,
+            field_type,
+            value,
+            options,
+            flags,
+            read_only: flags & READ_ONLY != 0,
+            required: flags & REQUIRED != 0,
+        });
+    }
+
+    Ok(fields)
+}
+
+/// Decodes a text string or a name, e.g. the state of a check box, to a string.
+fn text(doc: &Document, value: &Object) -> Option<String> {
+    match doc.dereference(value).ok()?.1 {
+        Object::Name(name) => Some(String::from_utf8_lossy(name).to_string()),
+        value @ Object::String(..) => decode_text_string(value).ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,546 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fmt::Write,
+    path::Path,
+};
+
+use lopdf::{
+    content::{Content, Operation},
+    Dictionary, Document, Object, Stream,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    merge_pdfs::inline_inherited_attributes,
+    number_pages::HELVETICA_WIDTHS,
+    overlay::{display_box, overlay, transform_rect},
+};
+
+const IDENTITY: [f32; 6] = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+
+/// Regions of a page to redact with [`redact()`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Redaction {
+    /// 1-based page number.
+    pub page: u32,
+
+    /// Regions to redact as `[left, bottom, right, top]`, in points from the bottom-left corner of
+    /// the page as displayed, i.e. with its rotation applied.
+    pub rects: Vec<[f32; 4]>,
+}
+
+/// Redacts regions of pages of a PDF file, e.g. to publish a sanitized version of a compiled
+/// report.
+///
+/// Unlike drawing boxes over the content, the underlying content is removed, so that it can't be
+/// recovered by copying text or by removing the boxes:
+///
+/// - Characters of text whose glyphs overlap the regions are removed. The rest of the text keeps
+///   its position.
+/// - Images and form XObjects which overlap the regions are removed as a whole.
+/// - Annotations, e.g. links and form fields, which overlap the regions are removed.
+///
+/// Then the regions are filled with opaque black boxes. Vector graphics are left as they are under
+/// the boxes. Content outside the pages, e.g. the metadata, outline, or attachments, is not
+/// redacted, so strip or replace it separately if needed.
+///
+/// The positions of characters are computed from the widths of the fonts, and the height of a line
+/// from their ascent and descent, so make the regions generous. Text of composite fonts is assumed
+/// to use two-byte codes, e.g. `Identity-H`, as in documents compiled by Typst.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file, which may be the same as the input file.
+/// - `redactions` - [`Redaction`]s to apply.
+///
+/// # Example
+///
+/// Following is an example of how to use the `redact` function:
+///
+/// ```no_run
+/// typster::redact(
+///     std::path::Path::new("report.pdf"),
+///     std::path::Path::new("report-redacted.pdf"),
+///     vec![typster::Redaction { page: 1, rects: vec![[72.0, 700.0, 300.0, 720.0]] }],
+/// )
+/// .unwrap();
+/// ```
+pub fn redact(
+    input: &Path,
+    output: &Path,
+    redactions: Vec<Redaction>,
+) -> Result<(), Box<dyn Error>> {
+    let mut doc = Document::load(input)?;
+    let pages = doc.get_pages().into_values().collect::<Vec<_>>();
+    if let Some(redaction) = redactions
+        .iter()
+        .find(|redaction| redaction.page == 0 || redaction.page as usize > pages.len())
+    {
+        return Err(format!("page {} does not exist", redaction.page).into());
+    }
+    inline_inherited_attributes(&mut doc, &pages)?;
+
+    let mut regions = BTreeMap::<u32, Vec<[f32; 4]>>::new();
+    for redaction in redactions {
+        let rects = redaction
+            .rects
+            .into_iter()
+            .map(|[x0, y0, x1, y1]| [x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)]);
+        regions.entry(redaction.page).or_default().extend(rects);
+    }
+
+    for (number, rects) in regions.into_iter().filter(|(_, rects)| !rects.is_empty()) {
+        let page = pages[number as usize - 1];
+        let display = display_box(doc.get_dictionary(page)?)?;
+        // Regions in the user space of the page, where the content is drawn.
+        let regions = rects
+            .iter()
+            .map(|&rect| transform_rect(display.matrix, rect))
+            .collect::<Vec<_>>();
+
+        let resources = match doc.get_dictionary(page)?.get(b"Resources") {
+            Ok(resources) => doc.dereference(resources)?.1.as_dict()?.clone(),
+            Err(_) => Dictionary::new(),
+        };
+        let content = Content::decode(&doc.get_page_content(page)?)?;
+        let operations = Redactor::new(&doc, &resources, &regions).redact(content.operations);
+        let mut contents = Stream::new(Dictionary::new(), Content { operations }.encode()?);
+        let _ = contents.compress();
+        let contents = doc.add_object(contents);
+
+        let annots = match doc.get_dictionary(page)?.get(b"Annots") {
+            Ok(annots) => doc.dereference(annots)?.1.as_array()?.clone(),
+            Err(_) => vec![],
+        };
+        let annots = annots
+            .into_iter()
+            .filter(|annot| {
+                let rect = doc
+                    .dereference(annot)
+                    .and_then(|(_, annot)| annot.as_dict())
+                    .ok()
+                    .and_then(|annot| floats(annot.get(b"Rect").ok()?))
+                    .and_then(|rect| <[f32; 4]>::try_from(rect).ok());
+                match rect {
+                    Some(rect) => !regions.iter().any(|region| overlaps(region, &rect)),
+                    None => true,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let dict = doc.get_dictionary_mut(page)?;
+        dict.set("Contents", contents);
+        if annots.is_empty() {
+            dict.remove(b"Annots");
+        } else {
+            dict.set("Annots", annots);
+        }
+
+        // Fill the regions on the page as displayed.
+        let mut content = String::from("0 g\n");
+        for [x0, y0, x1, y1] in rects {
+            writeln!(content, "{x0} {y0} {} {} re", x1 - x0, y1 - y0)?;
+        }
+        content.push_str("f\n");
+        let mut form = Dictionary::new();
+        form.set("Type", Object::Name(b"XObject".to_vec()));
+        form.set("Subtype", Object::Name(b"Form".to_vec()));
+        form.set(
+            "BBox",
+            vec![
+                Object::Integer(0),
+                Object::Integer(0),
+                Object::Real(display.width),
+                Object::Real(display.height),
+            ],
+        );
+        let form = doc.add_object(Stream::new(form, content.into_bytes()));
+        overlay(&mut doc, page, form, display.matrix)?;
+    }
+
+    doc.prune_objects();
+    doc.save(output)?;
+
+    Ok(())
+}
+
+/// Graphics state which affects the position of text and images.
+#[derive(Clone)]
+struct State {
+    ctm: [f32; 6],
+    font: Vec<u8>,
+    font_size: f32,
+    char_spacing: f32,
+    word_spacing: f32,
+    horizontal_scaling: f32,
+    leading: f32,
+    rise: f32,
+}
+
+/// Metrics of a font to compute the positions of its glyphs.
+struct Font {
+    /// Whether character codes are two bytes long, i.e. of a composite font.
+    two_bytes: bool,
+    /// Widths of the glyphs in text space units, i.e. scaled to the font size of 1.
+    widths: HashMap<u32, f32>,
+    default_width: f32,
+    ascent: f32,
+    descent: f32,
+}
+
+impl Font {
+    /// Fallback of a missing or malformed font, which approximates Helvetica.
+    fn fallback() -> Self {
+        let widths = (b' '..=b'~')
+            .map(|code| (code as u32, HELVETICA_WIDTHS[(code - b' ') as usize] as f32 / 1000.0))
+            .collect();
+        Self {
+            two_bytes: false,
+            widths,
+            default_width: 0.5,
+            ascent: 0.8,
+            descent: -0.2,
+        }
+    }
+
+    fn load(doc: &Document, font: &Dictionary) -> Self {
+        let mut metrics = Self::fallback();
+        let number = |value: &Object| doc.dereference(value).ok()?.1.as_float().ok();
+        let descriptor = |font: &Dictionary| {
+            font.get_deref(b"FontDescriptor", doc)
+                .and_then(Object::as_dict)
+                .ok()
+                .cloned()
+        };
+
+        let (widths, descriptor) = if font
+            .get(b"Subtype")
+            .and_then(Object::as_name)
+            .is_ok_and(|subtype| subtype == b"Type0")
+        {
+            let Some(descendant) = font
+                .get_deref(b"DescendantFonts", doc)
+                .and_then(Object::as_array)
+                .ok()
+                .and_then(|fonts| doc.dereference(fonts.first()?).ok()?.1.as_dict().ok())
+            else {
+                return metrics;
+            };
+            metrics.two_bytes = true;
+            metrics.default_width =
+                descendant.get(b"DW").ok().and_then(number).unwrap_or(1000.0) / 1000.0;
+
+            // Either `first [w1 w2 ...]`, or `first last w`.
+            let mut widths = HashMap::new();
+            let entries = descendant.get_deref(b"W", doc).and_then(Object::as_array);
+            let mut entries = entries.map(|entries| entries.iter()).into_iter().flatten();
+            while let Some(first) = entries.next().and_then(number) {
+                let first = first as u32;
+                match entries.next().map(|next| doc.dereference(next)) {
+                    Some(Ok((_, Object::Array(array)))) => {
+                        for (i, width) in array.iter().enumerate() {
+                            if let Some(width) = number(width) {
+                                widths.insert(first + i as u32, width / 1000.0);
+                            }
+                        }
+                    }
+                    Some(Ok((_, last))) => {
+                        let (Some(last), Some(width)) =
+                            (number(last), entries.next().and_then(number))
+                        else {
+                            break;
+                        };
+                        for code in first..=(last as u32).min(0xFFFF) {
+                            widths.insert(code, width / 1000.0);
+                        }
+                    }
+                    _ => break,
+                }
+            }
+            (widths, descriptor(descendant))
+        } else {
+            // Type 3 fonts have their own glyph space, while others have 1/1000 of text space.
+            let scale = font
+                .get_deref(b"FontMatrix", doc)
+                .and_then(Object::as_array)
+                .ok()
+                .and_then(|matrix| number(matrix.first()?))
+                .map(f32::abs)
+                .unwrap_or(0.001);
+            let first = font.get(b"FirstChar").ok().and_then(number).unwrap_or(0.0) as u32;
+            let widths = font
+                .get_deref(b"Widths", doc)
+                .and_then(Object::as_array)
+                .map(|widths| {
+                    widths
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, width)| Some((first + i as u32, number(width)? * scale)))
+                        .collect::<HashMap<_, _>>()
+                })
+                .unwrap_or_default();
+            // Standard fonts may have no widths.
+            let widths = if widths.is_empty() { metrics.widths.clone() } else { widths };
+            (widths, descriptor(font))
+        };
+        metrics.widths = widths;
+
+        if let Some(descriptor) = descriptor {
+            if let Some(width) = descriptor.get(b"MissingWidth").ok().and_then(number) {
+                if !metrics.two_bytes {
+                    metrics.default_width = width / 1000.0;
+                }
+            }
+            if let Some(ascent) = descriptor.get(b"Ascent").ok().and_then(number) {
+                metrics.ascent = ascent / 1000.0;
+            }
+            if let Some(descent) = descriptor.get(b"Descent").ok().and_then(number) {
+                metrics.descent = descent / 1000.0;
+            }
+        }
+        metrics
+    }
+
+    fn width(&self, code: u32) -> f32 {
+        self.widths.get(&code).copied().unwrap_or(self.default_width)
+    }
+}
+
+/// Removes text and images which overlap the regions from the content of a page.
+struct Redactor<'a> {
+    doc: &'a Document,
+    resources: &'a Dictionary,
+    regions: &'a [[f32; 4]],
+    fonts: HashMap<Vec<u8>, Font>,
+}
+
+impl<'a> Redactor<'a> {
+    fn new(doc: &'a Document, resources: &'a Dictionary, regions: &'a [[f32; 4]]) -> Self {
+        Self { doc, resources, regions, fonts: HashMap::new() }
+    }
+
+    fn redact(&mut self, operations: Vec<Operation>) -> Vec<Operation> {
+        let mut state = State {
+            ctm: IDENTITY,
+            font: vec![],
+            font_size: 0.0,
+            char_spacing: 0.0,
+            word_spacing: 0.0,
+            horizontal_scaling: 1.0,
+            leading: 0.0,
+            rise: 0.0,
+        };
+        let mut stack = vec![];
+        // Text matrix and text line matrix.
+        let (mut tm, mut tlm) = (IDENTITY, IDENTITY);
+
+        let mut output = Vec::with_capacity(operations.len());
+        for operation in operations {
+            let numbers = operation
+                .operands
+                .iter()
+                .map(|operand| operand.as_float().ok())
+                .collect::<Option<Vec<_>>>()
+                .unwrap_or_default();
+            match (operation.operator.as_str(), numbers.as_slice()) {
+                ("q", _) => stack.push(state.clone()),
+                ("Q", _) => state = stack.pop().unwrap_or(state),
+                ("cm", &[a, b, c, d, e, f]) => state.ctm = multiply([a, b, c, d, e, f], state.ctm),
+                ("BT", _) => (tm, tlm) = (IDENTITY, IDENTITY),
+                ("Tf", _) => {
+                    if let [Object::Name(font), size] = operation.operands.as_slice() {
+                        state.font = font.clone();
+                        state.font_size = size.as_float().unwrap_or(0.0);
+                    }
+                }
+                ("Tc", &[spacing]) => state.char_spacing = spacing,
+                ("Tw", &[spacing]) => state.word_spacing = spacing,
+                ("Tz", &[scaling]) => state.horizontal_scaling = scaling / 100.0,
+                ("TL", &[leading]) => state.leading = leading,
+                ("Ts", &[rise]) => state.rise = rise,
+                ("Td", &[tx, ty]) => {
+                    tlm = multiply([1.0, 0.0, 0.0, 1.0, tx, ty], tlm);
+                    tm = tlm;
+                }
+                ("TD", &[tx, ty]) => {
+                    state.leading = -ty;
+                    tlm = multiply([1.0, 0.0, 0.0, 1.0, tx, ty], tlm);
+                    tm = tlm;
+                }
+                ("Tm", &[a, b, c, d, e, f]) => (tm, tlm) = ([a, b, c, d, e, f], [a, b, c, d, e, f]),
+                ("T*", _) => {
+                    tlm = multiply([1.0, 0.0, 0.0, 1.0, 0.0, -state.leading], tlm);
+                    tm = tlm;
+                }
+                (operator @ ("Tj" | "TJ" | "'" | "\""), _) => {
+                    let mut prefix = vec![];
+                    if operator == "\"" {
+                        if let [word_spacing, char_spacing, ..] = operation.operands.as_slice() {
+                            state.word_spacing = word_spacing.as_float().unwrap_or(0.0);
+                            state.char_spacing = char_spacing.as_float().unwrap_or(0.0);
+                            prefix.push(Operation::new("Tw", vec![word_spacing.clone()]));
+                            prefix.push(Operation::new("Tc", vec![char_spacing.clone()]));
+                        }
+                    }
+                    if operator == "'" || operator == "\"" {
+                        tlm = multiply([1.0, 0.0, 0.0, 1.0, 0.0, -state.leading], tlm);
+                        tm = tlm;
+                        prefix.push(Operation::new("T*", vec![]));
+                    }
+                    let elements = match operation.operands.last() {
+                        Some(Object::Array(elements)) if operator == "TJ" => elements.clone(),
+                        Some(string) => vec![string.clone()],
+                        None => vec![],
+                    };
+
+                    let (elements, redacted) = self.show(&mut tm, &state, &elements);
+                    if redacted {
+                        output.extend(prefix);
+                        output.push(Operation::new("TJ", vec![Object::Array(elements)]));
+                        continue;
+                    }
+                }
+                ("Do", _) => {
+                    if let Some(bbox) = operation
+                        .operands
+                        .first()
+                        .and_then(|name| self.xobject_bbox(name, state.ctm))
+                    {
+                        if self.regions.iter().any(|region| overlaps(region, &bbox)) {
+                            continue;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            output.push(operation);
+        }
+        output
+    }
+
+    /// Advances the text matrix over the elements of a text showing operator, returning the
+    /// elements with the characters in the regions replaced by the equivalent spacing, and whether
+    /// any character was replaced.
+    fn show(
+        &mut self,
+        tm: &mut [f32; 6],
+        state: &State,
+        elements: &[Object],
+    ) -> (Vec<Object>, bool) {
+        let regions = self.regions;
+        let font = self.font(&state.font);
+        let (size, scaling) = (state.font_size, state.horizontal_scaling);
+        let mut output = vec![];
+        let mut redacted = false;
+        for element in elements {
+            match element {
+                Object::String(bytes, format) => {
+                    let mut kept = vec![];
+                    for code in bytes.chunks(if font.two_bytes { 2 } else { 1 }) {
+                        let value = code.iter().fold(0, |value, &byte| value << 8 | byte as u32);
+                        let width = font.width(value);
+                        let trm = multiply(
+                            [size * scaling, 0.0, 0.0, size, 0.0, state.rise],
+                            multiply(*tm, state.ctm),
+                        );
+                        let glyph = transform_rect(trm, [0.0, font.descent, width, font.ascent]);
+                        let word_spacing =
+                            if !font.two_bytes && value == 32 { state.word_spacing } else { 0.0 };
+                        let advance = (width * size + state.char_spacing + word_spacing) * scaling;
+
+                        if regions.iter().any(|region| overlaps(region, &glyph)) {
+                            redacted = true;
+                            if !kept.is_empty() {
+                                output.push(Object::String(std::mem::take(&mut kept), *format));
+                            }
+                            if size * scaling != 0.0 {
+                                output.push(Object::Real(-advance * 1000.0 / (size * scaling)));
+                            }
+                        } else {
+                            kept.extend_from_slice(code);
+                        }
+                        *tm = multiply([1.0, 0.0, 0.0, 1.0, advance, 0.0], *tm);
+                    }
+                    if !kept.is_empty() {
+                        output.push(Object::String(kept, *format));
+                    }
+                }
+                element => {
+                    let adjustment = element.as_float().unwrap_or(0.0);
+                    let advance = -adjustment / 1000.0 * size * scaling;
+                    *tm = multiply([1.0, 0.0, 0.0, 1.0, advance, 0.0], *tm);
+                    output.push(element.clone());
+                }
+            }
+        }
+        (output, redacted)
+    }
+
+    fn font(&mut self, name: &[u8]) -> &Font {
+        let (doc, resources) = (self.doc, self.resources);
+        self.fonts.entry(name.to_vec()).or_insert_with(|| {
+            resources
+                .get_deref(b"Font", doc)
+                .and_then(Object::as_dict)
+                .and_then(|fonts| fonts.get_deref(name, doc))
+                .and_then(Object::as_dict)
+                .map(|font| Font::load(doc, font))
+                .unwrap_or_else(|_| Font::fallback())
+        })
+    }
+
+    /// Returns the bounding box of the image or form XObject in the user space of the page.
+    fn xobject_bbox(&self, name: &Object, ctm: [f32; 6]) -> Option<[f32; 4]> {
+        let xobject = self
+            .resources
+            .get_deref(b"XObject", self.doc)
+            .and_then(Object::as_dict)
+            .and_then(|xobjects| xobjects.get_deref(name.as_name()?, self.doc))
+            .and_then(Object::as_stream)
+            .ok()?;
+        match xobject.dict.get(b"Subtype").and_then(Object::as_name).ok()? {
+            b"Image" => Some(transform_rect(ctm, [0.0, 0.0, 1.0, 1.0])),
+            b"Form" => {
+                let bbox = floats(xobject.dict.get(b"BBox").ok()?)?;
+                let matrix = floats(xobject.dict.get(b"Matrix").unwrap_or(&Object::Null))
+                    .and_then(|matrix| <[f32; 6]>::try_from(matrix).ok())
+                    .unwrap_or(IDENTITY);
+                Some(transform_rect(multiply(matrix, ctm), [bbox[0], bbox[1], bbox[2], bbox[3]]))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Multiplies the matrices, i.e. the transformation `m` followed by `n`.
+fn multiply(m: [f32; 6], n: [f32; 6]) -> [f32; 6] {
+    [
+        m[0] * n[0] + m[1] * n[2],
+        m[0] * n[1] + m[1] * n[3],
+        m[2] * n[0] + m[3] * n[2],
+        m[2] * n[1] + m[3] * n[3],
+        m[4] * n[0] + m[5] * n[2] + n[4],
+        m[4] * n[1] + m[5] * n[3] + n[5],
+    ]
+}
+
+/// Whether the rectangles, i.e. `[left, bottom, right, top]`, overlap.
+fn overlaps(a: &[f32; 4], b: &[f32; 4]) -> bool {
+    a[0] < b[2] && b[0] < a[2] && a[1] < b[3] && b[1] < a[3]
+}
+
+/// Returns the numbers of the array, e.g. a rectangle, normalized if it has four of them.
+fn floats(array: &Object) -> Option<Vec<f32>> {
+    let mut numbers = array
+        .as_array()
+        .ok()?
+        .iter()
+        .map(|value| value.as_float().ok())
+        .collect::<Option<Vec<_>>>()?;
+    if let &[x0, y0, x1, y1] = numbers.as_slice() {
+        numbers = vec![x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)];
+    }
+    Some(numbers)
+}
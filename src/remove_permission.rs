@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use crate::TypsterError;
+
+/// Removes permission of a PDF file, i.e. writes a decrypted copy of it, so that automated
+/// pipelines can re-process protected documents they own.
+///
+/// Note that in-place update is not possible, so the output file must be different from the input
+/// file.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file.
+/// - `password` - User or owner password of the input PDF file. Pass an empty string if anyone can
+///   open it.
+///
+/// # Example
+///
+/// Following is an example of how to use the `remove_permission` function:
+///
+/// ```no_run
+/// typster::remove_permission(
+///     std::path::PathBuf::from("sample-protected.pdf"),
+///     std::path::PathBuf::from("sample.pdf"),
+///     "owner",
+/// )
+/// .unwrap();
+/// ```
+pub fn remove_permission(
+    input: PathBuf,
+    output: PathBuf,
+    password: &str,
+) -> Result<(), TypsterError> {
+    if output.exists() && input.canonicalize()? == output.canonicalize()? {
+        return Err(TypsterError::Params("in-place update is not possible".to_string()));
+    }
+    qpdf::QPdf::read_encrypted(input, password)?
+        .writer()
+        .preserve_encryption(false)
+        .write(output)?;
+    Ok(())
+}
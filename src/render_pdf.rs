@@ -0,0 +1,74 @@
+use std::{collections::BTreeSet, error::Error, io::Cursor, path::Path};
+
+use image::{ImageFormat, RgbaImage};
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
+
+/// Rasterizes pages of an existing PDF file to PNG images, e.g. to generate previews of merged or
+/// stamped documents, which never went through the PNG export of [`compile()`](crate::compile()).
+///
+/// Rendering is done by [PDFium](https://pdfium.googlesource.com/pdfium/), which is loaded at
+/// runtime from the current directory, or from the system library path, e.g. `libpdfium.so` on
+/// Linux.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file.
+/// - `pages` - 1-based page numbers to render, in ascending order regardless of the order given,
+///   with duplicates removed. Empty to render all pages.
+/// - `ppi` - Pixels per inch, e.g. `144.0`.
+///
+/// # Returns
+///
+/// Result containing the PNG-encoded image of each page.
+///
+/// # Example
+///
+/// Following is an example of how to use the `render_pdf` function:
+///
+/// ```no_run
+/// let images = typster::render_pdf(std::path::Path::new("merged.pdf"), &[1], 72.0).unwrap();
+/// std::fs::write("preview.png", &images[0]).unwrap();
+/// ```
+pub fn render_pdf(path: &Path, pages: &[u32], ppi: f32) -> Result<Vec<Vec<u8>>, Box<dyn Error>> {
+    if ppi <= 0.0 {
+        return Err("ppi must be positive".into());
+    }
+
+    let bindings = Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path("./"))
+        .or_else(|_| Pdfium::bind_to_system_library())?;
+    let pdfium = Pdfium::new(bindings);
+    let document = pdfium.load_pdf_from_file(path, None)?;
+
+    let count = document.pages().iter().count() as u32;
+    let pages = if pages.is_empty() {
+        (1..=count).collect::<BTreeSet<_>>()
+    } else {
+        pages.iter().copied().collect()
+    };
+    if let Some(page) = pages.iter().find(|&&page| page == 0 || page > count) {
+        return Err(format!("page {page} does not exist").into());
+    }
+
+    let config = PdfRenderConfig::new().scale_page_by_factor(ppi / 72.0);
+    let mut images = Vec::with_capacity(pages.len());
+    for (_, page) in document
+        .pages()
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| pages.contains(&(*i as u32 + 1)))
+    {
+        let bitmap = page.render_with_config(&config)?;
+        let image = RgbaImage::from_raw(
+            bitmap.width() as u32,
+            bitmap.height() as u32,
+            bitmap.as_rgba_bytes(),
+        )
+        .ok_or("invalid bitmap")?;
+
+        let mut png = vec![];
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        images.push(png);
+    }
+
+    Ok(images)
+}
@@ -0,0 +1,72 @@
+use std::{collections::BTreeSet, path::Path};
+
+use lopdf::{Document, Object};
+
+use crate::merge_pdfs::inline_inherited_attributes;
+
+/// Rotates pages of a PDF file, e.g. to normalize landscape scans merged into a compiled document.
+///
+/// The rotation is added to the current rotation of each page, so rotating a page which is already
+/// rotated by 90 degrees by another 90 degrees turns it upside down.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `rotation` - Clockwise rotation in degrees, which must be a multiple of 90, e.g. `-90` to
+///   rotate counterclockwise.
+/// - `pages` - 1-based page numbers to rotate. All pages are rotated if empty.
+/// - `output` - Path to the output PDF file, which may be the same as the input file.
+///
+/// # Example
+///
+/// Following is an example of how to use the `rotate_pages` function:
+///
+/// ```no_run
+/// // Rotate the second and third pages clockwise.
+/// typster::rotate_pages(
+///     std::path::Path::new("sample.pdf"),
+///     90,
+///     &[2, 3],
+///     std::path::Path::new("rotated.pdf"),
+/// )
+/// .unwrap();
+/// ```
+pub fn rotate_pages(
+    input: &Path,
+    rotation: i64,
+    pages: &[usize],
+    output: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if rotation % 90 != 0 {
+        return Err(format!("rotation must be a multiple of 90 degrees: {rotation}").into());
+    }
+
+    let mut doc = Document::load(input)?;
+    let all = doc.get_pages().into_values().collect::<Vec<_>>();
+    if let Some(page) = pages.iter().find(|&&page| page == 0 || page > all.len()) {
+        return Err(format!("page {page} does not exist").into());
+    }
+    // Rotate each page once, even if it's given more than once.
+    let targets = if pages.is_empty() {
+        all
+    } else {
+        pages
+            .iter()
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|&page| all[page - 1])
+            .collect()
+    };
+    // The current rotation may be inherited from the page tree.
+    inline_inherited_attributes(&mut doc, &targets)?;
+
+    for id in targets {
+        let page = doc.get_dictionary_mut(id)?;
+        let current = page.get(b"Rotate").and_then(Object::as_i64).unwrap_or(0);
+        page.set("Rotate", (current + rotation).rem_euclid(360));
+    }
+
+    doc.save(output)?;
+
+    Ok(())
+}
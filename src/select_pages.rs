@@ -0,0 +1,108 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    path::Path,
+};
+
+use lopdf::{Document, Object};
+
+use crate::{
+    merge_pdfs::inline_inherited_attributes,
+    outline::{read_outline, write_outline},
+    OutlineEntry,
+};
+
+/// Extracts and reorders pages of a PDF file, e.g. to drop a draft watermark page, or to move the
+/// appendix to the front.
+///
+/// The outline is updated to point to the new page numbers, and entries pointing to dropped pages
+/// are removed, while their children are kept. Page labels are removed, as they no longer match
+/// the pages.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `order` - 1-based page numbers of the input file, in the order of the output file. A page may
+///   appear more than once.
+/// - `output` - Path to the output PDF file, which may be the same as the input file.
+///
+/// # Example
+///
+/// Following is an example of how to use the `select_pages` function:
+///
+/// ```no_run
+/// // Move the last page of a 3-page document to the front, and drop the second one.
+/// typster::select_pages(
+///     std::path::Path::new("sample.pdf"),
+///     &[3, 1],
+///     std::path::Path::new("selected.pdf"),
+/// )
+/// .unwrap();
+/// ```
+pub fn select_pages(
+    input: &Path,
+    order: &[usize],
+    output: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if order.is_empty() {
+        return Err("no pages to select".into());
+    }
+
+    let mut doc = Document::load(input)?;
+    let pages = doc.get_pages().into_values().collect::<Vec<_>>();
+    if let Some(page) = order.iter().find(|&&page| page == 0 || page > pages.len()) {
+        return Err(format!("page {page} does not exist").into());
+    }
+    let outline = read_outline(&doc)?;
+    inline_inherited_attributes(&mut doc, &pages)?;
+
+    let root = doc.catalog()?.get(b"Pages").and_then(Object::as_reference)?;
+    let mut kids = Vec::with_capacity(order.len());
+    // New page number of the first occurrence of each input page.
+    let mut numbers = HashMap::new();
+    for (i, &page) in order.iter().enumerate() {
+        let id = pages[page - 1];
+        // A page object can't appear twice in the page tree, so repeated pages are copied.
+        let id = match numbers.entry(page) {
+            Entry::Occupied(_) => {
+                let copy = doc.get_object(id)?.clone();
+                doc.add_object(copy)
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(i as u32 + 1);
+                id
+            }
+        };
+        doc.get_dictionary_mut(id)?.set("Parent", root);
+        kids.push(Object::Reference(id));
+    }
+    let root = doc.get_dictionary_mut(root)?;
+    root.set("Count", kids.len() as i64);
+    root.set("Kids", kids);
+
+    write_outline(&mut doc, &renumber_entries(outline, &numbers))?;
+    doc.catalog_mut()?.remove(b"PageLabels");
+
+    // Drop the pages which are no longer in the page tree, unless referenced elsewhere.
+    doc.prune_objects();
+    doc.save(output)?;
+
+    Ok(())
+}
+
+/// Maps the pages of the outline entries to the new page numbers. Entries pointing to dropped
+/// pages are replaced with their children.
+fn renumber_entries(
+    entries: Vec<OutlineEntry>,
+    numbers: &HashMap<usize, u32>,
+) -> Vec<OutlineEntry> {
+    entries
+        .into_iter()
+        .flat_map(|entry| {
+            let children = renumber_entries(entry.children, numbers);
+            match numbers.get(&(entry.page as usize)) {
+                Some(&page) => vec![OutlineEntry { page, children, ..entry }],
+                None => children,
+            }
+        })
+        .collect()
+}
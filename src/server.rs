@@ -0,0 +1,320 @@
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    net::TcpListener,
+    path::PathBuf,
+    sync::Arc,
+    thread,
+    time::Instant,
+};
+
+use ecow::EcoVec;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use typst::diag::{SourceDiagnostic, SourceResult, Warned};
+
+use crate::{
+    compile::{export, typst_compile},
+    compiler_session::MAX_CACHE_AGE,
+    error::{Diagnostic, Diagnostics},
+    jsonrpc::{params, response, Request, RpcError, METHOD_NOT_FOUND, PARSE_ERROR, SERVER_ERROR},
+    query::select,
+    world::SystemWorld,
+    CompileParams, FormatParams, Sandbox, TypsterError,
+};
+
+/// Maximum number of worlds kept warm, beyond which the least recently used one is dropped.
+const MAX_WORLDS: usize = 16;
+
+/// A long-running JSON-RPC 2.0 server, e.g. to give editors or build tools written in other
+/// languages fast repeated compilations without the startup cost of a process per compilation.
+///
+/// Messages are newline-delimited JSON objects. The server keeps a world, i.e. the loaded fonts,
+/// files, and memoized results of Typst, for each distinct `input`, `dict`, `font_paths`,
+/// `package_path`, `package_cache_path`, `sandbox`, and `deny_symlink_escape`, and reuses it for
+/// later requests, re-reading only the changed files, so that e.g. unsaved buffers sent as
+/// `shadow_files` don't search the fonts again. The 16 most recently used worlds are kept. The
+/// following methods are supported:
+///
+/// - `compile` - Takes [`CompileParams`], and returns `{"duration_ms": ...}`.
+/// - `format` - Takes [`FormatParams`], and returns `{"text": ...}`.
+/// - `query` - Takes [`CompileParams`] with additional `label` and optional `field`, and returns
+///   the elements with the label, or the field of them, like [`query()`]. The output file is not
+///   written.
+///
+/// While compiling, warnings and errors are streamed as `diagnostic` notifications, before the
+/// response, with the `id` of the request, `severity`, `message`, `hints`, and the `location` of
+/// [`SourceLocation`](crate::SourceLocation), if any. Errors of the methods have the codes of
+/// [`Server::error_code()`]. Requests for different worlds are served concurrently by
+/// [`serve_tcp()`](Server::serve_tcp()), while those for the same world wait for each other.
+///
+/// # Example
+///
+/// Following is an example of how to use the `Server`:
+///
+/// ```no_run
+/// // {"jsonrpc": "2.0", "id": 1, "method": "compile", "params": {"input": "main.typ"}}
+/// typster::Server::new().serve_stdio().unwrap();
+/// ```
+#[derive(Clone, Default)]
+pub struct Server {
+    /// Worlds kept warm between requests.
+    worlds: Arc<Mutex<WarmWorlds>>,
+}
+
+/// A warm world, created by the first request for it, and locked while a request uses it.
+type WorldSlot = Arc<Mutex<Option<SystemWorld>>>;
+
+/// Worlds kept warm between requests, keyed by the parameters which shape them, each locked on its
+/// own so that the map is only locked to look them up.
+#[derive(Default)]
+struct WarmWorlds {
+    /// Slots of the worlds, with the time they were last used.
+    slots: HashMap<String, (u64, WorldSlot)>,
+    /// Incremented on every lookup, to find the least recently used world.
+    clock: u64,
+}
+
+impl WarmWorlds {
+    /// Returns the slot of the world for a key, adding an empty one if there is none, and drops
+    /// the least recently used worlds beyond [`MAX_WORLDS`]. A dropped world in use is freed once
+    /// its request is done.
+    fn slot(&mut self, key: String) -> WorldSlot {
+        self.clock += 1;
+        let clock = self.clock;
+        let (used, slot) = self.slots.entry(key).or_default();
+        *used = clock;
+        let slot = Arc::clone(slot);
+        while self.slots.len() > MAX_WORLDS {
+            let Some(oldest) = self
+                .slots
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.slots.remove(&oldest);
+        }
+        slot
+    }
+}
+
+impl Server {
+    /// Creates a server without any warm world.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serves requests from stdin and writes responses to stdout, until stdin is closed.
+    pub fn serve_stdio(&self) -> io::Result<()> {
+        self.serve_connection(io::stdin().lock(), io::stdout().lock())
+    }
+
+    /// Serves requests from each connection accepted by `listener` on its own thread, sharing the
+    /// warm worlds between them. Runs until accepting a connection fails.
+    ///
+    /// # Arguments
+    ///
+    /// - `listener` - Listener bound to the address to serve on, e.g. `127.0.0.1:0` to let the OS
+    ///   choose a port.
+    pub fn serve_tcp(&self, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = self.clone();
+            thread::spawn(move || -> io::Result<()> {
+                let reader = BufReader::new(stream.try_clone()?);
+                server.serve_connection(reader, stream)
+            });
+        }
+        Ok(())
+    }
+
+    /// Serves requests read from `reader`, and writes responses and notifications to `writer`,
+    /// until `reader` reaches the end.
+    pub fn serve_connection(&self, reader: impl BufRead, mut writer: impl Write) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut notifications = vec![];
+            let response = self.handle(&line, &mut notifications);
+            for message in notifications.iter().chain(&response) {
+                serde_json::to_writer(&mut writer, message)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Returns the JSON-RPC error code the server responds with for an error.
+    ///
+    /// Codes are stable across releases: `-32001` for [`TypsterError::World`], `-32002` for
+    /// [`TypsterError::Diagnostics`], `-32003` for [`TypsterError::Io`], `-32004` for
+    /// [`TypsterError::Pdf`], `-32005` for [`TypsterError::Font`], `-32006` for
+    /// [`TypsterError::Params`], `-32007` for [`TypsterError::LimitExceeded`], `-32008` for
+    /// [`TypsterError::TimedOut`], and `-32009` for [`TypsterError::Image`]. Other failures of the
+    /// server have `-32000`.
+    pub fn error_code(error: &TypsterError) -> i64 {
+        match error {
+            TypsterError::World(_) => -32001,
+            TypsterError::Diagnostics(_) => -32002,
+            TypsterError::Io(_) => -32003,
+            TypsterError::Pdf(_) => -32004,
+            TypsterError::Font(_) => -32005,
+            TypsterError::Params(_) => -32006,
+            TypsterError::LimitExceeded(_) => -32007,
+            TypsterError::TimedOut(_) => -32008,
+            TypsterError::Image(_) => -32009,
+        }
+    }
+
+    /// Handles a request, and returns the response unless the request is a notification.
+    fn handle(&self, line: &str, notifications: &mut Vec<Value>) -> Option<Value> {
+        let request = match serde_json::from_str::<Request>(line) {
+            Ok(request) => request,
+            Err(err) => {
+                return Some(response(Value::Null, Err(RpcError(PARSE_ERROR, err.to_string()))))
+            }
+        };
+
+        let id = request.id.clone().unwrap_or(Value::Null);
+        let mut emit = |diagnostic: Diagnostic| {
+            notifications.push(json!({
+                "jsonrpc": "2.0",
+                "method": "diagnostic",
+                "params": { "id": id, "diagnostic": diagnostic },
+            }))
+        };
+        let result = match request.method.as_str() {
+            "compile" => params(request.params).and_then(|params| self.compile(&params, &mut emit)),
+            "format" => params(request.params)
+                .and_then(|params: FormatParams| Ok(json!({ "text": crate::format(&params)? }))),
+            "query" => params(request.params).and_then(|params| self.query(&params, &mut emit)),
+            method => Err(RpcError(METHOD_NOT_FOUND, format!("unknown method: {method}"))),
+        };
+        request.id.map(|id| response(id, result))
+    }
+
+    /// Compiles a document with a warm world, and writes the output file.
+    fn compile(
+        &self,
+        params: &CompileParams,
+        emit: &mut dyn FnMut(Diagnostic),
+    ) -> Result<Value, RpcError> {
+        let slot = self.worlds.lock().slot(world_key(params)?);
+        let mut slot = slot.lock();
+        let world = warm_world(&mut slot, params)?;
+
+        let start = Instant::now();
+        let Warned { output, warnings } = typst_compile(world);
+        if let Err(err) = world.check_limits() {
+            comemo::evict(MAX_CACHE_AGE);
+            return Err(err.into());
+        }
+        let result = output.and_then(|document| export(&document, world, params));
+        let duration = start.elapsed();
+        let result = finish(world, warnings, result, emit);
+        comemo::evict(MAX_CACHE_AGE);
+
+        result?;
+        Ok(json!({ "duration_ms": duration.as_millis() }))
+    }
+
+    /// Compiles a document with a warm world, and returns the elements with a label.
+    fn query(
+        &self,
+        params: &QueryParams,
+        emit: &mut dyn FnMut(Diagnostic),
+    ) -> Result<Value, RpcError> {
+        let slot = self.worlds.lock().slot(world_key(&params.compile)?);
+        let mut slot = slot.lock();
+        let world = warm_world(&mut slot, &params.compile)?;
+
+        let Warned { output, warnings } = typst_compile(world);
+        let result = finish(world, warnings, output, emit);
+        comemo::evict(MAX_CACHE_AGE);
+
+        let values = select(&result?, &params.label, params.field.as_deref());
+        serde_json::to_value(values).map_err(|err| RpcError(SERVER_ERROR, err.to_string()))
+    }
+}
+
+/// Parameters of the `query` method.
+#[derive(Debug, Deserialize)]
+struct QueryParams {
+    #[serde(flatten)]
+    compile: CompileParams,
+    /// Label of the elements to query, without angle brackets.
+    label: String,
+    /// Field of the elements to return instead of the elements themselves.
+    #[serde(default)]
+    field: Option<String>,
+}
+
+impl From<TypsterError> for RpcError {
+    fn from(err: TypsterError) -> Self {
+        Self(Server::error_code(&err), err.to_string())
+    }
+}
+
+/// Parameters which shape a world, i.e. its main file, its inputs, and the fonts and files it
+/// can read.
+#[derive(Serialize)]
+struct WorldKey<'a> {
+    input: &'a PathBuf,
+    dict: &'a [(String, String)],
+    font_paths: &'a [PathBuf],
+    package_path: &'a Option<PathBuf>,
+    package_cache_path: &'a Option<PathBuf>,
+    sandbox: &'a Option<Sandbox>,
+    deny_symlink_escape: bool,
+}
+
+/// Returns the key of the warm world for the parameters. The other parameters, e.g.
+/// `shadow_files`, are applied to the world on each request.
+fn world_key(params: &CompileParams) -> Result<String, TypsterError> {
+    let key = WorldKey {
+        input: &params.input,
+        dict: &params.dict,
+        font_paths: &params.font_paths,
+        package_path: &params.package_path,
+        package_cache_path: &params.package_cache_path,
+        sandbox: &params.sandbox,
+        deny_symlink_escape: params.deny_symlink_escape,
+    };
+    serde_json::to_string(&key).map_err(|err| TypsterError::Params(err.to_string()))
+}
+
+/// Returns the warm world of a slot, creating it on the first request, with the parameters of the
+/// request applied, and resets it so that changed files are read again.
+fn warm_world<'a>(
+    slot: &'a mut Option<SystemWorld>,
+    params: &CompileParams,
+) -> Result<&'a SystemWorld, TypsterError> {
+    let world = match slot {
+        Some(world) => world,
+        None => slot.insert(SystemWorld::new(params)?),
+    };
+    world.update(params);
+    world.reset();
+    Ok(world)
+}
+
+/// Emits the warnings and errors of a compilation, and returns its result.
+fn finish<T>(
+    world: &SystemWorld,
+    warnings: EcoVec<SourceDiagnostic>,
+    result: SourceResult<T>,
+    emit: &mut dyn FnMut(Diagnostic),
+) -> Result<T, TypsterError> {
+    let errors = result.as_ref().err();
+    for diagnostic in warnings.iter().chain(errors.into_iter().flatten()) {
+        emit(Diagnostic::new(world, diagnostic));
+    }
+    world.check_limits()?;
+    result.map_err(|errors| Diagnostics::new(warnings, errors).into())
+}
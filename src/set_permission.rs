@@ -1,12 +1,24 @@
-use std::{error::Error, fmt::Display, path::PathBuf};
+use std::{
+    fmt::Display,
+    fs::{read, remove_file, rename, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
 
-use qpdf::{EncryptionParams, EncryptionParamsR6};
+use qpdf::{
+    EncryptionParams, EncryptionParamsR4, EncryptionParamsR6, ObjectStreamMode, QPdf, QPdfWriter,
+    StreamDecodeLevel,
+};
 use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::{xmp::append_history_bytes, DocumentId, TypsterError};
 
 /// Parameters for PDF permission.
 ///
 /// See also [`set_permission()`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PermissionParams {
     /// User password, which is required to open the document. Set to [`None`] to allow anyone to
     /// open.
@@ -39,6 +51,31 @@ pub struct PermissionParams {
 
     /// Encrypt metadata.
     pub encrypt_metadata: bool,
+
+    /// Encryption algorithm. Use [`EncryptionLevel::Aes128`] for viewers which cannot open AES-256
+    /// encrypted files.
+    #[serde(default)]
+    pub encryption_level: EncryptionLevel,
+
+    /// Linearize the output, a.k.a. fast web view, so that viewers can start rendering the first
+    /// page before the whole file is downloaded.
+    #[serde(default)]
+    pub linearize: bool,
+
+    /// How to treat the document ID. qpdf always regenerates the second element of the pair, so
+    /// only the first one, i.e. the permanent identifier, is preserved or set.
+    #[serde(default)]
+    pub document_id: DocumentId,
+
+    /// Append an `xmpMM:History` entry for this modification to the XMP metadata, so that the
+    /// provenance chain of the document remains intact.
+    #[serde(default)]
+    pub record_history: bool,
+
+    /// Allow the output file to be the same as the input file. The encrypted file is written to a
+    /// temporary file next to it, which then atomically replaces the input file.
+    #[serde(default)]
+    pub allow_in_place: bool,
 }
 
 /// PDF print permission for [`PermissionParams`].
@@ -63,6 +100,35 @@ impl Display for PrintPermission {
     }
 }
 
+/// Encryption algorithm for [`PermissionParams`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncryptionLevel {
+    /// AES-128, i.e. revision 4 of the standard security handler (PDF 1.6, or Acrobat 7).
+    Aes128,
+    /// AES-256, i.e. revision 6 of the standard security handler (PDF 1.7, or Acrobat 9).
+    #[default]
+    Aes256,
+}
+
+impl Display for EncryptionLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncryptionLevel::Aes128 => write!(f, "aes128"),
+            EncryptionLevel::Aes256 => write!(f, "aes256"),
+        }
+    }
+}
+
+impl From<String> for EncryptionLevel {
+    fn from(level: String) -> EncryptionLevel {
+        match level.to_lowercase().replace(['-', '_'], "").as_str() {
+            "aes128" | "r4" => EncryptionLevel::Aes128,
+            _ => EncryptionLevel::Aes256,
+        }
+    }
+}
+
 impl From<&PrintPermission> for qpdf::writer::PrintPermission {
     fn from(permission: &PrintPermission) -> qpdf::writer::PrintPermission {
         match permission {
@@ -85,18 +151,88 @@ impl From<String> for PrintPermission {
 
 impl From<&PermissionParams> for EncryptionParams {
     fn from(params: &PermissionParams) -> EncryptionParams {
-        EncryptionParams::R6(EncryptionParamsR6 {
-            user_password: params.user_password.clone().unwrap_or_default(),
-            owner_password: params.owner_password.clone().unwrap_or_default(),
-            allow_accessibility: params.allow_accessibility,
-            allow_extract: params.allow_extract,
-            allow_assemble: params.allow_assemble,
-            allow_annotate_and_form: params.allow_annotate_and_form,
-            allow_form_filling: params.allow_form_filling,
-            allow_modify_other: params.allow_modify_other,
-            allow_print: (&params.allow_print).into(),
-            encrypt_metadata: params.encrypt_metadata,
-        })
+        let user_password = params.user_password.clone().unwrap_or_default();
+        let owner_password = params.owner_password.clone().unwrap_or_default();
+        match params.encryption_level {
+            EncryptionLevel::Aes128 => EncryptionParams::R4(EncryptionParamsR4 {
+                user_password,
+                owner_password,
+                allow_accessibility: params.allow_accessibility,
+                allow_extract: params.allow_extract,
+                allow_assemble: params.allow_assemble,
+                allow_annotate_and_form: params.allow_annotate_and_form,
+                allow_form_filling: params.allow_form_filling,
+                allow_modify_other: params.allow_modify_other,
+                allow_print: (&params.allow_print).into(),
+                encrypt_metadata: params.encrypt_metadata,
+                use_aes: true,
+            }),
+            EncryptionLevel::Aes256 => EncryptionParams::R6(EncryptionParamsR6 {
+                user_password,
+                owner_password,
+                allow_accessibility: params.allow_accessibility,
+                allow_extract: params.allow_extract,
+                allow_assemble: params.allow_assemble,
+                allow_annotate_and_form: params.allow_annotate_and_form,
+                allow_form_filling: params.allow_form_filling,
+                allow_modify_other: params.allow_modify_other,
+                allow_print: (&params.allow_print).into(),
+                encrypt_metadata: params.encrypt_metadata,
+            }),
+        }
+    }
+}
+
+impl PermissionParams {
+    /// Preset which allows viewing, printing, and copying, but no modification of any kind,
+    /// including commenting and form filling.
+    ///
+    /// Set `owner_password` so that the permissions cannot be changed by anyone.
+    pub fn read_only() -> Self {
+        Self {
+            allow_accessibility: true,
+            allow_extract: true,
+            allow_assemble: false,
+            allow_annotate_and_form: false,
+            allow_form_filling: false,
+            allow_modify_other: false,
+            allow_print: PrintPermission::Full,
+            ..Default::default()
+        }
+    }
+
+    /// Preset which allows viewing and printing only. Content copying is allowed for accessibility
+    /// only, e.g. for screen readers.
+    ///
+    /// Set `owner_password` so that the permissions cannot be changed by anyone.
+    pub fn print_only() -> Self {
+        Self {
+            allow_accessibility: true,
+            allow_extract: false,
+            allow_assemble: false,
+            allow_annotate_and_form: false,
+            allow_form_filling: false,
+            allow_modify_other: false,
+            allow_print: PrintPermission::Full,
+            ..Default::default()
+        }
+    }
+
+    /// Preset which disallows content copying and page extraction, except for accessibility, while
+    /// allowing printing, commenting, and form filling.
+    ///
+    /// Set `owner_password` so that the permissions cannot be changed by anyone.
+    pub fn no_copy() -> Self {
+        Self {
+            allow_accessibility: true,
+            allow_extract: false,
+            allow_assemble: false,
+            allow_annotate_and_form: true,
+            allow_form_filling: true,
+            allow_modify_other: false,
+            allow_print: PrintPermission::Full,
+            ..Default::default()
+        }
     }
 }
 
@@ -113,14 +249,20 @@ impl Default for PermissionParams {
             allow_modify_other: false,
             allow_print: PrintPermission::Full,
             encrypt_metadata: true,
+            encryption_level: EncryptionLevel::Aes256,
+            linearize: false,
+            document_id: DocumentId::Preserve,
+            record_history: false,
+            allow_in_place: false,
         }
     }
 }
 
 /// Sets permission of a PDF file.
 ///
-/// Note that in-place update is not possible, so the output file must be different from the input
-/// file. The only supported encryption algorithm is AES-256 (PDF 1.7, or Acrobat 9).
+/// The output file must be different from the input file, unless `params.allow_in_place` is set.
+/// The encryption algorithm is AES-256 (PDF 1.7, or Acrobat 9) by default, or AES-128
+/// (PDF 1.6, or Acrobat 7) if specified by `params.encryption_level`.
 ///
 /// # Arguments
 ///
@@ -138,17 +280,16 @@ impl Default for PermissionParams {
 ///         .join("sample.pdf");
 ///
 /// // Compile a document first
-/// let params = typster::CompileParams {
-///     input: std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-///         .join("examples")
-///         .join("sample.typ"),
-///     output: output.clone(),
-///     font_paths: vec!["assets".into()],
-///     dict: vec![("input".to_string(), "value".to_string())],
-///     ppi: None,
-///     package_path: None,
-///     package_cache_path: None,
-/// };
+/// let params = typster::CompileParams::builder()
+///     .input(
+///         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///             .join("examples")
+///             .join("sample.typ"),
+///     )
+///     .output(output.clone())
+///     .font_path("assets")
+///     .dict("input", "value")
+///     .build();
 /// match typster::compile(&params) {
 ///     Ok(duration) => println!("Compilation succeeded in {duration:?}"),
 ///     Err(why) => eprintln!("{why}"),
@@ -167,19 +308,102 @@ impl Default for PermissionParams {
 ///     },
 /// ).unwrap();
 /// ```
+#[instrument(skip_all, fields(input = %input.display(), output = %output.display()))]
 pub fn set_permission(
     input: PathBuf,
     output: PathBuf,
     params: &PermissionParams,
-) -> Result<(), Box<dyn Error>> {
-    // Should be canonicalized before equality check, but output is not created yet.
-    if input == output {
-        return Err("in-place update is not possible".into());
+) -> Result<(), TypsterError> {
+    if output.exists() && input.canonicalize()? == output.canonicalize()? {
+        if !params.allow_in_place {
+            return Err(TypsterError::Params("in-place update is not allowed".to_string()));
+        }
+        return replace(&output, &set_permission_bytes(&read(&input)?, params)?);
     }
-    qpdf::QPdf::read(input)
-        .unwrap()
-        .writer()
+
+    let pdf = if params.record_history {
+        let pdf = append_history_bytes(&read(input)?, "/", "set permissions")?;
+        QPdf::read_from_memory(pdf)?
+    } else {
+        QPdf::read(input)?
+    };
+    params.document_id.apply_qpdf(&pdf);
+    Ok(writer(&pdf, params).write(output)?)
+}
+
+/// Sets permission of a PDF document in memory, e.g. to encrypt a compiled document in a web
+/// service without writing intermediate plaintext files to disk.
+///
+/// Works the same way as [`set_permission()`].
+///
+/// # Arguments
+///
+/// - `pdf` - Contents of the PDF file.
+/// - `params` - [`PermissionParams`] to set.
+///
+/// # Returns
+///
+/// Result containing the contents of the encrypted PDF file.
+///
+/// # Example
+///
+/// Following is an example of how to use the `set_permission_bytes` function:
+///
+/// ```no_run
+/// let pdf = std::fs::read("sample.pdf").unwrap();
+/// let params = typster::PermissionParams {
+///     owner_password: Some("owner".to_string()),
+///     ..Default::default()
+/// };
+/// let encrypted = typster::set_permission_bytes(&pdf, &params).unwrap();
+/// ```
+#[instrument(skip_all, fields(bytes = pdf.len()))]
+pub fn set_permission_bytes(
+    pdf: &[u8],
+    params: &PermissionParams,
+) -> Result<Vec<u8>, TypsterError> {
+    let pdf = if params.record_history {
+        QPdf::read_from_memory(append_history_bytes(pdf, "/", "set permissions")?)?
+    } else {
+        QPdf::read_from_memory(pdf)?
+    };
+    params.document_id.apply_qpdf(&pdf);
+    Ok(writer(&pdf, params).write_to_memory()?)
+}
+
+/// Creates a writer which encrypts the document with the parameters.
+///
+/// The document is otherwise written as is, i.e. the Info dictionary and the XMP metadata are
+/// carried over untouched. In particular, stream data is not decoded and re-encoded, so that the
+/// XMP packet stays byte-for-byte identical.
+fn writer(pdf: &QPdf, params: &PermissionParams) -> QPdfWriter {
+    let mut writer = pdf.writer();
+    writer
         .encryption_params(params.into())
-        .write(output)
-        .map_err(|e| e.into())
+        .linearize(params.linearize)
+        .stream_decode_level(StreamDecodeLevel::None)
+        .object_stream_mode(ObjectStreamMode::Preserve);
+    writer
+}
+
+/// Atomically replaces the file with the contents, by writing them to a temporary file in the same
+/// directory first, then renaming it.
+fn replace(path: &Path, contents: &[u8]) -> Result<(), TypsterError> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| TypsterError::Params("invalid output path".to_string()))?
+        .to_string_lossy();
+    let temp = path.with_file_name(format!(".{name}.{}.tmp", std::process::id()));
+
+    let result = File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| rename(&temp, path));
+    if result.is_err() {
+        let _ = remove_file(&temp);
+    }
+
+    Ok(result?)
 }
@@ -0,0 +1,178 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    error::Error,
+    fs::{create_dir_all, read_dir},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread::{available_parallelism, scope},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{set_permission, PermissionParams};
+
+/// Result of setting permission of a single file with [`set_permission_dir()`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionReport {
+    /// Path to the input PDF file.
+    pub input: PathBuf,
+
+    /// Path to the output PDF file.
+    pub output: PathBuf,
+
+    /// Error message if setting permission failed, or [`None`] on success.
+    pub error: Option<String>,
+}
+
+/// Sets the same permission to many PDF files in parallel, e.g. to protect a large document set for
+/// archival.
+///
+/// A failure of a file doesn't stop the others, and is reported in the returned list instead.
+///
+/// # Arguments
+///
+/// - `input` - Path to a directory, whose PDF files (non-recursive) are processed, or a glob
+///   pattern, e.g. `documents/**/*.pdf`.
+/// - `output_dir` - Path to the output directory, which is created if missing. Each output file has
+///   the same path relative to it as its input file relative to the input directory, or to the
+///   directory the glob pattern starts from, e.g. `protected/2024/report.pdf` for
+///   `documents/2024/report.pdf` with `documents/**/*.pdf`. Input files which would still have the
+///   same output file fail rather than overwrite each other.
+/// - `params` - [`PermissionParams`] to set.
+///
+/// # Returns
+///
+/// Result containing a [`PermissionReport`] per input file, in the order of the input paths.
+///
+/// # Example
+///
+/// Following is an example of how to use the `set_permission_dir` function:
+///
+/// ```no_run
+/// let reports = typster::set_permission_dir(
+///     "documents/*.pdf",
+///     std::path::Path::new("protected"),
+///     &typster::PermissionParams {
+///         owner_password: Some("owner".to_string()),
+///         ..typster::PermissionParams::read_only()
+///     },
+/// )
+/// .unwrap();
+/// for report in reports.iter().filter(|report| report.error.is_some()) {
+///     eprintln!("{}: {}", report.input.display(), report.error.as_ref().unwrap());
+/// }
+/// ```
+pub fn set_permission_dir(
+    input: &str,
+    output_dir: &Path,
+    params: &PermissionParams,
+) -> Result<Vec<PermissionReport>, Box<dyn Error>> {
+    let (base, mut inputs) = if Path::new(input).is_dir() {
+        let inputs = read_dir(input)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+            })
+            .collect::<Vec<_>>();
+        (PathBuf::from(input), inputs)
+    } else {
+        let inputs = glob::glob(input)?
+            .filter_map(Result::ok)
+            .filter(|path| path.is_file())
+            .collect();
+        (glob_base(input), inputs)
+    };
+    inputs.sort();
+    create_dir_all(output_dir)?;
+
+    // Outputs are assigned before processing, so that no two workers write the same file.
+    let mut assigned = HashMap::<_, &PathBuf>::new();
+    let outputs = inputs
+        .iter()
+        .map(|input| {
+            let output = match input.strip_prefix(&base) {
+                Ok(relative) if relative.file_name().is_some() => output_dir.join(relative),
+                _ => match input.file_name() {
+                    Some(name) => output_dir.join(name),
+                    None => return Err("invalid input path".to_string()),
+                },
+            };
+            match assigned.entry(output.clone()) {
+                Entry::Occupied(other) => Err(format!(
+                    "output {} is the same as that of {}",
+                    output.display(),
+                    other.get().display()
+                )),
+                Entry::Vacant(entry) => {
+                    entry.insert(input);
+                    Ok(output)
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let next = AtomicUsize::new(0);
+    let reports = Mutex::new(Vec::with_capacity(inputs.len()));
+    let workers = available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(inputs.len());
+
+    scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = inputs.get(i) else {
+                    break;
+                };
+                let report = match &outputs[i] {
+                    Ok(output) => {
+                        let error = output
+                            .parent()
+                            .map_or(Ok(()), create_dir_all)
+                            .map_err(|e| e.to_string())
+                            .and_then(|_| {
+                                set_permission(input.clone(), output.clone(), params)
+                                    .map_err(|e| e.to_string())
+                            })
+                            .err();
+                        PermissionReport {
+                            input: input.clone(),
+                            output: output.clone(),
+                            error,
+                        }
+                    }
+                    Err(error) => PermissionReport {
+                        input: input.clone(),
+                        output: PathBuf::new(),
+                        error: Some(error.to_string()),
+                    },
+                };
+                reports.lock().unwrap().push((i, report));
+            });
+        }
+    });
+
+    let mut reports = reports.into_inner().unwrap();
+    reports.sort_by_key(|(i, _)| *i);
+    Ok(reports.into_iter().map(|(_, report)| report).collect())
+}
+
+/// Returns the directory a glob pattern starts from, i.e. the leading components of its directory
+/// without wildcards, e.g. `documents` for `documents/**/*.pdf`.
+fn glob_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .components()
+        .take_while(|component| {
+            !component.as_os_str().to_string_lossy().contains(['*', '?', '[', '{'])
+        })
+        .collect()
+}
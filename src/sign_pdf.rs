@@ -0,0 +1,261 @@
+use std::{error::Error, fs, path::PathBuf};
+
+use chrono::Local;
+use lopdf::{text_string, Dictionary, Document, Object, StringFormat::Hexadecimal};
+use openssl::{
+    pkcs12::Pkcs12,
+    pkcs7::{Pkcs7, Pkcs7Flags},
+    stack::Stack,
+};
+use tracing::instrument;
+
+use crate::timestamp::add_timestamp;
+
+/// Placeholder of the byte range, wide enough to be overwritten with the actual one in place.
+const BYTE_RANGE_PLACEHOLDER: i64 = 9_999_999_999;
+
+/// Parameters for signing a PDF file.
+///
+/// See also [`sign_pdf()`].
+#[derive(Debug, Clone)]
+pub struct SigningParams {
+    /// PKCS#12 (`.p12` or `.pfx`) key material, i.e. the signing certificate, its private key, and
+    /// optionally the intermediate certificates.
+    pub pkcs12: Vec<u8>,
+
+    /// Password of the PKCS#12 key material.
+    pub password: String,
+
+    /// Name of the signature field.
+    pub field_name: String,
+
+    /// Name of the signer. Leave empty to omit.
+    pub name: String,
+
+    /// Reason for signing, e.g. `Approved`. Leave empty to omit.
+    pub reason: String,
+
+    /// Location of signing, e.g. `Tokyo`. Leave empty to omit.
+    pub location: String,
+
+    /// Contact information of the signer, e.g. an email address. Leave empty to omit.
+    pub contact_info: String,
+
+    /// URL of an RFC 3161 time-stamping authority, e.g. `http://timestamp.digicert.com`, to embed a
+    /// time-stamp token into the signature, so that it remains verifiable after the signing
+    /// certificate expires. The token must match the digest and nonce of the request. Leave `None`
+    /// to omit.
+    pub timestamp_url: Option<String>,
+
+    /// Space reserved for the signature, in bytes. Increase it if signing fails because the
+    /// signature is too large, e.g. with a long certificate chain.
+    pub signature_size: usize,
+}
+
+impl Default for SigningParams {
+    fn default() -> Self {
+        Self {
+            pkcs12: vec![],
+            password: String::new(),
+            field_name: "Signature1".to_string(),
+            name: String::new(),
+            reason: String::new(),
+            location: String::new(),
+            contact_info: String::new(),
+            timestamp_url: None,
+            signature_size: 8192,
+        }
+    }
+}
+
+/// Signs a PDF file with a detached CMS (PKCS#7) signature, i.e. `adbe.pkcs7.detached`, in an
+/// invisible signature field on the first page.
+///
+/// Note that the file is rewritten rather than incrementally updated, so existing signatures of
+/// the input file are invalidated. The output file must be different from the input file.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file.
+/// - `params` - [`SigningParams`] to sign with.
+///
+/// # Example
+///
+/// Following is an example of how to use the `sign_pdf` function:
+///
+/// ```no_run
+/// typster::sign_pdf(
+///     std::path::PathBuf::from("contract.pdf"),
+///     std::path::PathBuf::from("contract-signed.pdf"),
+///     &typster::SigningParams {
+///         pkcs12: std::fs::read("signer.p12").unwrap(),
+///         password: "password".to_string(),
+///         reason: "Approved".to_string(),
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+/// ```
+#[instrument(skip_all, fields(input = %input.display(), output = %output.display()))]
+pub fn sign_pdf(
+    input: PathBuf,
+    output: PathBuf,
+    params: &SigningParams,
+) -> Result<(), Box<dyn Error>> {
+    if output.exists() && input.canonicalize()? == output.canonicalize()? {
+        return Err("in-place update is not possible".into());
+    }
+
+    let mut doc = Document::load(input)?;
+    add_signature_field(&mut doc, params)?;
+    let mut pdf = Vec::new();
+    doc.save_to(&mut pdf)?;
+
+    // The signature covers the whole file except the contents of the signature itself.
+    let placeholder = [b"<", "0".repeat(params.signature_size * 2).as_bytes(), b">"].concat();
+    let contents_start = find(&pdf, &placeholder, 0).ok_or("signature placeholder not found")?;
+    let contents_end = contents_start + placeholder.len();
+    let byte_range = [0, contents_start, contents_end, pdf.len() - contents_end];
+
+    let byte_range_placeholder =
+        format!("[0 {BYTE_RANGE_PLACEHOLDER} {BYTE_RANGE_PLACEHOLDER} {BYTE_RANGE_PLACEHOLDER}]");
+    let byte_range_start = find(&pdf, byte_range_placeholder.as_bytes(), 0)
+        .ok_or("byte range placeholder not found")?;
+    let byte_range_end = byte_range_start + byte_range_placeholder.len();
+    let actual =
+        format!("[{} {} {} {}]", byte_range[0], byte_range[1], byte_range[2], byte_range[3]);
+    let width = byte_range_placeholder.len();
+    if actual.len() > width {
+        return Err("byte range does not fit in the placeholder".into());
+    }
+    pdf[byte_range_start..byte_range_end].copy_from_slice(format!("{actual:width$}").as_bytes());
+
+    let signed = [&pdf[..contents_start], &pdf[contents_end..]].concat();
+    let mut signature = sign(&signed, params)?;
+    if let Some(url) = &params.timestamp_url {
+        signature = add_timestamp(&signature, url)?;
+    }
+    if signature.len() > params.signature_size {
+        return Err(format!(
+            "signature of {} bytes does not fit in signature_size of {} bytes",
+            signature.len(),
+            params.signature_size
+        )
+        .into());
+    }
+    let hex = signature.iter().map(|byte| format!("{byte:02X}")).collect::<String>();
+    pdf[contents_start + 1..contents_start + 1 + hex.len()].copy_from_slice(hex.as_bytes());
+
+    fs::write(output, pdf)?;
+    Ok(())
+}
+
+/// Adds a signature dictionary with placeholders, and an invisible signature field referring to
+/// it on the first page.
+fn add_signature_field(doc: &mut Document, params: &SigningParams) -> Result<(), Box<dyn Error>> {
+    let mut signature = Dictionary::new();
+    signature.set("Type", Object::Name(b"Sig".to_vec()));
+    signature.set("Filter", Object::Name(b"Adobe.PPKLite".to_vec()));
+    signature.set("SubFilter", Object::Name(b"adbe.pkcs7.detached".to_vec()));
+    signature.set(
+        "ByteRange",
+        vec![
+            Object::Integer(0),
+            Object::Integer(BYTE_RANGE_PLACEHOLDER),
+            Object::Integer(BYTE_RANGE_PLACEHOLDER),
+            Object::Integer(BYTE_RANGE_PLACEHOLDER),
+        ],
+    );
+    signature.set("Contents", Object::String(vec![0; params.signature_size], Hexadecimal));
+    // e.g. D:20240102030405+09'00'
+    signature.set(
+        "M",
+        Object::string_literal(format!(
+            "D:{}'",
+            Local::now().format("%Y%m%d%H%M%S%:z").to_string().replace(':', "'")
+        )),
+    );
+    for (key, value) in [
+        ("Name", &params.name),
+        ("Reason", &params.reason),
+        ("Location", &params.location),
+        ("ContactInfo", &params.contact_info),
+    ] {
+        if !value.is_empty() {
+            signature.set(key, text_string(value));
+        }
+    }
+    let signature = doc.add_object(signature);
+
+    let page = *doc.get_pages().get(&1).ok_or("the PDF file has no pages")?;
+    let mut field = Dictionary::new();
+    field.set("Type", Object::Name(b"Annot".to_vec()));
+    field.set("Subtype", Object::Name(b"Widget".to_vec()));
+    field.set("FT", Object::Name(b"Sig".to_vec()));
+    field.set("T", text_string(&params.field_name));
+    field.set("V", signature);
+    field.set("Rect", vec![Object::Integer(0); 4]);
+    // Print and Locked
+    field.set("F", 132);
+    field.set("P", page);
+    let field = doc.add_object(field);
+
+    // Append the field to the annotations of the page, which may be an indirect array.
+    let annots = doc.get_dictionary(page)?.get(b"Annots").ok().cloned();
+    match annots {
+        Some(Object::Reference(id)) => {
+            doc.get_object_mut(id)?.as_array_mut()?.push(Object::Reference(field))
+        }
+        Some(Object::Array(mut annots)) => {
+            annots.push(Object::Reference(field));
+            doc.get_dictionary_mut(page)?.set("Annots", annots);
+        }
+        _ => doc
+            .get_dictionary_mut(page)?
+            .set("Annots", vec![Object::Reference(field)]),
+    }
+
+    let mut form = doc
+        .catalog()?
+        .get_deref(b"AcroForm", doc)
+        .and_then(Object::as_dict)
+        .cloned()
+        .unwrap_or_default();
+    let mut fields = form
+        .get_deref(b"Fields", doc)
+        .and_then(Object::as_array)
+        .cloned()
+        .unwrap_or_default();
+    fields.push(Object::Reference(field));
+    form.set("Fields", fields);
+    // SignaturesExist and AppendOnly
+    form.set("SigFlags", 3);
+    doc.catalog_mut()?.set("AcroForm", form);
+
+    Ok(())
+}
+
+/// Creates a detached CMS (PKCS#7) signature of the data with the PKCS#12 key material.
+fn sign(data: &[u8], params: &SigningParams) -> Result<Vec<u8>, Box<dyn Error>> {
+    let pkcs12 = Pkcs12::from_der(&params.pkcs12)?.parse2(&params.password)?;
+    let cert = pkcs12.cert.ok_or("no certificate in the PKCS#12 key material")?;
+    let key = pkcs12.pkey.ok_or("no private key in the PKCS#12 key material")?;
+    let chain = match pkcs12.ca {
+        Some(chain) => chain,
+        None => Stack::new()?,
+    };
+
+    let signature =
+        Pkcs7::sign(&cert, &key, &chain, data, Pkcs7Flags::DETACHED | Pkcs7Flags::BINARY)?;
+    Ok(signature.to_der()?)
+}
+
+/// Finds the position of the needle in the haystack, starting from the given position.
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|i| from + i)
+}
@@ -0,0 +1,208 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::{Deserialize, Serialize};
+
+use crate::{fill_form::inherited, TypsterError};
+
+/// What contributes to the size of a PDF file, returned by [`size_breakdown()`]. Sizes are of the
+/// streams as stored, i.e. compressed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeReport {
+    /// Size of the file, in bytes.
+    pub file_bytes: u64,
+    /// Embedded fonts, largest first.
+    pub fonts: Vec<FontSize>,
+    /// Images, largest first.
+    pub images: Vec<ImageSize>,
+    /// Size of the content streams of the pages and of form XObjects, in bytes.
+    pub content_bytes: u64,
+    /// Size of everything else, e.g. the structure of the document, metadata, annotations, and
+    /// the cross-reference table, in bytes.
+    pub other_bytes: u64,
+}
+
+/// Size of an embedded font in [`SizeReport`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FontSize {
+    /// PostScript name of the font, without the subset tag, e.g. `NotoSansJP-Regular`.
+    pub name: String,
+    /// Size of the font program, usually a subset of the glyphs used, in bytes.
+    pub bytes: u64,
+}
+
+/// Size of an image in [`SizeReport`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageSize {
+    /// Object number of the image in the file.
+    pub object: u32,
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Size of the image stream, including its soft mask, i.e. alpha channel, if any, in bytes.
+    pub bytes: u64,
+    /// 1-based numbers of the pages which draw the image directly.
+    pub pages: Vec<u32>,
+}
+
+/// Breaks down the size of a PDF file into its fonts, images, and content streams, e.g. to decide
+/// which of the `embed_*` features and which images are bloating a compiled document.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file.
+///
+/// # Returns
+///
+/// Result containing the [`SizeReport`] of the file, or a [`TypsterError`] telling why it failed,
+/// e.g. [`TypsterError::Io`] if the file doesn't exist.
+///
+/// # Example
+///
+/// Following is an example of how to use the `size_breakdown` function:
+///
+/// ```no_run
+/// let report = typster::size_breakdown(std::path::Path::new("report.pdf")).unwrap();
+/// for font in &report.fonts {
+///     println!("{}: {} KiB", font.name, font.bytes / 1024);
+/// }
+/// for image in &report.images {
+///     let (width, height, kib) = (image.width, image.height, image.bytes / 1024);
+///     println!("{width}x{height} on pages {:?}: {kib} KiB", image.pages);
+/// }
+/// ```
+pub fn size_breakdown(path: &Path) -> Result<SizeReport, TypsterError> {
+    let file_bytes = fs::metadata(path)?.len();
+    let doc = Document::load(path)?;
+    let stream_bytes = |id: ObjectId| {
+        doc.get_object(id)
+            .and_then(Object::as_stream)
+            .map_or(0, |stream| stream.content.len() as u64)
+    };
+    let mut counted = HashSet::new();
+
+    let mut fonts = BTreeMap::<String, u64>::new();
+    for object in doc.objects.values() {
+        if !object.type_name().is_ok_and(|name| name == "FontDescriptor") {
+            continue;
+        }
+        let Ok(descriptor) = object.as_dict() else {
+            continue;
+        };
+        let file = [b"FontFile".as_slice(), b"FontFile2", b"FontFile3"]
+            .iter()
+            .find_map(|key| descriptor.get(key).and_then(Object::as_reference).ok());
+        let Some(file) = file.filter(|file| counted.insert(*file)) else {
+            continue;
+        };
+        *fonts.entry(font_name(descriptor)).or_default() += stream_bytes(file);
+    }
+
+    // Soft masks are counted with the images they mask.
+    let masks = doc
+        .objects
+        .values()
+        .filter_map(|object| object.as_stream().ok())
+        .filter_map(|stream| stream.dict.get(b"SMask").and_then(Object::as_reference).ok())
+        .collect::<HashSet<_>>();
+    let mut images = BTreeMap::<ObjectId, ImageSize>::new();
+    for (id, object) in &doc.objects {
+        let Ok(stream) = object.as_stream() else {
+            continue;
+        };
+        if !is_subtype(&stream.dict, b"Image") || masks.contains(id) {
+            continue;
+        }
+        let mut bytes = stream.content.len() as u64;
+        if let Ok(mask) = stream.dict.get(b"SMask").and_then(Object::as_reference) {
+            if counted.insert(mask) {
+                bytes += stream_bytes(mask);
+            }
+        }
+        let dimension = |key: &[u8]| stream.dict.get(key).and_then(Object::as_i64).unwrap_or(0);
+        images.insert(
+            *id,
+            ImageSize {
+                object: id.0,
+                width: dimension(b"Width") as u32,
+                height: dimension(b"Height") as u32,
+                bytes,
+                pages: vec![],
+            },
+        );
+    }
+
+    let mut content_bytes = 0;
+    for (number, page) in doc.get_pages() {
+        for id in doc.get_page_contents(page) {
+            if counted.insert(id) {
+                content_bytes += stream_bytes(id);
+            }
+        }
+        let xobjects = inherited(&doc, page, b"Resources")
+            .and_then(|resources| resources.as_dict().ok())
+            .and_then(|resources| resources.get_deref(b"XObject", &doc).ok())
+            .and_then(|xobjects| xobjects.as_dict().ok());
+        for (_, xobject) in xobjects.into_iter().flat_map(Dictionary::iter) {
+            if let Some(image) = xobject.as_reference().ok().and_then(|id| images.get_mut(&id)) {
+                if !image.pages.contains(&number) {
+                    image.pages.push(number);
+                }
+            }
+        }
+    }
+    for (id, object) in &doc.objects {
+        if object
+            .as_stream()
+            .is_ok_and(|stream| is_subtype(&stream.dict, b"Form"))
+            && counted.insert(*id)
+        {
+            content_bytes += stream_bytes(*id);
+        }
+    }
+
+    let mut fonts = fonts
+        .into_iter()
+        .map(|(name, bytes)| FontSize { name, bytes })
+        .collect::<Vec<_>>();
+    fonts.sort_by_key(|font| Reverse(font.bytes));
+    let mut images = images.into_values().collect::<Vec<_>>();
+    images.sort_by_key(|image| Reverse(image.bytes));
+
+    let accounted = fonts.iter().map(|font| font.bytes).sum::<u64>()
+        + images.iter().map(|image| image.bytes).sum::<u64>()
+        + content_bytes;
+    Ok(SizeReport {
+        file_bytes,
+        fonts,
+        images,
+        content_bytes,
+        other_bytes: file_bytes.saturating_sub(accounted),
+    })
+}
+
+/// Returns the name of the font of a font descriptor, without the subset tag, e.g. ABCDEF+.
+fn font_name(descriptor: &Dictionary) -> String {
+    let name = descriptor
+        .get(b"FontName")
+        .and_then(Object::as_name)
+        .unwrap_or_default();
+    let name = String::from_utf8_lossy(name);
+    match name.split_once('+') {
+        Some((tag, name)) if tag.len() == 6 => name.to_string(),
+        _ => name.into_owned(),
+    }
+}
+
+/// Returns whether a stream dictionary is of the subtype.
+fn is_subtype(dict: &Dictionary, subtype: &[u8]) -> bool {
+    dict.get(b"Subtype")
+        .and_then(Object::as_name)
+        .is_ok_and(|name| name == subtype)
+}
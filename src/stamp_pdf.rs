@@ -0,0 +1,150 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    path::{Path, PathBuf},
+};
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use tracing::instrument;
+
+use crate::{
+    compile::compile_pdf,
+    merge_pdfs::{inline_inherited_attributes, offset_references},
+    overlay::{display_box, overlay},
+    world::SystemWorld,
+    CompileParams, TypsterError,
+};
+
+/// Parameters for stamping a PDF file.
+///
+/// See also [`stamp_pdf()`].
+#[derive(Debug, Clone, Default)]
+pub struct StampParams {
+    /// Path to the Typst template of the stamp, e.g. a `CONFIDENTIAL` watermark or a footer.
+    ///
+    /// It's compiled for each page with the following `sys.inputs`, in addition to
+    /// [`dict`](StampParams::dict):
+    ///
+    /// - `page` - 1-based page number.
+    /// - `total` - Number of pages.
+    /// - `width`, `height` - Size of the page as displayed, in points, e.g. `595.28`.
+    ///
+    /// The first page of the stamp is overlaid on the page, aligned to its bottom-left corner, so
+    /// the template should set its page size accordingly, with a transparent background, e.g.
+    /// `#set page(width: float(sys.inputs.width) * 1pt, height: float(sys.inputs.height) * 1pt,
+    /// margin: 0pt)`.
+    pub template: PathBuf,
+
+    /// String key-value pairs visible through `sys.inputs` in the template.
+    pub dict: Vec<(String, String)>,
+
+    /// Adds additional directories to search for fonts.
+    pub font_paths: Vec<PathBuf>,
+}
+
+/// Overlays a stamp compiled from a Typst template on every page of a PDF file, e.g. to watermark
+/// a document as `CONFIDENTIAL`, or to add page numbers to a scanned document.
+///
+/// Stamps of the same content, e.g. a watermark which doesn't depend on the page number, are
+/// embedded only once.
+///
+/// # Arguments
+///
+/// - `input` - Path to the input PDF file.
+/// - `output` - Path to the output PDF file, which may be the same as the input file.
+/// - `params` - [`StampParams`] to stamp with.
+///
+/// # Example
+///
+/// Following is an example of how to use the `stamp_pdf` function:
+///
+/// ```no_run
+/// typster::stamp_pdf(
+///     std::path::Path::new("sample.pdf"),
+///     std::path::Path::new("stamped.pdf"),
+///     &typster::StampParams {
+///         template: std::path::PathBuf::from("confidential.typ"),
+///         dict: vec![("text".to_string(), "CONFIDENTIAL".to_string())],
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+/// ```
+#[instrument(skip_all, fields(input = %input.display(), output = %output.display()))]
+pub fn stamp_pdf(input: &Path, output: &Path, params: &StampParams) -> Result<(), Box<dyn Error>> {
+    let mut doc = Document::load(input)?;
+    let pages = doc.get_pages().into_values().collect::<Vec<_>>();
+    inline_inherited_attributes(&mut doc, &pages)?;
+
+    let compile_params = CompileParams {
+        input: params.template.clone(),
+        dict: params.dict.clone(),
+        font_paths: params.font_paths.clone(),
+        ..Default::default()
+    };
+    let mut world = SystemWorld::new(&compile_params).map_err(TypsterError::from)?;
+
+    // Form XObjects of the stamps, keyed by the compiled PDF.
+    let mut stamps = HashMap::<Vec<u8>, ObjectId>::new();
+    for (i, &page) in pages.iter().enumerate() {
+        let display = display_box(doc.get_dictionary(page)?)?;
+
+        let mut dict = params.dict.clone();
+        dict.extend([
+            ("page".to_string(), (i + 1).to_string()),
+            ("total".to_string(), pages.len().to_string()),
+            ("width".to_string(), display.width.to_string()),
+            ("height".to_string(), display.height.to_string()),
+        ]);
+        world.set_inputs(&dict);
+        world.reset();
+        let pdf = compile_pdf(&world)?;
+
+        let stamp = match stamps.get(&pdf) {
+            Some(&stamp) => stamp,
+            None => {
+                let stamp = import_stamp(&mut doc, &pdf)?;
+                stamps.insert(pdf, stamp);
+                stamp
+            }
+        };
+        overlay(&mut doc, page, stamp, display.matrix)?;
+    }
+
+    doc.prune_objects();
+    doc.save(output)?;
+
+    Ok(())
+}
+
+/// Imports the first page of the compiled stamp into the document as a form XObject.
+fn import_stamp(doc: &mut Document, pdf: &[u8]) -> Result<ObjectId, Box<dyn Error>> {
+    let mut stamp = Document::load_mem(pdf)?;
+    let page = *stamp.get_pages().get(&1).ok_or("the stamp has no pages")?;
+    inline_inherited_attributes(&mut stamp, &[page])?;
+    let content = stamp.get_page_content(page)?;
+    let page = stamp.get_dictionary(page)?;
+    let mut resources = page
+        .get(b"Resources")
+        .cloned()
+        .unwrap_or(Object::Dictionary(Dictionary::new()));
+    let bbox = page.get(b"MediaBox")?.clone();
+
+    // Move all objects to unused ids, then add the form referring to them.
+    let offset = doc.max_id;
+    for (id, mut object) in std::mem::take(&mut stamp.objects) {
+        offset_references(&mut object, offset);
+        doc.objects.insert((id.0 + offset, id.1), object);
+    }
+    doc.max_id += stamp.max_id;
+
+    offset_references(&mut resources, offset);
+    let mut form = Dictionary::new();
+    form.set("Type", Object::Name(b"XObject".to_vec()));
+    form.set("Subtype", Object::Name(b"Form".to_vec()));
+    form.set("BBox", bbox);
+    form.set("Resources", resources);
+    let mut form = Stream::new(form, content);
+    let _ = form.compress();
+    Ok(doc.add_object(form))
+}
@@ -0,0 +1,86 @@
+use image::{ImageFormat, Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use typst::{
+    diag::{At, SourceResult},
+    model::Document,
+};
+use typst_syntax::Span;
+
+use crate::CompileParams;
+
+/// Options of the export of all pages as one tall image, for [`CompileParams::strip`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Strip {
+    /// Space between two pages, in points, scaled with the pages by
+    /// [`CompileParams::ppi`]. Defaults to `0.0`.
+    pub gap: f32,
+    /// Color of the gaps, of the margins of pages narrower than the widest one, and behind
+    /// transparent pages, as RGBA. Defaults to opaque white.
+    pub background: [u8; 4],
+}
+
+impl Default for Strip {
+    fn default() -> Self {
+        Self { gap: 0.0, background: [255, 255, 255, 255] }
+    }
+}
+
+/// Export all pages to one image, stacked vertically and centered horizontally, in the format of
+/// the extension of the output, PNG or WebP.
+pub(crate) fn export_strip(
+    document: &Document,
+    params: &CompileParams,
+    strip: &Strip,
+) -> SourceResult<()> {
+    let ppi = params.ppi.unwrap_or(144.0);
+    let format = ImageFormat::from_path(&params.output)
+        .ok()
+        .filter(|format| matches!(format, ImageFormat::Png | ImageFormat::WebP))
+        .ok_or("a strip can only be exported to PNG or WebP")
+        .at(Span::detached())?;
+
+    let pages = document
+        .pages
+        .iter()
+        .map(|page| typst_render::render(page, ppi / 72.0))
+        .collect::<Vec<_>>();
+    let gap = (strip.gap.max(0.0) * ppi / 72.0).round() as u32;
+    let width = pages.iter().map(|page| page.width()).max().unwrap_or(1);
+    let height = pages.iter().map(|page| page.height()).sum::<u32>()
+        + gap * pages.len().saturating_sub(1) as u32;
+
+    let mut image = RgbaImage::from_pixel(width, height.max(1), Rgba(strip.background));
+    let mut top = 0;
+    for page in &pages {
+        let left = (width - page.width()) / 2;
+        for (i, pixel) in page.pixels().iter().enumerate() {
+            let (x, y) = (i as u32 % page.width(), i as u32 / page.width());
+            let target = image.get_pixel_mut(left + x, top + y);
+            target.0 = over([pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()], target.0);
+        }
+        top += page.height() + gap;
+    }
+
+    image
+        .save_with_format(&params.output, format)
+        .map_err(|err| format!("failed to write strip: {err}"))
+        .at(Span::detached())
+}
+
+/// Composites a premultiplied pixel of a page over a straight background pixel, and returns a
+/// straight pixel.
+fn over(page: [u8; 4], background: [u8; 4]) -> [u8; 4] {
+    let (alpha, background_alpha) = (page[3] as u32, background[3] as u32);
+    // Alpha of the result, and the contribution of the background, out of 255 * 255.
+    let out_alpha = alpha * 255 + background_alpha * (255 - alpha);
+    if out_alpha == 0 {
+        return [0; 4];
+    }
+    let channel = |i: usize| {
+        let premultiplied =
+            page[i] as u32 * 255 * 255 + background[i] as u32 * background_alpha * (255 - alpha);
+        (premultiplied / out_alpha).min(255) as u8
+    };
+    [channel(0), channel(1), channel(2), (out_alpha / 255) as u8]
+}
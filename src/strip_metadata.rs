@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use lopdf::{Document, Object};
+
+/// Keys which hold identifying data in any dictionary of a PDF document.
+///
+/// - `Metadata`: XMP packet of the document, a page, or any other object.
+/// - `PieceInfo`: private data of the application which created or modified the object.
+/// - `LastModified`: modification date of the object for `PieceInfo`.
+const IDENTIFYING_KEYS: [&[u8]; 3] = [b"Metadata", b"PieceInfo", b"LastModified"];
+
+/// Strips metadata from a PDF file, for documents which must be published anonymously.
+///
+/// Following data is removed:
+///
+/// - the document information dictionary, i.e. title, author, dates, and so on.
+/// - XMP packets of the document and all other objects.
+/// - private data of applications (`PieceInfo`).
+/// - the document ID.
+///
+/// # Argument
+///
+/// - `path` - Path to the PDF file, which will be updated in place.
+///
+/// # Example
+///
+/// Following is an example of how to use the `strip_metadata` function:
+///
+/// ```rust
+/// let output = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///     .join("examples")
+///     .join("sample.pdf");
+///
+/// // Compile a document first
+/// let params = typster::CompileParams::builder()
+///     .input(
+///         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///             .join("examples")
+///             .join("sample.typ"),
+///     )
+///     .output(output.clone())
+///     .font_path("assets")
+///     .dict("input", "value")
+///     .build();
+/// match typster::compile(&params) {
+///     Ok(duration) => println!("Compilation succeeded in {duration:?}"),
+///     Err(why) => eprintln!("{why}"),
+/// }
+///
+/// // Then strip metadata
+/// typster::strip_metadata(&output).unwrap();
+/// ```
+pub fn strip_metadata(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut doc = Document::load(path)?;
+
+    doc.trailer.remove(b"Info");
+    doc.trailer.remove(b"ID");
+
+    for object in doc.objects.values_mut() {
+        let dict = match object {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => &mut stream.dict,
+            _ => continue,
+        };
+        for key in IDENTIFYING_KEYS {
+            dict.remove(key);
+        }
+    }
+
+    // Drop the now unreferenced objects, e.g. the XMP streams and the information dictionary.
+    doc.prune_objects();
+    doc.save(path)?;
+
+    Ok(())
+}
@@ -0,0 +1,193 @@
+use std::{
+    env,
+    error::Error,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use image::{ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    diff::{compare_page, render},
+    CompileParams,
+};
+
+/// Environment variable which, when set, makes [`assert_compiles_like()`] write the golden images
+/// from the current rendering, including missing ones, instead of comparing against them.
+pub const BLESS_ENV: &str = "TYPSTER_BLESS";
+
+/// Sequence number of the temporary files written by [`write_png()`].
+static NEXT_TEMP: AtomicUsize = AtomicUsize::new(0);
+
+/// How much a rendering may differ from its golden image, for
+/// [`assert_compiles_like_with()`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tolerance {
+    /// Maximum difference of a color channel for a pixel to be considered unchanged, e.g. to
+    /// ignore anti-aliasing differences between platforms.
+    pub channel: u8,
+    /// Maximum fraction of the pixels of a page which may be changed, from `0.0` to `1.0`.
+    pub pixels: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self { channel: 2, pixels: 0.001 }
+    }
+}
+
+/// Asserts that a Typst document renders like its golden images, with the default
+/// [`Tolerance`], e.g. in an integration test of a document template.
+///
+/// See [`assert_compiles_like_with()`] for details.
+///
+/// # Arguments
+///
+/// - `goldens_dir` - Directory of the golden images.
+/// - `params` - [`CompileParams`] of the document. Its `ppi`, `144.0` by default, is used for
+///   rendering. The output file is not written.
+///
+/// # Panics
+///
+/// If the document fails to compile, a golden image is missing, or its rendering differs from the
+/// golden images.
+///
+/// # Example
+///
+/// Following is an example of how to use the `assert_compiles_like` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("tests/invoice.typ").ppi(72.0).build();
+/// typster::assert_compiles_like("tests/goldens", &params);
+/// ```
+pub fn assert_compiles_like(goldens_dir: impl AsRef<Path>, params: &CompileParams) {
+    assert_compiles_like_with(goldens_dir, params, Tolerance::default());
+}
+
+/// Asserts that a Typst document renders like its golden images within a [`Tolerance`].
+///
+/// The golden image of each page is `<goldens_dir>/<input stem>-<page>.png`, e.g.
+/// `invoice-1.png`. A missing golden is a failure, so that a test never passes by comparing a
+/// rendering against itself. Set the [`BLESS_ENV`] environment variable to write all of them from
+/// the current rendering, e.g. for a new document or after an intended change, then review and
+/// commit them. On failure, the current rendering is written next to each golden which is missing
+/// or doesn't match as `<input stem>-<page>.actual.png`, along with an image of the differences,
+/// with the changed pixels in red, as `<input stem>-<page>.diff.png`.
+///
+/// # Arguments
+///
+/// - `goldens_dir` - Directory of the golden images.
+/// - `params` - [`CompileParams`] of the document. Its `ppi`, `144.0` by default, is used for
+///   rendering. The output file is not written.
+/// - `tolerance` - How much a page may differ from its golden image.
+///
+/// # Panics
+///
+/// If the document fails to compile, a golden image is missing, or its rendering differs from the
+/// golden images.
+///
+/// # Example
+///
+/// Following is an example of how to use the `assert_compiles_like_with` function:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("tests/invoice.typ").ppi(72.0).build();
+/// let tolerance = typster::Tolerance { channel: 8, pixels: 0.01 };
+/// typster::assert_compiles_like_with("tests/goldens", &params, tolerance);
+/// ```
+pub fn assert_compiles_like_with(
+    goldens_dir: impl AsRef<Path>,
+    params: &CompileParams,
+    tolerance: Tolerance,
+) {
+    if let Err(why) = compare_to_goldens(goldens_dir.as_ref(), params, tolerance) {
+        panic!("{} doesn't render like its goldens: {why}", params.input.display());
+    }
+}
+
+/// Renders a document, and compares each page against its golden image.
+fn compare_to_goldens(
+    dir: &Path,
+    params: &CompileParams,
+    tolerance: Tolerance,
+) -> Result<(), Box<dyn Error>> {
+    let pages = render(params, params.ppi.unwrap_or(144.0))?;
+    let stem = params
+        .input
+        .file_stem()
+        .ok_or("input has no file name")?
+        .to_string_lossy();
+    let golden = |page: usize, suffix: &str| dir.join(format!("{stem}-{page}{suffix}.png"));
+    fs::create_dir_all(dir)?;
+
+    let bless = env::var_os(BLESS_ENV).is_some();
+    let mut failures = String::new();
+    for (i, actual) in pages.iter().enumerate() {
+        let page = i + 1;
+        let path = golden(page, "");
+        let (actual_path, diff_path) = (golden(page, ".actual"), golden(page, ".diff"));
+        if bless {
+            write_png(actual, &path)?;
+            continue;
+        }
+        if !path.exists() {
+            write_png(actual, &actual_path)?;
+            writeln!(
+                failures,
+                "page {page}: {} is missing, set {BLESS_ENV} to write it from {}",
+                path.display(),
+                actual_path.display()
+            )?;
+            continue;
+        }
+
+        let expected = image::open(&path)?.into_rgba8();
+        let (changed, diff) = compare_page(&expected, actual, tolerance.channel);
+        let total = diff.width() as f64 * diff.height() as f64;
+        if changed as f64 > tolerance.pixels * total {
+            write_png(actual, &actual_path)?;
+            write_png(&diff, &diff_path)?;
+            writeln!(
+                failures,
+                "page {page}: {changed} of {total} pixels changed, see {}",
+                diff_path.display()
+            )?;
+        } else {
+            // Remove the artifacts of a previous failure.
+            let _ = fs::remove_file(actual_path);
+            let _ = fs::remove_file(diff_path);
+        }
+    }
+
+    let extra = golden(pages.len() + 1, "");
+    if bless {
+        // Remove the goldens of the pages which don't exist anymore.
+        (pages.len() + 1..)
+            .map(|page| golden(page, ""))
+            .take_while(|path| path.exists())
+            .try_for_each(fs::remove_file)?;
+    } else if extra.exists() {
+        writeln!(failures, "{} pages, but {} exists", pages.len(), extra.display())?;
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("\n{failures}").into())
+    }
+}
+
+/// Writes an image as a PNG file through a temporary file, so that tests comparing against the
+/// same golden in parallel never read it half-written.
+fn write_png(image: &RgbaImage, path: &Path) -> Result<(), Box<dyn Error>> {
+    let mut temp = PathBuf::from(path);
+    let sequence = NEXT_TEMP.fetch_add(1, Ordering::Relaxed);
+    temp.set_extension(format!("{}-{sequence}.tmp", process::id()));
+    image.save_with_format(&temp, ImageFormat::Png)?;
+    fs::rename(temp, path)?;
+    Ok(())
+}
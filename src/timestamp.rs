@@ -0,0 +1,237 @@
+use std::{error::Error, io::Read, time::Duration};
+
+use openssl::{rand::rand_bytes, sha::sha256};
+
+/// DER-encoded OID of the `id-aa-timeStampToken` attribute, i.e. 1.2.840.113549.1.9.16.2.14.
+const TIMESTAMP_TOKEN: &[u8] =
+    &[0x06, 0x0B, 0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x10, 0x02, 0x0E];
+/// DER-encoded algorithm identifier of SHA-256, i.e. 2.16.840.1.101.3.4.2.1 with NULL parameters.
+const SHA256: &[u8] =
+    &[0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00];
+
+/// Upper limit of a time-stamp response, to avoid reading an unexpected response endlessly.
+const MAX_RESPONSE_SIZE: u64 = 1024 * 1024;
+/// Time limit of connecting to the time-stamping authority.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Time limit of each read of the response, so that a stalled authority doesn't hang signing.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Requests an RFC 3161 time-stamp token of the signature value from the time-stamping authority,
+/// and embeds it into the CMS (PKCS#7) signature as an unsigned attribute.
+///
+/// # Arguments
+///
+/// - `signature` - DER-encoded CMS (PKCS#7) signature.
+/// - `url` - URL of the time-stamping authority.
+///
+/// # Returns
+///
+/// Result containing the DER-encoded signature with the time-stamp token.
+pub(crate) fn add_timestamp(signature: &[u8], url: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let digest = {
+        let signer_info = first_signer_info(signature)?;
+        let fields = children(content(signer_info)?)?;
+        let digest = fields
+            .iter()
+            .find(|field| field.first() == Some(&0x04))
+            .ok_or("no signature value in the signer info")?;
+        sha256(content(digest)?)
+    };
+    embed_token(signature, &request(url, &digest)?)
+}
+
+/// Sends a time-stamp request of the SHA-256 digest to the time-stamping authority, and returns the
+/// time-stamp token of the response, once checked against the request.
+fn request(url: &str, digest: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut nonce = [0; 8];
+    rand_bytes(&mut nonce)?;
+    // Keep the nonce a positive integer.
+    nonce[0] = nonce[0] & 0x7F | 0x01;
+
+    let imprint = encode(0x30, &[encode(0x30, SHA256), encode(0x04, digest)].concat());
+    let request = encode(
+        0x30,
+        &[
+            encode(0x02, &[1]),
+            imprint.clone(),
+            encode(0x02, &nonce),
+            // certReq, so that the token contains the certificate of the authority.
+            encode(0x01, &[0xFF]),
+        ]
+        .concat(),
+    );
+
+    let mut response = vec![];
+    ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .build()
+        .post(url)
+        .set("Content-Type", "application/timestamp-query")
+        .send_bytes(&request)?
+        .into_reader()
+        .take(MAX_RESPONSE_SIZE)
+        .read_to_end(&mut response)?;
+
+    // TimeStampResp ::= SEQUENCE { status PKIStatusInfo, timeStampToken ContentInfo OPTIONAL }
+    let parts = children(content(&response)?)?;
+    let status = parts
+        .first()
+        .map(|status| children(content(status)?))
+        .transpose()?
+        .and_then(|status| status.first().copied())
+        .ok_or("invalid time-stamp response")?;
+    // 0: granted, 1: grantedWithMods
+    if !matches!(content(status)?, [0] | [1]) {
+        return Err("time-stamp request was rejected".into());
+    }
+    let token = parts.get(1).ok_or("no time-stamp token")?;
+    check_token(token, &imprint, &nonce)?;
+    Ok(token.to_vec())
+}
+
+/// Checks that the time-stamp token is over the message imprint of the request, and carries its
+/// nonce, so that a token of another request isn't embedded.
+fn check_token(token: &[u8], imprint: &[u8], nonce: &[u8]) -> Result<(), Box<dyn Error>> {
+    // ContentInfo ::= SEQUENCE { contentType, [0] EXPLICIT SignedData }
+    let explicit = *children(content(token)?)?.get(1).ok_or("invalid time-stamp token")?;
+    let signed_data = *children(content(explicit)?)?
+        .first()
+        .ok_or("invalid time-stamp token")?;
+    // SignedData ::= SEQUENCE { version, digestAlgorithms, encapContentInfo, ... }
+    let encap_content_info = *children(content(signed_data)?)?
+        .get(2)
+        .ok_or("invalid time-stamp token")?;
+    // EncapsulatedContentInfo ::= SEQUENCE { eContentType, eContent [0] EXPLICIT OCTET STRING }
+    let explicit = *children(content(encap_content_info)?)?
+        .get(1)
+        .ok_or("no TSTInfo in the token")?;
+    let octets = *children(content(explicit)?)?
+        .first()
+        .ok_or("no TSTInfo in the token")?;
+    // TSTInfo ::= SEQUENCE { version, policy, messageImprint, serialNumber, genTime,
+    //   accuracy OPTIONAL, ordering DEFAULT FALSE, nonce INTEGER OPTIONAL, ... }
+    let tst_info = children(content(content(octets)?)?)?;
+    let token_imprint = tst_info.get(2).ok_or("invalid TSTInfo")?;
+    if !same_imprint(token_imprint, imprint)? {
+        return Err("time-stamp token is not over the signature value".into());
+    }
+    let token_nonce = tst_info.iter().skip(5).find(|field| field.first() == Some(&0x02));
+    if token_nonce.map(|nonce| content(nonce)).transpose()? != Some(nonce) {
+        return Err("time-stamp token does not match the nonce of the request".into());
+    }
+    Ok(())
+}
+
+/// Returns whether the message imprints have the same hash algorithm and hashed message, ignoring
+/// the algorithm parameters, which may be either absent or NULL.
+fn same_imprint(imprint: &[u8], other: &[u8]) -> Result<bool, Box<dyn Error>> {
+    // MessageImprint ::= SEQUENCE { hashAlgorithm AlgorithmIdentifier, hashedMessage OCTET STRING }
+    let (fields, other) = (children(content(imprint)?)?, children(content(other)?)?);
+    let ([algorithm, digest], [other_algorithm, other_digest]) = (&fields[..], &other[..]) else {
+        return Err("invalid message imprint".into());
+    };
+    let oid = children(content(algorithm)?)?.first().copied();
+    Ok(oid.is_some()
+        && oid == children(content(other_algorithm)?)?.first().copied()
+        && digest == other_digest)
+}
+
+/// Embeds the time-stamp token into the unsigned attributes of the first signer info.
+fn embed_token(signature: &[u8], token: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    // ContentInfo ::= SEQUENCE { contentType, [0] EXPLICIT SignedData }
+    let content_info = children(content(signature)?)?;
+    let [content_type, explicit] = content_info[..] else {
+        return Err("invalid signature".into());
+    };
+    let signed_data = *children(content(explicit)?)?.first().ok_or("invalid signature")?;
+    // SignedData ::= SEQUENCE { ..., signerInfos SET OF SignerInfo }
+    let mut signed_data_fields = children(content(signed_data)?)?;
+    let signer_infos = signed_data_fields.pop().ok_or("invalid signature")?;
+    let mut infos = children(content(signer_infos)?)?;
+    if infos.is_empty() {
+        return Err("no signer info in the signature".into());
+    }
+    let mut fields = children(content(infos.remove(0))?)?;
+
+    // Attribute ::= SEQUENCE { attrType, attrValues SET OF AttributeValue }
+    let attribute = encode(0x30, &[TIMESTAMP_TOKEN, &encode(0x31, token)].concat());
+    // unsignedAttrs [1] IMPLICIT SET OF Attribute, which is the last field if exists.
+    let unsigned = match fields.last() {
+        Some(last) if last.first() == Some(&0xA1) => {
+            let existing = content(fields.pop().unwrap())?;
+            encode(0xA1, &[existing, &attribute].concat())
+        }
+        _ => encode(0xA1, &attribute),
+    };
+    let signer_info = encode(0x30, &[fields.concat(), unsigned].concat());
+
+    let signer_infos = encode(0x31, &[signer_info, infos.concat()].concat());
+    let signed_data = encode(0x30, &[signed_data_fields.concat(), signer_infos].concat());
+    Ok(encode(0x30, &[content_type, &encode(0xA0, &signed_data)].concat()))
+}
+
+/// Returns the first signer info of a CMS (PKCS#7) signature.
+fn first_signer_info(signature: &[u8]) -> Result<&[u8], Box<dyn Error>> {
+    let content_info = children(content(signature)?)?;
+    let explicit = content_info.get(1).ok_or("invalid signature")?;
+    let signed_data = *children(content(explicit)?)?.first().ok_or("invalid signature")?;
+    let signer_infos = *children(content(signed_data)?)?.last().ok_or("invalid signature")?;
+    children(content(signer_infos)?)?
+        .first()
+        .copied()
+        .ok_or_else(|| "no signer info".into())
+}
+
+/// Reads the header of the DER element at the start of the bytes, and returns the length of the
+/// header and of the whole element.
+fn header(der: &[u8]) -> Result<(usize, usize), Box<dyn Error>> {
+    let length = *der.get(1).ok_or("truncated DER element")?;
+    let (header, length) = if length < 0x80 {
+        (2, length as usize)
+    } else {
+        let octets = (length & 0x7F) as usize;
+        if octets == 0 || octets > 4 {
+            return Err("unsupported DER length".into());
+        }
+        let bytes = der.get(2..2 + octets).ok_or("truncated DER element")?;
+        (2 + octets, bytes.iter().fold(0, |length, &byte| length << 8 | byte as usize))
+    };
+    if der.len() < header + length {
+        return Err("truncated DER element".into());
+    }
+    Ok((header, header + length))
+}
+
+/// Returns the content of the DER element at the start of the bytes.
+fn content(der: &[u8]) -> Result<&[u8], Box<dyn Error>> {
+    let (header, end) = header(der)?;
+    Ok(&der[header..end])
+}
+
+/// Splits the content of a constructed DER element into its child elements.
+fn children(mut content: &[u8]) -> Result<Vec<&[u8]>, Box<dyn Error>> {
+    let mut children = vec![];
+    while !content.is_empty() {
+        let (_, end) = header(content)?;
+        children.push(&content[..end]);
+        content = &content[end..];
+    }
+    Ok(children)
+}
+
+/// Encodes a DER element with the tag and content.
+fn encode(tag: u8, content: &[u8]) -> Vec<u8> {
+    let length = content.len();
+    let mut der = vec![tag];
+    if length < 0x80 {
+        der.push(length as u8);
+    } else {
+        let bytes = length.to_be_bytes();
+        let skip = bytes.iter().take_while(|&&byte| byte == 0).count();
+        der.push(0x80 | (bytes.len() - skip) as u8);
+        der.extend_from_slice(&bytes[skip..]);
+    }
+    der.extend_from_slice(content);
+    der
+}
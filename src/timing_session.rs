@@ -0,0 +1,120 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use typst::World;
+use typst_syntax::Span;
+
+use crate::{compile::compile_world, world::SystemWorld, CompileParams, TypsterError};
+
+/// Number of sessions which are not finished or dropped yet.
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Discards the recorded events unless a session is active.
+///
+/// `typst-timing` can't be disabled once a session enabled it, so every compilation of the process
+/// keeps recording, e.g. the ones of `watch()` or a long-running server. This is called after each
+/// of them so that the recorder doesn't grow without bound.
+pub(crate) fn discard_timings() {
+    if typst_timing::is_enabled() && ACTIVE.load(Ordering::Acquire) == 0 {
+        typst_timing::clear();
+    }
+}
+
+/// Records where the time of one or more compilations goes with `typst-timing`, and writes it as a
+/// trace in the Chrome trace format, like `typst compile --timings`, e.g. to profile a performance
+/// regression in a document template with `chrome://tracing` or [Perfetto](https://ui.perfetto.dev).
+///
+/// `typst-timing` records globally, so only one session should be active at a time, and the
+/// compilations of other threads while it is active are recorded too. `typst-timing` can't be
+/// disabled once enabled, so starting a session turns recording on for the rest of the process.
+/// The recorded events are discarded when the session is [finished](TimingSession::finish()) or
+/// dropped, and after each later compilation outside of a session, which still takes the lock of
+/// the global recorder for every event.
+///
+/// # Example
+///
+/// Following is an example of how to use the `TimingSession`:
+///
+/// ```no_run
+/// let params = typster::CompileParams::builder().input("template.typ").build();
+/// let mut session = typster::TimingSession::start();
+/// session.compile(&params).unwrap();
+/// // Compile again to see the effect of the memoized results.
+/// session.compile(&params).unwrap();
+/// session.finish(std::path::Path::new("timings.json")).unwrap();
+/// ```
+pub struct TimingSession {
+    /// Worlds of the compilations, to resolve the source locations of the recorded events.
+    worlds: Vec<SystemWorld>,
+}
+
+impl TimingSession {
+    /// Starts recording, discarding the events recorded before.
+    pub fn start() -> Self {
+        ACTIVE.fetch_add(1, Ordering::AcqRel);
+        typst_timing::clear();
+        if !typst_timing::is_enabled() {
+            typst_timing::enable();
+        }
+        Self { worlds: vec![] }
+    }
+
+    /// Compiles a document while recording, the same way as [`compile()`](crate::compile()).
+    ///
+    /// # Arguments
+    ///
+    /// - `params` - [`CompileParams`] of the document.
+    ///
+    /// # Returns
+    ///
+    /// Result containing the duration of the compilation, or a [`TypsterError`] telling why it
+    /// failed. The events of a failed compilation are recorded too.
+    pub fn compile(&mut self, params: &CompileParams) -> Result<Duration, TypsterError> {
+        let world = SystemWorld::new(params)?;
+        let result = compile_world(&world, params);
+        self.worlds.push(world);
+        result
+    }
+
+    /// Writes the recorded events to a file in the Chrome trace format, and discards them.
+    ///
+    /// # Arguments
+    ///
+    /// - `path` - Path to the output JSON file.
+    ///
+    /// # Returns
+    ///
+    /// Result indicating whether the trace was written.
+    pub fn finish(self, path: &Path) -> Result<(), TypsterError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        typst_timing::export_json(&mut writer, |span| {
+            self.resolve(span)
+                .unwrap_or_else(|| ("unknown".to_string(), 0))
+        })
+        .map_err(io::Error::other)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Resolves the file and the 1-based line of a span with the world which read it.
+    fn resolve(&self, span: Span) -> Option<(String, u32)> {
+        let id = span.id()?;
+        self.worlds.iter().find_map(|world| {
+            let source = world.source(id).ok()?;
+            let line = source.byte_to_line(source.range(span)?.start)?;
+            Some((format!("{id:?}"), line as u32 + 1))
+        })
+    }
+}
+
+impl Drop for TimingSession {
+    fn drop(&mut self) {
+        typst_timing::clear();
+        ACTIVE.fetch_sub(1, Ordering::AcqRel);
+    }
+}
@@ -1,21 +1,34 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    path::Path,
+    str::FromStr,
+};
 
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, TimeZone, Timelike, Utc};
 use lopdf::{text_string, Dictionary, Document, Object};
 use serde::{Deserialize, Serialize};
+use tracing::instrument;
 use xmp_toolkit::{
-    xmp_ns::{DC, XMP, XMP_RIGHTS},
-    OpenFileOptions, XmpDateTime, XmpFile, XmpMeta, XmpValue,
+    xmp_ns::{DC, PDF, XMP, XMP_RIGHTS},
+    XmpDate, XmpDateTime, XmpError, XmpMeta, XmpTime, XmpTimeZone, XmpValue,
+};
+
+use crate::{
+    xmp::{append_history, copy_history, read_xmp, write_xmp},
+    DocumentId, TypsterError,
 };
 
 /// PDF, dublin core, and [Extensible Metadata Platform (XMP)](https://www.adobe.com/devnet/xmp.html) metadata for a PDF document.
 ///
 /// See also [`update_metadata()`] and [Extensible Metadata Platform (XMP) Specification: Part 1, Data Model, Serialization, and Core Properties](https://github.com/adobe/XMP-Toolkit-SDK/blob/main/docs/XMPSpecificationPart1.pdf) for detail.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct PdfMetadata {
-    /// Title of the document.
+    /// Title of the document, optionally in multiple languages.
     /// - Acrobat Reader: Title
     /// - Apple Preview: Title
-    pub title: String,
+    pub title: LocalizedText,
 
     /// Author of the document.
     /// - Acrobat Reader: Author
@@ -27,10 +40,10 @@ pub struct PdfMetadata {
     /// - Apple Preview: PDF Producer _and_ Content creator
     pub application: String,
 
-    /// Subject of the document.
+    /// Subject of the document, optionally in multiple languages.
     /// - Acrobat Reader: Subject _and_ Description
     /// - Apple Preview: Subject
-    pub subject: String,
+    pub subject: LocalizedText,
 
     /// Copyright status. `true` means `Marked`.
     /// - Acrobat Reader: Copyright Status
@@ -42,44 +55,326 @@ pub struct PdfMetadata {
     /// - Apple Preview: (None)
     pub copyright_notice: String,
 
-    /// Keywords, which should be set as an array, but will be concatenated and set as a single
-    /// property.
+    /// Keywords, which will be set as an XMP array (`dc:subject`), and also concatenated and set as
+    /// a single property.
     /// - Acrobat Reader: Keywords
     /// - Apple Preview: (None)
     pub keywords: Vec<String>,
 
-    /// Language (RFC 3066)
+    /// Language (RFC 3066), which is set as the `Lang` of the document catalog, e.g. for screen
+    /// readers, and as `dc:language`. Nothing is set if empty.
     pub language: String,
 
     /// Custom properties.
     /// - Acrobat Reader: Custom properties
     /// - Apple Preview: (None)
     pub custom_properties: HashMap<String, String>,
+
+    /// Custom XMP namespaces, e.g. for the metadata schema of an organization, and properties to
+    /// write into them.
+    /// - Acrobat Reader: Additional Metadata (Advanced)
+    /// - Apple Preview: (None)
+    #[serde(default)]
+    pub custom_namespaces: Vec<XmpNamespace>,
+
+    /// Creation date. [`None`] means today, without time information.
+    /// - Acrobat Reader: Created
+    /// - Apple Preview: Creation date
+    #[serde(default)]
+    pub creation_date: Option<DateTime<FixedOffset>>,
+
+    /// Modification date. [`None`] means today, without time information.
+    /// - Acrobat Reader: Modified
+    /// - Apple Preview: Modification date
+    #[serde(default)]
+    pub modification_date: Option<DateTime<FixedOffset>>,
+
+    /// Keep the creation and modification dates of the file untouched, ignoring `creation_date`
+    /// and `modification_date`.
+    #[serde(default)]
+    pub preserve_dates: bool,
+
+    /// Publisher of the document (`dc:publisher`).
+    /// - Acrobat Reader: Additional Metadata (Advanced)
+    /// - Apple Preview: (None)
+    #[serde(default)]
+    pub publisher: String,
+
+    /// Contributors to the document other than the author, e.g. editors or translators
+    /// (`dc:contributor`).
+    /// - Acrobat Reader: Additional Metadata (Advanced)
+    /// - Apple Preview: (None)
+    #[serde(default)]
+    pub contributors: Vec<String>,
+
+    /// Date of an event in the lifecycle of the document, e.g. the publication date (`dc:date`).
+    /// Unlike `creation_date`, nothing is set if [`None`].
+    /// - Acrobat Reader: Additional Metadata (Advanced)
+    /// - Apple Preview: (None)
+    #[serde(default)]
+    pub date: Option<DateTime<FixedOffset>>,
+
+    /// Unambiguous identifier of the document, e.g. a DOI or an ISBN (`dc:identifier`).
+    /// - Acrobat Reader: Additional Metadata (Advanced)
+    /// - Apple Preview: (None)
+    #[serde(default)]
+    pub identifier: String,
+
+    /// Nature or genre of the document, e.g. `Text` or `Thesis` (`dc:type`).
+    /// - Acrobat Reader: Additional Metadata (Advanced)
+    /// - Apple Preview: (None)
+    #[serde(default, rename = "type")]
+    pub document_type: String,
+
+    /// Whether the document has been modified to include trapping information, which print shops
+    /// ask for. Set to [`None`] to leave it unset.
+    /// - Acrobat Reader: Trapped (Advanced)
+    /// - Apple Preview: (None)
+    #[serde(default)]
+    pub trapped: Option<Trapped>,
+
+    /// Other entries of the document information dictionary with typed values, keyed by name. They
+    /// are set after all the above, so they can also override the standard entries.
+    #[serde(default)]
+    pub info_entries: BTreeMap<String, InfoValue>,
+
+    /// Keep the `xmpMM:History` entries of the file, and append an entry for this modification,
+    /// so that the provenance chain of the document remains intact.
+    #[serde(default)]
+    pub record_history: bool,
+
+    /// How to treat the document ID. The ID of the input file is kept by default.
+    #[serde(default)]
+    pub document_id: DocumentId,
 }
 
 impl Default for PdfMetadata {
     fn default() -> Self {
         Self {
-            title: "".to_string(),
+            title: LocalizedText::default(),
             author: "".to_string(),
             application: "typster".to_string(),
-            subject: "".to_string(),
+            subject: LocalizedText::default(),
             copyright_status: true,
             copyright_notice: "© 2023 Author. All rights reserved.".to_string(),
             keywords: vec![],
             language: "en".to_string(),
             custom_properties: HashMap::new(),
+            custom_namespaces: vec![],
+            creation_date: None,
+            modification_date: None,
+            preserve_dates: false,
+            publisher: "".to_string(),
+            contributors: vec![],
+            date: None,
+            identifier: "".to_string(),
+            document_type: "".to_string(),
+            trapped: None,
+            info_entries: BTreeMap::new(),
+            record_history: false,
+            document_id: DocumentId::Preserve,
+        }
+    }
+}
+
+/// A custom XMP namespace and its properties, for [`PdfMetadata::custom_namespaces`].
+///
+/// # Example
+///
+/// ```rust
+/// let records = typster::XmpNamespace {
+///     uri: "http://example.com/ns/records/1.0/".to_string(),
+///     prefix: "records".to_string(),
+///     properties: [("RetentionPeriod".to_string(), "10y".to_string())].into(),
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XmpNamespace {
+    /// URI of the namespace, e.g. `http://example.com/ns/records/1.0/`.
+    pub uri: String,
+
+    /// Preferred prefix of the namespace, e.g. `records`. Another one is used if the prefix is
+    /// already registered for a different URI.
+    pub prefix: String,
+
+    /// Simple properties to write, keyed by name.
+    pub properties: BTreeMap<String, String>,
+}
+
+/// Trapping status of a document, for [`PdfMetadata::trapped`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trapped {
+    /// The document has been fully trapped.
+    True,
+    /// The document has not been trapped.
+    False,
+    /// Either it is unknown whether the document has been trapped, or it has been partly trapped.
+    Unknown,
+}
+
+impl Trapped {
+    /// Name of the value in the document information dictionary and XMP metadata.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Trapped::True => "True",
+            Trapped::False => "False",
+            Trapped::Unknown => "Unknown",
+        }
+    }
+}
+
+impl FromStr for Trapped {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_lowercase().as_str() {
+            "true" => Ok(Trapped::True),
+            "false" => Ok(Trapped::False),
+            "unknown" => Ok(Trapped::Unknown),
+            _ => Err(format!("invalid trapped value: {value}")),
+        }
+    }
+}
+
+/// Typed value of an entry of the document information dictionary, for
+/// [`PdfMetadata::info_entries`].
+///
+/// # Example
+///
+/// ```rust
+/// let entries = std::collections::BTreeMap::from([
+///     ("Department".to_string(), typster::InfoValue::Text("Sales".to_string())),
+///     ("Revision".to_string(), typster::InfoValue::Integer(3)),
+/// ]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InfoValue {
+    /// A text string.
+    Text(String),
+    /// A name, e.g. `True` of `Trapped`.
+    Name(String),
+    /// A date, which is written as a date string, e.g. `D:20240102030405+09'00'`.
+    Date(DateTime<FixedOffset>),
+    /// An integer.
+    Integer(i64),
+    /// A real number.
+    Real(f32),
+    /// A boolean.
+    Boolean(bool),
+}
+
+impl From<&InfoValue> for Object {
+    fn from(value: &InfoValue) -> Object {
+        match value {
+            InfoValue::Text(text) => text_string(text),
+            InfoValue::Name(name) => Object::Name(name.as_bytes().to_vec()),
+            InfoValue::Date(date) => text_string(&to_pdf_date(Some(date))),
+            InfoValue::Integer(integer) => Object::Integer(*integer),
+            InfoValue::Real(real) => Object::Real(*real),
+            InfoValue::Boolean(boolean) => Object::Boolean(*boolean),
+        }
+    }
+}
+
+/// Text which is either a single string, or alternatives keyed by language tags (RFC 3066), e.g.
+/// `en` or `ja`, for [`PdfMetadata`].
+///
+/// In XMP, alternatives are written as language alternative entries, while the
+/// [default text](LocalizedText::default_text()) is written everywhere else, e.g. in the document
+/// information dictionary.
+///
+/// # Example
+///
+/// ```rust
+/// let title = typster::LocalizedText::Alternatives(
+///     [("en".to_string(), "Title".to_string()), ("ja".to_string(), "タイトル".to_string())].into(),
+/// );
+/// assert_eq!(title.default_text(), "Title");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LocalizedText {
+    /// A single text, which is written as `x-default`.
+    Default(String),
+    /// Texts keyed by language tags. `x-default` can be specified explicitly, otherwise the first
+    /// one is used as the default.
+    Alternatives(BTreeMap<String, String>),
+}
+
+impl LocalizedText {
+    /// Returns the text to use where only a single language is supported; the `x-default`
+    /// alternative if any, or the first one.
+    pub fn default_text(&self) -> &str {
+        match self {
+            LocalizedText::Default(text) => text,
+            LocalizedText::Alternatives(texts) => texts
+                .get("x-default")
+                .or_else(|| texts.values().next())
+                .map_or("", String::as_str),
         }
     }
+
+    /// Returns `true` if there is no text in any language.
+    pub fn is_empty(&self) -> bool {
+        match self {
+            LocalizedText::Default(text) => text.is_empty(),
+            LocalizedText::Alternatives(texts) => texts.values().all(String::is_empty),
+        }
+    }
+
+    /// Sets the text as a language alternative property of the XMP metadata.
+    fn set_xmp(&self, xmp: &mut XmpMeta, ns: &str, name: &str) -> Result<(), XmpError> {
+        xmp.set_localized_text(ns, name, None, "x-default", self.default_text())?;
+        if let LocalizedText::Alternatives(texts) = self {
+            for (lang, text) in texts.iter().filter(|(lang, _)| *lang != "x-default") {
+                xmp.set_localized_text(ns, name, None, lang, text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for LocalizedText {
+    fn default() -> Self {
+        LocalizedText::Default(String::new())
+    }
+}
+
+impl fmt::Display for LocalizedText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.default_text())
+    }
+}
+
+impl From<String> for LocalizedText {
+    fn from(text: String) -> Self {
+        LocalizedText::Default(text)
+    }
+}
+
+impl From<&str> for LocalizedText {
+    fn from(text: &str) -> Self {
+        LocalizedText::Default(text.to_string())
+    }
+}
+
+impl From<BTreeMap<String, String>> for LocalizedText {
+    fn from(texts: BTreeMap<String, String>) -> Self {
+        LocalizedText::Alternatives(texts)
+    }
 }
 
 /// Updates the metadata of a PDF file.
 ///
 /// Note that:
 ///
-/// - All metadata will be overwritten, not merged.
-/// - The creation is set automatically to the current date _without_ time information which means
-///   time is always 0:00 UTC, for some privacy reasons (or my preference.)
+/// - All metadata will be overwritten, not merged, except for the dates if
+///   [`PdfMetadata::preserve_dates`] is set.
+/// - Unless specified, the creation and modification dates are set automatically to the current
+///   date _without_ time information which means time is always 0:00 UTC, for some privacy reasons
+///   (or my preference.)
 ///
 /// # Arguments
 ///
@@ -96,17 +391,16 @@ impl Default for PdfMetadata {
 ///     .join("sample.pdf");
 ///
 /// // Compile a document first
-/// let params = typster::CompileParams {
-///     input: std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-///         .join("examples")
-///         .join("sample.typ"),
-///     output: output.clone(),
-///     font_paths: vec!["assets".into()],
-///     dict: vec![("input".to_string(), "value".to_string())],
-///     ppi: None,
-///     package_path: None,
-///     package_cache_path: None,
-/// };
+/// let params = typster::CompileParams::builder()
+///     .input(
+///         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///             .join("examples")
+///             .join("sample.typ"),
+///     )
+///     .output(output.clone())
+///     .font_path("assets")
+///     .dict("input", "value")
+///     .build();
 /// match typster::compile(&params) {
 ///     Ok(duration) => println!("Compilation succeeded in {duration:?}"),
 ///     Err(why) => eprintln!("{why}"),
@@ -118,64 +412,227 @@ impl Default for PdfMetadata {
 /// custom_properties.insert("custom".to_string(), "properties".to_string());
 ///
 /// let metadata = typster::PdfMetadata {
-///     title: "Title (typster)".to_string(),
+///     title: "Title (typster)".into(),
 ///     author: "Author (typster)".to_string(),
 ///     application: "Application (typster)".to_string(),
-///     subject: "Subject (typster)".to_string(),
+///     subject: "Subject (typster)".into(),
 ///     copyright_status: true,
 ///     copyright_notice: "Copyright notice (typster)".to_string(),
 ///     keywords: vec!["typster".to_string(), "rust".to_string(), "pdf".to_string()],
 ///     language: "en".to_string(),
 ///     custom_properties,
+///     ..Default::default()
 /// };
 ///
 /// typster::update_metadata(&output, &metadata).unwrap();
 /// ```
-pub fn update_metadata(
-    path: &Path,
-    metadata: &PdfMetadata,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut f = XmpFile::new()?;
-    f.open_file(path, OpenFileOptions::default().only_xmp().for_update())?;
+#[instrument(skip_all, fields(path = %path.display()))]
+pub fn update_metadata(path: &Path, metadata: &PdfMetadata) -> Result<(), TypsterError> {
+    let pdf = fs::read(path)?;
+    fs::write(path, update_metadata_bytes(&pdf, metadata)?)?;
+    Ok(())
+}
+
+/// Updates the metadata of a PDF document in memory, e.g. to post-process a compiled document in a
+/// web service without temporary files.
+///
+/// Works the same way as [`update_metadata()`].
+///
+/// # Arguments
+///
+/// - `pdf` - Contents of the PDF file.
+/// - `metadata` - [`PdfMetadata`] to set.
+///
+/// # Returns
+///
+/// Result containing the contents of the updated PDF file.
+///
+/// # Example
+///
+/// Following is an example of how to use the `update_metadata_bytes` function:
+///
+/// ```no_run
+/// let pdf = std::fs::read("sample.pdf").unwrap();
+/// let metadata = typster::PdfMetadata { title: "Title (typster)".into(), ..Default::default() };
+/// let updated = typster::update_metadata_bytes(&pdf, &metadata).unwrap();
+/// ```
+#[instrument(skip_all, fields(bytes = pdf.len()))]
+pub fn update_metadata_bytes(pdf: &[u8], metadata: &PdfMetadata) -> Result<Vec<u8>, TypsterError> {
+    let mut doc = Document::load_mem(pdf)?;
+    let existing_xmp = read_xmp(&doc);
 
     let mut xmp = XmpMeta::new()?;
 
-    xmp.set_localized_text(DC, "title", None, "x-default", &metadata.title)?;
+    metadata.title.set_xmp(&mut xmp, DC, "title")?;
     xmp.set_localized_text(XMP, "CreatorTool", None, "x-default", &metadata.application)?;
-    xmp.set_localized_text(DC, "description", None, "x-default", &metadata.subject)?;
+    metadata.subject.set_xmp(&mut xmp, DC, "description")?;
     xmp.set_property_bool(XMP_RIGHTS, "Marked", &XmpValue::from(metadata.copyright_status))?;
     xmp.set_localized_text(DC, "rights", None, "x-default", &metadata.copyright_notice)?;
-    let mut now = XmpDateTime::current()?;
-    now.time = None;
-    xmp.set_property_date(XMP, "CreateDate", &XmpValue::from(now.clone()))?;
-    if !f.can_put_xmp(&xmp) {
-        return Err("The file cannot be updated with a given set of XMP metadata for some reason. This depends on the size of the packet, the options with which the file was opened, and the capabilities of the handler for the file format.".into());
+    for keyword in &metadata.keywords {
+        xmp.append_array_item(
+            DC,
+            &XmpValue::from("subject").set_is_array(true),
+            &XmpValue::from(keyword.as_str()),
+        )?;
+    }
+    xmp.set_property(PDF, "Keywords", &XmpValue::from(metadata.keywords.join(", ")))?;
+    let bags = [
+        ("language", std::slice::from_ref(&metadata.language)),
+        ("publisher", std::slice::from_ref(&metadata.publisher)),
+        ("contributor", metadata.contributors.as_slice()),
+        ("type", std::slice::from_ref(&metadata.document_type)),
+    ];
+    for (name, values) in bags {
+        for value in values.iter().filter(|value| !value.is_empty()) {
+            xmp.append_array_item(
+                DC,
+                &XmpValue::from(name).set_is_array(true),
+                &XmpValue::from(value.as_str()),
+            )?;
+        }
+    }
+    if let Some(date) = &metadata.date {
+        xmp.append_array_item(
+            DC,
+            &XmpValue::from("date").set_is_ordered(true),
+            &XmpValue::from(date.to_rfc3339().as_str()),
+        )?;
+    }
+    if !metadata.identifier.is_empty() {
+        xmp.set_property(DC, "identifier", &XmpValue::from(metadata.identifier.as_str()))?;
+    }
+    if let Some(trapped) = metadata.trapped {
+        xmp.set_property(PDF, "Trapped", &XmpValue::from(trapped.name()))?;
+    }
+    for namespace in &metadata.custom_namespaces {
+        XmpMeta::register_namespace(&namespace.uri, &namespace.prefix)?;
+        for (name, value) in &namespace.properties {
+            xmp.set_property(&namespace.uri, name, &XmpValue::from(value.as_str()))?;
+        }
+    }
+    if metadata.preserve_dates {
+        for name in ["CreateDate", "ModifyDate"] {
+            if let Some(date) = existing_xmp.as_ref().and_then(|xmp| xmp.property_date(XMP, name)) {
+                xmp.set_property_date(XMP, name, &date)?;
+            }
+        }
+    } else {
+        let created = to_xmp_date(metadata.creation_date.as_ref())?;
+        xmp.set_property_date(XMP, "CreateDate", &XmpValue::from(created))?;
+        let modified = to_xmp_date(metadata.modification_date.as_ref())?;
+        xmp.set_property_date(XMP, "ModifyDate", &XmpValue::from(modified))?;
+    }
+    if metadata.record_history {
+        if let Some(existing) = &existing_xmp {
+            copy_history(existing, &mut xmp)?;
+        }
+        append_history(&mut xmp, "/metadata", "updated metadata")?;
+    }
+    write_xmp(&mut doc, &xmp)?;
+    if !metadata.language.is_empty() {
+        doc.catalog_mut()?.set("Lang", text_string(&metadata.language));
     }
 
-    f.put_xmp(&xmp)?;
-    f.close();
-
-    let mut doc = Document::load(path)?;
+    let existing = doc
+        .trailer
+        .get_deref(b"Info", &doc)
+        .and_then(Object::as_dict)
+        .cloned()
+        .unwrap_or_default();
     doc.trailer.remove(b"Info");
 
     let mut dict = Dictionary::new();
-    dict.set("Title", text_string(&metadata.title));
-    dict.set("Subject", text_string(&metadata.subject));
+    dict.set("Title", text_string(metadata.title.default_text()));
+    dict.set("Subject", text_string(metadata.subject.default_text()));
     dict.set("Author", text_string(&metadata.author));
     dict.set("Producer", text_string(&metadata.application));
     dict.set("Creator", text_string(&metadata.application));
-    let now = chrono::Local::now().format("%Y%m%d").to_string();
-    dict.set("CreationDate", text_string(&now));
-    dict.set("ModDate", text_string(&now));
+    if metadata.preserve_dates {
+        for key in ["CreationDate", "ModDate"] {
+            if let Ok(date) = existing.get(key.as_bytes()) {
+                dict.set(key, date.clone());
+            }
+        }
+    } else {
+        dict.set("CreationDate", text_string(&to_pdf_date(metadata.creation_date.as_ref())));
+        dict.set("ModDate", text_string(&to_pdf_date(metadata.modification_date.as_ref())));
+    }
     dict.set("Keywords", text_string(&metadata.keywords.join(", ")));
     metadata
         .custom_properties
         .iter()
         .for_each(|(k, v)| dict.set(k.to_string(), text_string(v)));
+    if let Some(trapped) = metadata.trapped {
+        dict.set("Trapped", Object::Name(trapped.name().as_bytes().to_vec()));
+    }
+    for (key, value) in &metadata.info_entries {
+        dict.set(key.as_str(), Object::from(value));
+    }
     let t = doc.add_object(Object::Dictionary(dict));
 
     doc.trailer.set("Info", t);
-    doc.save(path)?;
+    metadata.document_id.apply(&mut doc);
 
-    Ok(())
+    let mut buf = Vec::new();
+    doc.save_to(&mut buf)?;
+    Ok(buf)
+}
+
+/// Converts a date to an XMP date, or today without time information if [`None`].
+pub(crate) fn to_xmp_date(date: Option<&DateTime<FixedOffset>>) -> Result<XmpDateTime, XmpError> {
+    let Some(date) = date else {
+        let mut now = XmpDateTime::current()?;
+        now.time = None;
+        return Ok(now);
+    };
+
+    let offset = date.offset().local_minus_utc();
+    Ok(XmpDateTime {
+        date: Some(XmpDate {
+            year: date.year(),
+            month: date.month() as i32,
+            day: date.day() as i32,
+        }),
+        time: Some(XmpTime {
+            hour: date.hour() as i32,
+            minute: date.minute() as i32,
+            second: date.second() as i32,
+            nanosecond: date.nanosecond() as i32,
+            time_zone: Some(XmpTimeZone {
+                hour: offset / 3600,
+                minute: (offset % 3600).abs() / 60,
+            }),
+        }),
+    })
+}
+
+/// Converts an XMP date back to a date. A date without time information is at 0:00 UTC.
+pub(crate) fn from_xmp_date(date_time: &XmpDateTime) -> Option<DateTime<FixedOffset>> {
+    let XmpDate { year, month, day } = date_time.date.as_ref()?;
+    let date = NaiveDate::from_ymd_opt(*year, (*month).try_into().ok()?, (*day).try_into().ok()?)?;
+    let Some(time) = &date_time.time else {
+        return Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?).fixed_offset());
+    };
+    let offset = time.time_zone.as_ref().map_or(0, |tz| {
+        let minute = if tz.hour < 0 { -tz.minute } else { tz.minute };
+        tz.hour * 3600 + minute * 60
+    });
+    let naive = date.and_hms_nano_opt(
+        time.hour.try_into().ok()?,
+        time.minute.try_into().ok()?,
+        time.second.try_into().ok()?,
+        time.nanosecond.try_into().ok()?,
+    )?;
+    FixedOffset::east_opt(offset)?.from_local_datetime(&naive).single()
+}
+
+/// Converts a date to a PDF date string, or today without time information if [`None`].
+fn to_pdf_date(date: Option<&DateTime<FixedOffset>>) -> String {
+    match date {
+        // e.g. D:20240102030405+09'00'
+        Some(date) => {
+            format!("D:{}'", date.format("%Y%m%d%H%M%S%:z").to_string().replace(':', "'"))
+        }
+        None => chrono::Local::now().format("%Y%m%d").to_string(),
+    }
 }
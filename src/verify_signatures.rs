@@ -0,0 +1,215 @@
+use std::{error::Error, fs, path::Path};
+
+use lopdf::{decode_text_string, Dictionary, Document, Object};
+use openssl::{
+    nid::Nid,
+    pkcs7::{Pkcs7, Pkcs7Flags},
+    stack::Stack,
+    x509::{
+        store::{X509StoreBuilder, X509StoreRef},
+        X509,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// Result of verifying a signature with [`verify_signatures()`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SignatureReport {
+    /// Name of the signature field.
+    pub field_name: String,
+
+    /// Common name of the signing certificate's subject. Empty if unknown.
+    pub signer: String,
+
+    /// Signing time as written in the signature dictionary, e.g. `D:20240102030405+09'00'`. Empty
+    /// if not specified.
+    pub signed_at: String,
+
+    /// Reason for signing. Empty if not specified.
+    pub reason: String,
+
+    /// Whether the signed byte range covers the whole file except the signature itself, i.e. the
+    /// file was not modified after signing.
+    pub covers_whole_document: bool,
+
+    /// Whether the signature matches the signed bytes, i.e. they were not tampered with.
+    pub integrity: bool,
+
+    /// Whether the signing certificate chains up to a trusted certificate.
+    pub trusted: bool,
+
+    /// Error message if the signature could not be verified at all, e.g. unsupported format.
+    pub error: Option<String>,
+}
+
+impl SignatureReport {
+    /// Whether the signature is valid, i.e. intact, covering the whole document, and trusted.
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none() && self.covers_whole_document && self.integrity && self.trusted
+    }
+}
+
+/// Verifies the signatures of a PDF file, e.g. to validate incoming signed documents.
+///
+/// Only detached CMS (PKCS#7) signatures, i.e. `adbe.pkcs7.detached` and `ETSI.CAdES.detached`,
+/// are supported. Other signatures are reported with an error.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file.
+/// - `trusted_certificates` - PEM-encoded certificates to trust in addition to the system's
+///   default trust store, e.g. a company's root certificate.
+///
+/// # Returns
+///
+/// Result containing a [`SignatureReport`] per signature field. The list is empty if the file is
+/// not signed.
+///
+/// # Example
+///
+/// Following is an example of how to use the `verify_signatures` function:
+///
+/// ```no_run
+/// let reports =
+///     typster::verify_signatures(std::path::Path::new("contract-signed.pdf"), &[]).unwrap();
+/// for report in reports {
+///     println!("{}: {}", report.field_name, report.is_valid());
+/// }
+/// ```
+pub fn verify_signatures(
+    path: &Path,
+    trusted_certificates: &[Vec<u8>],
+) -> Result<Vec<SignatureReport>, Box<dyn Error>> {
+    let pdf = fs::read(path)?;
+    let doc = Document::load_mem(&pdf)?;
+
+    let mut store = X509StoreBuilder::new()?;
+    store.set_default_paths()?;
+    for pem in trusted_certificates {
+        for cert in X509::stack_from_pem(pem)? {
+            store.add_cert(cert)?;
+        }
+    }
+    let store = store.build();
+
+    let mut fields = vec![];
+    if let Ok(Object::Array(roots)) = doc
+        .catalog()?
+        .get_deref(b"AcroForm", &doc)
+        .and_then(Object::as_dict)
+        .and_then(|form| form.get_deref(b"Fields", &doc))
+    {
+        collect_signature_fields(&doc, roots, false, &mut fields, 0);
+    }
+
+    Ok(fields
+        .into_iter()
+        .map(|(field, signature)| {
+            let text = |dict: &Dictionary, key: &[u8]| {
+                dict.get_deref(key, &doc)
+                    .ok()
+                    .and_then(|value| decode_text_string(value).ok())
+                    .unwrap_or_default()
+            };
+            let mut report = SignatureReport {
+                field_name: text(field, b"T"),
+                signed_at: text(signature, b"M"),
+                reason: text(signature, b"Reason"),
+                ..Default::default()
+            };
+            if let Err(e) = verify(&pdf, &doc, signature, &store, &mut report) {
+                report.error = Some(e.to_string());
+            }
+            report
+        })
+        .collect())
+}
+
+/// Collects signed signature fields and their signature dictionaries, descending into `Kids`.
+fn collect_signature_fields<'a>(
+    doc: &'a Document,
+    fields: &'a [Object],
+    inherited_signature: bool,
+    found: &mut Vec<(&'a Dictionary, &'a Dictionary)>,
+    depth: usize,
+) {
+    // Guard against cycles of malformed files.
+    if depth > 32 {
+        return;
+    }
+    for field in fields {
+        let Ok(field) = doc.dereference(field).and_then(|(_, field)| field.as_dict()) else {
+            continue;
+        };
+        let is_signature = match field.get(b"FT").and_then(Object::as_name) {
+            Ok(name) => name == b"Sig",
+            Err(_) => inherited_signature,
+        };
+        if let Ok(kids) = field.get_deref(b"Kids", doc).and_then(Object::as_array) {
+            collect_signature_fields(doc, kids, is_signature, found, depth + 1);
+        }
+        if !is_signature {
+            continue;
+        }
+        if let Ok(signature) = field.get_deref(b"V", doc).and_then(Object::as_dict) {
+            found.push((field, signature));
+        }
+    }
+}
+
+/// Verifies a signature against the file contents, filling in the report.
+fn verify(
+    pdf: &[u8],
+    doc: &Document,
+    signature: &Dictionary,
+    store: &X509StoreRef,
+    report: &mut SignatureReport,
+) -> Result<(), Box<dyn Error>> {
+    let sub_filter = signature.get(b"SubFilter").and_then(Object::as_name)?;
+    if sub_filter != b"adbe.pkcs7.detached" && sub_filter != b"ETSI.CAdES.detached" {
+        return Err(
+            format!("unsupported SubFilter: {}", String::from_utf8_lossy(sub_filter)).into()
+        );
+    }
+
+    let byte_range = signature
+        .get_deref(b"ByteRange", doc)
+        .and_then(Object::as_array)?
+        .iter()
+        .map(|value| value.as_i64().ok().and_then(|value| usize::try_from(value).ok()))
+        .collect::<Option<Vec<_>>>()
+        .filter(|byte_range| byte_range.len() == 4)
+        .ok_or("invalid ByteRange")?;
+    let (start, first, second, rest) = (byte_range[0], byte_range[1], byte_range[2], byte_range[3]);
+    let signed = pdf
+        .get(start..start + first)
+        .zip(pdf.get(second..second + rest))
+        .map(|(first, second)| [first, second].concat())
+        .ok_or("ByteRange is out of the file")?;
+    // The gap must be exactly the hexadecimal string of the signature.
+    report.covers_whole_document = start == 0
+        && second + rest == pdf.len()
+        && pdf.get(start + first) == Some(&b'<')
+        && second.checked_sub(1).and_then(|i| pdf.get(i)) == Some(&b'>');
+
+    let contents = signature.get_deref(b"Contents", doc).and_then(Object::as_str)?;
+    let pkcs7 = Pkcs7::from_der(contents)?;
+    let certs = Stack::new()?;
+    if let Some(signer) = pkcs7.signers(&certs, Pkcs7Flags::empty())?.iter().next() {
+        report.signer = signer
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .map(|entry| String::from_utf8_lossy(entry.data().as_slice()).to_string())
+            .unwrap_or_default();
+    }
+
+    let flags = Pkcs7Flags::DETACHED | Pkcs7Flags::BINARY;
+    report.integrity = pkcs7
+        .verify(&certs, store, Some(&signed), None, flags | Pkcs7Flags::NOVERIFY)
+        .is_ok();
+    report.trusted =
+        report.integrity && pkcs7.verify(&certs, store, Some(&signed), None, flags).is_ok();
+
+    Ok(())
+}
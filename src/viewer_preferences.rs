@@ -0,0 +1,181 @@
+use std::path::Path;
+
+use lopdf::{Dictionary, Document, Object};
+
+use crate::FittingType;
+
+/// Initial view of a PDF file.
+///
+/// See also [`set_viewer_preferences()`].
+#[derive(Debug, Clone, Default)]
+pub struct ViewerPreferences {
+    /// 1-based page number to open the document at. Set to [`None`] to open at the first page.
+    pub open_page: Option<u32>,
+
+    /// How to fit the page in the window when the document is opened. Set to [`None`] to leave it
+    /// to the viewer.
+    pub fitting_type: Option<FittingType>,
+
+    /// Page layout when the document is opened. Set to [`None`] to leave it to the viewer.
+    pub page_layout: Option<PageLayout>,
+
+    /// Hide the toolbars of the viewer.
+    pub hide_toolbar: bool,
+
+    /// Hide the menu bar of the viewer.
+    pub hide_menubar: bool,
+
+    /// Hide user interface elements in the document's window, e.g. scroll bars, leaving only the
+    /// contents displayed.
+    pub hide_window_ui: bool,
+
+    /// Resize the document's window to fit the size of the first displayed page.
+    pub fit_window: bool,
+
+    /// Position the document's window in the center of the screen.
+    pub center_window: bool,
+
+    /// Display the document title, instead of the file name, in the title bar of the window.
+    pub display_doc_title: bool,
+}
+
+/// Page layout for [`ViewerPreferences`].
+#[derive(Debug, Clone)]
+pub enum PageLayout {
+    /// Display one page at a time.
+    SinglePage,
+    /// Display the pages in one column.
+    OneColumn,
+    /// Display the pages in two columns, with odd-numbered pages on the left.
+    TwoColumnLeft,
+    /// Display the pages in two columns, with odd-numbered pages on the right.
+    TwoColumnRight,
+    /// Display the pages two at a time, with odd-numbered pages on the left.
+    TwoPageLeft,
+    /// Display the pages two at a time, with odd-numbered pages on the right.
+    TwoPageRight,
+}
+
+impl PageLayout {
+    fn name(&self) -> &'static [u8] {
+        match self {
+            PageLayout::SinglePage => b"SinglePage",
+            PageLayout::OneColumn => b"OneColumn",
+            PageLayout::TwoColumnLeft => b"TwoColumnLeft",
+            PageLayout::TwoColumnRight => b"TwoColumnRight",
+            PageLayout::TwoPageLeft => b"TwoPageLeft",
+            PageLayout::TwoPageRight => b"TwoPageRight",
+        }
+    }
+}
+
+impl From<&str> for PageLayout {
+    fn from(value: &str) -> Self {
+        match value {
+            "one_column" => PageLayout::OneColumn,
+            "two_column_left" => PageLayout::TwoColumnLeft,
+            "two_column_right" => PageLayout::TwoColumnRight,
+            "two_page_left" => PageLayout::TwoPageLeft,
+            "two_page_right" => PageLayout::TwoPageRight,
+            _ => PageLayout::SinglePage,
+        }
+    }
+}
+
+/// Sets the initial view of a PDF file, i.e. the open action, the page layout, and the viewer
+/// preferences of the document catalog.
+///
+/// All of them will be overwritten, not merged, except other viewer preferences than the ones in
+/// [`ViewerPreferences`], e.g. the reading direction, which are kept.
+///
+/// # Arguments
+///
+/// - `path` - Path to the PDF file, which will be updated in place.
+/// - `preferences` - [`ViewerPreferences`] to set.
+///
+/// # Example
+///
+/// Following is an example of how to use the `set_viewer_preferences` function:
+///
+/// ```no_run
+/// typster::set_viewer_preferences(
+///     std::path::Path::new("sample.pdf"),
+///     &typster::ViewerPreferences {
+///         open_page: Some(2),
+///         fitting_type: Some(typster::FittingType::Width),
+///         page_layout: Some(typster::PageLayout::TwoPageRight),
+///         hide_toolbar: true,
+///         ..Default::default()
+///     },
+/// )
+/// .unwrap();
+/// ```
+pub fn set_viewer_preferences(
+    path: &Path,
+    preferences: &ViewerPreferences,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut doc = Document::load(path)?;
+
+    let open_action = if preferences.open_page.is_some() || preferences.fitting_type.is_some() {
+        let number = preferences.open_page.unwrap_or(1);
+        let page = *doc
+            .get_pages()
+            .get(&number)
+            .ok_or_else(|| format!("page {number} does not exist"))?;
+        let fit = preferences.fitting_type.as_ref().unwrap_or(&FittingType::Page);
+
+        let mut dest = vec![Object::Reference(page), Object::Name(fit.destination().to_vec())];
+        // `FitH` and `FitV` take the coordinate to scroll to, which is kept as is by `null`.
+        if !matches!(fit, FittingType::Page) {
+            dest.push(Object::Null);
+        }
+        Some(dest)
+    } else {
+        None
+    };
+
+    let mut viewer_preferences = doc
+        .catalog()?
+        .get_deref(b"ViewerPreferences", &doc)
+        .and_then(Object::as_dict)
+        .cloned()
+        .unwrap_or_else(|_| Dictionary::new());
+    for (key, value) in [
+        ("HideToolbar", preferences.hide_toolbar),
+        ("HideMenubar", preferences.hide_menubar),
+        ("HideWindowUI", preferences.hide_window_ui),
+        ("FitWindow", preferences.fit_window),
+        ("CenterWindow", preferences.center_window),
+        ("DisplayDocTitle", preferences.display_doc_title),
+    ] {
+        if value {
+            viewer_preferences.set(key, true);
+        } else {
+            viewer_preferences.remove(key.as_bytes());
+        }
+    }
+
+    let catalog = doc.catalog_mut()?;
+    match open_action {
+        Some(dest) => catalog.set("OpenAction", dest),
+        None => {
+            catalog.remove(b"OpenAction");
+        }
+    }
+    match &preferences.page_layout {
+        Some(layout) => catalog.set("PageLayout", Object::Name(layout.name().to_vec())),
+        None => {
+            catalog.remove(b"PageLayout");
+        }
+    }
+    if viewer_preferences.is_empty() {
+        catalog.remove(b"ViewerPreferences");
+    } else {
+        catalog.set("ViewerPreferences", viewer_preferences);
+    }
+
+    doc.prune_objects();
+    doc.save(path)?;
+
+    Ok(())
+}
@@ -1,58 +1,308 @@
 use std::{
-    error::Error, fmt::Display, fs::remove_file, future::IntoFuture, net::SocketAddr,
-    path::PathBuf, sync::Arc,
+    collections::HashSet,
+    error::Error,
+    fs::{canonicalize, create_dir_all, remove_file, write},
+    future::IntoFuture,
+    io, mem,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
 
 use axum::{
     body::Body,
     extract::{
         ws::{Message, WebSocket},
-        State, WebSocketUpgrade,
+        Query, State, WebSocketUpgrade,
+    },
+    http::{
+        header::{ACCEPT, CONTENT_TYPE, VARY},
+        HeaderMap, StatusCode,
     },
     response::{Html, IntoResponse, Response},
     routing::get,
     Router,
 };
-use log::{error, info};
 use notify::{
-    event::{DataChange, ModifyKind::Data},
+    event::{
+        CreateKind, DataChange,
+        ModifyKind::{self, Data, Name},
+        RemoveKind, RenameMode,
+    },
     Event,
-    EventKind::Modify,
-    RecursiveMode, Watcher,
+    EventKind::{Create, Modify, Remove},
+    RecommendedWatcher, RecursiveMode, Watcher,
 };
-use tokio::{fs, net::TcpListener, select, sync::Notify};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs,
+    net::TcpListener,
+    select,
+    sync::{
+        broadcast::{self, error::RecvError},
+        mpsc, Notify,
+    },
+    task::{self, JoinHandle},
+    time,
+};
+use tracing::{error, info};
+use typst::model::Document;
 
-use crate::CompileParams;
+use crate::{
+    compile::CompileStage, error::Diagnostic, world::SystemWorld, CompileParams, CompilerSession,
+    FittingType, TypsterError,
+};
 
 pub struct SharedState {
     pub port: u16,
     pub address: String,
     pub input: PathBuf,
     pub output: PathBuf,
-    pub changed: Notify,
+    pub events: broadcast::Sender<String>,
     pub shutdown: Notify,
+    pub paused: AtomicBool,
     pub fitting_type: FittingType,
+    /// Document of the last successful compilation, rendered to PNG or SVG on request.
+    pub document: Mutex<Option<Document>>,
+    /// Pixels per inch of the PNG pages.
+    pub ppi: f32,
+}
+
+impl SharedState {
+    /// Sends an event to all connected clients, if any.
+    fn publish(&self, event: &WatchEvent) {
+        match serde_json::to_string(event) {
+            // Fails only if no client is connected.
+            Ok(event) => _ = self.events.send(event),
+            Err(why) => error!("failed to serialize event: {why}"),
+        }
+    }
+}
+
+/// An event sent to the clients of the `/listen` WebSocket, as a JSON object tagged by `type`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum WatchEvent {
+    /// The document compiled, and the output file was written.
+    Compiled {
+        /// Duration of the compilation, in milliseconds.
+        duration: u64,
+        /// Number of pages of the document.
+        pages: usize,
+    },
+    /// The document failed to compile, and the output file is left as is.
+    Error {
+        /// Warnings and errors of the compilation.
+        diagnostics: Vec<Diagnostic>,
+    },
+    /// The document is being compiled, sent when a stage starts and periodically in between.
+    Progress {
+        /// Stage of the compilation.
+        stage: CompileStage,
+        /// Time since the compilation started, in milliseconds.
+        elapsed: u64,
+    },
+    /// Watched files changed, and the document is being recompiled.
+    FileChanged {
+        /// Paths of the changed files, relative to the watched directory.
+        paths: Vec<PathBuf>,
+    },
 }
 
-// list of supported extensions
-const EXTENSIONS: [&str; 16] = [
-    "cbor", "csv", "gif", "htm", "html", "jpeg", "jpg", "json", "png", "svg", "toml", "txt", "typ",
-    "xml", "yaml", "yml",
-];
+impl WatchEvent {
+    /// Returns the event of the result of a compilation of the session.
+    fn from_result(
+        session: &CompilerSession,
+        result: Result<(Duration, usize), TypsterError>,
+    ) -> Self {
+        match result {
+            Ok((duration, pages)) => {
+                info!("compilation succeeded in {duration:?}");
+                Self::Compiled { duration: duration.as_millis() as u64, pages }
+            }
+            Err(why) => {
+                error!("{why}");
+                let diagnostics = match &why {
+                    TypsterError::Diagnostics(diagnostics) => diagnostics
+                        .iter()
+                        .map(|diagnostic| Diagnostic::new(session.world(), diagnostic))
+                        .collect(),
+                    why => vec![Diagnostic::error(why.to_string())],
+                };
+                Self::Error { diagnostics }
+            }
+        }
+    }
+}
+
+/// Interval of the progress events of a compilation.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Path of the file [`WatchHandle::persist_url()`] writes the URL to, relative to the directory
+/// of the input file.
+const URL_FILE: &str = ".typster/preview-url";
+
+/// Files the document depends on, updated after each compilation.
+#[derive(Default)]
+struct Dependencies {
+    /// Files read by the last compilation, except packages.
+    files: HashSet<PathBuf>,
+    /// Directories of the files, watched non-recursively rather than the files themselves, so
+    /// that files replaced on save, e.g. renamed over by editors, are still watched.
+    dirs: HashSet<PathBuf>,
+    /// Directories of the packages used by the last compilation, watched recursively.
+    packages: HashSet<PathBuf>,
+    /// Font directories of the parameters, watched recursively.
+    fonts: Vec<PathBuf>,
+}
+
+impl Dependencies {
+    /// Returns whether a change of the file affects the document.
+    fn contains(&self, path: &Path) -> bool {
+        self.files.contains(path)
+            || self.packages.iter().any(|dir| path.starts_with(dir))
+            || self.contains_font(path)
+    }
+
+    /// Returns whether the file is a font in one of the font directories.
+    fn contains_font(&self, path: &Path) -> bool {
+        is_font(path) && self.fonts.iter().any(|dir| path.starts_with(dir))
+    }
+
+    /// Returns whether the directory is watched recursively, along with its subdirectories.
+    fn covers(&self, dir: &Path) -> bool {
+        self.fonts.iter().any(|fonts| dir.starts_with(fonts))
+    }
+}
+
+/// Returns the paths of an event which affect the document, i.e. of the files it depends on which
+/// were written, or replaced by a rename, e.g. by editors which save to a temporary file and
+/// rename it over the original, and of the fonts which were removed or renamed away.
+fn changed_paths(event: &Event, dependencies: &Dependencies) -> Vec<PathBuf> {
+    match event.kind {
+        // Files are created when fonts are added.
+        Modify(
+            Data(DataChange::Content)
+            | Name(RenameMode::To | RenameMode::Both | RenameMode::Any)
+            | ModifyKind::Any,
+        )
+        | Create(CreateKind::File) => event
+            .paths
+            .iter()
+            .filter(|p| dependencies.contains(p))
+            .cloned()
+            .collect(),
+        // Other files are only removed while being replaced, and are then written again.
+        Remove(RemoveKind::File | RemoveKind::Any) | Modify(Name(RenameMode::From)) => event
+            .paths
+            .iter()
+            .filter(|p| dependencies.contains_font(p))
+            .cloned()
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Returns whether the file is a font, which Typst loads from font directories.
+fn is_font(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| matches!(ext.as_str(), "otc" | "otf" | "ttc" | "ttf"))
+}
+
+/// Watches the files read by the last compilation of the world, and stops watching the
+/// directories which no longer contain any of them.
+fn update_watches(
+    watcher: &Mutex<Option<RecommendedWatcher>>,
+    dependencies: &Mutex<Dependencies>,
+    world: &SystemWorld,
+) {
+    let (files, packages) = world.dependency_paths();
+    let files = files.into_iter().collect::<HashSet<_>>();
+    let packages = packages.into_iter().collect::<HashSet<_>>();
+    // The event handler of the watcher locks the dependencies, and the watcher may wait for the
+    // handler while watching, so they are not locked at the same time.
+    let (dirs, old_dirs, old_packages) = {
+        let mut dependencies = dependencies.lock();
+        // Directories within font directories are already watched.
+        let dirs = files
+            .iter()
+            .filter_map(|file| file.parent())
+            .filter(|dir| !dependencies.covers(dir))
+            .map(Path::to_path_buf)
+            .collect::<HashSet<_>>();
+        dependencies.files = files;
+        (
+            dirs.clone(),
+            mem::replace(&mut dependencies.dirs, dirs),
+            mem::replace(&mut dependencies.packages, packages.clone()),
+        )
+    };
+    let mut watcher = watcher.lock();
+    let Some(watcher) = watcher.as_mut() else {
+        return;
+    };
+    for (new, old, mode) in [
+        (&dirs, &old_dirs, RecursiveMode::NonRecursive),
+        (&packages, &old_packages, RecursiveMode::Recursive),
+    ] {
+        for path in old.difference(new) {
+            // Fails only if the directory has been removed, which stops watching it anyway.
+            let _ = watcher.unwatch(path);
+        }
+        for path in new.difference(old) {
+            if let Err(why) = watcher.watch(path, mode) {
+                error!("failed to watch {}: {why}", path.display());
+            }
+        }
+    }
+}
 
 /// Starts a web server that serves the output PDF file, while watching for changes in the input
 /// Typst file and recompiles when a change is detected.
 ///
-///Changes for `typ` file, along with files with extension `cbor`, `csv`, `gif`, `htm`, `html`,
-/// `jpeg`, `jpg`, `json`, `png`, `svg`, `toml`, `txt`, `xml`, `yaml`, and `yml` in the same
-/// directory, recursively, will be watched. This is inspired by [ItsEthra/typst-live](https://github.com/ItsEthra/typst-live/).
+/// Only the files the document depends on, i.e. the ones read by the last compilation, along with
+/// the directories of the packages it uses, are watched, so that changes of unrelated files in the
+/// same directory, e.g. of a large monorepo, don't trigger a compilation. The set is updated after
+/// each compilation. Font files in [`CompileParams::font_paths`] are watched as well, and the
+/// fonts are searched again when one is added, changed, or removed. This is inspired by [ItsEthra/typst-live](https://github.com/ItsEthra/typst-live/).
+///
+/// The page served at the root embeds the output PDF file, and reloads it on each successful
+/// compilation. The output is served at `/output`, as the PDF file, or as a page of the document
+/// rendered to PNG or SVG, e.g. for frontends or mobile browsers which don't render PDF files
+/// well. The format is the one of the `format` query parameter, `pdf`, `png`, or `svg`, or else
+/// the one preferred by the `Accept` header, and the page is the one of the 1-based `page` query
+/// parameter, e.g. `/output?format=svg&page=2`, or else the first one. Custom preview frontends
+/// can listen to the same events on the `/listen` WebSocket, which sends one JSON object per text
+/// message:
+///
+/// - `{"type": "file-changed", "paths": [...]}` - Watched files changed, with their paths relative
+///   to the watched directory, and the document is being recompiled.
+/// - `{"type": "progress", "stage": ..., "elapsed": ...}` - The document is being compiled, at
+///   the `parsing` stage of the main file, the `layout` stage, i.e. evaluation and layout, or the
+///   `export` stage, and `elapsed` milliseconds have passed. Sent when a stage starts, and every
+///   500 milliseconds.
+/// - `{"type": "compiled", "duration": ..., "pages": ...}` - The document compiled in `duration`
+///   milliseconds, and the output PDF file was written.
+/// - `{"type": "error", "diagnostics": [...]}` - The document failed to compile, with its warnings
+///   and errors, each with `severity`, `message`, `hints`, and the `location` of
+///   [`SourceLocation`](crate::SourceLocation), if any. The output PDF file is left as is.
 ///
 /// # Arguments
 ///
 /// - `params` - [`CompileParams`] struct.
 /// - `open` - Whether to open the output PDF file with the default browser once after the server
 ///   launches.
-/// - `app` - Open the output PDF file with the given application
+/// - `app` - Open the output PDF file with the given application, e.g. `Google Chrome.app`, or
+///   with a command template, if it contains `{url}`, e.g. `firefox --new-window {url}`. The
+///   template is split into arguments like a shell does, and `{url}` is replaced with the URL of
+///   the preview.
 ///
 /// # Example
 ///
@@ -60,19 +310,20 @@ const EXTENSIONS: [&str; 16] = [
 ///
 ///```no_run
 /// let rt = tokio::runtime::Runtime::new().unwrap();
-/// let params = typster::CompileParams {
-///     input: std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-///         .join("examples")
-///         .join("sample.typ"),
-///     output: std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-///         .join("examples")
-///         .join("sample.pdf"),
-///     font_paths: vec!["assets".into()],
-///     dict: vec![("input".to_string(), "value".to_string())],
-///     ppi: None,
-///     package_path: None,
-///     package_cache_path: None,
-/// };
+/// let params = typster::CompileParams::builder()
+///     .input(
+///         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///             .join("examples")
+///             .join("sample.typ"),
+///     )
+///     .output(
+///         std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+///             .join("examples")
+///             .join("sample.pdf"),
+///     )
+///     .font_path("assets")
+///     .dict("input", "value")
+///     .build();
 ///
 /// rt.block_on(async {
 ///     if let Err(error) = typster::watch(&params, true, None, Some(typster::FittingType::Width)).await {
@@ -80,145 +331,397 @@ const EXTENSIONS: [&str; 16] = [
 ///     }
 /// });
 /// ```
+pub async fn watch(
+    params: &CompileParams,
+    open: bool,
+    app: Option<&str>,
+    fitting_type: Option<FittingType>,
+) -> Result<(), Box<dyn Error>> {
+    let handle = spawn_watch(params, open, app, fitting_type).await?;
 
-/// Fitting type for the PDF output (Google Chrome only, maybe)
-#[derive(Debug, Clone, Default)]
-pub enum FittingType {
-    /// Fit to page
-    Page,
-    /// Fit to width
-    #[default]
-    Width,
-    /// Fit to height
-    Height,
+    let state = Arc::clone(&handle.state);
+    tokio::spawn(async move {
+        info!("Press Ctrl+C to exit");
+        async {
+            tokio::signal::ctrl_c()
+                .await
+                .expect("Failed to register handler for Ctrl+C");
+        }
+        .await;
+        state.shutdown.notify_one();
+    });
+
+    handle.wait().await
 }
 
-impl From<&str> for FittingType {
-    fn from(value: &str) -> Self {
-        match value {
-            "page" => FittingType::Page,
-            "width" => FittingType::Width,
-            "height" => FittingType::Height,
-            _ => FittingType::Page,
+/// A preview server started by [`spawn_watch()`], running in the background until
+/// [`WatchHandle::shutdown()`] is called.
+pub struct WatchHandle {
+    /// URL of the preview, e.g. `http://127.0.0.1:49152`.
+    url: String,
+    /// State shared with the server.
+    state: Arc<SharedState>,
+    /// Task of the server, which stops on shutdown.
+    server: JoinHandle<Result<(), Box<dyn Error + Send + Sync>>>,
+    /// File the URL was written to, removed once the server stops.
+    url_file: Option<PathBuf>,
+    /// Queue of the changed files, to trigger a compilation on resume.
+    changes: mpsc::UnboundedSender<Vec<PathBuf>>,
+}
+
+impl WatchHandle {
+    /// Returns the URL of the preview, e.g. `http://127.0.0.1:49152`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Writes the URL of the preview to `.typster/preview-url` in the directory of the input
+    /// file, so that editor tooling can find and open it. The file is removed once the server
+    /// stops, when awaited with [`WatchHandle::wait()`].
+    ///
+    /// # Returns
+    ///
+    /// Result containing the path of the file.
+    pub fn persist_url(&mut self) -> io::Result<PathBuf> {
+        let path = self.state.input.parent().unwrap_or(&self.state.input).join(URL_FILE);
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
         }
+        write(&path, &self.url)?;
+        self.url_file = Some(path.clone());
+        Ok(path)
     }
-}
 
-impl Display for FittingType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // https://chromium.googlesource.com/chromium/src/+/6363f8da6aae63abedc87f60b629585f10bd8940/chrome/browser/resources/pdf/open_pdf_params_parser.js#61
-        match self {
-            FittingType::Page => write!(f, "fit"),
-            FittingType::Width => write!(f, "fith"),
-            FittingType::Height => write!(f, "fitv"),
+    /// Suspends recompilation, e.g. while many files are rewritten by a script. Changes are still
+    /// collected, and compiled at once on [`WatchHandle::resume()`]. A compilation in progress is
+    /// not interrupted.
+    pub fn pause(&self) {
+        self.state.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// Resumes recompilation, and recompiles the document once if files changed while paused.
+    pub fn resume(&self) {
+        self.state.paused.store(false, Ordering::SeqCst);
+        // An empty change wakes the compilation task up. Fails only if it has stopped, which has
+        // been logged.
+        let _ = self.changes.send(vec![]);
+    }
+
+    /// Returns whether recompilation is paused.
+    pub fn is_paused(&self) -> bool {
+        self.state.paused.load(Ordering::SeqCst)
+    }
+
+    /// Stops watching, and shuts the server down. The output file is removed.
+    pub fn shutdown(&self) {
+        self.state.shutdown.notify_one();
+    }
+
+    /// Waits until the server stops, i.e. until [`WatchHandle::shutdown()`] is called.
+    pub async fn wait(self) -> Result<(), Box<dyn Error>> {
+        let result = self.server.await;
+        if let Some(path) = &self.url_file {
+            // Fails only if the file has been removed in the meantime.
+            let _ = remove_file(path);
         }
+        result?.map_err(|err| err as Box<dyn Error>)
     }
 }
 
-pub async fn watch(
+/// Starts the preview server of [`watch()`] in the background, and returns once the document is
+/// compiled for the first time and the server is listening, e.g. to get the URL of the preview.
+///
+/// # Arguments
+///
+/// - `params` - [`CompileParams`] struct.
+/// - `open` - Whether to open the output PDF file with the default browser once after the server
+///   launches.
+/// - `app` - Open the output PDF file with the given application, e.g. `Google Chrome.app`, or
+///   with a command template, if it contains `{url}`, e.g. `firefox --new-window {url}`. The
+///   template is split into arguments like a shell does, and `{url}` is replaced with the URL of
+///   the preview.
+/// - `fitting_type` - How the PDF file fits in the preview.
+///
+/// # Returns
+///
+/// Result containing the [`WatchHandle`] of the server.
+///
+/// # Example
+///
+/// Following is an example of how to use the `spawn_watch` function:
+///
+/// ```no_run
+/// let rt = tokio::runtime::Runtime::new().unwrap();
+/// let params = typster::CompileParams::builder().input("main.typ").output("main.pdf").build();
+///
+/// rt.block_on(async {
+///     let mut handle = typster::spawn_watch(&params, false, None, None).await.unwrap();
+///     println!("Preview at {}", handle.url());
+///     handle.persist_url().unwrap();
+///
+///     tokio::signal::ctrl_c().await.unwrap();
+///     handle.shutdown();
+///     handle.wait().await.unwrap();
+/// });
+/// ```
+pub async fn spawn_watch(
     params: &CompileParams,
     open: bool,
     app: Option<&str>,
     fitting_type: Option<FittingType>,
-) -> Result<(), Box<dyn Error>> {
+) -> Result<WatchHandle, Box<dyn Error>> {
     let addr = SocketAddr::from(([127, 0, 0, 1], 0));
     let listener = TcpListener::bind(&addr).await?;
     let address = listener.local_addr()?.ip().to_string();
     let port = listener.local_addr()?.port();
 
     let input = params.input.clone();
-    let output = params.output.clone();
-    let params = params.clone();
+    let output_path = params.output.clone();
 
-    match crate::compile(&params) {
-        Ok(duration) => {
-            info!("Initial compilation succeeded in {duration:?}. Watching for changes...")
+    // Keep the session alive across compilations, so that fonts are searched only once, and
+    // unchanged files and Typst's memoized results are reused.
+    let params = params.clone();
+    let session = task::spawn_blocking(move || CompilerSession::new(&params)).await??;
+    let (mut session, result) = compile(session).await?;
+    let document = match result {
+        Ok((duration, document)) => {
+            info!("Initial compilation succeeded in {duration:?}. Watching for changes...");
+            Some(document)
         }
-        Err(why) => error!("{why}"),
-    }
+        Err(why) => {
+            error!("{why}");
+            None
+        }
+    };
 
     let state = Arc::new(SharedState {
         port,
         address,
         input: input.clone(),
-        output,
-        changed: Notify::new(),
+        output: output_path,
+        events: broadcast::channel(16).0,
         shutdown: Notify::new(),
+        paused: AtomicBool::new(false),
         fitting_type: fitting_type.unwrap_or_default(),
+        document: Mutex::new(document),
+        ppi: session.params().ppi.unwrap_or(144.0),
     });
-    let state_handler = Arc::clone(&state);
     let state_selector = Arc::clone(&state);
+    let state_compiler = Arc::clone(&state);
 
     let router = Router::new()
         .route("/", get(root))
-        .route("/target.pdf", get(pdf))
+        .route("/output", get(output))
         .route("/listen", get(listen))
         .with_state(Arc::clone(&state));
-    info!("Listening on {}:{}", state.address, state.port);
+    let url = format!("http://{}:{}", state.address, state.port);
+    info!("Listening on {url}");
 
     if open {
-        if let Some(app) = app {
-            match open::with_detached(format!("http://{}:{}", state.address, state.port), app) {
-                Ok(_) => info!("Opened in default browser"),
-                Err(why) => error!("{why}"),
-            }
-        } else {
-            match open::that_detached(format!("http://{}:{}", state.address, state.port)) {
-                Ok(_) => info!("Opened in default browser"),
-                Err(why) => error!("{why}"),
-            }
+        match open_preview(&url, app) {
+            Ok(_) => info!("Opened in browser"),
+            Err(why) => error!("failed to open the preview: {why}"),
         }
     }
 
-    tokio::spawn(async move {
-        info!("Press Ctrl+C to exit");
-        async {
-            tokio::signal::ctrl_c()
-                .await
-                .expect("Failed to register handler for Ctrl+C");
-        }
-        .await;
-        state_handler.shutdown.notify_waiters();
-    });
+    // The watcher only queues the changes, and compilations run on a blocking thread one at a
+    // time, so that neither the delivery of events nor the runtime waits for Typst.
+    let (changes, mut queue) = mpsc::unbounded_channel::<Vec<PathBuf>>();
+    let resume = changes.clone();
 
+    // Events have absolute paths, which are sent relative to the watched directory.
+    let root = canonicalize(input.parent().unwrap()).unwrap_or_default();
+    // Font directories are resolved the same way as the paths of the files read, so that the
+    // paths of the events match.
+    let fonts = session
+        .params()
+        .font_paths
+        .iter()
+        .map(|dir| canonicalize(dir).unwrap_or_else(|_| dir.clone()))
+        .collect::<Vec<_>>();
+    let dependencies =
+        Arc::new(Mutex::new(Dependencies { fonts: fonts.clone(), ..Default::default() }));
+    let watched = Arc::clone(&dependencies);
     let mut watcher = notify::recommended_watcher(move |res: Result<Event, _>| match res {
         Ok(event) => {
-            if let Modify(Data(DataChange::Content)) = event.kind {
-                let changed = !event
-                    .paths
-                    .iter()
-                    .filter_map(|p| p.extension())
-                    .map(|e| e.to_string_lossy().to_lowercase())
-                    .filter(|e| EXTENSIONS.contains(&e.as_str()))
-                    .collect::<Vec<_>>()
-                    .is_empty();
-                if !changed {
-                    return;
-                }
-                info!("Change detected. Recompiling...");
-                match crate::compile(&params) {
-                    Ok(duration) => info!("compilation succeeded in {duration:?}"),
-                    Err(why) => error!("{why}"),
-                }
-                state.changed.notify_one()
+            let paths = changed_paths(&event, &watched.lock())
+                .into_iter()
+                .map(|p| p.strip_prefix(&root).map(Path::to_path_buf).unwrap_or(p))
+                .collect::<Vec<_>>();
+            if !paths.is_empty() {
+                // Fails only if the compilation task has stopped, which has been logged.
+                let _ = changes.send(paths);
             }
         }
         Err(e) => error!("watch error: {:?}", e),
     })?;
-    watcher.watch(input.parent().unwrap(), RecursiveMode::Recursive)?;
-    let server = axum::serve(listener, router).into_future();
+    for dir in &fonts {
+        if let Err(why) = watcher.watch(dir, RecursiveMode::Recursive) {
+            error!("failed to watch {}: {why}", dir.display());
+        }
+    }
+    let watcher = Arc::new(Mutex::new(Some(watcher)));
+    update_watches(&watcher, &dependencies, session.world());
+
+    let state_watcher = Arc::clone(&watcher);
+    tokio::spawn(async move {
+        let mut pending = vec![];
+        while let Some(changed) = queue.recv().await {
+            // A save emits several events, and more may arrive during a compilation or while
+            // paused, so compile once for all of them.
+            pending.extend(changed);
+            while let Ok(more) = queue.try_recv() {
+                pending.extend(more);
+            }
+            if pending.is_empty() || state_compiler.paused.load(Ordering::SeqCst) {
+                continue;
+            }
+            let mut paths = std::mem::take(&mut pending);
+            paths.sort();
+            paths.dedup();
+            let fonts_changed = paths.iter().any(|path| is_font(path));
+            state_compiler.publish(&WatchEvent::FileChanged { paths });
+            if fonts_changed {
+                // Fonts are searched only when a session is created.
+                info!("Fonts changed. Reloading fonts...");
+                session = match reload(session).await {
+                    Ok(reloaded) => reloaded,
+                    Err(why) => {
+                        error!("font reload task failed: {why}");
+                        return;
+                    }
+                };
+            }
+            info!("Change detected. Recompiling...");
+            let progress = Some(Arc::clone(&state_compiler));
+            let (compiled, result) = match compile_with_progress(session, progress).await {
+                Ok(compiled) => compiled,
+                Err(why) => {
+                    error!("compilation task failed: {why}");
+                    return;
+                }
+            };
+            session = compiled;
+            // The document may have started or stopped depending on files.
+            update_watches(&state_watcher, &dependencies, session.world());
+            let result = result.map(|(duration, document)| {
+                let pages = document.pages.len();
+                *state_compiler.document.lock() = Some(document);
+                (duration, pages)
+            });
+            state_compiler.publish(&WatchEvent::from_result(&session, result));
+        }
+    });
+
+    let server = tokio::spawn(async move {
+        let server = axum::serve(listener, router).into_future();
+        select! {
+            _ = server => {}
+            _ = state_selector.shutdown.notified() => {
+                info!("Shutting down...");
+                // Dropping the watcher stops watching.
+                watcher.lock().take();
+                remove_file(&state_selector.output)?;
+            }
+        }
+
+        info!("Bye!");
+        Ok::<_, Box<dyn Error + Send + Sync>>(())
+    });
 
-    select! {
-        _ = server => {}
-        _ = state_selector.shutdown.notified() => {
-            info!("Shutting down...");
-            watcher.unwatch(input.parent().unwrap())?;
-            remove_file(&state_selector.output)?;
+    Ok(WatchHandle {
+        url,
+        state,
+        server,
+        url_file: None,
+        changes: resume,
+    })
+}
+
+/// Opens the preview with the default browser, an application, or a command template with `{url}`.
+fn open_preview(url: &str, app: Option<&str>) -> io::Result<()> {
+    match app {
+        Some(template) if template.contains("{url}") => {
+            let args = expand_command(template, url)?;
+            // The browser outlives the server, so it isn't waited for.
+            Command::new(&args[0]).args(&args[1..]).spawn().map(drop)
         }
+        Some(app) => open::with_detached(url, app),
+        None => open::that_detached(url),
     }
+}
 
-    info!("Bye!");
-    Ok(())
+/// Splits a command template into the program and its arguments like a POSIX shell, and replaces
+/// `{url}` in each of them with the URL, so that a URL with spaces or quotes stays one argument.
+fn expand_command(template: &str, url: &str) -> io::Result<Vec<String>> {
+    let args = shlex::split(template)
+        .filter(|args| !args.is_empty())
+        .ok_or_else(|| io::Error::other(format!("invalid command: {template}")))?;
+    Ok(args.iter().map(|arg| arg.replace("{url}", url)).collect())
+}
+
+/// Creates a new session with the parameters of a session on a blocking thread, searching the
+/// fonts again, and returns the previous one if it can't be created.
+async fn reload(session: CompilerSession) -> Result<CompilerSession, task::JoinError> {
+    task::spawn_blocking(move || match CompilerSession::new(session.params()) {
+        Ok(reloaded) => reloaded,
+        Err(why) => {
+            error!("{why}");
+            session
+        }
+    })
+    .await
+}
+
+/// Compiles a session on a blocking thread, and returns it with the result of the compilation.
+async fn compile(
+    session: CompilerSession,
+) -> Result<(CompilerSession, Result<(Duration, Document), TypsterError>), task::JoinError> {
+    compile_with_progress(session, None).await
+}
+
+/// Compiles a session like [`compile()`], and sends the progress of the compilation to the
+/// clients of the state, if any: when it starts, when the document is exported, and
+/// periodically in between, so that a long compilation doesn't look frozen.
+async fn compile_with_progress(
+    mut session: CompilerSession,
+    state: Option<Arc<SharedState>>,
+) -> Result<(CompilerSession, Result<(Duration, Document), TypsterError>), task::JoinError> {
+    let start = Instant::now();
+    let stage = Arc::new(Mutex::new(CompileStage::Parsing));
+    let progress = move |stage: CompileStage| WatchEvent::Progress {
+        stage,
+        elapsed: start.elapsed().as_millis() as u64,
+    };
+
+    // The start of each stage is reported by the compilation, and the ticker reports the current
+    // one in between.
+    let ticker = state.clone().map(|state| {
+        let stage = Arc::clone(&stage);
+        tokio::spawn(async move {
+            let mut interval =
+                time::interval_at((start + PROGRESS_INTERVAL).into(), PROGRESS_INTERVAL);
+            loop {
+                interval.tick().await;
+                let current = *stage.lock();
+                state.publish(&progress(current));
+            }
+        })
+    });
+    let result = task::spawn_blocking(move || {
+        let result = session.compile_document(|current| {
+            *stage.lock() = current;
+            if let Some(state) = &state {
+                state.publish(&progress(current));
+            }
+        });
+        (session, result)
+    })
+    .await;
+    if let Some(ticker) = ticker {
+        ticker.abort();
+    }
+    result
 }
 
 pub async fn root(State(state): State<Arc<SharedState>>) -> Html<String> {
@@ -230,13 +733,129 @@ pub async fn root(State(state): State<Arc<SharedState>>) -> Html<String> {
         .into()
 }
 
-pub async fn pdf(State(state): State<Arc<SharedState>>) -> impl IntoResponse {
-    Response::builder()
-        .header("Content-Type", "application/pdf")
-        .body(Body::from(match fs::read(&state.output).await {
+/// Formats the output can be served in by `/output`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Pdf,
+    Png,
+    Svg,
+}
+
+impl OutputFormat {
+    /// Returns the format of a `format` query parameter, e.g. `png`, or of a media type of an
+    /// `Accept` header, e.g. `image/png`.
+    fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "pdf" | "application/pdf" | "*/*" | "application/*" => Some(Self::Pdf),
+            "png" | "image/png" | "image/*" => Some(Self::Png),
+            "svg" | "image/svg+xml" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+
+    /// Returns the format preferred by an `Accept` header, i.e. the supported media type with the
+    /// highest quality, the first one on a tie, or PDF if none is supported.
+    fn negotiate(accept: &str) -> Self {
+        let mut best = None;
+        for range in accept.split(',') {
+            let mut parts = range.split(';');
+            let Some(format) = parts.next().and_then(Self::parse) else {
+                continue;
+            };
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if quality > 0.0 && !best.is_some_and(|(_, best)| quality <= best) {
+                best = Some((format, quality));
+            }
+        }
+        best.map_or(Self::Pdf, |(format, _)| format)
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Pdf => "application/pdf",
+            Self::Png => "image/png",
+            Self::Svg => "image/svg+xml",
+        }
+    }
+}
+
+/// Query parameters of `/output`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct OutputQuery {
+    /// Format of the output, `pdf`, `png`, or `svg`, which takes precedence over the `Accept`
+    /// header.
+    format: Option<String>,
+    /// 1-based number of the page to render to PNG or SVG. Defaults to the first page.
+    page: Option<usize>,
+}
+
+/// Serves the output PDF file, or a page of the document as PNG or SVG, in the format of the
+/// `format` query parameter or else of the `Accept` header.
+pub async fn output(
+    State(state): State<Arc<SharedState>>,
+    Query(query): Query<OutputQuery>,
+    headers: HeaderMap,
+) -> Response {
+    let format = match query.format.as_deref() {
+        Some(name) => match OutputFormat::parse(name) {
+            Some(format) => format,
+            None => {
+                let message = format!("unsupported format: {name}");
+                return (StatusCode::BAD_REQUEST, message).into_response();
+            }
+        },
+        None => headers
+            .get(ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .map_or(OutputFormat::Pdf, OutputFormat::negotiate),
+    };
+
+    let body = match format {
+        OutputFormat::Pdf => match fs::read(&state.output).await {
             Ok(data) => data,
-            Err(why) => panic!("{:#?}", why),
-        }))
+            Err(why) => return (StatusCode::NOT_FOUND, why.to_string()).into_response(),
+        },
+        OutputFormat::Png | OutputFormat::Svg => {
+            let number = query.page.unwrap_or(1);
+            let page = match &*state.document.lock() {
+                Some(document) => {
+                    number.checked_sub(1).and_then(|i| document.pages.get(i)).cloned()
+                }
+                None => {
+                    let message = "the document has not compiled yet";
+                    return (StatusCode::SERVICE_UNAVAILABLE, message).into_response();
+                }
+            };
+            let Some(page) = page else {
+                let message = format!("page {number} is out of range");
+                return (StatusCode::NOT_FOUND, message).into_response();
+            };
+            let ppi = state.ppi;
+            let rendered = task::spawn_blocking(move || match format {
+                OutputFormat::Svg => Ok(typst_svg::svg(&page).into_bytes()),
+                _ => typst_render::render(&page, ppi / 72.0)
+                    .encode_png()
+                    .map_err(|err| err.to_string()),
+            })
+            .await;
+            match rendered {
+                Ok(Ok(data)) => data,
+                Ok(Err(why)) => return (StatusCode::INTERNAL_SERVER_ERROR, why).into_response(),
+                Err(why) => {
+                    return (StatusCode::INTERNAL_SERVER_ERROR, why.to_string()).into_response()
+                }
+            }
+        }
+    };
+
+    Response::builder()
+        .header(CONTENT_TYPE, format.content_type())
+        .header(VARY, "Accept")
+        .body(Body::from(body))
         .unwrap()
 }
 
@@ -248,8 +867,227 @@ pub async fn listen(
 }
 
 async fn handler(mut socket: WebSocket, state: Arc<SharedState>) {
+    let mut events = state.events.subscribe();
     loop {
-        state.changed.notified().await;
-        _ = socket.send(Message::Text("refresh".into())).await;
+        match events.recv().await {
+            Ok(event) => {
+                if socket.send(Message::Text(event)).await.is_err() {
+                    break;
+                }
+            }
+            // A slow client misses the oldest events, but the latest ones are still relevant.
+            Err(RecvError::Lagged(_)) => continue,
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_event_serialization() {
+        let events = [
+            (
+                WatchEvent::Compiled { duration: 42, pages: 12 },
+                json!({"type": "compiled", "duration": 42, "pages": 12}),
+            ),
+            (
+                WatchEvent::Error {
+                    diagnostics: vec![Diagnostic::error("timed out".to_string())],
+                },
+                json!({"type": "error", "diagnostics": [
+                    {"severity": "error", "message": "timed out", "hints": [], "location": null},
+                ]}),
+            ),
+            (
+                WatchEvent::Progress { stage: CompileStage::Parsing, elapsed: 0 },
+                json!({"type": "progress", "stage": "parsing", "elapsed": 0}),
+            ),
+            (
+                WatchEvent::Progress { stage: CompileStage::Layout, elapsed: 500 },
+                json!({"type": "progress", "stage": "layout", "elapsed": 500}),
+            ),
+            (
+                WatchEvent::Progress { stage: CompileStage::Export, elapsed: 880 },
+                json!({"type": "progress", "stage": "export", "elapsed": 880}),
+            ),
+            (
+                WatchEvent::FileChanged { paths: vec![PathBuf::from("chapters/intro.typ")] },
+                json!({"type": "file-changed", "paths": ["chapters/intro.typ"]}),
+            ),
+        ];
+        for (event, expected) in events {
+            assert_eq!(serde_json::to_value(&event).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_expand_command() {
+        let url = "http://127.0.0.1:3000/?name=a b";
+        assert_eq!(
+            expand_command("firefox --new-window {url}", url).unwrap(),
+            ["firefox", "--new-window", url],
+        );
+        assert_eq!(
+            expand_command(r#"'/opt/My Browser/browser' --app="{url}" --kiosk"#, url).unwrap(),
+            ["/opt/My Browser/browser", &format!("--app={url}"), "--kiosk"],
+        );
+        assert!(expand_command("firefox '{url}", url).is_err());
+        assert!(expand_command("   ", url).is_err());
+    }
+
+    #[test]
+    fn test_dependencies_contains() {
+        let dependencies = Dependencies {
+            files: HashSet::from([PathBuf::from("/doc/main.typ"), PathBuf::from("/doc/logo.svg")]),
+            dirs: HashSet::from([PathBuf::from("/doc")]),
+            packages: HashSet::from([PathBuf::from("/cache/preview/cetz/0.3.1")]),
+            fonts: vec![],
+        };
+        assert!(dependencies.contains(Path::new("/doc/main.typ")));
+        assert!(dependencies.contains(Path::new("/cache/preview/cetz/0.3.1/src/draw.typ")));
+        // Files in the watched directories are ignored unless the document reads them.
+        assert!(!dependencies.contains(Path::new("/doc/notes.typ")));
+        assert!(!dependencies.contains(Path::new("/cache/preview/cetz/0.2.2/src/draw.typ")));
+        assert!(!dependencies.covers(Path::new("/doc")));
+    }
+
+    #[test]
+    fn test_changed_paths_rename_over() {
+        let dependencies = Dependencies {
+            files: HashSet::from([PathBuf::from("/doc/main.typ")]),
+            ..Default::default()
+        };
+        let event = |kind, paths: &[&str]| Event {
+            kind,
+            paths: paths.iter().map(PathBuf::from).collect(),
+            attrs: Default::default(),
+        };
+
+        // Editors write a temporary file, and rename it over the dependency.
+        let saved = [
+            event(Create(CreateKind::File), &["/doc/.main.typ.swp"]),
+            event(Modify(Data(DataChange::Content)), &["/doc/.main.typ.swp"]),
+            event(Modify(Name(RenameMode::From)), &["/doc/.main.typ.swp"]),
+            event(Modify(Name(RenameMode::To)), &["/doc/main.typ"]),
+            event(Modify(Name(RenameMode::Both)), &["/doc/.main.typ.swp", "/doc/main.typ"]),
+        ];
+        let changed = saved
+            .iter()
+            .map(|event| changed_paths(event, &dependencies))
+            .collect::<Vec<_>>();
+        let main = vec![PathBuf::from("/doc/main.typ")];
+        assert_eq!(changed, [vec![], vec![], vec![], main.clone(), main.clone()]);
+
+        assert_eq!(
+            changed_paths(&event(Modify(ModifyKind::Any), &["/doc/main.typ"]), &dependencies),
+            main
+        );
+        let metadata = Modify(ModifyKind::Metadata(notify::event::MetadataKind::Any));
+        assert!(changed_paths(&event(metadata, &["/doc/main.typ"]), &dependencies).is_empty());
+    }
+
+    #[test]
+    fn test_watch_rename_over() {
+        let dir = std::env::temp_dir().join(format!("typster-rename-over-{}", std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let dir = canonicalize(dir).unwrap();
+        let main = dir.join("main.typ");
+        write(&main, "= Before").unwrap();
+        let dependencies = Dependencies {
+            files: HashSet::from([main.clone()]),
+            dirs: HashSet::from([dir.clone()]),
+            ..Default::default()
+        };
+
+        let (events, received) = std::sync::mpsc::channel();
+        let mut watcher = notify::recommended_watcher(events).unwrap();
+        watcher.watch(&dir, RecursiveMode::NonRecursive).unwrap();
+        let temporary = dir.join(".main.typ.tmp");
+        write(&temporary, "= After").unwrap();
+        std::fs::rename(&temporary, &main).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut changed = vec![];
+        while changed.is_empty() {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let Ok(event) = received.recv_timeout(timeout) else {
+                break;
+            };
+            changed = changed_paths(&event.unwrap(), &dependencies);
+        }
+        drop(watcher);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(changed, [main]);
+    }
+
+    #[test]
+    fn test_dependencies_contains_fonts() {
+        let dependencies = Dependencies {
+            fonts: vec![PathBuf::from("/doc/fonts")],
+            ..Default::default()
+        };
+        assert!(dependencies.contains(Path::new("/doc/fonts/Inter.ttf")));
+        assert!(dependencies.contains(Path::new("/doc/fonts/cjk/NotoSansCJK.TTC")));
+        assert!(!dependencies.contains(Path::new("/doc/fonts/LICENSE.txt")));
+        assert!(!dependencies.contains(Path::new("/doc/Inter.otf")));
+        assert!(dependencies.covers(Path::new("/doc/fonts/cjk")));
+
+        // Fonts removed or renamed away are no longer found.
+        let event = |kind| Event {
+            kind,
+            paths: vec![PathBuf::from("/doc/fonts/Inter.ttf")],
+            attrs: Default::default(),
+        };
+        for kind in [Remove(RemoveKind::File), Modify(Name(RenameMode::From))] {
+            assert_eq!(
+                changed_paths(&event(kind), &dependencies),
+                [Path::new("/doc/fonts/Inter.ttf")]
+            );
+        }
+        let dependencies = Dependencies {
+            files: HashSet::from([PathBuf::from("/doc/fonts/Inter.ttf")]),
+            ..Default::default()
+        };
+        assert!(changed_paths(&event(Remove(RemoveKind::File)), &dependencies).is_empty());
+        assert!(!dependencies.covers(Path::new("/doc")));
+    }
+
+    #[test]
+    fn test_output_format_parse() {
+        assert_eq!(OutputFormat::parse("pdf"), Some(OutputFormat::Pdf));
+        assert_eq!(OutputFormat::parse(" PNG "), Some(OutputFormat::Png));
+        assert_eq!(OutputFormat::parse("image/svg+xml"), Some(OutputFormat::Svg));
+        assert_eq!(OutputFormat::parse("image/*"), Some(OutputFormat::Png));
+        assert_eq!(OutputFormat::parse("*/*"), Some(OutputFormat::Pdf));
+        assert_eq!(OutputFormat::parse("text/html"), None);
+    }
+
+    #[test]
+    fn test_output_format_negotiate() {
+        assert_eq!(OutputFormat::negotiate(""), OutputFormat::Pdf);
+        assert_eq!(OutputFormat::negotiate("text/html"), OutputFormat::Pdf);
+        assert_eq!(OutputFormat::negotiate("image/svg+xml"), OutputFormat::Svg);
+        // Browsers ask for images with a wildcard.
+        assert_eq!(
+            OutputFormat::negotiate("image/avif,image/webp,image/*,*/*;q=0.8"),
+            OutputFormat::Png,
+        );
+        // The highest quality wins, and the first one on a tie.
+        assert_eq!(
+            OutputFormat::negotiate("application/pdf;q=0.5, image/png;q=0.9, image/svg+xml;q=0.9"),
+            OutputFormat::Png,
+        );
+        // Excluded formats are never served, but PDF is the fallback.
+        assert_eq!(OutputFormat::negotiate("image/svg+xml;q=0"), OutputFormat::Pdf);
+        assert_eq!(
+            OutputFormat::negotiate("image/svg+xml;q=0, image/png; q=0.1"),
+            OutputFormat::Png,
+        );
+        assert_eq!(OutputFormat::negotiate("image/png;q=oops"), OutputFormat::Png);
     }
 }
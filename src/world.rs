@@ -1,16 +1,20 @@
 use std::{
     collections::HashMap,
-    fmt, fs, io,
+    fmt, fs,
+    hash::Hash,
+    io,
     io::Read,
     mem,
     path::{Path, PathBuf},
     sync::OnceLock,
+    time::{Duration, Instant, SystemTime},
 };
 
 use chrono::{DateTime, Datelike, Local};
 use ecow::{eco_format, EcoString};
 use once_cell::sync::Lazy;
 use parking_lot::Mutex;
+use tracing::{info_span, instrument};
 use typst::{
     diag::{FileError, FileResult},
     foundations::{Bytes, Datetime, Dict, IntoValue},
@@ -24,17 +28,24 @@ use typst_timing::{timed, TimingScope};
 
 use crate::{
     fonts::{FontSearcher, FontSlot},
-    package,
+    package, CompileParams, FileAccess, FileAccessHook, Limits, TypsterError,
 };
 
 /// Static `FileId` allocated for stdin.
 /// This is to ensure that a file is read in the correct way.
 static STDIN_ID: Lazy<FileId> = Lazy::new(|| FileId::new_fake(VirtualPath::new("<stdin>")));
 
+/// Maximum number of bytes of the package files shared by all worlds.
+const SHARED_FILES_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Large package files shared by all worlds, see [`CompileParams::shared_file_threshold`].
+static SHARED_FILES: Lazy<Mutex<SharedFiles>> =
+    Lazy::new(|| Mutex::new(SharedFiles::new(SHARED_FILES_SIZE)));
+
 /// A world that provides access to the operating system.
 pub struct SystemWorld {
-    /// The root relative to which absolute paths are resolved.
-    root: PathBuf,
+    /// Resolves file ids to paths and reads them.
+    resolver: FileResolver,
     /// The input path.
     main: FileId,
     /// Typst's standard library.
@@ -45,8 +56,6 @@ pub struct SystemWorld {
     fonts: Vec<FontSlot>,
     /// Maps file ids to source files and buffers.
     slots: Mutex<HashMap<FileId, FileSlot>>,
-    /// Holds information about where packages are stored.
-    package_storage: PackageStorage,
     /// The current datetime if requested. This is stored here to ensure it is
     /// always the same within one compilation. Reset between compilations.
     now: OnceLock<DateTime<Local>>,
@@ -54,18 +63,11 @@ pub struct SystemWorld {
 
 impl SystemWorld {
     /// Create a new system world.
-    pub fn new(
-        input: &Path,
-        font_paths: &[PathBuf],
-        inputs: Vec<(String, String)>,
-        package_path: &Option<PathBuf>,
-        package_cache_path: &Option<PathBuf>,
-    ) -> Result<Self, WorldCreationError> {
+    #[instrument(name = "world", skip_all, fields(input = %params.input.display()))]
+    pub fn new(params: &CompileParams) -> Result<Self, WorldCreationError> {
         // Resolve the input path.
-        let input = input.canonicalize().map_err(|err| match err.kind() {
-            io::ErrorKind::NotFound => {
-                WorldCreationError::InputNotFound(input.to_path_buf().clone())
-            }
+        let input = params.input.canonicalize().map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => WorldCreationError::InputNotFound(params.input.clone()),
             _ => WorldCreationError::Io(err),
         })?;
 
@@ -87,32 +89,126 @@ impl SystemWorld {
             VirtualPath::within_root(&input, &root).ok_or(WorldCreationError::InputOutsideRoot)?;
         let main = FileId::new(None, main_path);
 
-        let library = {
-            // Convert the input pairs to a dictionary.
-            let inputs: Dict = inputs
-                .iter()
-                .map(|(k, v)| (k.as_str().into(), v.as_str().into_value()))
-                .collect();
+        let shadows = resolve_shadows(&params.shadow_files, &root);
+
+        // Resolve the directories the sandbox allows to read, if any. The root always is.
+        let sandbox = match &params.sandbox {
+            Some(sandbox) => Some(
+                std::iter::once(root.clone())
+                    .chain(sandbox.allowlist.iter().map(|path| {
+                        let path = root.join(path);
+                        path.canonicalize().unwrap_or(path)
+                    }))
+                    .collect(),
+            ),
+            None if params.deny_symlink_escape => Some(vec![root.clone()]),
+            None => None,
+        };
 
-            Library::builder().with_inputs(inputs).build()
+        let resolver = FileResolver {
+            root,
+            package_storage: package::storage(&params.package_path, &params.package_cache_path),
+            shadows,
+            sandbox,
+            archive: None,
+            hook: params.on_file_access.clone(),
+            shared_file_threshold: params.shared_file_threshold,
+            limits: params.limits,
+            timeout: params.timeout,
+            usage: Mutex::new(Usage::new(None)),
         };
 
+        Self::with_resolver(resolver, main, params)
+    }
+
+    /// Create a new world whose project files are read from the files of an archive, keyed by
+    /// their path relative to the archive root, instead of from disk. Packages are still read
+    /// from disk.
+    #[cfg(feature = "archive")]
+    #[instrument(name = "world", skip_all, fields(input = %entry.display()))]
+    pub fn from_archive(
+        files: HashMap<PathBuf, Bytes>,
+        entry: &Path,
+        params: &CompileParams,
+    ) -> Result<Self, WorldCreationError> {
+        let main_path = VirtualPath::new(entry);
+        if !files.contains_key(main_path.as_rootless_path()) {
+            return Err(WorldCreationError::InputNotFound(entry.to_path_buf()));
+        }
+
+        let resolver = FileResolver {
+            root: PathBuf::new(),
+            package_storage: package::storage(&params.package_path, &params.package_cache_path),
+            shadows: HashMap::new(),
+            sandbox: None,
+            archive: Some(files),
+            hook: params.on_file_access.clone(),
+            shared_file_threshold: params.shared_file_threshold,
+            limits: params.limits,
+            timeout: params.timeout,
+            usage: Mutex::new(Usage::new(None)),
+        };
+
+        Self::with_resolver(resolver, FileId::new(None, main_path), params)
+    }
+
+    /// Create a new world with the given file resolver and main file.
+    fn with_resolver(
+        mut resolver: FileResolver,
+        main: FileId,
+        params: &CompileParams,
+    ) -> Result<Self, WorldCreationError> {
+        // The timeout of the first compilation includes the font search.
+        let deadline = params.timeout.map(|timeout| Instant::now() + timeout);
+
         let mut searcher = FontSearcher::new();
-        searcher.search(font_paths);
+        info_span!("fonts").in_scope(|| searcher.search(&params.font_paths));
+        // The font search doesn't count towards the duration limit of the first compilation.
+        *resolver.usage.get_mut() = Usage::new(deadline);
 
         Ok(Self {
-            root,
+            resolver,
             main,
-            library: LazyHash::new(library),
+            library: LazyHash::new(library(&params.dict)),
             book: LazyHash::new(searcher.book),
             fonts: searcher.fonts,
             slots: Mutex::new(HashMap::new()),
-            package_storage: package::storage(package_path, package_cache_path),
             now: OnceLock::new(),
         })
     }
 }
 
+/// Resolves the shadowed paths the same way `system_path` resolves file ids, so that they can be
+/// looked up directly. Files which don't exist on disk can't be canonicalized, so they are
+/// normalized lexically, e.g. `./new.typ` or `chapters/../new.typ`.
+fn resolve_shadows(
+    shadow_files: &HashMap<PathBuf, String>,
+    root: &Path,
+) -> HashMap<PathBuf, Bytes> {
+    shadow_files
+        .iter()
+        .map(|(path, text)| {
+            let path = root.join(path);
+            let path = path.canonicalize().unwrap_or(path);
+            let path = VirtualPath::within_root(&path, root)
+                .and_then(|vpath| vpath.resolve(root))
+                .unwrap_or(path);
+            (path, Bytes::from(text.as_bytes()))
+        })
+        .collect()
+}
+
+/// Creates the standard library with the input pairs visible through `sys.inputs`.
+fn library(dict: &[(String, String)]) -> Library {
+    // Convert the input pairs to a dictionary.
+    let inputs: Dict = dict
+        .iter()
+        .map(|(k, v)| (k.as_str().into(), v.as_str().into_value()))
+        .collect();
+
+    Library::builder().with_inputs(inputs).build()
+}
+
 impl World for SystemWorld {
     fn library(&self) -> &LazyHash<Library> {
         &self.library
@@ -127,11 +223,11 @@ impl World for SystemWorld {
     }
 
     fn source(&self, id: FileId) -> FileResult<Source> {
-        self.slot(id, |slot| slot.source(&self.root, &self.package_storage))
+        self.slot(id, |slot| slot.source(&self.resolver))
     }
 
     fn file(&self, id: FileId) -> FileResult<Bytes> {
-        self.slot(id, |slot| slot.file(&self.root, &self.package_storage))
+        self.slot(id, |slot| slot.file(&self.resolver))
     }
 
     fn font(&self, index: usize) -> Option<Font> {
@@ -155,6 +251,120 @@ impl World for SystemWorld {
 }
 
 impl SystemWorld {
+    /// Reset the compilation state in preparation of a new compilation, keeping the loaded files
+    /// so that unchanged ones are reused.
+    pub fn reset(&mut self) {
+        for slot in self.slots.get_mut().values_mut() {
+            slot.reset();
+        }
+        self.now.take();
+        let deadline = self.resolver.timeout.map(|timeout| Instant::now() + timeout);
+        *self.resolver.usage.get_mut() = Usage::new(deadline);
+    }
+
+    /// Replaces the parameters which don't shape the world, i.e. the shadowed files, the resource
+    /// limits, and the timeout, so that a warm world can serve compilations which only differ in
+    /// them. Takes effect on the next [`reset()`](Self::reset()).
+    #[cfg(feature = "server")]
+    pub(crate) fn update(&mut self, params: &CompileParams) {
+        let resolver = &mut self.resolver;
+        resolver.shadows = resolve_shadows(&params.shadow_files, &resolver.root);
+        resolver.hook = params.on_file_access.clone();
+        resolver.shared_file_threshold = params.shared_file_threshold;
+        resolver.limits = params.limits;
+        resolver.timeout = params.timeout;
+    }
+
+    /// Returns [`TypsterError::TimedOut`] if the current compilation has exceeded
+    /// [`CompileParams::timeout`], or [`TypsterError::LimitExceeded`] if it has exceeded a limit
+    /// of [`CompileParams::limits`].
+    pub(crate) fn check_limits(&self) -> Result<(), TypsterError> {
+        let usage = self.resolver.usage.lock();
+        if let (Some(timeout), true) = (self.resolver.timeout, usage.is_overdue()) {
+            return Err(TypsterError::TimedOut(timeout));
+        }
+        if let Some(message) = &usage.exceeded {
+            return Err(TypsterError::LimitExceeded(message.to_string()));
+        }
+        match self.resolver.limits.max_duration {
+            Some(max) if usage.start.elapsed() > max => {
+                Err(TypsterError::LimitExceeded(format!("took longer than {max:?}")))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Replaces the input pairs visible through `sys.inputs`, e.g. to compile the same document for
+    /// each page of another one.
+    #[cfg(feature = "pdf_stamp")]
+    pub(crate) fn set_inputs(&mut self, dict: &[(String, String)]) {
+        self.library = LazyHash::new(library(dict));
+    }
+
+    /// Returns the files read in the current compilation, except packages, with their contents,
+    /// sorted by path.
+    #[cfg(feature = "pdf_edit")]
+    pub(crate) fn dependencies(&self) -> Vec<(FileId, Vec<u8>)> {
+        let mut files = self
+            .slots
+            .lock()
+            .values()
+            .filter(|slot| slot.id.package().is_none() && slot.id != *STDIN_ID)
+            .filter_map(|slot| Some((slot.id, slot.accessed_bytes()?)))
+            .collect::<Vec<_>>();
+        files.sort_by(|(a, _), (b, _)| {
+            a.vpath().as_rootless_path().cmp(b.vpath().as_rootless_path())
+        });
+        files
+    }
+
+    /// Returns the paths of the files read in the current compilation, except packages, and the
+    /// directories of the packages used, e.g. to watch them for changes.
+    #[cfg(feature = "watch")]
+    pub(crate) fn dependency_paths(&self) -> (Vec<PathBuf>, Vec<PathBuf>) {
+        let (mut files, mut packages) = (vec![], vec![]);
+        for slot in self.slots.lock().values() {
+            if slot.id == *STDIN_ID || !(slot.source.accessed || slot.file.accessed) {
+                continue;
+            }
+            match slot.id.package() {
+                // The package has been prepared when its files were read, so this doesn't
+                // download it again.
+                Some(spec) => packages.extend(
+                    self.resolver
+                        .package_storage
+                        .prepare_package(spec, &mut ProgressSink {})
+                        .ok(),
+                ),
+                None => files.extend(self.resolver.system_path(slot.id).ok()),
+            }
+        }
+        for paths in [&mut files, &mut packages] {
+            paths.sort();
+            paths.dedup();
+        }
+        (files, packages)
+    }
+
+    /// Returns the Typst sources parsed in the current compilation, except packages, sorted by
+    /// path.
+    pub(crate) fn sources(&self) -> Vec<Source> {
+        let mut sources = self
+            .slots
+            .lock()
+            .values()
+            .filter(|slot| slot.id.package().is_none() && slot.source.accessed)
+            .filter_map(|slot| slot.source.data.as_ref()?.as_ref().ok().cloned())
+            .collect::<Vec<_>>();
+        sources.sort_by(|a, b| {
+            a.id()
+                .vpath()
+                .as_rootless_path()
+                .cmp(b.id().vpath().as_rootless_path())
+        });
+        sources
+    }
+
     /// Access the canonical slot for the given file id.
     fn slot<F, T>(&self, id: FileId, f: F) -> T
     where
@@ -183,14 +393,34 @@ impl FileSlot {
         Self { id, file: SlotCell::new(), source: SlotCell::new() }
     }
 
+    /// Marks the file as not yet accessed in preparation of the next compilation.
+    fn reset(&mut self) {
+        self.source.reset();
+        self.file.reset();
+    }
+
+    /// Returns the contents of the file if it has been successfully read in the current
+    /// compilation.
+    #[cfg(feature = "pdf_edit")]
+    fn accessed_bytes(&self) -> Option<Vec<u8>> {
+        if let (true, Some(Ok(file))) = (self.file.accessed, &self.file.data) {
+            return Some(file.to_vec());
+        }
+        if let (true, Some(Ok(source))) = (self.source.accessed, &self.source.data) {
+            return Some(source.text().as_bytes().to_vec());
+        }
+        None
+    }
+
     /// Retrieve the source for this file.
-    fn source(
-        &mut self,
-        project_root: &Path,
-        package_storage: &PackageStorage,
-    ) -> FileResult<Source> {
-        self.source.get_or_init(
-            || read(self.id, project_root, package_storage),
+    fn source(&mut self, resolver: &FileResolver) -> FileResult<Source> {
+        let start = Instant::now();
+        let mut cache_hit = true;
+        let result = self.source.get_or_init(
+            || {
+                cache_hit = false;
+                resolver.limited(|| resolver.read(self.id))
+            },
             |data, prev| {
                 let name = if prev.is_some() { "reparsing file" } else { "parsing file" };
                 let _scope = TimingScope::new(name, None);
@@ -202,13 +432,26 @@ impl FileSlot {
                     Ok(Source::new(self.id, text.into()))
                 }
             },
-        )
+        );
+        let bytes = result.as_ref().map_or(0, |source| source.text().len());
+        resolver.notify(self.id, bytes, start.elapsed(), cache_hit);
+        result
     }
 
     /// Retrieve the file's bytes.
-    fn file(&mut self, project_root: &Path, package_storage: &PackageStorage) -> FileResult<Bytes> {
-        self.file
-            .get_or_init(|| read(self.id, project_root, package_storage), |data, _| Ok(data.into()))
+    fn file(&mut self, resolver: &FileResolver) -> FileResult<Bytes> {
+        let start = Instant::now();
+        let mut cache_hit = true;
+        let result = self.file.get_or_init(
+            || {
+                cache_hit = false;
+                resolver.limited(|| resolver.read_bytes(self.id))
+            },
+            |data, _| Ok(data),
+        );
+        let bytes = result.as_ref().map_or(0, |file| file.len());
+        resolver.notify(self.id, bytes, start.elapsed(), cache_hit);
+        result
     }
 }
 
@@ -228,11 +471,16 @@ impl<T: Clone> SlotCell<T> {
         Self { data: None, fingerprint: 0, accessed: false }
     }
 
+    /// Marks the cell as not yet accessed in preparation of the next compilation.
+    fn reset(&mut self) {
+        self.accessed = false;
+    }
+
     /// Gets the contents of the cell or initialize them.
-    fn get_or_init(
+    fn get_or_init<D: Hash>(
         &mut self,
-        load: impl FnOnce() -> FileResult<Vec<u8>>,
-        f: impl FnOnce(Vec<u8>, Option<T>) -> FileResult<T>,
+        load: impl FnOnce() -> FileResult<D>,
+        f: impl FnOnce(D, Option<T>) -> FileResult<T>,
     ) -> FileResult<T> {
         // If we accessed the file already in this compilation, retrieve it.
         if mem::replace(&mut self.accessed, true) {
@@ -260,36 +508,211 @@ impl<T: Clone> SlotCell<T> {
     }
 }
 
-/// Resolves the path of a file id on the system, downloading a package if
-/// necessary.
-fn system_path(
-    project_root: &Path,
-    id: FileId,
-    package_storage: &PackageStorage,
-) -> FileResult<PathBuf> {
-    // Determine the root path relative to which the file path
-    // will be resolved.
-    let buf;
-    let mut root = project_root;
-    if let Some(spec) = id.package() {
-        buf = package_storage.prepare_package(spec, &mut ProgressSink {})?;
-        root = &buf;
-    }
-
-    // Join the path to the root. If it tries to escape, deny
-    // access. Note: It can still escape via symlinks.
-    id.vpath().resolve(root).ok_or(FileError::AccessDenied)
+/// Resolves file ids to paths on the system and reads them.
+struct FileResolver {
+    /// The root relative to which absolute paths are resolved.
+    root: PathBuf,
+    /// Holds information about where packages are stored.
+    package_storage: PackageStorage,
+    /// In-memory file contents which take precedence over the files on disk, keyed by their
+    /// absolute path.
+    shadows: HashMap<PathBuf, Bytes>,
+    /// Canonical directories and files which may be read, besides packages. [`None`] means
+    /// unrestricted.
+    sandbox: Option<Vec<PathBuf>>,
+    /// The files of the project, keyed by their path relative to the root, if it is mounted from
+    /// an archive rather than read from disk.
+    archive: Option<HashMap<PathBuf, Bytes>>,
+    /// Callback invoked on every file read.
+    hook: Option<FileAccessHook>,
+    /// Size in bytes from which package files are shared by all worlds instead of read by each.
+    shared_file_threshold: Option<u64>,
+    /// Resource limits of a compilation.
+    limits: Limits,
+    /// Maximum duration of a compilation, after which files can't be read anymore.
+    timeout: Option<Duration>,
+    /// Resources used by the current compilation.
+    usage: Mutex<Usage>,
 }
 
-/// Reads a file from a `FileId`.
-///
-/// If the ID represents stdin it will read from standard input,
-/// otherwise it gets the file path of the ID and reads the file from disk.
-fn read(id: FileId, project_root: &Path, package_storage: &PackageStorage) -> FileResult<Vec<u8>> {
-    if id == *STDIN_ID {
-        read_from_stdin()
-    } else {
-        read_from_disk(&system_path(project_root, id, package_storage)?)
+/// Resources used by a compilation, checked against its [`Limits`].
+struct Usage {
+    /// When the compilation started.
+    start: Instant,
+    /// When the compilation times out, if it has a timeout.
+    deadline: Option<Instant>,
+    /// Number of files read.
+    reads: usize,
+    /// Number of bytes of the files read.
+    bytes: u64,
+    /// Message of the first limit exceeded, if any.
+    exceeded: Option<EcoString>,
+}
+
+impl Usage {
+    /// Starts measuring a compilation which times out at `deadline`, if any.
+    fn new(deadline: Option<Instant>) -> Self {
+        Self {
+            start: Instant::now(),
+            deadline,
+            reads: 0,
+            bytes: 0,
+            exceeded: None,
+        }
+    }
+
+    /// Returns whether the compilation has passed its deadline.
+    fn is_overdue(&self) -> bool {
+        self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Records that a limit has been exceeded, and returns the error failing the file read.
+    fn exceed(&mut self, message: EcoString) -> FileError {
+        let error = FileError::Other(Some(eco_format!("limit exceeded: {message}")));
+        self.exceeded.get_or_insert(message);
+        error
+    }
+}
+
+impl FileResolver {
+    /// Resolves the path of a file id on the system, downloading a package if
+    /// necessary.
+    fn system_path(&self, id: FileId) -> FileResult<PathBuf> {
+        // Determine the root path relative to which the file path
+        // will be resolved.
+        let buf;
+        let mut root = self.root.as_path();
+        if let Some(spec) = id.package() {
+            buf = self.package_storage.prepare_package(spec, &mut ProgressSink {})?;
+            root = &buf;
+        }
+
+        // Join the path to the root. If it tries to escape, deny
+        // access. Note: It can still escape via symlinks, unless sandboxed or
+        // `deny_symlink_escape` is set.
+        let path = id.vpath().resolve(root).ok_or(FileError::AccessDenied)?;
+
+        if let Some(allowed) = &self.sandbox {
+            // Resolve symlinks, so that they can't be used to escape.
+            let canonical = path.canonicalize().map_err(|err| FileError::from_io(err, &path))?;
+            let permitted = if id.package().is_some() {
+                root.canonicalize().is_ok_and(|root| canonical.starts_with(root))
+            } else {
+                allowed.iter().any(|allowed| canonical.starts_with(allowed))
+            };
+            if !permitted {
+                return Err(FileError::AccessDenied);
+            }
+        }
+
+        Ok(path)
+    }
+
+    /// Reads a file from a `FileId`.
+    ///
+    /// If the ID represents stdin it will read from standard input. Otherwise it gets the file
+    /// path of the ID and reads the shadowed contents, if any, or the file from disk.
+    #[instrument(level = "debug", skip_all, fields(path = %id.vpath().as_rootless_path().display()))]
+    fn read(&self, id: FileId) -> FileResult<Vec<u8>> {
+        if id == *STDIN_ID {
+            return read_from_stdin();
+        }
+
+        if let Some(data) = self.shadow(id) {
+            return Ok(data.to_vec());
+        }
+
+        if let (None, Some(archive)) = (id.package(), &self.archive) {
+            let path = id.vpath().as_rootless_path();
+            return archive
+                .get(path)
+                .map(|data| data.to_vec())
+                .ok_or_else(|| FileError::NotFound(path.to_path_buf()));
+        }
+
+        let path = self.system_path(id)?;
+        self.check_size(&path)?;
+        read_from_disk(&path)
+    }
+
+    /// Reads a file from a `FileId` the same way as [`read()`](Self::read()), except that package
+    /// files of at least `shared_file_threshold` bytes are shared by all worlds.
+    fn read_bytes(&self, id: FileId) -> FileResult<Bytes> {
+        match self.shared_file_threshold {
+            Some(threshold) if id.package().is_some() => {
+                let path = self.system_path(id)?;
+                self.check_size(&path)?;
+                read_shared(&path, threshold)
+            }
+            _ => self.read(id).map(Bytes::from),
+        }
+    }
+
+    /// Reads a file with `read`, failing if it exceeds a limit of the compilation.
+    fn limited<D: AsRef<[u8]>>(&self, read: impl FnOnce() -> FileResult<D>) -> FileResult<D> {
+        {
+            let mut usage = self.usage.lock();
+            if usage.is_overdue() {
+                return Err(FileError::Other(Some("compilation timed out".into())));
+            }
+            usage.reads += 1;
+            if let Some(max) = self.limits.max_file_reads.filter(|max| usage.reads > *max) {
+                return Err(usage.exceed(eco_format!("read more than {max} files")));
+            }
+            if let Some(max) = self.limits.max_duration.filter(|max| usage.start.elapsed() > *max) {
+                return Err(usage.exceed(eco_format!("took longer than {max:?}")));
+            }
+        }
+
+        let data = read()?;
+        let len = data.as_ref().len() as u64;
+        let mut usage = self.usage.lock();
+        self.admit_size(&mut usage, len)?;
+        usage.bytes += len;
+        if let Some(max) = self.limits.max_memory.filter(|max| usage.bytes > *max) {
+            return Err(usage.exceed(eco_format!("read more than {max} bytes of files")));
+        }
+        Ok(data)
+    }
+
+    /// Checks the size of a file on disk before reading it, so that a file exceeding
+    /// `max_file_size` is not read at all.
+    fn check_size(&self, path: &Path) -> FileResult<()> {
+        match (self.limits.max_file_size, fs::metadata(path)) {
+            (Some(_), Ok(metadata)) => self.admit_size(&mut self.usage.lock(), metadata.len()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if a file of `len` bytes exceeds `max_file_size`.
+    fn admit_size(&self, usage: &mut Usage, len: u64) -> FileResult<()> {
+        match self.limits.max_file_size {
+            Some(max) if len > max => {
+                Err(usage.exceed(eco_format!("read a file larger than {max} bytes")))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Invokes the file access hook, if any.
+    fn notify(&self, id: FileId, bytes: usize, duration: Duration, cache_hit: bool) {
+        if let Some(hook) = &self.hook {
+            hook.call(&FileAccess {
+                package: id.package().map(|spec| spec.to_string()),
+                path: id.vpath().as_rootless_path().to_path_buf(),
+                bytes,
+                duration,
+                cache_hit,
+            });
+        }
+    }
+
+    /// Returns the in-memory contents of a file id, if it is shadowed.
+    fn shadow(&self, id: FileId) -> Option<&Bytes> {
+        if id.package().is_some() {
+            return None;
+        }
+        self.shadows.get(&id.vpath().resolve(&self.root)?)
     }
 }
 
@@ -303,6 +726,90 @@ fn read_from_disk(path: &Path) -> FileResult<Vec<u8>> {
     }
 }
 
+/// Reads a package file from disk, or returns the shared bytes of it if it has at least
+/// `threshold` bytes. A file is read again if it has been modified since it was shared.
+fn read_shared(path: &Path, threshold: u64) -> FileResult<Bytes> {
+    let f = |e| FileError::from_io(e, path);
+    let metadata = fs::metadata(path).map_err(f)?;
+    if metadata.is_dir() {
+        return Err(FileError::IsDirectory);
+    }
+    if metadata.len() < threshold {
+        return fs::read(path).map(Bytes::from).map_err(f);
+    }
+
+    let version = (metadata.modified().map_err(f)?, metadata.len());
+    if let Some(bytes) = SHARED_FILES.lock().get(path, version) {
+        return Ok(bytes);
+    }
+    // The lock is not held while reading, so that other worlds aren't blocked.
+    let bytes = Bytes::from(fs::read(path).map_err(f)?);
+    SHARED_FILES.lock().insert(path.to_path_buf(), version, bytes.clone());
+    Ok(bytes)
+}
+
+/// Files shared by all worlds, keyed by their path, with the modification time and the size they
+/// were read at. The least recently used files are evicted once their total size exceeds the
+/// capacity, and their memory is freed once no world holds them anymore.
+struct SharedFiles {
+    files: HashMap<PathBuf, SharedFile>,
+    /// Total size of the files.
+    size: u64,
+    /// Maximum total size of the files.
+    capacity: u64,
+    /// Incremented on every access, to find the least recently used file.
+    clock: u64,
+}
+
+struct SharedFile {
+    version: (SystemTime, u64),
+    bytes: Bytes,
+    used: u64,
+}
+
+impl SharedFiles {
+    fn new(capacity: u64) -> Self {
+        Self { files: HashMap::new(), size: 0, capacity, clock: 0 }
+    }
+
+    /// Returns the bytes of a file, if it's shared at this version.
+    fn get(&mut self, path: &Path, version: (SystemTime, u64)) -> Option<Bytes> {
+        self.clock += 1;
+        let file = self.files.get_mut(path).filter(|file| file.version == version)?;
+        file.used = self.clock;
+        Some(file.bytes.clone())
+    }
+
+    /// Shares the bytes of a file, replacing any previous version of it, and evicts the least
+    /// recently used files beyond the capacity. A file larger than the capacity is not shared.
+    fn insert(&mut self, path: PathBuf, version: (SystemTime, u64), bytes: Bytes) {
+        let len = bytes.len() as u64;
+        if let Some(previous) = self.files.remove(&path) {
+            self.size -= previous.bytes.len() as u64;
+        }
+        if len > self.capacity {
+            return;
+        }
+        while self.size + len > self.capacity {
+            let Some(oldest) = self
+                .files
+                .iter()
+                .min_by_key(|(_, file)| file.used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+            if let Some(evicted) = self.files.remove(&oldest) {
+                self.size -= evicted.bytes.len() as u64;
+            }
+        }
+        self.clock += 1;
+        self.size += len;
+        self.files
+            .insert(path, SharedFile { version, bytes, used: self.clock });
+    }
+}
+
 /// Read from stdin.
 fn read_from_stdin() -> FileResult<Vec<u8>> {
     let mut buf = Vec::new();
@@ -356,3 +863,35 @@ impl From<WorldCreationError> for EcoString {
         eco_format!("{err}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shared_files_eviction() {
+        let version = (SystemTime::UNIX_EPOCH, 4);
+        let bytes = || Bytes::from(vec![0; 4]);
+        let mut shared = SharedFiles::new(8);
+        shared.insert("a".into(), version, bytes());
+        shared.insert("b".into(), version, bytes());
+        assert!(shared.get(Path::new("a"), version).is_some());
+
+        // The least recently used file is evicted.
+        shared.insert("c".into(), version, bytes());
+        assert!(shared.get(Path::new("b"), version).is_none());
+        assert!(shared.get(Path::new("a"), version).is_some());
+        assert!(shared.get(Path::new("c"), version).is_some());
+        assert_eq!(shared.size, 8);
+
+        // A modified file is not returned, and a replaced one frees its size.
+        assert!(shared.get(Path::new("a"), (SystemTime::UNIX_EPOCH, 5)).is_none());
+        shared.insert("a".into(), (SystemTime::UNIX_EPOCH, 2), Bytes::from(vec![0; 2]));
+        assert_eq!(shared.size, 6);
+
+        // A file larger than the capacity is not shared.
+        shared.insert("d".into(), version, Bytes::from(vec![0; 16]));
+        assert!(shared.get(Path::new("d"), version).is_none());
+        assert_eq!(shared.size, 6);
+    }
+}
@@ -0,0 +1,132 @@
+use std::str::FromStr;
+
+use lopdf::{Dictionary, Document, Object, Stream};
+use xmp_toolkit::{xmp_ns::XMP_MM, ToStringOptions, XmpDateTime, XmpError, XmpMeta, XmpValue};
+
+use crate::TypsterError;
+
+/// Namespace of the `ResourceEvent` structure, i.e. an entry of `xmpMM:History`.
+const ST_EVT: &str = "http://ns.adobe.com/xap/1.0/sType/ResourceEvent#";
+
+/// Fields of the `ResourceEvent` structure.
+#[cfg(feature = "pdf_metadata")]
+const ST_EVT_FIELDS: [&str; 6] =
+    ["action", "changed", "instanceID", "parameters", "softwareAgent", "when"];
+
+/// Reads the XMP metadata of the document catalog, if any.
+pub(crate) fn read_xmp(doc: &Document) -> Option<XmpMeta> {
+    let stream = doc
+        .catalog()
+        .ok()?
+        .get_deref(b"Metadata", doc)
+        .ok()?
+        .as_stream()
+        .ok()?;
+    let content = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    XmpMeta::from_str(std::str::from_utf8(&content).ok()?).ok()
+}
+
+/// Replaces the XMP metadata of the document catalog.
+pub(crate) fn write_xmp(doc: &mut Document, xmp: &XmpMeta) -> Result<(), TypsterError> {
+    let packet = xmp.to_string_with_options(ToStringOptions::default())?;
+    // Metadata streams should not be compressed, so that they can be found by tools which are not
+    // aware of PDF.
+    let stream = Stream::new(
+        Dictionary::from_iter([("Type", "Metadata".into()), ("Subtype", "XML".into())]),
+        packet.into_bytes(),
+    )
+    .with_compression(false);
+
+    let existing = doc.catalog()?.get(b"Metadata").and_then(Object::as_reference);
+    match existing {
+        Ok(id) => {
+            doc.objects.insert(id, Object::Stream(stream));
+        }
+        Err(_) => {
+            let id = doc.add_object(stream);
+            doc.catalog_mut()?.set("Metadata", id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies the `xmpMM:History` entries of `from` to `to`, so that the provenance chain of a document
+/// remains intact when its XMP metadata is rewritten.
+#[cfg(feature = "pdf_metadata")]
+pub(crate) fn copy_history(from: &XmpMeta, to: &mut XmpMeta) -> Result<(), XmpError> {
+    for i in 1..=from.array_len(XMP_MM, "History") {
+        let event = new_history_event(to)?;
+        for field in ST_EVT_FIELDS {
+            let path = format!("History[{i}]/stEvt:{field}");
+            if let Some(value) = from.property(XMP_MM, &path) {
+                to.set_struct_field(XMP_MM, &event, ST_EVT, field, &value)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Appends a `saved` event of typster to `xmpMM:History`.
+///
+/// # Arguments
+///
+/// - `xmp` - XMP metadata to update.
+/// - `changed` - Changed part of the document, e.g. `/metadata`.
+/// - `parameters` - Description of the modification, e.g. `set permissions`.
+pub(crate) fn append_history(
+    xmp: &mut XmpMeta,
+    changed: &str,
+    parameters: &str,
+) -> Result<(), XmpError> {
+    let event = new_history_event(xmp)?;
+    let agent = format!("typster {}", crate::version());
+    for (field, value) in [
+        ("action", "saved"),
+        ("changed", changed),
+        ("parameters", parameters),
+        ("softwareAgent", agent.as_str()),
+    ] {
+        xmp.set_struct_field(XMP_MM, &event, ST_EVT, field, &XmpValue::from(value))?;
+    }
+    xmp.set_property_date(
+        XMP_MM,
+        &format!("{event}/stEvt:when"),
+        &XmpValue::from(XmpDateTime::current()?),
+    )?;
+
+    Ok(())
+}
+
+/// Appends a `saved` event of typster to `xmpMM:History` of a PDF document in memory.
+#[cfg(feature = "pdf_permission")]
+pub(crate) fn append_history_bytes(
+    pdf: &[u8],
+    changed: &str,
+    parameters: &str,
+) -> Result<Vec<u8>, TypsterError> {
+    let mut doc = Document::load_mem(pdf)?;
+    let mut xmp = match read_xmp(&doc) {
+        Some(xmp) => xmp,
+        None => XmpMeta::new()?,
+    };
+    append_history(&mut xmp, changed, parameters)?;
+    write_xmp(&mut doc, &xmp)?;
+
+    let mut buf = Vec::new();
+    doc.save_to(&mut buf)?;
+    Ok(buf)
+}
+
+/// Appends an empty `ResourceEvent` structure to `xmpMM:History`, and returns its path.
+fn new_history_event(xmp: &mut XmpMeta) -> Result<String, XmpError> {
+    xmp.append_array_item(
+        XMP_MM,
+        &XmpValue::from("History").set_is_ordered(true),
+        &XmpValue::from("").set_is_struct(true),
+    )?;
+    Ok(format!("History[{}]", xmp.array_len(XMP_MM, "History")))
+}
@@ -0,0 +1,91 @@
+use std::{fs, path::Path, str::FromStr};
+
+use lopdf::{text_string, Dictionary, Document, Object};
+use xmp_toolkit::{
+    xmp_ns::{DC, PDF, XMP},
+    ToStringOptions, XmpMeta,
+};
+
+use crate::xmp::{read_xmp, write_xmp};
+
+/// Exports the XMP metadata of a PDF file to a standalone `.xmp` sidecar file, e.g. to version it
+/// alongside Typst sources.
+///
+/// # Arguments
+///
+/// - `pdf` - Path to the PDF file.
+/// - `xmp` - Path to the sidecar file to write.
+///
+/// # Example
+///
+/// Following is an example of how to use the `export_xmp` function:
+///
+/// ```no_run
+/// typster::export_xmp(
+///     std::path::Path::new("sample.pdf"),
+///     std::path::Path::new("sample.xmp"),
+/// ).unwrap();
+/// ```
+pub fn export_xmp(pdf: &Path, xmp: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let doc = Document::load(pdf)?;
+    let meta = read_xmp(&doc).ok_or("the PDF file has no XMP metadata")?;
+    fs::write(xmp, meta.to_string_with_options(ToStringOptions::default())?)?;
+    Ok(())
+}
+
+/// Applies a `.xmp` sidecar file onto a PDF file, e.g. to re-apply versioned metadata after every
+/// compilation.
+///
+/// The XMP metadata of the PDF file is replaced with the sidecar. Title, author, subject, keywords,
+/// and creator tool are also copied into the document information dictionary, so that viewers
+/// which only read it show the same values.
+///
+/// # Arguments
+///
+/// - `pdf` - Path to the PDF file, which will be updated in place.
+/// - `xmp` - Path to the sidecar file to apply.
+///
+/// # Example
+///
+/// Following is an example of how to use the `import_xmp` function:
+///
+/// ```no_run
+/// typster::import_xmp(
+///     std::path::Path::new("sample.pdf"),
+///     std::path::Path::new("sample.xmp"),
+/// ).unwrap();
+/// ```
+pub fn import_xmp(pdf: &Path, xmp: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let meta = XmpMeta::from_str(&fs::read_to_string(xmp)?)?;
+
+    let mut doc = Document::load(pdf)?;
+    write_xmp(&mut doc, &meta)?;
+
+    let mut info = doc
+        .trailer
+        .get_deref(b"Info", &doc)
+        .and_then(Object::as_dict)
+        .cloned()
+        .unwrap_or_else(|_| Dictionary::new());
+    let localized = |name: &str| {
+        meta.localized_text(DC, name, None, "x-default")
+            .map(|(value, _)| value.value)
+    };
+    let entries = [
+        ("Title", localized("title")),
+        ("Author", meta.array_item(DC, "creator", 1).map(|value| value.value)),
+        ("Subject", localized("description")),
+        ("Keywords", meta.property(PDF, "Keywords").map(|value| value.value)),
+        ("Creator", meta.property(XMP, "CreatorTool").map(|value| value.value)),
+    ];
+    for (key, value) in entries {
+        if let Some(value) = value {
+            info.set(key, text_string(&value));
+        }
+    }
+    let id = doc.add_object(Object::Dictionary(info));
+    doc.trailer.set("Info", id);
+
+    doc.save(pdf)?;
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use typst::{
+    diag::{At, SourceResult},
+    model::Document,
+};
+use typst_syntax::Span;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{compile::output_template, CompileParams};
+
+/// Default template of the names of the entries of a zip output.
+const DEFAULT_ZIP_ENTRY: &str = "{0p}.png";
+
+/// Export to a zip archive of one PNG or SVG per page, named after
+/// [`CompileParams::zip_entry`].
+pub(crate) fn export_zip(document: &Document, params: &CompileParams) -> SourceResult<()> {
+    let template = params.zip_entry.as_deref().unwrap_or(DEFAULT_ZIP_ENTRY);
+    write_zip(document, params, template)
+        .map_err(|err| format!("failed to write zip archive: {err}"))
+        .at(Span::detached())
+}
+
+fn write_zip(
+    document: &Document,
+    params: &CompileParams,
+    template: &str,
+) -> Result<(), Box<dyn Error>> {
+    let svg = match Path::new(template).extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("png") => false,
+        Some(ext) if ext.eq_ignore_ascii_case("svg") => true,
+        _ => return Err(format!("entry {template} must have the png or svg extension").into()),
+    };
+    if !output_template::has_indexable_template(template) && document.pages.len() > 1 {
+        return Err(format!("cannot name multiple entries without `{{n}}` in {template}").into());
+    }
+
+    // PNGs are already compressed, so they are stored as is. The modification time of the
+    // entries is left unset, so that the archive is reproducible.
+    let options = SimpleFileOptions::default().compression_method(if svg {
+        CompressionMethod::Deflated
+    } else {
+        CompressionMethod::Stored
+    });
+    let ppi = params.ppi.unwrap_or(144.0);
+    let mut zip = ZipWriter::new(BufWriter::new(File::create(&params.output)?));
+    for (i, page) in document.pages.iter().enumerate() {
+        let name = output_template::format(template, i + 1, document.pages.len());
+        zip.start_file(name, options)?;
+        if svg {
+            zip.write_all(typst_svg::svg(page).as_bytes())?;
+        } else {
+            zip.write_all(&typst_render::render(page, ppi / 72.0).encode_png()?)?;
+        }
+    }
+    zip.finish()?.flush()?;
+    Ok(())
+}
@@ -0,0 +1,9 @@
+= Citations
+
+The TeXbook @knuth is a classic, while Typst #cite(<typst>) is new.
+
+#pagebreak()
+
+See @knuth again.
+
+#bibliography("works.yml")
@@ -0,0 +1,17 @@
+#set math.equation(numbering: "(1)")
+
+= Figures
+
+#figure(rect(), caption: [A box]) <box>
+
+#figure(table(columns: 2)[a][b], caption: [A table]) <data>
+
+The equation $x$ is inline, while the following is not.
+
+$ E = m c^2 $ <energy>
+
+#pagebreak()
+
+#counter(figure.where(kind: image)).update(9)
+
+#figure(circle(), caption: [A circle])
@@ -0,0 +1,5 @@
+#let applicant = (name: "Jane (Doe)", agree: true, plan: "Premium")
+
+#metadata(applicant) <form>
+
+Application of #applicant.name for the #applicant.plan plan.
@@ -1,23 +1,103 @@
 use std::{
     collections::HashMap,
-    fs::{read_to_string, remove_file},
+    ffi::{CStr, CString},
+    fs::{copy, create_dir_all, read_to_string, remove_dir_all, remove_file},
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    panic::AssertUnwindSafe,
     path::{Path, PathBuf},
     process::Command,
+    sync::{Arc, Mutex},
+    thread::{spawn, JoinHandle},
+    time::Duration,
 };
 
 use anyhow::{anyhow, Result};
-use sha2_hasher::Sha2Hasher;
+use chrono::DateTime;
+use lopdf::Document;
+use openssl::{
+    cms::CmsContentInfo,
+    pkcs12::Pkcs12,
+    pkcs7::{Pkcs7, Pkcs7Flags},
+    sha::Sha256,
+    stack::Stack,
+    symm::{decrypt, Cipher},
+    x509::X509,
+};
 use test_context::{test_context, TestContext};
 use typster::{
-    compile, format, set_permission, typst_version, update_metadata, CompileParams, FormatParams,
-    PdfMetadata, PermissionParams, PrintPermission,
+    add_links, analyze_refs, assert_compiles_like, assert_compiles_like_with, attach_files,
+    audit_accessibility, compile, compile_archive, compile_in_memory, compile_to_writer,
+    convert_to_pdfa, count, diff, document_info, embed_e_invoice, encrypt_for_recipients,
+    export_xmp, extract_citations, extract_figures, extract_links, extract_notes, extract_outline,
+    extract_pdf_text, fill_form, flatten, format, get_metadata, get_outline, get_permission,
+    import_xmp, impose, index_symbols, linearize, linearize_bytes, list_fonts, merge_pdfs,
+    number_pages, optimize_pdf, parse, pdf_equivalent, preflight, query, query_form_values,
+    read_form, redact, remove_permission, render_pdf, rotate_pages, select_pages, set_outline,
+    set_page_labels, set_permission, set_permission_bytes, set_permission_dir,
+    set_viewer_preferences, sign_pdf, size_breakdown, stamp_pdf, strip_metadata, typst_version,
+    typster_compile, typster_format, typster_set_permission, typster_string_free,
+    typster_update_metadata, update_metadata, update_metadata_bytes, verify_signatures,
+    AccessibilityIssue, Attachment, AttachmentRelationship, CompileParams, CompilerSession,
+    DocumentId, EInvoiceParams, EncryptionLevel, Figure, FileAccessHook, FittingType,
+    FormFieldType, FormatParams, Heading, HyperlinkTarget, Imposition, InMemoryParams, InfoValue,
+    LabelDefinition, LanguageServer, Limits, Link, LinkTarget, NumberingParams, OptimizeParams,
+    OutlineEntry, PageLabel, PageLabelStyle, PageLayout, PageNotes, PageStatus, PdfMetadata,
+    PdfStandard, PdfaLevel, PermissionParams, Pipeline, PreflightCheck, PreflightProfile,
+    PrintPermission, Project, RecipientParams, Redaction, RefFinding, Reference, Sandbox,
+    SigningParams, SourceLocation, StampParams, Strip, Symbol, SymbolKind, TimingSession,
+    Tolerance, Trapped, TypsterError, TypsterStatus, ViewerPreferences, XmpNamespace,
 };
 
 struct TypsterTestContext {
     export_pdf: (PathBuf, CompileParams),
     export_png: (PathBuf, CompileParams),
+    shadow_files: (PathBuf, CompileParams),
+    embed_source: (PathBuf, CompileParams),
+    sandbox: (PathBuf, CompileParams),
+    file_access: (PathBuf, CompileParams),
+    limits: (PathBuf, CompileParams),
+    timeout: (PathBuf, CompileParams),
+    extract_outline: CompileParams,
+    count: CompileParams,
+    extract_links: CompileParams,
+    extract_figures: CompileParams,
+    analyze_refs: CompileParams,
+    extract_citations: CompileParams,
+    document_info: CompileParams,
+    pipeline: (PathBuf, CompileParams),
     update_metadata: (PathBuf, CompileParams),
+    update_metadata_bytes: (PathBuf, CompileParams),
+    strip_metadata: (PathBuf, CompileParams),
+    xmp_sidecar: (PathBuf, CompileParams),
+    outline: (PathBuf, CompileParams),
+    page_labels: (PathBuf, CompileParams),
+    merge_pdfs: (PathBuf, (PathBuf, CompileParams)),
+    select_pages: (PathBuf, (PathBuf, CompileParams)),
+    rotate_pages: (PathBuf, (PathBuf, CompileParams)),
+    optimize_pdf: (PathBuf, (PathBuf, CompileParams)),
+    stamp_pdf: (PathBuf, (PathBuf, CompileParams)),
+    number_pages: (PathBuf, (PathBuf, CompileParams)),
+    impose: (PathBuf, (PathBuf, CompileParams)),
+    fill_form: (PathBuf, (PathBuf, CompileParams)),
+    extract_pdf_text: (PathBuf, CompileParams),
+    preflight: (PathBuf, CompileParams),
+    size_breakdown: (PathBuf, CompileParams),
+    render_pdf: (PathBuf, CompileParams),
+    add_links: (PathBuf, (PathBuf, CompileParams)),
+    redact: (PathBuf, (PathBuf, CompileParams)),
+    attach_files: (PathBuf, CompileParams),
+    viewer_preferences: (PathBuf, CompileParams),
+    e_invoice: (PathBuf, CompileParams),
+    convert_to_pdfa: (PathBuf, (PathBuf, CompileParams)),
+    linearize: (PathBuf, (PathBuf, CompileParams)),
     set_permission: (PathBuf, (PathBuf, CompileParams)),
+    set_permission_bytes: (PathBuf, CompileParams),
+    set_permission_metadata: (PathBuf, (PathBuf, CompileParams)),
+    set_permission_dir: (PathBuf, CompileParams),
+    encrypt_for_recipients: (PathBuf, (PathBuf, CompileParams)),
+    sign_pdf: (PathBuf, (PathBuf, CompileParams)),
+    sign_pdf_timestamp: (PathBuf, (PathBuf, CompileParams)),
     format: (String, FormatParams),
 }
 
@@ -27,19 +107,113 @@ impl TestContext for TypsterTestContext {
         let params = |n| {
             (
                 path(n),
-                CompileParams {
-                    input: path("sample.typ"),
-                    output: path(n),
-                    ..Default::default()
-                },
+                CompileParams::builder()
+                    .input(path("sample.typ"))
+                    .output(path(n))
+                    .build(),
             )
         };
 
         TypsterTestContext {
             export_pdf: params("export_pdf.pdf"),
             export_png: params("export_png.png"),
+            shadow_files: params("shadow_files.pdf"),
+            embed_source: params("embed_source.pdf"),
+            sandbox: params("sandbox.pdf"),
+            file_access: params("file_access.pdf"),
+            limits: params("limits.pdf"),
+            timeout: params("timeout.pdf"),
+            extract_outline: CompileParams::builder()
+                .input(path("report.typ"))
+                .output(path("extract_outline.pdf"))
+                .build(),
+            count: CompileParams::builder()
+                .input(path("report.typ"))
+                .output(path("count.pdf"))
+                .build(),
+            extract_links: CompileParams::builder()
+                .input(path("report.typ"))
+                .output(path("extract_links.pdf"))
+                .build(),
+            extract_figures: CompileParams::builder()
+                .input(path("figures.typ"))
+                .output(path("extract_figures.pdf"))
+                .build(),
+            analyze_refs: CompileParams::builder()
+                .input(path("refs.typ"))
+                .output(path("analyze_refs.pdf"))
+                .build(),
+            extract_citations: CompileParams::builder()
+                .input(path("citations.typ"))
+                .output(path("extract_citations.pdf"))
+                .build(),
+            document_info: CompileParams::builder()
+                .input(path("report.typ"))
+                .output(path("document_info.pdf"))
+                .build(),
+            pipeline: params("pipeline.pdf"),
             update_metadata: params("update_metadata.pdf"),
+            update_metadata_bytes: params("update_metadata_bytes.pdf"),
+            strip_metadata: params("strip_metadata.pdf"),
+            xmp_sidecar: params("xmp_sidecar.pdf"),
+            outline: params("outline.pdf"),
+            page_labels: params("page_labels.pdf"),
+            merge_pdfs: (path("merge_pdfs_merged.pdf"), params("merge_pdfs.pdf")),
+            select_pages: (path("select_pages_selected.pdf"), params("select_pages.pdf")),
+            rotate_pages: (path("rotate_pages_rotated.pdf"), params("rotate_pages.pdf")),
+            optimize_pdf: (path("optimize_pdf_optimized.pdf"), params("optimize_pdf.pdf")),
+            stamp_pdf: (path("stamp_pdf_stamped.pdf"), params("stamp_pdf.pdf")),
+            number_pages: (path("number_pages_numbered.pdf"), params("number_pages.pdf")),
+            impose: (path("impose_imposed.pdf"), params("impose.pdf")),
+            fill_form: (
+                path("fill_form_filled.pdf"),
+                (
+                    path("form.pdf"),
+                    CompileParams::builder()
+                        .input(path("form.typ"))
+                        .output(path("form_values.pdf"))
+                        .build(),
+                ),
+            ),
+            extract_pdf_text: params("extract_pdf_text.pdf"),
+            preflight: (
+                path("preflight.pdf"),
+                CompileParams::builder()
+                    .input(path("report.typ"))
+                    .output(path("preflight.pdf"))
+                    .build(),
+            ),
+            size_breakdown: (
+                path("size_breakdown.pdf"),
+                CompileParams::builder()
+                    .input(path("report.typ"))
+                    .output(path("size_breakdown.pdf"))
+                    .build(),
+            ),
+            render_pdf: params("render_pdf.pdf"),
+            add_links: (path("add_links_linked.pdf"), params("add_links.pdf")),
+            redact: (path("redact_redacted.pdf"), params("redact.pdf")),
+            attach_files: params("attach_files.pdf"),
+            viewer_preferences: params("viewer_preferences.pdf"),
+            e_invoice: params("e_invoice.pdf"),
+            convert_to_pdfa: (path("convert_to_pdfa_converted.pdf"), params("convert_to_pdfa.pdf")),
+            linearize: (path("linearize_linearized.pdf"), params("linearize.pdf")),
             set_permission: (path("set_permission_protected.pdf"), params("set_permission.pdf")),
+            set_permission_bytes: params("set_permission_bytes.pdf"),
+            set_permission_metadata: (
+                path("set_permission_metadata_protected.pdf"),
+                params("set_permission_metadata.pdf"),
+            ),
+            set_permission_dir: params("set_permission_dir.pdf"),
+            encrypt_for_recipients: (
+                path("encrypt_for_recipients_encrypted.pdf"),
+                params("encrypt_for_recipients.pdf"),
+            ),
+            sign_pdf: (path("sign_pdf_signed.pdf"), params("sign_pdf.pdf")),
+            sign_pdf_timestamp: (
+                path("sign_pdf_timestamp_signed.pdf"),
+                params("sign_pdf_timestamp.pdf"),
+            ),
             format: (
                 read_to_string(path("formatted.typ")).unwrap().trim().to_string(),
                 FormatParams { input: path("sample.typ"), column: 80 },
@@ -50,6 +224,13 @@ impl TestContext for TypsterTestContext {
     fn teardown(self) {}
 }
 
+/// Returns the directory of the golden images of the tests.
+fn goldens() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("goldens")
+}
+
 #[test_context(TypsterTestContext)]
 #[test]
 fn test_export_pdf(
@@ -57,7 +238,7 @@ fn test_export_pdf(
 ) -> Result<()> {
     assert!(compile(params).is_ok());
     assert!(out.exists());
-    assert_eq!(out.sha256()?, "846c0af3f2cca66e1f5ea7e17dd2c966a2b5f7fa03b0e1144beeadcda4a4c295");
+    assert_compiles_like(goldens(), params);
 
     remove_file(out)?;
     Ok(())
@@ -70,7 +251,93 @@ fn test_export_png(
 ) -> Result<()> {
     assert!(compile(params).is_ok());
     assert!(out.exists());
-    assert_eq!(out.sha256()?, "c51390dfecbd91d39ed3eef90c54258578219eeca2878ea06ff593faf4abb0e1");
+    assert_compiles_like(goldens(), params);
+
+    // An unchanged page is not rendered again, so the overwritten output is kept.
+    let mut params = params.clone();
+    params.png_page_cache = true;
+    compile(&params)?;
+    let hashes = out.with_file_name("export_png.png.hashes.json");
+    assert!(hashes.exists());
+    std::fs::write(out, b"stale")?;
+    compile(&params)?;
+    assert_eq!(std::fs::read(out)?, b"stale");
+
+    params.ppi = Some(72.0);
+    compile(&params)?;
+    assert_ne!(std::fs::read(out)?, b"stale");
+
+    // A changed page is rendered again.
+    compile(&params)?;
+    std::fs::write(out, b"stale")?;
+    compile(&params)?;
+    assert_eq!(std::fs::read(out)?, b"stale");
+    let text = read_to_string(&params.input)?;
+    params
+        .shadow_files
+        .insert(params.input.clone(), format!("#set page(fill: red)\n{text}"));
+    compile(&params)?;
+    assert_ne!(std::fs::read(out)?, b"stale");
+
+    remove_file(hashes)?;
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test]
+fn test_export_strip() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let out = dir.join("export_strip.png");
+    let mut params = CompileParams::builder()
+        .input(dir.join("report.typ"))
+        .output(&out)
+        .ppi(72.0)
+        .strip(Strip { gap: 10.0, ..Default::default() })
+        .build();
+    compile(&params)?;
+
+    // Both A4 pages are stacked in one image, without a `{n}` template.
+    let png = std::fs::read(&out)?;
+    assert!(png.starts_with(b"\x89PNG"));
+    let dimension =
+        |at: usize| u32::from_be_bytes([png[at], png[at + 1], png[at + 2], png[at + 3]]);
+    let (width, height) = (dimension(16), dimension(20));
+    assert_eq!(width, 595);
+    assert_eq!(height, 842 * 2 + 10);
+    remove_file(&out)?;
+
+    params.output = out.with_extension("webp");
+    compile(&params)?;
+    let webp = std::fs::read(&params.output)?;
+    assert!(webp.starts_with(b"RIFF") && &webp[8..12] == b"WEBP");
+    remove_file(&params.output)?;
+    Ok(())
+}
+
+#[test]
+fn test_export_zip() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let out = dir.join("export_zip.zip");
+    let mut params = CompileParams::builder()
+        .input(dir.join("report.typ"))
+        .output(&out)
+        .ppi(72.0)
+        .build();
+    compile(&params)?;
+
+    // Entry names are stored uncompressed, in the local headers and the central directory.
+    let contains = |zip: &[u8], name: &str| zip.windows(name.len()).any(|w| w == name.as_bytes());
+    let zip = std::fs::read(&out)?;
+    assert!(zip.starts_with(b"PK\x03\x04"));
+    assert!(contains(&zip, "1.png") && contains(&zip, "2.png"));
+
+    params.zip_entry = Some("page-{n}-of-{t}.svg".to_string());
+    compile(&params)?;
+    let zip = std::fs::read(&out)?;
+    assert!(contains(&zip, "page-1-of-2.svg") && contains(&zip, "page-2-of-2.svg"));
+
+    params.zip_entry = Some("page.svg".to_string());
+    assert!(compile(&params).is_err());
 
     remove_file(out)?;
     Ok(())
@@ -78,98 +345,2675 @@ fn test_export_png(
 
 #[test_context(TypsterTestContext)]
 #[test]
-fn test_update_metadata(
-    TypsterTestContext { update_metadata: (out, params), .. }: &TypsterTestContext,
+fn test_shadow_files(
+    TypsterTestContext { shadow_files: (out, params), .. }: &TypsterTestContext,
 ) -> Result<()> {
-    let mut custom_properties = HashMap::new();
-    custom_properties.insert("robots".to_string(), "noindex".to_string());
-    custom_properties.insert("custom".to_string(), "properties".to_string());
+    // Neither the shadowed main file content nor `unsaved.typ` exists on disk.
+    let mut params = params.clone();
+    params
+        .shadow_files
+        .insert(params.input.clone(), "#include \"unsaved.typ\"".to_string());
+    params
+        .shadow_files
+        .insert("unsaved.typ".into(), "= Unsaved".to_string());
 
-    let metadata = PdfMetadata {
-        title: "Title タイトル (typster)".to_string(),
-        author: "Author 著者 (typster)".to_string(),
-        application: "Application アプリケーション (typster)".to_string(),
-        subject: "Subject 題名 (typster)".to_string(),
-        copyright_status: true,
-        copyright_notice: "Copyright notice (typster)".to_string(),
-        keywords: vec!["typster".to_string(), "rust".to_string(), "pdf".to_string()],
-        language: "en".to_string(),
-        custom_properties,
-    };
-
-    assert!(compile(params).is_ok());
+    assert!(compile(&params).is_ok());
     assert!(out.exists());
-    assert!(update_metadata(out, &metadata).is_ok());
-    assert!(out.metadata()?.len() > 0);
 
-    let props = get_properties(out)?;
-    assert_eq!(props.get("Title"), Some(&"Title タイトル (typster)".to_string()));
-    assert_eq!(props.get("Author"), Some(&"Author 著者 (typster)".to_string()));
-    assert_eq!(props.get("Creator"), Some(&"Application アプリケーション (typster)".to_string()));
-    assert_eq!(props.get("Producer"), Some(&"Application アプリケーション (typster)".to_string()));
-    assert_eq!(
-        props.get("Creator Tool"),
-        Some(&"Application アプリケーション (typster)".to_string())
-    );
-    assert_eq!(props.get("Subject"), Some(&"Subject 題名 (typster)".to_string()));
-    assert_eq!(props.get("Marked"), Some(&"True".to_string()));
-    assert_eq!(props.get("Rights"), Some(&"Copyright notice (typster)".to_string()));
-    assert_eq!(props.get("Keywords"), Some(&"typster, rust, pdf".to_string()));
-    assert_eq!(props.get("Language"), Some(&"en".to_string()));
-    assert_eq!(props.get("Robots"), Some(&"noindex".to_string()));
-    assert_eq!(props.get("Custom"), Some(&"properties".to_string()));
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_shadow_files_relative_path(
+    TypsterTestContext { shadow_files: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    // Neither `new.typ` nor `chapters/` exists on disk, so their paths can't be canonicalized.
+    let mut params = params.clone();
+    params.output = out.with_file_name("shadow_files_relative.pdf");
+    params
+        .shadow_files
+        .insert(params.input.clone(), "#include \"new.typ\"\n#include \"old.typ\"".to_string());
+    params.shadow_files.insert("./new.typ".into(), "= New".to_string());
+    params
+        .shadow_files
+        .insert("chapters/../old.typ".into(), "= Old".to_string());
+
+    assert!(compile(&params).is_ok());
+    assert!(params.output.exists());
+
+    remove_file(&params.output)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_embed_source(
+    TypsterTestContext { embed_source: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let mut params = params.clone();
+    params
+        .shadow_files
+        .insert(params.input.clone(), "#include \"chapter.typ\"\n#read(\"data.csv\")".to_string());
+    params
+        .shadow_files
+        .insert("chapter.typ".into(), "= Chapter".to_string());
+    params
+        .shadow_files
+        .insert("data.csv".into(), "name,value".to_string());
+
+    params.embed_source = true;
+    assert!(compile(&params).is_ok());
+    let pdf = String::from_utf8_lossy(&std::fs::read(out)?).to_string();
+    assert!(pdf.contains("(sample.typ)"));
+    assert!(!pdf.contains("(chapter.typ)"));
+
+    params.embed_dependencies = true;
+    assert!(compile(&params).is_ok());
+    let pdf = String::from_utf8_lossy(&std::fs::read(out)?).to_string();
+    assert!(pdf.contains("(sample.typ)"));
+    assert!(pdf.contains("(chapter.typ)"));
+    assert!(pdf.contains("(data.csv)"));
 
     remove_file(out)?;
     Ok(())
 }
 
+#[cfg(unix)]
 #[test_context(TypsterTestContext)]
 #[test]
-fn test_set_permission(
-    TypsterTestContext {
-        set_permission: (out_permission, (out, params)), ..
-    }: &TypsterTestContext,
+fn test_sandbox(
+    TypsterTestContext { sandbox: (out, params), .. }: &TypsterTestContext,
 ) -> Result<()> {
-    assert!(compile(params).is_ok());
-    assert!(set_permission(
-        out.clone(),
-        out_permission.clone(),
-        &PermissionParams {
-            owner_password: Some("owner".to_string()),
-            allow_print: PrintPermission::None,
-            ..Default::default()
-        },
-    )
-    .is_ok());
-    assert!(out_permission.exists());
-    assert!(out_permission.metadata()?.len() > 0);
+    let link = out.with_file_name("sandbox_link.toml");
+    let _ = remove_file(&link);
+    std::os::unix::fs::symlink(Path::new("..").join("Cargo.toml"), &link)?;
 
-    let props = get_properties(out_permission)?;
-    assert!(props.get("Encryption").is_some());
-    assert_eq!(props.get("User Access"), Some(&"Copy, Annotate, Extract".to_string()));
+    let mut params = params.clone();
+    params
+        .shadow_files
+        .insert(params.input.clone(), "#read(\"sandbox_link.toml\")".to_string());
+    assert!(compile(&params).is_ok());
 
+    params.deny_symlink_escape = true;
+    assert!(compile(&params).is_err());
+
+    params.deny_symlink_escape = false;
+    params.sandbox = Some(Sandbox::default());
+    assert!(compile(&params).is_err());
+
+    params.sandbox = Some(Sandbox { allowlist: vec!["../Cargo.toml".into()] });
+    assert!(compile(&params).is_ok());
+
+    remove_file(link)?;
     remove_file(out)?;
-    remove_file(out_permission)?;
     Ok(())
 }
 
 #[test_context(TypsterTestContext)]
 #[test]
-fn test_format(
-    TypsterTestContext { format: (expected, params), .. }: &TypsterTestContext,
+fn test_file_access(
+    TypsterTestContext { file_access: (out, params), .. }: &TypsterTestContext,
 ) -> Result<()> {
-    assert_eq!(*expected, format(params).map_err(|e| anyhow!(e.to_string()))?.trim());
+    let accessed = Arc::new(Mutex::new(vec![]));
+    let mut params = params.clone();
+    params.on_file_access = Some(FileAccessHook::new({
+        let accessed = Arc::clone(&accessed);
+        move |access| accessed.lock().unwrap().push(access.clone())
+    }));
+
+    assert!(compile(&params).is_ok());
+    let accessed = accessed.lock().unwrap();
+    assert!(accessed
+        .iter()
+        .any(|access| access.path == Path::new("sample.typ") && access.bytes > 0));
 
+    remove_file(out)?;
     Ok(())
 }
 
+#[test_context(TypsterTestContext)]
 #[test]
-fn test_typst_version() -> Result<()> {
-    assert_eq!(typst_version(), "0.12.0");
+fn test_limits(
+    TypsterTestContext { limits: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let mut params = params.clone();
+    params.limits = Limits { max_file_reads: Some(0), ..Default::default() };
+    assert!(matches!(compile(&params), Err(TypsterError::LimitExceeded(_))));
+
+    params.limits = Limits { max_file_size: Some(1), ..Default::default() };
+    assert!(matches!(compile(&params), Err(TypsterError::LimitExceeded(_))));
+
+    params.limits = Limits { max_memory: Some(1), ..Default::default() };
+    assert!(matches!(compile(&params), Err(TypsterError::LimitExceeded(_))));
+
+    params.limits = Limits {
+        max_duration: Some(Duration::ZERO),
+        ..Default::default()
+    };
+    assert!(matches!(compile(&params), Err(TypsterError::LimitExceeded(_))));
+    assert!(!out.exists());
+
+    params.limits = Limits {
+        max_memory: Some(1024 * 1024),
+        max_duration: Some(Duration::from_secs(60)),
+        max_file_reads: Some(100),
+        max_file_size: Some(1024 * 1024),
+    };
+    compile(&params)?;
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_timeout(
+    TypsterTestContext { timeout: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let mut params = params.clone();
+    params.timeout = Some(Duration::ZERO);
+    assert!(matches!(compile(&params), Err(TypsterError::TimedOut(_))));
+
+    params.timeout = Some(Duration::from_secs(60));
+    compile(&params)?;
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test]
+fn test_typster_error() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+
+    let params = CompileParams::builder().input(dir.join("missing.typ")).build();
+    assert!(matches!(compile(&params), Err(TypsterError::World(_))));
+
+    // A missing font directory is skipped, like the fonts of a missing system directory.
+    let params = CompileParams::builder()
+        .input(dir.join("sample.typ"))
+        .output(dir.join("typster_error.pdf"))
+        .font_path(dir.join("missing"))
+        .build();
+    assert!(compile(&params).is_ok());
+    remove_file(&params.output)?;
 
+    let invalid = dir.join("typster_error.typ");
+    std::fs::write(&invalid, "#let x = ")?;
+    let params = CompileParams::builder().input(&invalid).build();
+    let result = compile(&params);
+    remove_file(&invalid)?;
+    let Err(TypsterError::Diagnostics(diagnostics)) = &result else {
+        return Err(anyhow!("expected diagnostics, got {result:?}"));
+    };
+    assert_eq!(diagnostics.errors().len(), 1);
+    assert!(diagnostics.to_string().starts_with("Error: "));
+
+    let result = update_metadata(&dir.join("stamp.typ"), &PdfMetadata::default());
+    assert!(matches!(result, Err(TypsterError::Pdf(_))));
+
+    let pdf = dir.join("form.pdf");
+    let result = set_permission(pdf.clone(), pdf, &PermissionParams::default());
+    assert!(matches!(result, Err(TypsterError::Params(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_ffi() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let out = dir.join("ffi.pdf");
+    let json = |value: serde_json::Value| CString::new(value.to_string()).unwrap();
+    let path = |path: &Path| CString::new(path.to_str().unwrap()).unwrap();
+    let mut error = std::ptr::null_mut();
+
+    let params = json(serde_json::json!({
+        "input": dir.join("sample.typ"),
+        "output": out,
+        "dict": [["input", "value"]],
+    }));
+    let status = unsafe { typster_compile(params.as_ptr(), &mut error) };
+    assert_eq!(status, TypsterStatus::Ok);
+    assert!(error.is_null());
+
+    let metadata = json(serde_json::json!({ "title": "Title (ffi)" }));
+    let status =
+        unsafe { typster_update_metadata(path(&out).as_ptr(), metadata.as_ptr(), &mut error) };
+    assert_eq!(status, TypsterStatus::Ok);
+    let read = get_metadata(&out).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(read.title.default_text(), "Title (ffi)");
+
+    let permission = json(serde_json::json!({ "owner_password": "owner", "allow_extract": false }));
+    let status = unsafe {
+        typster_set_permission(
+            path(&out).as_ptr(),
+            path(&out).as_ptr(),
+            permission.as_ptr(),
+            &mut error,
+        )
+    };
+    assert_eq!(status, TypsterStatus::Params);
+    assert!(!error.is_null());
+    let message = unsafe { CStr::from_ptr(error) }.to_str()?.to_string();
+    unsafe { typster_string_free(error) };
+    assert!(message.contains("in-place"));
+
+    let params = json(serde_json::json!({ "input": dir.join("formatted.typ"), "column": 120 }));
+    let mut output = std::ptr::null_mut();
+    let status = unsafe { typster_format(params.as_ptr(), &mut output, &mut error) };
+    assert_eq!(status, TypsterStatus::Ok);
+    assert!(!unsafe { CStr::from_ptr(output) }.to_bytes().is_empty());
+    unsafe { typster_string_free(output) };
+
+    let status = unsafe { typster_compile(c"{".as_ptr(), std::ptr::null_mut()) };
+    assert_eq!(status, TypsterStatus::InvalidArgument);
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test]
+fn test_compile_in_memory() -> Result<()> {
+    let mut params = InMemoryParams {
+        files: [
+            ("main.typ".into(), b"#include \"chapters/intro.typ\"\n#sys.inputs.name".to_vec()),
+            ("chapters/intro.typ".into(), b"= Introduction\nWritten by".to_vec()),
+        ]
+        .into(),
+        main: "main.typ".into(),
+        dict: vec![("name".to_string(), "typster".to_string())],
+        ..Default::default()
+    };
+    let pdf = compile_in_memory(&params).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(pdf.starts_with(b"%PDF"));
+
+    params
+        .files
+        .insert("main.typ".into(), b"#import \"@preview/example:0.1.0\"".to_vec());
+    assert!(matches!(compile_in_memory(&params), Err(TypsterError::Diagnostics(_))));
+
+    params.main = "missing.typ".into();
+    assert!(matches!(compile_in_memory(&params), Err(TypsterError::World(_))));
+
+    Ok(())
+}
+
+/// Packs files into a zip archive in memory.
+fn zip_archive(files: &[(&str, &str)]) -> Result<Vec<u8>> {
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec![]));
+    for (name, text) in files {
+        zip.start_file(*name, zip::write::SimpleFileOptions::default())?;
+        zip.write_all(text.as_bytes())?;
+    }
+    Ok(zip.finish()?.into_inner())
+}
+
+/// Packs files into a tar archive in memory. Names are written as is, even with `..`, which
+/// `tar::Builder` would refuse.
+fn tar_archive(files: &[(&str, &str)]) -> Result<Vec<u8>> {
+    let mut tar = tar::Builder::new(vec![]);
+    for (name, text) in files {
+        let mut header = tar::Header::new_old();
+        header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+        header.set_size(text.len() as u64);
+        header.set_mode(0o644);
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_cksum();
+        tar.append(&header, text.as_bytes())?;
+    }
+    Ok(tar.into_inner()?)
+}
+
+#[test]
+fn test_compile_archive() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let files =
+        [("main.typ", "#include \"chapters/intro.typ\""), ("chapters/intro.typ", "= Intro")];
+    let tar = tar_archive(&files)?;
+    let mut gz = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+    gz.write_all(&tar)?;
+    let archives = [
+        ("archive_zip", zip_archive(&files)?),
+        ("archive_tar", tar),
+        ("archive_tar_gz", gz.finish()?),
+    ];
+
+    for (name, bytes) in archives {
+        let output = dir.join(format!("{name}.pdf"));
+        let params = CompileParams::builder().output(&output).build();
+        compile_archive(bytes.as_slice(), "main.typ", &params)
+            .map_err(|e| anyhow!(e.to_string()))?;
+        assert!(std::fs::read(&output)?.starts_with(b"%PDF"));
+
+        // The format is detected from the contents, not from the extension.
+        let path = dir.join(name);
+        std::fs::write(&path, &bytes)?;
+        compile_archive(path.as_path(), "main.typ", &params).map_err(|e| anyhow!(e.to_string()))?;
+
+        remove_file(path)?;
+        remove_file(output)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_compile_archive_missing_entry() -> Result<()> {
+    let output = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("archive_missing.pdf");
+    let params = CompileParams::builder().output(&output).build();
+    let archive = zip_archive(&[("chapters/intro.typ", "= Intro")])?;
+
+    assert!(compile_archive(archive, "main.typ", &params).is_err());
+    assert!(!output.exists());
+    Ok(())
+}
+
+#[test]
+fn test_compile_archive_zip_slip() -> Result<()> {
+    let output = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("archive_zip_slip.pdf");
+    let params = CompileParams::builder().output(&output).build();
+
+    for pack in [zip_archive, tar_archive] {
+        // The entry escaping the root is skipped, rather than unpacked as `main.typ`.
+        let archive = pack(&[("../main.typ", "= Escaped")])?;
+        assert!(compile_archive(archive, "main.typ", &params).is_err());
+
+        // Other entries are still read.
+        let archive = pack(&[("main.typ", "= Main"), ("../evil.typ", "= Evil")])?;
+        compile_archive(archive, "main.typ", &params).map_err(|e| anyhow!(e.to_string()))?;
+        assert!(output.exists());
+        remove_file(&output)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn test_compile_archive_limits() -> Result<()> {
+    let output = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("archive_limits.pdf");
+    let large = "a".repeat(64 * 1024);
+    let files = [("main.typ", "= Main"), ("data.txt", large.as_str())];
+
+    for pack in [zip_archive, tar_archive] {
+        let archive = pack(&files)?;
+        for limits in [
+            Limits { max_file_size: Some(1024), ..Default::default() },
+            Limits { max_memory: Some(1024), ..Default::default() },
+        ] {
+            let mut params = CompileParams::builder().output(&output).build();
+            params.limits = limits;
+            let error = compile_archive(archive.as_slice(), "main.typ", &params).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<TypsterError>(),
+                Some(TypsterError::LimitExceeded(_))
+            ));
+            assert!(!output.exists());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_timing_session() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let params = CompileParams::builder()
+        .input(dir.join("report.typ"))
+        .output(dir.join("timing-session.pdf"))
+        .build();
+    let trace = dir.join("timing-session.json");
+
+    let mut session = TimingSession::start();
+    session.compile(&params)?;
+    session.compile(&params)?;
+    session.finish(&trace)?;
+
+    let events: Vec<serde_json::Value> = serde_json::from_str(&read_to_string(&trace)?)?;
+    assert!(!events.is_empty());
+    assert!(events.iter().all(|event| event["name"].is_string()));
+
+    remove_file(params.output)?;
+    remove_file(trace)?;
     Ok(())
 }
 
+#[test]
+fn test_list_fonts() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets").join("fonts");
+    let embedded = list_fonts(&[]);
+    let fonts = list_fonts(&[dir]);
+
+    // Fonts in nested directories are found in addition to the embedded ones.
+    for family in ["source code pro", "noto sans jp"] {
+        assert!(fonts[family].len() > embedded.get(family).map_or(0, Vec::len));
+    }
+    assert!(embedded.keys().all(|family| fonts.contains_key(family)));
+
+    Ok(())
+}
+
+#[test]
+fn test_compile_to_writer() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let params = CompileParams::builder()
+        .input(dir.join("report.typ"))
+        .output(dir.join("compile-to-writer.png"))
+        .build();
+
+    let mut pdf = vec![];
+    let report = compile_to_writer(&params, &mut pdf)?;
+    assert!(pdf.starts_with(b"%PDF"));
+    assert_eq!(report.pages, 2);
+    assert_eq!(report.buffered_bytes, pdf.len());
+    assert!(!params.output.exists());
+
+    let mut params = params;
+    params.embed_source = true;
+    let mut embedded = vec![];
+    let report = compile_to_writer(&params, &mut embedded)?;
+    assert!(embedded.len() > report.buffered_bytes);
+
+    let params = CompileParams::builder().input(dir.join("missing.typ")).build();
+    assert!(matches!(compile_to_writer(&params, vec![]), Err(TypsterError::World(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_shared_file_threshold() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/shared-files");
+    let package = dir.join("packages/local/data/0.1.0");
+    std::fs::create_dir_all(&package)?;
+    std::fs::write(
+        package.join("typst.toml"),
+        "[package]\nname = \"data\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n",
+    )?;
+    std::fs::write(package.join("lib.typ"), r#"#let size = read("data.txt").len()"#)?;
+    let data = package.join("data.txt");
+    std::fs::write(&data, "a".repeat(4096))?;
+    let input = dir.join("main.typ");
+    std::fs::write(&input, "#import \"@local/data:0.1.0\": size\n#metadata(size) <size>")?;
+    let params = CompileParams::builder()
+        .input(&input)
+        .package_path(dir.join("packages"))
+        .shared_file_threshold(1024)
+        .build();
+
+    // Both compilations share the same bytes.
+    let size = || query(&params, "size", Some("value")).map_err(|e| anyhow!(e.to_string()));
+    assert_eq!(size()?, vec![serde_json::json!(4096)]);
+    assert_eq!(size()?, vec![serde_json::json!(4096)]);
+
+    // A modified file is read again, and a small one is not shared.
+    std::fs::write(&data, "a".repeat(8192))?;
+    assert_eq!(size()?, vec![serde_json::json!(8192)]);
+    std::fs::write(&data, "a".repeat(16))?;
+    assert_eq!(size()?, vec![serde_json::json!(16)]);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_compiler_session() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let input = dir.join("session.typ");
+    let output = dir.join("session.pdf");
+    std::fs::write(&input, "= First")?;
+    let params = CompileParams::builder().input(&input).output(&output).build();
+
+    let mut session = CompilerSession::new(&params)?;
+    assert_eq!(session.params().input, input);
+    session.compile()?;
+    let first = std::fs::read(&output)?;
+
+    // Changed files are read again.
+    std::fs::write(&input, "= Second")?;
+    session.compile()?;
+    let second = std::fs::read(&output)?;
+    assert_ne!(first, second);
+
+    std::fs::write(&input, "#let x = ")?;
+    assert!(matches!(session.compile(), Err(TypsterError::Diagnostics(_))));
+
+    remove_file(input)?;
+    remove_file(output)?;
+    Ok(())
+}
+
+#[test]
+fn test_query() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let params = CompileParams::builder()
+        .input(dir.join("report.typ"))
+        .output(dir.join("query.pdf"))
+        .build();
+
+    let levels = query(&params, "results", Some("level")).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(levels, vec![serde_json::json!(1)]);
+    let elements = query(&params, "results", None).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(elements.len(), 1);
+    assert_eq!(elements[0]["func"], "heading");
+    assert!(query(&params, "missing", None)
+        .map_err(|e| anyhow!(e.to_string()))?
+        .is_empty());
+    assert!(!params.output.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_diff() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let before = CompileParams::builder()
+        .input(dir.join("report.typ"))
+        .output(dir.join("diff.pdf"))
+        .ppi(18.0)
+        .build();
+
+    let pages = diff(&before, &before)?;
+    assert!(pages
+        .iter()
+        .all(|page| page.status == PageStatus::Unchanged && page.image.is_none()));
+
+    let text = read_to_string(&before.input)?;
+    let mut after = before.clone();
+    after
+        .shadow_files
+        .insert(before.input.clone(), format!("#set page(fill: red)\n{text}\n#pagebreak()\nAdded"));
+    let pages = diff(&before, &after)?;
+    assert_eq!(pages[0].status, PageStatus::Changed);
+    assert!(pages[0].changed_ratio() > 0.5);
+    assert!(pages[0]
+        .image
+        .as_ref()
+        .is_some_and(|image| image.starts_with(b"\x89PNG")));
+    assert_eq!(pages.last().map(|page| page.status), Some(PageStatus::Added));
+    assert!(!before.output.exists());
+
+    // A document with errors fails with its diagnostics.
+    after
+        .shadow_files
+        .insert(before.input.clone(), "#let x = ".to_string());
+    assert!(matches!(diff(&before, &after), Err(TypsterError::Diagnostics(_))));
+
+    Ok(())
+}
+
+#[test]
+fn test_assert_compiles_like() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let goldens = dir.join("assert_compiles_like");
+    let _ = remove_dir_all(&goldens);
+    let params = CompileParams::builder()
+        .input(dir.join("report.typ"))
+        .output(dir.join("assert_compiles_like.pdf"))
+        .ppi(18.0)
+        .build();
+
+    // A missing golden is a failure, and the rendering is written next to it for review.
+    let result =
+        std::panic::catch_unwind(AssertUnwindSafe(|| assert_compiles_like(&goldens, &params)));
+    assert!(result.is_err());
+    assert!(goldens.join("report-1.actual.png").exists());
+    assert!(!goldens.join("report-1.png").exists());
+
+    // The goldens written by the PNG export at the same resolution match.
+    let mut export = params.clone();
+    export.output = goldens.join("report-{p}.png");
+    compile(&export)?;
+    assert_compiles_like(&goldens, &params);
+    assert!(!goldens.join("report-1.actual.png").exists());
+
+    let mut changed = params.clone();
+    let text = read_to_string(&params.input)?;
+    changed
+        .shadow_files
+        .insert(params.input.clone(), format!("#set page(fill: red)\n{text}"));
+    let result =
+        std::panic::catch_unwind(AssertUnwindSafe(|| assert_compiles_like(&goldens, &changed)));
+    assert!(result.is_err());
+    assert!(goldens.join("report-1.diff.png").exists());
+    assert!(goldens.join("report-1.actual.png").exists());
+
+    assert_compiles_like_with(&goldens, &changed, Tolerance { channel: 255, pixels: 0.0 });
+    assert!(!params.output.exists());
+
+    remove_dir_all(goldens)?;
+    Ok(())
+}
+
+#[test]
+fn test_cli() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let out = dir.join("cli.pdf");
+    let protected = dir.join("cli-protected.pdf");
+    let typster = |args: &[&str]| -> Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_typster")).args(args).output()?;
+        if !output.status.success() {
+            return Err(anyhow!(String::from_utf8(output.stderr)?));
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    };
+    let input = dir.join("report.typ");
+    let input = input.to_str().unwrap_or_default();
+
+    typster(&["compile", input, out.to_str().unwrap_or_default()])?;
+    assert!(out.exists());
+
+    typster(&["meta", out.to_str().unwrap_or_default(), "--title", "CLI", "--author", "typster"])?;
+    let metadata: serde_json::Value =
+        serde_json::from_str(&typster(&["meta", out.to_str().unwrap_or_default()])?)?;
+    assert_eq!(metadata["author"], "typster");
+
+    // Other fields are kept.
+    typster(&["meta", out.to_str().unwrap_or_default(), "--subject", "Report"])?;
+    let updated: serde_json::Value =
+        serde_json::from_str(&typster(&["meta", out.to_str().unwrap_or_default()])?)?;
+    assert_eq!(updated["author"], "typster");
+    assert_eq!(updated["title"], metadata["title"]);
+    assert_eq!(updated["copyright_status"], metadata["copyright_status"]);
+    assert_eq!(updated["copyright_notice"], metadata["copyright_notice"]);
+
+    typster(&[
+        "protect",
+        out.to_str().unwrap_or_default(),
+        protected.to_str().unwrap_or_default(),
+        "--owner-password",
+        "owner",
+        "--allow-print",
+        "low",
+    ])?;
+    assert!(protected.exists());
+
+    let levels: serde_json::Value =
+        serde_json::from_str(&typster(&["query", input, "results", "--field", "level"])?)?;
+    assert_eq!(levels, serde_json::json!([1]));
+
+    assert!(!typster(&["format", input])?.is_empty());
+    assert!(!typster(&["fonts"])?.is_empty());
+    assert!(typster(&["compile", dir.join("missing.typ").to_str().unwrap_or_default()]).is_err());
+
+    remove_file(out)?;
+    remove_file(protected)?;
+    Ok(())
+}
+
+#[test]
+fn test_language_server() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("lsp");
+    create_dir_all(&dir)?;
+    let main = dir.join("main.typ");
+    std::fs::write(&main, "#let greet(name) = [Hi #name]\n#greet(\"you\")\n#missing\n")?;
+    let uri = format!("file://{}", main.display());
+
+    let message = |id: Option<u32>, method: &str, params: serde_json::Value| {
+        let mut message =
+            serde_json::json!({ "jsonrpc": "2.0", "method": method, "params": params });
+        if let Some(id) = id {
+            message["id"] = id.into();
+        }
+        let body = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{body}", body.len())
+    };
+    let document = serde_json::json!({ "textDocument": { "uri": uri } });
+    let input = [
+        message(
+            Some(1),
+            "initialize",
+            serde_json::json!({ "rootUri": format!("file://{}", dir.display()) }),
+        ),
+        message(None, "initialized", serde_json::json!({})),
+        message(
+            None,
+            "textDocument/didOpen",
+            serde_json::json!({ "textDocument": { "uri": uri, "text": "#let   x=1" } }),
+        ),
+        message(Some(2), "textDocument/formatting", document.clone()),
+        message(Some(3), "textDocument/documentSymbol", document.clone()),
+        message(Some(4), "shutdown", serde_json::Value::Null),
+        message(None, "exit", serde_json::Value::Null),
+    ]
+    .concat();
+
+    let mut output = vec![];
+    LanguageServer::new().serve_connection(input.as_bytes(), &mut output)?;
+    let output = String::from_utf8(output)?;
+    let messages = output
+        .split("Content-Length: ")
+        .filter(|message| !message.is_empty())
+        .map(|message| serde_json::from_str(message.split_once("\r\n\r\n").unwrap_or_default().1))
+        .collect::<Result<Vec<serde_json::Value>, _>>()?;
+
+    assert_eq!(messages.len(), 5);
+    assert_eq!(messages[0]["result"]["capabilities"]["documentFormattingProvider"], true);
+    // The file on disk is compiled, not the open buffer.
+    assert_eq!(messages[1]["method"], "textDocument/publishDiagnostics");
+    let diagnostics = &messages[1]["params"]["diagnostics"];
+    assert_eq!(diagnostics[0]["severity"], 1);
+    assert_eq!(diagnostics[0]["range"]["start"]["line"], 2);
+    assert_eq!(messages[2]["result"][0]["newText"].as_str().map(str::trim_end), Some("#let x = 1"));
+    assert_eq!(messages[3]["result"][0]["name"], "greet");
+    assert_eq!(messages[3]["result"][0]["kind"], 12);
+    assert_eq!(messages[4]["result"], serde_json::Value::Null);
+
+    remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_server() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let out = dir.join("server.pdf");
+    let request = |id: u32, method: &str, params: serde_json::Value| {
+        serde_json::json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+            .to_string()
+    };
+    let requests = [
+        request(
+            1,
+            "compile",
+            serde_json::json!({ "input": dir.join("report.typ"), "output": out }),
+        ),
+        // The second compilation reuses the world of the first one.
+        request(
+            2,
+            "compile",
+            serde_json::json!({ "input": dir.join("report.typ"), "output": out }),
+        ),
+        request(
+            3,
+            "query",
+            serde_json::json!({ "input": dir.join("report.typ"), "label": "results", "field": "level" }),
+        ),
+        request(4, "format", serde_json::json!({ "input": dir.join("formatted.typ"), "column": 120 })),
+        request(5, "compile", serde_json::json!({ "input": dir.join("missing.typ") })),
+        request(6, "unknown", serde_json::Value::Null),
+        "not json".to_string(),
+    ]
+    .join("\n");
+
+    let mut output = vec![];
+    Server::new().serve_connection(requests.as_bytes(), &mut output)?;
+    let responses = String::from_utf8(output)?
+        .lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(responses.len(), 7);
+    assert!(responses[0]["result"]["duration_ms"].is_u64());
+    assert!(responses[1]["result"]["duration_ms"].is_u64());
+    assert_eq!(responses[2]["result"], serde_json::json!([1]));
+    assert!(!responses[3]["result"]["text"].as_str().unwrap_or_default().is_empty());
+    assert_eq!(responses[4]["error"]["code"], -32001);
+    assert_eq!(responses[5]["error"]["code"], -32601);
+    assert_eq!(responses[6]["error"]["code"], -32700);
+    assert!(out.exists());
+
+    // Errors are streamed as notifications before the response.
+    let invalid = dir.join("server.typ");
+    std::fs::write(&invalid, "#let x = ")?;
+    let requests = request(7, "compile", serde_json::json!({ "input": invalid, "output": out }));
+    let mut output = vec![];
+    Server::new().serve_connection(requests.as_bytes(), &mut output)?;
+    remove_file(&invalid)?;
+    let responses = String::from_utf8(output)?
+        .lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+    let notification = &responses[0]["params"];
+    assert_eq!(responses[0]["method"], "diagnostic");
+    assert_eq!(notification["id"], 7);
+    assert_eq!(notification["diagnostic"]["severity"], "error");
+    assert_eq!(notification["diagnostic"]["location"]["path"], "server.typ");
+    assert_eq!(responses.last().unwrap()["error"]["code"], -32002);
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test]
+fn test_server_shadow_files() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let input = dir.join("server_shadow.typ");
+    std::fs::write(&input, "#metadata(\"disk\") <value>")?;
+    let query = |id: u32, shadow_files: serde_json::Value| {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "query",
+            "params": {
+                "input": input,
+                "label": "value",
+                "field": "value",
+                "shadow_files": shadow_files,
+            },
+        })
+        .to_string()
+    };
+    // The same world serves every request, with the unsaved buffer of each one.
+    let requests = [
+        query(1, serde_json::json!({ "server_shadow.typ": "#metadata(\"first\") <value>" })),
+        query(2, serde_json::json!({ "server_shadow.typ": "#metadata(\"second\") <value>" })),
+        query(3, serde_json::json!({})),
+    ]
+    .join("\n");
+
+    let mut output = vec![];
+    Server::new().serve_connection(requests.as_bytes(), &mut output)?;
+    remove_file(&input)?;
+    let responses = String::from_utf8(output)?
+        .lines()
+        .map(serde_json::from_str::<serde_json::Value>)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0]["result"], serde_json::json!(["first"]));
+    assert_eq!(responses[1]["result"], serde_json::json!(["second"]));
+    assert_eq!(responses[2]["result"], serde_json::json!(["disk"]));
+    Ok(())
+}
+
+#[test]
+fn test_compile_params_from_file() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+
+    let params =
+        CompileParams::from_file(&dir.join("compile.toml")).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(params.input, dir.join("sample.typ"));
+    assert_eq!(params.output, dir.join("from_file.pdf"));
+    assert_eq!(params.font_paths, vec![dir.join("../assets/fonts")]);
+    assert_eq!(params.dict, vec![("input".to_string(), "value".to_string())]);
+    assert!(compile(&params).is_ok());
+    assert!(params.output.exists());
+    remove_file(&params.output)?;
+
+    let json = dir.join("from_file.json");
+    std::fs::write(&json, r#"{ "input": "sample.typ", "ppi": 72.0 }"#)?;
+    let params = CompileParams::from_file(&json).map_err(|e| anyhow!(e.to_string()));
+    remove_file(&json)?;
+    let params = params?;
+    assert_eq!(params.input, dir.join("sample.typ"));
+    assert_eq!(params.ppi, Some(72.0));
+    assert_eq!(params.output, PathBuf::new());
+
+    assert!(CompileParams::from_file(&dir.join("sample.typ")).is_err());
+
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_extract_outline(
+    TypsterTestContext { extract_outline: params, .. }: &TypsterTestContext,
+) -> Result<()> {
+    let headings = extract_outline(params).map_err(|e| anyhow!(e.to_string()))?;
+    let heading = |level, text: &str, page, label: Option<&str>| Heading {
+        level,
+        text: text.to_string(),
+        page,
+        label: label.map(str::to_string),
+    };
+    assert_eq!(
+        headings,
+        vec![
+            heading(1, "Introduction", 1, Some("intro")),
+            heading(2, "Background", 1, None),
+            heading(1, "Results", 2, Some("results")),
+        ]
+    );
+    assert!(!params.output.exists());
+
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_count(TypsterTestContext { count: params, .. }: &TypsterTestContext) -> Result<()> {
+    let stats = count(params).map_err(|e| anyhow!(e.to_string()))?;
+    let headings = stats
+        .sections
+        .iter()
+        .map(|section| section.heading.as_ref().map(|heading| heading.text.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(headings, vec![Some("Introduction"), Some("Background"), Some("Results")]);
+    assert_eq!(stats.cjk_characters, 5);
+    assert_eq!(stats.sections[2].cjk_characters, 5);
+    assert_eq!(stats.words, stats.sections.iter().map(|section| section.words).sum::<usize>());
+    assert!(stats.words > stats.cjk_characters);
+    assert!(stats.characters > stats.words);
+    assert!(!params.output.exists());
+
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_extract_links(
+    TypsterTestContext { extract_links: params, .. }: &TypsterTestContext,
+) -> Result<()> {
+    let links = extract_links(params).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0].page, 1);
+    assert_eq!(links[0].text, "Section 2");
+    assert_eq!(
+        links[0].target,
+        HyperlinkTarget::Internal { page: 2, label: Some("results".to_string()) }
+    );
+    assert_eq!(links[1].page, 1);
+    assert_eq!(links[1].text, "the Typst website");
+    assert_eq!(links[1].target, HyperlinkTarget::Url("https://typst.app".to_string()));
+    assert!(!params.output.exists());
+
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_extract_figures(
+    TypsterTestContext { extract_figures: params, .. }: &TypsterTestContext,
+) -> Result<()> {
+    let figures = extract_figures(params).map_err(|e| anyhow!(e.to_string()))?;
+    let figure =
+        |kind: &str, number: &str, caption: Option<&str>, label: Option<&str>, page| Figure {
+            kind: kind.to_string(),
+            number: Some(number.to_string()),
+            caption: caption.map(str::to_string),
+            label: label.map(str::to_string),
+            page,
+        };
+    assert_eq!(
+        figures,
+        vec![
+            figure("image", "1", Some("A box"), Some("box"), 1),
+            figure("table", "1", Some("A table"), Some("data"), 1),
+            figure("equation", "(1)", None, Some("energy"), 1),
+            figure("image", "10", Some("A circle"), None, 2),
+        ]
+    );
+    assert!(!params.output.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_extract_notes() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let params = CompileParams::builder()
+        .input(dir.join("slides.typ"))
+        .output(dir.join("extract_notes.pdf"))
+        .build();
+    let notes = extract_notes(&params, "notes")?;
+    let page = |page, text: &str| PageNotes { page, text: text.to_string() };
+    assert_eq!(
+        notes,
+        vec![
+            page(1, "Greet the audience"),
+            page(2, ""),
+            page(3, "Mention the growth\n\nCompare with last year\nTake questions"),
+        ]
+    );
+    assert!(!params.output.exists());
+
+    let missing = CompileParams::builder().input(dir.join("missing.typ")).build();
+    assert!(matches!(extract_notes(&missing, "notes"), Err(TypsterError::World(_))));
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_analyze_refs(
+    TypsterTestContext { analyze_refs: params, .. }: &TypsterTestContext,
+) -> Result<()> {
+    let report = analyze_refs(params).map_err(|e| anyhow!(e.to_string()))?;
+    let location = |line, column| SourceLocation { path: PathBuf::from("refs.typ"), line, column };
+    let label = |name: &str, line, column| LabelDefinition {
+        name: name.to_string(),
+        location: location(line, column),
+    };
+    let reference = |target: &str, line, column| Reference {
+        target: target.to_string(),
+        location: location(line, column),
+    };
+    assert_eq!(
+        report.labels,
+        vec![
+            label("intro", 4, 16),
+            label("methods", 8, 11),
+            label("box", 10, 35),
+            label("box", 12, 41),
+            label("note", 14, 17),
+        ]
+    );
+    assert_eq!(report.references, vec![reference("intro", 6, 5), reference("missing", 6, 16)]);
+    assert_eq!(
+        report.findings,
+        vec![
+            RefFinding::UnresolvedReference(reference("missing", 6, 16)),
+            RefFinding::UnusedLabel(label("methods", 8, 11)),
+            RefFinding::UnusedLabel(label("box", 10, 35)),
+            RefFinding::DuplicateLabel(label("box", 12, 41)),
+            RefFinding::UnusedLabel(label("box", 12, 41)),
+        ]
+    );
+    assert!(!params.output.exists());
+
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_extract_citations(
+    TypsterTestContext { extract_citations: params, .. }: &TypsterTestContext,
+) -> Result<()> {
+    let works = extract_citations(params).map_err(|e| anyhow!(e.to_string()))?;
+    let keys = works.iter().map(|work| work.key.as_str()).collect::<Vec<_>>();
+    assert_eq!(keys, vec!["knuth", "typst"]);
+
+    assert_eq!(works[0].pages, vec![1, 2]);
+    assert_eq!(works[0].entry_type, "book");
+    assert_eq!(works[0].title.as_deref(), Some("The TeXbook"));
+    assert_eq!(works[0].authors, vec!["Donald E. Knuth"]);
+    assert_eq!(works[0].year, Some(1984));
+
+    assert_eq!(works[1].pages, vec![1]);
+    assert_eq!(works[1].authors, vec!["Laurenz Mädje"]);
+    assert_eq!(works[1].url.as_deref(), Some("https://typst.app/"));
+    assert!(!params.output.exists());
+
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_update_metadata(
+    TypsterTestContext { update_metadata: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let mut custom_properties = HashMap::new();
+    custom_properties.insert("robots".to_string(), "noindex".to_string());
+    custom_properties.insert("custom".to_string(), "properties".to_string());
+
+    let metadata = PdfMetadata {
+        title: "Title タイトル (typster)".into(),
+        author: "Author 著者 (typster)".to_string(),
+        application: "Application アプリケーション (typster)".to_string(),
+        subject: "Subject 題名 (typster)".into(),
+        copyright_status: true,
+        copyright_notice: "Copyright notice (typster)".to_string(),
+        keywords: vec!["typster".to_string(), "rust".to_string(), "pdf".to_string()],
+        // Typst writes `en` by default.
+        language: "ja".to_string(),
+        custom_properties,
+        custom_namespaces: vec![XmpNamespace {
+            uri: "http://example.com/ns/records/1.0/".to_string(),
+            prefix: "records".to_string(),
+            properties: [("RetentionPeriod".to_string(), "10y".to_string())].into(),
+        }],
+        creation_date: Some(DateTime::parse_from_rfc3339("2024-01-02T03:04:05+09:00")?),
+        publisher: "Publisher (typster)".to_string(),
+        contributors: vec!["Editor (typster)".to_string()],
+        date: Some(DateTime::parse_from_rfc3339("2024-02-03T04:05:06+09:00")?),
+        identifier: "doi:10.1234/typster".to_string(),
+        document_type: "Text".to_string(),
+        trapped: Some(Trapped::True),
+        info_entries: [("Revision".to_string(), InfoValue::Integer(3))].into(),
+        record_history: true,
+        ..Default::default()
+    };
+
+    assert!(compile(params).is_ok());
+    assert!(out.exists());
+    assert!(update_metadata(out, &metadata).is_ok());
+    assert!(out.metadata()?.len() > 0);
+
+    let props = get_properties(out)?;
+    assert_eq!(props.get("Title"), Some(&"Title タイトル (typster)".to_string()));
+    assert_eq!(props.get("Author"), Some(&"Author 著者 (typster)".to_string()));
+    assert_eq!(props.get("Creator"), Some(&"Application アプリケーション (typster)".to_string()));
+    assert_eq!(props.get("Producer"), Some(&"Application アプリケーション (typster)".to_string()));
+    assert_eq!(
+        props.get("Creator Tool"),
+        Some(&"Application アプリケーション (typster)".to_string())
+    );
+    // exiftool lists the XMP `dc:subject` array, i.e. the keywords, after the PDF Subject under the
+    // same name.
+    assert_eq!(props.get("Subject"), Some(&"typster, rust, pdf".to_string()));
+    assert_eq!(props.get("Description"), Some(&"Subject 題名 (typster)".to_string()));
+    assert_eq!(props.get("Marked"), Some(&"True".to_string()));
+    assert_eq!(props.get("Rights"), Some(&"Copyright notice (typster)".to_string()));
+    assert_eq!(props.get("Keywords"), Some(&"typster, rust, pdf".to_string()));
+    assert_eq!(props.get("Language"), Some(&"ja".to_string()));
+    assert_eq!(props.get("Robots"), Some(&"noindex".to_string()));
+    assert_eq!(props.get("Custom"), Some(&"properties".to_string()));
+    assert_eq!(props.get("Retention Period"), Some(&"10y".to_string()));
+    assert_eq!(props.get("Publisher"), Some(&"Publisher (typster)".to_string()));
+    assert_eq!(props.get("Contributor"), Some(&"Editor (typster)".to_string()));
+    assert_eq!(props.get("Trapped"), Some(&"True".to_string()));
+    assert_eq!(props.get("Revision"), Some(&"3".to_string()));
+    assert_eq!(props.get("History Action"), Some(&"saved".to_string()));
+    assert_eq!(
+        props.get("History Software Agent"),
+        Some(&format!("typster {}", typster::version()))
+    );
+
+    let read = get_metadata(out).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(read.title, metadata.title);
+    assert_eq!(read.author, metadata.author);
+    assert_eq!(read.application, metadata.application);
+    assert_eq!(read.subject, metadata.subject);
+    assert_eq!(read.copyright_status, metadata.copyright_status);
+    assert_eq!(read.copyright_notice, metadata.copyright_notice);
+    assert_eq!(read.keywords, metadata.keywords);
+    assert_eq!(read.language, metadata.language);
+    assert_eq!(read.custom_properties, metadata.custom_properties);
+    assert_eq!(read.creation_date, metadata.creation_date);
+    assert_eq!(read.publisher, metadata.publisher);
+    assert_eq!(read.contributors, metadata.contributors);
+    assert_eq!(read.date, metadata.date);
+    assert_eq!(read.identifier, metadata.identifier);
+    assert_eq!(read.document_type, metadata.document_type);
+    assert_eq!(read.trapped, metadata.trapped);
+    assert_eq!(read.info_entries, metadata.info_entries);
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_update_metadata_bytes(
+    TypsterTestContext { update_metadata_bytes: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let metadata = PdfMetadata {
+        title: "Title (typster)".into(),
+        document_id: DocumentId::Set {
+            permanent: b"typster-permanent".to_vec(),
+            changing: b"typster-changing".to_vec(),
+        },
+        ..Default::default()
+    };
+
+    assert!(compile(params).is_ok());
+    let pdf = std::fs::read(out)?;
+    let updated = update_metadata_bytes(&pdf, &metadata).map_err(|e| anyhow!(e.to_string()))?;
+    std::fs::write(out, &updated)?;
+
+    let read = get_metadata(out).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(read.title, metadata.title);
+
+    let hex = |id: &[u8]| id.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    let updated = String::from_utf8_lossy(&updated).to_lowercase();
+    assert!(updated.contains(&hex(b"typster-permanent")));
+    assert!(updated.contains(&hex(b"typster-changing")));
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_strip_metadata(
+    TypsterTestContext { strip_metadata: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    assert!(!get_metadata(out)
+        .map_err(|e| anyhow!(e.to_string()))?
+        .title
+        .is_empty());
+
+    assert!(strip_metadata(out).is_ok());
+    let read = get_metadata(out).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(read.title.is_empty());
+    assert!(read.author.is_empty());
+    assert!(read.keywords.is_empty());
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_xmp_sidecar(
+    TypsterTestContext { xmp_sidecar: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let sidecar = out.with_extension("xmp");
+    let metadata = PdfMetadata {
+        title: "Title (typster)".into(),
+        author: "Author (typster)".to_string(),
+        keywords: vec!["typster".to_string(), "rust".to_string()],
+        ..Default::default()
+    };
+
+    assert!(compile(params).is_ok());
+    assert!(update_metadata(out, &metadata).is_ok());
+    assert!(export_xmp(out, &sidecar).is_ok());
+    assert!(read_to_string(&sidecar)?.contains("Title (typster)"));
+
+    // Recompiling drops the metadata, and importing the sidecar brings it back.
+    assert!(compile(params).is_ok());
+    assert!(import_xmp(out, &sidecar).is_ok());
+    let read = get_metadata(out).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(read.title, metadata.title);
+    assert_eq!(read.author, metadata.author);
+    assert_eq!(read.keywords, metadata.keywords);
+
+    remove_file(sidecar)?;
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_outline(
+    TypsterTestContext { outline: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let outline = vec![OutlineEntry {
+        title: "Chapter 章 (typster)".to_string(),
+        page: 1,
+        children: vec![OutlineEntry {
+            title: "Section (typster)".to_string(),
+            page: 1,
+            ..Default::default()
+        }],
+    }];
+
+    assert!(compile(params).is_ok());
+    assert!(set_outline(out, outline.clone()).is_ok());
+    assert_eq!(get_outline(out).map_err(|e| anyhow!(e.to_string()))?, outline);
+
+    let missing = vec![OutlineEntry { page: 100, ..Default::default() }];
+    assert!(set_outline(out, missing).is_err());
+
+    assert!(set_outline(out, vec![]).is_ok());
+    assert!(get_outline(out).map_err(|e| anyhow!(e.to_string()))?.is_empty());
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_page_labels(
+    TypsterTestContext { page_labels: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+
+    let labels = vec![PageLabel {
+        style: PageLabelStyle::UpperRoman,
+        prefix: "A-".to_string(),
+        ..Default::default()
+    }];
+    assert!(set_page_labels(out, labels).is_ok());
+    assert!(String::from_utf8_lossy(&std::fs::read(out)?).contains("/PageLabels"));
+
+    let missing = vec![PageLabel { start_page: 100, ..Default::default() }];
+    assert!(set_page_labels(out, missing).is_err());
+
+    let not_first = vec![PageLabel { start_page: 2, ..Default::default() }];
+    assert!(set_page_labels(out, not_first).is_err());
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_merge_pdfs(
+    TypsterTestContext { merge_pdfs: (out_merged, (out, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let annex = out.with_file_name("merge_pdfs_annex.pdf");
+    assert!(compile(params).is_ok());
+    std::fs::copy(out, &annex)?;
+    assert!(set_outline(
+        out,
+        vec![OutlineEntry {
+            title: "Report".to_string(),
+            page: 1,
+            ..Default::default()
+        }]
+    )
+    .is_ok());
+    assert!(set_outline(
+        &annex,
+        vec![OutlineEntry {
+            title: "Annex".to_string(),
+            page: 1,
+            ..Default::default()
+        }]
+    )
+    .is_ok());
+    assert!(set_page_labels(
+        &annex,
+        vec![PageLabel { prefix: "A-".to_string(), ..Default::default() }]
+    )
+    .is_ok());
+
+    assert!(merge_pdfs(&[out.clone(), annex.clone()], out_merged).is_ok());
+    assert!(merge_pdfs(&[], out_merged).is_err());
+
+    let outline = get_outline(out_merged).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(outline.len(), 2);
+    assert_eq!(outline[0].title, "Report");
+    assert_eq!(outline[0].page, 1);
+    assert_eq!(outline[1].title, "Annex");
+    // The annex starts right after the pages of the report.
+    assert!(outline[1].page > 1);
+    let pdf = String::from_utf8_lossy(&std::fs::read(out_merged)?).to_string();
+    assert!(pdf.contains("/PageLabels"));
+    assert!(pdf.contains("(A-)"));
+
+    remove_file(annex)?;
+    remove_file(out)?;
+    remove_file(out_merged)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_select_pages(
+    TypsterTestContext { select_pages: (out_selected, (out, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    assert!(set_outline(
+        out,
+        vec![OutlineEntry {
+            title: "Cover".to_string(),
+            page: 1,
+            ..Default::default()
+        }]
+    )
+    .is_ok());
+
+    // Repeat the first page.
+    assert!(select_pages(out, &[1, 1], out_selected).is_ok());
+    assert_eq!(count_pages(out_selected)?, 2);
+    let outline = get_outline(out_selected).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(outline.len(), 1);
+    assert_eq!(outline[0].page, 1);
+
+    // Drop the first page, in place.
+    assert!(select_pages(out_selected, &[2], out_selected).is_ok());
+    assert_eq!(count_pages(out_selected)?, 1);
+    assert!(get_outline(out_selected)
+        .map_err(|e| anyhow!(e.to_string()))?
+        .is_empty());
+
+    assert!(select_pages(out, &[], out_selected).is_err());
+    assert!(select_pages(out, &[0], out_selected).is_err());
+    assert!(select_pages(out, &[100], out_selected).is_err());
+
+    remove_file(out)?;
+    remove_file(out_selected)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_rotate_pages(
+    TypsterTestContext { rotate_pages: (out_rotated, (out, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+
+    assert!(rotate_pages(out, 90, &[], out_rotated).is_ok());
+    let pdf = String::from_utf8_lossy(&std::fs::read(out_rotated)?).to_string();
+    assert!(pdf.contains("/Rotate 90"));
+
+    // Rotations add up, in place.
+    assert!(rotate_pages(out_rotated, -180, &[1], out_rotated).is_ok());
+    let pdf = String::from_utf8_lossy(&std::fs::read(out_rotated)?).to_string();
+    assert!(pdf.contains("/Rotate 270"));
+
+    assert!(rotate_pages(out, 45, &[], out_rotated).is_err());
+    assert!(rotate_pages(out, 90, &[0], out_rotated).is_err());
+    assert!(rotate_pages(out, 90, &[100], out_rotated).is_err());
+
+    remove_file(out)?;
+    remove_file(out_rotated)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_optimize_pdf(
+    TypsterTestContext { optimize_pdf: (out_optimized, (out, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+
+    let report = optimize_pdf(out, out_optimized, &OptimizeParams::default())
+        .map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(report.input_size, std::fs::metadata(out)?.len());
+    assert_eq!(report.output_size, std::fs::metadata(out_optimized)?.len());
+    assert!(report.output_size <= report.input_size);
+    assert!(report.saved() >= 0);
+    assert_eq!(report.downsampled_images, 0);
+    assert_eq!(
+        get_outline(out_optimized).map_err(|e| anyhow!(e.to_string()))?.len(),
+        get_outline(out).map_err(|e| anyhow!(e.to_string()))?.len()
+    );
+
+    let invalid = OptimizeParams { jpeg_quality: 0, ..Default::default() };
+    assert!(optimize_pdf(out, out_optimized, &invalid).is_err());
+
+    remove_file(out)?;
+    remove_file(out_optimized)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_stamp_pdf(
+    TypsterTestContext { stamp_pdf: (out_stamped, (out, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let stamp_params = StampParams {
+        template: out.with_file_name("stamp.typ"),
+        dict: vec![("text".to_string(), "CONFIDENTIAL".to_string())],
+        ..Default::default()
+    };
+
+    assert!(compile(params).is_ok());
+    assert!(stamp_pdf(out, out_stamped, &stamp_params).is_ok());
+    assert_eq!(count_pages(out_stamped)?, count_pages(out)?);
+    let pdf = String::from_utf8_lossy(&std::fs::read(out_stamped)?).to_string();
+    assert!(pdf.contains("/TypsterStamp1 Do"));
+
+    // The template requires `text` in `sys.inputs`.
+    let missing_input = StampParams { dict: vec![], ..stamp_params.clone() };
+    assert!(stamp_pdf(out, out_stamped, &missing_input).is_err());
+    let missing_template = StampParams {
+        template: out.with_file_name("missing.typ"),
+        ..stamp_params
+    };
+    assert!(stamp_pdf(out, out_stamped, &missing_template).is_err());
+
+    remove_file(out)?;
+    remove_file(out_stamped)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_number_pages(
+    TypsterTestContext { number_pages: (out_numbered, (out, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    let pages = count_pages(out)? as u64;
+
+    let numbering_params = NumberingParams {
+        prefix: "ACME".to_string(),
+        start: 42,
+        ..Default::default()
+    };
+    let next =
+        number_pages(out, out_numbered, &numbering_params).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(next, 42 + pages);
+    let pdf = String::from_utf8_lossy(&std::fs::read(out_numbered)?).to_string();
+    assert!(pdf.contains("(ACME000042) Tj"));
+    assert_eq!(count_pages(out_numbered)?, pages as usize);
+
+    let next = number_pages(out, out_numbered, &NumberingParams::page_numbers())
+        .map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(next, 1 + pages);
+    let pdf = String::from_utf8_lossy(&std::fs::read(out_numbered)?).to_string();
+    assert!(pdf.contains("(1) Tj"));
+
+    let non_ascii = NumberingParams {
+        prefix: "株式会社".to_string(),
+        ..Default::default()
+    };
+    assert!(number_pages(out, out_numbered, &non_ascii).is_err());
+
+    remove_file(out)?;
+    remove_file(out_numbered)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_impose(
+    TypsterTestContext { impose: (out_imposed, (out, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    let pages = count_pages(out)?;
+
+    assert!(impose(out, out_imposed, Imposition::Booklet).is_ok());
+    assert_eq!(count_pages(out_imposed)?, pages.div_ceil(4) * 2);
+    assert!(impose(out, out_imposed, Imposition::NUp(2)).is_ok());
+    assert_eq!(count_pages(out_imposed)?, pages.div_ceil(2));
+    assert!(impose(out, out_imposed, Imposition::NUp(4)).is_ok());
+    assert_eq!(count_pages(out_imposed)?, pages.div_ceil(4));
+
+    assert!(impose(out, out_imposed, Imposition::NUp(3)).is_err());
+
+    remove_file(out)?;
+    remove_file(out_imposed)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_fill_form(
+    TypsterTestContext { fill_form: (out, (form, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let values = query_form_values(params, "form").map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(values["name"], "Jane (Doe)");
+    assert_eq!(values["agree"], "true");
+    let fields = read_form(form).map_err(|e| anyhow!(e.to_string()))?;
+    let names = fields.iter().map(|field| field.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["name", "agree", "plan"]);
+    assert_eq!(fields[1].field_type, FormFieldType::CheckBox);
+    assert_eq!(fields[1].value, "Off");
+    assert_eq!(fields[2].field_type, FormFieldType::RadioButton);
+    assert_eq!(fields[2].options, ["Basic", "Premium"]);
+
+    fill_form(form, out, &values).map_err(|e| anyhow!(e.to_string()))?;
+
+    let pdf = String::from_utf8_lossy(&std::fs::read(out)?).to_string();
+    assert!(pdf.contains("/V(Jane (Doe))"));
+    assert!(pdf.contains(r"(Jane \(Doe\)) Tj"));
+    assert!(pdf.contains("/V/Yes/AS/Yes"));
+    assert!(pdf.contains("/V/Premium"));
+    assert!(pdf.contains("/AS/Premium"));
+    let fields = read_form(out).map_err(|e| anyhow!(e.to_string()))?;
+    let values = fields.iter().map(|field| field.value.as_str()).collect::<Vec<_>>();
+    assert_eq!(values, ["Jane (Doe)", "Yes", "Premium"]);
+
+    flatten(out, out).map_err(|e| anyhow!(e.to_string()))?;
+    let pdf = String::from_utf8_lossy(&std::fs::read(out)?).to_string();
+    assert!(!pdf.contains("/AcroForm"));
+    assert!(!pdf.contains("/Widget"));
+    assert!(pdf.contains(r"(Jane \(Doe\)) Tj"));
+    assert!(read_form(out).map_err(|e| anyhow!(e.to_string()))?.is_empty());
+
+    let unknown = HashMap::from([("nmae".to_string(), "Jane Doe".to_string())]);
+    assert!(fill_form(form, out, &unknown).is_err());
+    let invalid = HashMap::from([("plan".to_string(), "Gold".to_string())]);
+    assert!(fill_form(form, out, &invalid).is_err());
+    assert!(query_form_values(params, "missing").is_err());
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_extract_pdf_text(
+    TypsterTestContext { extract_pdf_text: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    let pages = count_pages(out)?;
+    let text = extract_pdf_text(out, &[]).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(text.len(), pages);
+    assert!(text.iter().any(|page| !page.trim().is_empty()));
+
+    let text = extract_pdf_text(out, &[2, 1, 2]).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(text.len(), 2);
+    assert!(extract_pdf_text(out, &[0]).is_err());
+    assert!(extract_pdf_text(out, &[pages as u32 + 1]).is_err());
+
+    let form = out.with_file_name("form.pdf");
+    let text = extract_pdf_text(&form, &[1]).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(text[0].contains("Name:"));
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test]
+fn test_pdf_equivalent() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let (a, b) = (dir.join("pdf_equivalent_a.pdf"), dir.join("pdf_equivalent_b.pdf"));
+    let params = CompileParams::builder()
+        .input(dir.join("report.typ"))
+        .output(&a)
+        .build();
+    compile(&params)?;
+    let mut other = params.clone();
+    other.output = b.clone();
+    compile(&other)?;
+    assert!(pdf_equivalent(&a, &b).map_err(|e| anyhow!(e.to_string()))?);
+
+    let text = read_to_string(&params.input)?;
+    other
+        .shadow_files
+        .insert(params.input.clone(), text.replace("Typster works", "Typster worked"));
+    compile(&other)?;
+    assert!(!pdf_equivalent(&a, &b).map_err(|e| anyhow!(e.to_string()))?);
+
+    remove_file(a)?;
+    remove_file(b)?;
+    Ok(())
+}
+
+#[test]
+fn test_audit_accessibility() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let out = dir.join("audit_accessibility.pdf");
+    let params = CompileParams::builder()
+        .input(dir.join("report.typ"))
+        .output(&out)
+        .build();
+    compile(&params)?;
+
+    let findings = audit_accessibility(&out).map_err(|e| anyhow!(e.to_string()))?;
+    let issues = findings.iter().map(|finding| finding.issue).collect::<Vec<_>>();
+    assert_eq!(issues, [AccessibilityIssue::MissingLanguage, AccessibilityIssue::NotTagged]);
+    assert!(findings.iter().all(|finding| finding.page.is_none()));
+
+    let mut other = params.clone();
+    let text = read_to_string(&params.input)?;
+    other
+        .shadow_files
+        .insert(params.input.clone(), format!("#set text(lang: \"en\")\n{text}"));
+    compile(&other)?;
+    let findings = audit_accessibility(&out).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(findings
+        .iter()
+        .all(|finding| finding.issue != AccessibilityIssue::MissingLanguage));
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_preflight(
+    TypsterTestContext { preflight: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    compile(params)?;
+    let profile = PreflightProfile { allow_transparency: false, ..Default::default() };
+    assert!(preflight(out, &profile)?.is_empty());
+
+    let mut params = params.clone();
+    let text = read_to_string(&params.input)?;
+    params
+        .shadow_files
+        .insert(params.input.clone(), format!("{text}\n#page(width: 10cm)[Narrow]"));
+    compile(&params)?;
+    let findings = preflight(out, &profile)?;
+    assert_eq!(findings.len(), 1);
+    assert_eq!(findings[0].check, PreflightCheck::PageBoxes);
+    assert_eq!(findings[0].page, 3);
+
+    // Not a PDF file
+    assert!(matches!(preflight(&params.input, &profile), Err(TypsterError::Pdf(_))));
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_size_breakdown(
+    TypsterTestContext { size_breakdown: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    compile(params)?;
+
+    let report = size_breakdown(out)?;
+    assert_eq!(report.file_bytes, std::fs::metadata(out)?.len());
+    assert!(!report.fonts.is_empty());
+    assert!(report
+        .fonts
+        .iter()
+        .all(|font| font.bytes > 0 && !font.name.contains('+')));
+    assert!(report.fonts.windows(2).all(|fonts| fonts[0].bytes >= fonts[1].bytes));
+    assert!(report.images.is_empty());
+    assert!(report.content_bytes > 0);
+    let fonts = report.fonts.iter().map(|font| font.bytes).sum::<u64>();
+    assert_eq!(fonts + report.content_bytes + report.other_bytes, report.file_bytes);
+
+    // Missing file
+    let missing = out.with_file_name("size_breakdown_missing.pdf");
+    assert!(matches!(size_breakdown(&missing), Err(TypsterError::Io(_))));
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_render_pdf(
+    TypsterTestContext { render_pdf: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    let pages = count_pages(out)?;
+    let images = render_pdf(out, &[], 72.0).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(images.len(), pages);
+    assert!(images.iter().all(|image| image.starts_with(b"\x89PNG")));
+
+    let images = render_pdf(out, &[1, 1], 144.0).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(images.len(), 1);
+    assert!(render_pdf(out, &[pages as u32 + 1], 72.0).is_err());
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_add_links(
+    TypsterTestContext { add_links: (out_linked, (out, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    let links = [
+        Link {
+            page: 1,
+            rect: [72.0, 700.0, 200.0, 714.0],
+            target: LinkTarget::Uri("https://example.com/dashboard".to_string()),
+        },
+        Link {
+            page: 1,
+            rect: [72.0, 680.0, 200.0, 694.0],
+            target: LinkTarget::Page(2),
+        },
+    ];
+    add_links(out, out_linked, &links).map_err(|e| anyhow!(e.to_string()))?;
+
+    let pdf = String::from_utf8_lossy(&std::fs::read(out_linked)?).to_string();
+    assert!(pdf.contains("/URI(https://example.com/dashboard)"));
+    assert!(pdf.contains("/Fit]"));
+
+    let invalid = Link { target: LinkTarget::Page(0), ..links[1].clone() };
+    assert!(add_links(out, out_linked, &[invalid]).is_err());
+    let invalid = Link {
+        target: LinkTarget::Uri("https://例.jp".to_string()),
+        ..links[0].clone()
+    };
+    assert!(add_links(out, out_linked, &[invalid]).is_err());
+
+    remove_file(out)?;
+    remove_file(out_linked)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_redact(
+    TypsterTestContext { redact: (out_redacted, (out, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    let text = extract_pdf_text(out, &[1, 2]).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(!text[0].trim().is_empty());
+
+    // Redact the whole first page.
+    let redaction = Redaction { page: 1, rects: vec![[0.0, 0.0, 10000.0, 10000.0]] };
+    redact(out, out_redacted, vec![redaction]).map_err(|e| anyhow!(e.to_string()))?;
+    let redacted = extract_pdf_text(out_redacted, &[1, 2]).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(redacted[0].trim().is_empty());
+    assert_eq!(redacted[1], text[1]);
+
+    let invalid = Redaction { page: 0, rects: vec![[0.0, 0.0, 100.0, 100.0]] };
+    assert!(redact(out, out_redacted, vec![invalid]).is_err());
+
+    remove_file(out)?;
+    remove_file(out_redacted)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_attach_files(
+    TypsterTestContext { attach_files: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let attachment = |name: &str| Attachment {
+        name: name.to_string(),
+        bytes: b"name,value\ntypster,1\n".to_vec(),
+        mime: "text/csv".to_string(),
+        relationship: AttachmentRelationship::Data,
+    };
+
+    assert!(compile(params).is_ok());
+    assert!(attach_files(out, vec![attachment("data.csv")]).is_ok());
+    assert!(attach_files(out, vec![attachment("data.csv"), attachment("more.csv")]).is_ok());
+
+    let pdf = String::from_utf8_lossy(&std::fs::read(out)?).to_string();
+    assert!(pdf.contains("/EmbeddedFiles"));
+    assert!(pdf.contains("/AFRelationship"));
+    // The attachment with the same name is replaced, not duplicated.
+    assert_eq!(pdf.matches("(data.csv)").count(), 3);
+    assert_eq!(pdf.matches("(more.csv)").count(), 3);
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_viewer_preferences(
+    TypsterTestContext { viewer_preferences: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let preferences = ViewerPreferences {
+        fitting_type: Some(FittingType::Width),
+        page_layout: Some(PageLayout::TwoPageRight),
+        hide_toolbar: true,
+        ..Default::default()
+    };
+
+    assert!(compile(params).is_ok());
+    assert!(set_viewer_preferences(out, &preferences).is_ok());
+
+    let pdf = String::from_utf8_lossy(&std::fs::read(out)?).to_string();
+    assert!(pdf.contains("/OpenAction"));
+    assert!(pdf.contains("/FitH"));
+    assert!(pdf.contains("/TwoPageRight"));
+    assert!(pdf.contains("/HideToolbar"));
+
+    let missing = ViewerPreferences { open_page: Some(100), ..Default::default() };
+    assert!(set_viewer_preferences(out, &missing).is_err());
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_e_invoice(
+    TypsterTestContext { e_invoice: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let params_e_invoice = EInvoiceParams {
+        xml: b"<?xml version=\"1.0\" encoding=\"UTF-8\"?><rsm:CrossIndustryInvoice/>".to_vec(),
+        ..Default::default()
+    };
+
+    assert!(compile(params).is_ok());
+    assert!(embed_e_invoice(out, &params_e_invoice).is_ok());
+    // Embedding again replaces the invoice and keeps a single schema description.
+    assert!(embed_e_invoice(out, &params_e_invoice).is_ok());
+
+    let props = get_properties(out)?;
+    assert_eq!(props.get("Document File Name"), Some(&"factur-x.xml".to_string()));
+    assert_eq!(props.get("Conformance Level"), Some(&"EN 16931".to_string()));
+    assert_eq!(props.get("Part"), Some(&"3".to_string()));
+
+    let pdf = String::from_utf8_lossy(&std::fs::read(out)?).to_string();
+    assert_eq!(pdf.matches("Factur-X PDFA Extension Schema").count(), 1);
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_convert_to_pdfa(
+    TypsterTestContext {
+        convert_to_pdfa: (out_converted, (out, params)), ..
+    }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    let report =
+        convert_to_pdfa(out, out_converted, PdfaLevel::A2b).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(report.fixed.contains(&"added an sRGB output intent".to_string()));
+
+    let props = get_properties(out_converted)?;
+    assert_eq!(props.get("Part"), Some(&"2".to_string()));
+    assert_eq!(props.get("Conformance"), Some(&"B".to_string()));
+
+    // Converting again has nothing left to fix.
+    let again = convert_to_pdfa(out_converted, out_converted, PdfaLevel::A2b)
+        .map_err(|e| anyhow!(e.to_string()))?;
+    assert!(again.fixed.is_empty());
+    assert_eq!(again.unresolved, report.unresolved);
+
+    remove_file(out)?;
+    remove_file(out_converted)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_linearize(
+    TypsterTestContext { linearize: (out_linearized, (out, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    assert!(linearize(out.clone(), out_linearized.clone()).is_ok());
+    assert!(linearize(out.clone(), out.clone()).is_err());
+    let same = out.parent().unwrap().join(".").join(out.file_name().unwrap());
+    assert!(linearize(out.clone(), same).is_err());
+
+    // The linearization parameter dictionary must be the first object in the file.
+    let pdf = std::fs::read(out_linearized)?;
+    let head = String::from_utf8_lossy(&pdf[..pdf.len().min(1024)]);
+    assert!(head.contains("/Linearized"));
+
+    let pdf = linearize_bytes(&std::fs::read(out)?).map_err(|e| anyhow!(e.to_string()))?;
+    let head = String::from_utf8_lossy(&pdf[..pdf.len().min(1024)]);
+    assert!(head.contains("/Linearized"));
+
+    remove_file(out)?;
+    remove_file(out_linearized)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_set_permission(
+    TypsterTestContext {
+        set_permission: (out_permission, (out, params)), ..
+    }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    assert!(set_permission(
+        out.clone(),
+        out_permission.clone(),
+        &PermissionParams {
+            owner_password: Some("owner".to_string()),
+            allow_print: PrintPermission::None,
+            ..Default::default()
+        },
+    )
+    .is_ok());
+    assert!(out_permission.exists());
+    assert!(out_permission.metadata()?.len() > 0);
+
+    let info = get_permission(out_permission.clone(), None).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(info.encrypted);
+    assert_eq!(info.revision, Some(6));
+    assert_eq!(info.key_length, Some(256));
+    assert!(info.allow_accessibility);
+    assert!(info.allow_extract);
+    assert!(!info.allow_assemble);
+    assert!(info.allow_annotate_and_form);
+    assert!(!info.allow_form_filling);
+    assert!(!info.allow_modify_other);
+    assert!(matches!(info.allow_print, PrintPermission::None));
+
+    assert!(remove_permission(out_permission.clone(), out.clone(), "owner").is_ok());
+    let same = out_permission
+        .parent()
+        .unwrap()
+        .join(".")
+        .join(out_permission.file_name().unwrap());
+    assert!(matches!(
+        remove_permission(out_permission.clone(), same, "owner"),
+        Err(TypsterError::Params(_))
+    ));
+    let info = get_permission(out.clone(), None).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(!info.encrypted);
+    assert_eq!(info.revision, None);
+    assert!(info.allow_assemble);
+    assert!(matches!(info.allow_print, PrintPermission::Full));
+
+    // Low resolution printing, and no copying except for accessibility
+    set_permission(
+        out.clone(),
+        out_permission.clone(),
+        &PermissionParams {
+            owner_password: Some("owner".to_string()),
+            allow_print: PrintPermission::Low,
+            ..PermissionParams::no_copy()
+        },
+    )?;
+    let info = get_permission(out_permission.clone(), None).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(info.allow_accessibility);
+    assert!(!info.allow_extract);
+    assert!(info.allow_form_filling);
+    assert!(matches!(info.allow_print, PrintPermission::Low));
+
+    // In-place update
+    assert!(set_permission(out.clone(), out.clone(), &PermissionParams::default()).is_err());
+    assert!(set_permission(
+        out.clone(),
+        out.clone(),
+        &PermissionParams { allow_in_place: true, ..Default::default() },
+    )
+    .is_ok());
+    let info = get_permission(out.clone(), None).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(info.encrypted);
+
+    remove_file(out)?;
+    remove_file(out_permission)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_set_permission_bytes(
+    TypsterTestContext { set_permission_bytes: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert!(compile(params).is_ok());
+    let pdf = std::fs::read(out)?;
+    let encrypted = set_permission_bytes(
+        &pdf,
+        &PermissionParams {
+            owner_password: Some("owner".to_string()),
+            encryption_level: EncryptionLevel::Aes128,
+            ..PermissionParams::print_only()
+        },
+    )
+    .map_err(|e| anyhow!(e.to_string()))?;
+    std::fs::write(out, encrypted)?;
+
+    let info = get_permission(out.clone(), None).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(info.encrypted);
+    assert_eq!(info.revision, Some(4));
+    assert_eq!(info.key_length, Some(128));
+    assert!(!info.allow_extract);
+    assert!(!info.allow_annotate_and_form);
+    assert!(matches!(info.allow_print, PrintPermission::Full));
+
+    remove_file(out)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_set_permission_metadata(
+    TypsterTestContext {
+        set_permission_metadata: (out_permission, (out, params)),
+        ..
+    }: &TypsterTestContext,
+) -> Result<()> {
+    let (before, after) = (out.with_extension("before.xmp"), out.with_extension("after.xmp"));
+    let metadata = PdfMetadata {
+        title: "Title (typster)".into(),
+        author: "Author (typster)".to_string(),
+        keywords: vec!["typster".to_string(), "rust".to_string()],
+        info_entries: [("Revision".to_string(), InfoValue::Integer(3))].into(),
+        ..Default::default()
+    };
+
+    assert!(compile(params).is_ok());
+    assert!(update_metadata(out, &metadata).is_ok());
+    assert!(export_xmp(out, &before).is_ok());
+
+    // Encrypt and decrypt again, which must carry over the metadata untouched.
+    assert!(set_permission(
+        out.clone(),
+        out_permission.clone(),
+        &PermissionParams {
+            owner_password: Some("owner".to_string()),
+            ..Default::default()
+        },
+    )
+    .is_ok());
+    assert!(remove_permission(out_permission.clone(), out.clone(), "owner").is_ok());
+
+    assert!(export_xmp(out, &after).is_ok());
+    assert_eq!(read_to_string(&before)?, read_to_string(&after)?);
+    let read = get_metadata(out).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(read.title, metadata.title);
+    assert_eq!(read.author, metadata.author);
+    assert_eq!(read.keywords, metadata.keywords);
+    assert_eq!(read.info_entries.get("Revision"), Some(&InfoValue::Integer(3)));
+
+    remove_file(before)?;
+    remove_file(after)?;
+    remove_file(out)?;
+    remove_file(out_permission)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_pipeline(
+    TypsterTestContext { pipeline: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let decrypted = out.with_extension("decrypted.pdf");
+    Pipeline::new(params.clone())
+        .permission(PermissionParams {
+            owner_password: Some("owner".to_string()),
+            ..PermissionParams::print_only()
+        })
+        .metadata(PdfMetadata {
+            title: "Title (typster)".into(),
+            ..Default::default()
+        })
+        .run()
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    let info = get_permission(out.clone(), None).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(info.encrypted);
+    assert!(!info.allow_extract);
+
+    assert!(remove_permission(out.clone(), decrypted.clone(), "owner").is_ok());
+    let read = get_metadata(&decrypted).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(read.title.default_text(), "Title (typster)");
+
+    // A failed step writes nothing.
+    remove_file(out)?;
+    let mut invalid = params.clone();
+    invalid.input = params.input.with_extension("missing.typ");
+    assert!(Pipeline::new(invalid).run().is_err());
+    assert!(!out.exists());
+
+    remove_file(decrypted)?;
+    Ok(())
+}
+
+#[test]
+fn test_project() -> Result<()> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("project");
+    let project = Project::load(&dir).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(project.targets.len(), 2);
+    let report = &project.targets[0];
+    assert_eq!(report.compile.input, dir.join("../report.typ"));
+    assert_eq!(report.compile.output, dir.join("out/report.pdf"));
+    assert_eq!(report.compile.font_paths, vec![dir.join("../../assets/fonts")]);
+    assert_eq!(report.compile.dict, vec![("input".to_string(), "value".to_string())]);
+    assert_eq!(report.compile.pdf_standards, vec![PdfStandard::A2b]);
+    assert!(report.permission.is_none());
+
+    let (report, sample) = (dir.join("out/report.pdf"), dir.join("out/sample.pdf"));
+    let outputs = project.build().map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(outputs, vec![report.clone(), sample.clone()]);
+    let read = get_metadata(&report).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(read.title.default_text(), "Report (typster)");
+    let info = get_permission(sample, None).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(info.encrypted);
+    assert!(!info.allow_extract);
+
+    assert!(Project::load(&dir.join("missing")).is_err());
+    remove_dir_all(dir.join("out"))?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_set_permission_dir(
+    TypsterTestContext { set_permission_dir: (out, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let (input_dir, output_dir) = (out.with_extension("in"), out.with_extension("out"));
+    assert!(compile(params).is_ok());
+    create_dir_all(&input_dir)?;
+    copy(out, input_dir.join("a.pdf"))?;
+    copy(out, input_dir.join("b.pdf"))?;
+    std::fs::write(input_dir.join("broken.pdf"), "not a PDF")?;
+    std::fs::write(input_dir.join("ignored.txt"), "not a PDF")?;
+
+    let reports = set_permission_dir(
+        input_dir.to_str().unwrap(),
+        &output_dir,
+        &PermissionParams {
+            owner_password: Some("owner".to_string()),
+            ..Default::default()
+        },
+    )
+    .map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(reports.len(), 3);
+    assert!(reports[0].error.is_none());
+    assert!(reports[1].error.is_none());
+    assert!(reports[2].error.is_some());
+    let info =
+        get_permission(output_dir.join("b.pdf"), None).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(info.encrypted);
+
+    // Glob pattern
+    let pattern = input_dir.join("[ab].pdf");
+    let reports = set_permission_dir(
+        pattern.to_str().unwrap(),
+        &output_dir,
+        &PermissionParams {
+            owner_password: Some("owner".to_string()),
+            ..Default::default()
+        },
+    )
+    .map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(reports.len(), 2);
+    assert!(reports.iter().all(|report| report.error.is_none()));
+
+    // Recursive glob pattern, whose files of the same name don't overwrite each other.
+    create_dir_all(input_dir.join("2024"))?;
+    copy(out, input_dir.join("2024/a.pdf"))?;
+    let pattern = input_dir.join("**/a.pdf");
+    let recursive_dir = out.with_extension("recursive");
+    let reports = set_permission_dir(
+        pattern.to_str().unwrap(),
+        &recursive_dir,
+        &PermissionParams {
+            owner_password: Some("owner".to_string()),
+            ..Default::default()
+        },
+    )
+    .map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(reports.len(), 2);
+    assert!(reports.iter().all(|report| report.error.is_none()));
+    assert_eq!(reports[0].output, recursive_dir.join("2024/a.pdf"));
+    assert_eq!(reports[1].output, recursive_dir.join("a.pdf"));
+    assert!(recursive_dir.join("2024/a.pdf").exists());
+    assert!(recursive_dir.join("a.pdf").exists());
+
+    remove_dir_all(recursive_dir)?;
+    remove_dir_all(input_dir)?;
+    remove_dir_all(output_dir)?;
+    remove_file(out)?;
+    Ok(())
+}
+
+/// Derives the file encryption key of the public-key security handler with AES-256, following
+/// ISO 32000-2, 7.6.5.3: SHA-256 of the 20-byte seed, the bytes of every string of Recipients in
+/// order, and 4 bytes of 0xFF if the metadata isn't encrypted.
+fn public_key_file_key(seed: &[u8], recipients: &[&[u8]], encrypt_metadata: bool) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(seed);
+    for recipient in recipients {
+        hasher.update(recipient);
+    }
+    if !encrypt_metadata {
+        hasher.update(&[0xFF; 4]);
+    }
+    hasher.finish().to_vec()
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_encrypt_for_recipients(
+    TypsterTestContext {
+        encrypt_for_recipients: (out_encrypted, (out, params)),
+        ..
+    }: &TypsterTestContext,
+) -> Result<()> {
+    // Known-answer vectors of the key derivation, so that the reader below doesn't merely repeat
+    // the recipe of the implementation.
+    let seed: Vec<u8> = (0..20).collect();
+    let vector: [&[u8]; 2] = [b"first recipient", b"second recipient"];
+    let hex = |key: Vec<u8>| key.iter().map(|b| format!("{b:02x}")).collect::<String>();
+    assert_eq!(
+        hex(public_key_file_key(&seed, &vector, true)),
+        "31e419f5b573485d87891fedf2cd3987062f7b058f0aab8b1b73ae5a5f3936f5"
+    );
+    assert_eq!(
+        hex(public_key_file_key(&seed, &vector, false)),
+        "5595ee25bcc1c4babe70ea8ca04f0447c9531a3b392a2de11efced7662508d6b"
+    );
+
+    let recipient_params = RecipientParams {
+        certificates: vec![
+            std::fs::read(out.with_file_name("recipient.pem"))?,
+            std::fs::read(out.with_file_name("signer.pem"))?,
+        ],
+        allow_print: PrintPermission::Low,
+        encrypt_metadata: false,
+        ..Default::default()
+    };
+
+    assert!(compile(params).is_ok());
+    encrypt_for_recipients(out.clone(), out_encrypted.clone(), &recipient_params)
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    let doc = Document::load(out_encrypted)?;
+    let encrypt = doc.get_encrypted()?;
+    assert_eq!(encrypt.get(b"Filter")?.as_name_str()?, "Adobe.PubSec");
+    assert_eq!(encrypt.get(b"SubFilter")?.as_name_str()?, "adbe.pkcs7.s5");
+    let filter = encrypt.get(b"CF")?.as_dict()?.get(b"DefaultCryptFilter")?.as_dict()?;
+    assert_eq!(filter.get(b"CFM")?.as_name_str()?, "AESV3");
+    let encrypt_metadata = filter.get(b"EncryptMetadata")?.as_bool()?;
+    assert!(!encrypt_metadata);
+    let recipients = filter
+        .get(b"Recipients")?
+        .as_array()?
+        .iter()
+        .map(|recipient| recipient.as_str())
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The recipient recovers the seed and the permissions with the private key of the certificate,
+    // then derives the file encryption key from them.
+    let pkcs12 = Pkcs12::from_der(&std::fs::read(out.with_file_name("recipient.p12"))?)?
+        .parse2("typster")?;
+    let envelope = CmsContentInfo::from_der(recipients[0])?
+        .decrypt(&pkcs12.pkey.unwrap(), &pkcs12.cert.unwrap())?;
+    assert_eq!(envelope.len(), 24);
+    let permissions = u32::from_be_bytes(envelope[20..].try_into()?);
+    assert_ne!(permissions & (1 << 2), 0);
+    assert_eq!(permissions & (1 << 11), 0);
+    let key = public_key_file_key(&envelope[..20], &recipients, encrypt_metadata);
+
+    // The metadata is left unencrypted.
+    let plain = Document::load(out)?;
+    let metadata = plain.catalog()?.get(b"Metadata")?.as_reference()?;
+    assert_eq!(
+        doc.get_object(metadata)?.as_stream()?.content,
+        plain.get_object(metadata)?.as_stream()?.content
+    );
+
+    // The page content decrypts to the one of the input file.
+    let page = *plain.get_pages().get(&1).unwrap();
+    let contents = plain.get_dictionary(page)?.get(b"Contents")?.as_reference()?;
+    let encrypted = &doc.get_object(contents)?.as_stream()?.content;
+    assert_eq!(
+        decrypt(Cipher::aes_256_cbc(), &key, Some(&encrypted[..16]), &encrypted[16..])?,
+        plain.get_object(contents)?.as_stream()?.content
+    );
+
+    // In place, through a different spelling of the path
+    let same = out.parent().unwrap().join(".").join(out.file_name().unwrap());
+    assert!(encrypt_for_recipients(out.clone(), same, &recipient_params).is_err());
+
+    // Already encrypted
+    assert!(encrypt_for_recipients(
+        out_encrypted.clone(),
+        out.with_file_name("encrypt_for_recipients_twice.pdf"),
+        &recipient_params
+    )
+    .is_err());
+
+    // No recipients
+    let none = RecipientParams { certificates: vec![], ..recipient_params };
+    assert!(encrypt_for_recipients(out.clone(), out_encrypted.clone(), &none).is_err());
+
+    remove_file(out)?;
+    remove_file(out_encrypted)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_sign_pdf(
+    TypsterTestContext { sign_pdf: (out_signed, (out, params)), .. }: &TypsterTestContext,
+) -> Result<()> {
+    let signing_params = SigningParams {
+        pkcs12: std::fs::read(out.with_file_name("signer.p12"))?,
+        password: "typster".to_string(),
+        reason: "Approved".to_string(),
+        ..Default::default()
+    };
+
+    assert!(compile(params).is_ok());
+    assert!(sign_pdf(out.clone(), out_signed.clone(), &signing_params).is_ok());
+    let same = out.parent().unwrap().join(".").join(out.file_name().unwrap());
+    assert!(sign_pdf(out.clone(), same, &signing_params).is_err());
+    let pdf = String::from_utf8_lossy(&std::fs::read(out_signed)?).to_string();
+    assert!(pdf.contains("/SubFilter/adbe.pkcs7.detached"));
+    assert!(pdf.contains("/ByteRange[0 "));
+    assert!(!pdf.contains("9999999999"));
+
+    let trusted = std::fs::read(out.with_file_name("signer.pem"))?;
+    let reports = verify_signatures(out_signed, &[trusted]).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].field_name, "Signature1");
+    assert_eq!(reports[0].signer, "typster test signer");
+    assert_eq!(reports[0].reason, "Approved");
+    assert!(reports[0].is_valid());
+    let reports = verify_signatures(out_signed, &[]).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(reports[0].integrity);
+    assert!(!reports[0].trusted);
+    assert!(verify_signatures(out, &[])
+        .map_err(|e| anyhow!(e.to_string()))?
+        .is_empty());
+
+    // Tampering with the signed bytes breaks the integrity.
+    let mut tampered = std::fs::read(out_signed)?;
+    let i = tampered.windows(9).position(|w| w == b"/Reason(A").unwrap() + 8;
+    tampered[i] = b'B';
+    std::fs::write(out_signed, tampered)?;
+    let reports = verify_signatures(out_signed, &[]).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(!reports[0].integrity);
+
+    // Unreachable time-stamping authority
+    let unreachable = SigningParams {
+        timestamp_url: Some("http://127.0.0.1:9/".to_string()),
+        ..signing_params.clone()
+    };
+    assert!(sign_pdf(out.clone(), out_signed.clone(), &unreachable).is_err());
+
+    // Wrong password
+    let wrong = SigningParams { password: "wrong".to_string(), ..signing_params };
+    assert!(sign_pdf(out.clone(), out_signed.clone(), &wrong).is_err());
+
+    remove_file(out)?;
+    remove_file(out_signed)?;
+    Ok(())
+}
+
+/// Encodes a DER element with the tag and content.
+fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+    let length = content.len().to_be_bytes();
+    let skip = length.iter().take_while(|&&byte| byte == 0).count();
+    let header = if content.len() < 0x80 {
+        vec![tag, content.len() as u8]
+    } else {
+        [&[tag, 0x80 | (length.len() - skip) as u8], &length[skip..]].concat()
+    };
+    [header, content.to_vec()].concat()
+}
+
+/// Serves a single RFC 3161 time-stamp request on a local port, and responds with the token made
+/// by `respond` from the request. Returns the URL of the mock time-stamping authority, and the
+/// handle which yields the request and the token.
+fn mock_tsa(
+    respond: impl FnOnce(&[u8]) -> Result<Vec<u8>> + Send + 'static,
+) -> Result<(String, JoinHandle<Result<(Vec<u8>, Vec<u8>)>>)> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}/", listener.local_addr()?);
+    let handle = spawn(move || {
+        let (mut stream, _) = listener.accept()?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    length = value.trim().parse()?;
+                }
+            }
+        }
+        let mut request = vec![0; length];
+        reader.read_exact(&mut request)?;
+        let token = respond(&request)?;
+
+        // TimeStampResp ::= SEQUENCE { status PKIStatusInfo, timeStampToken ContentInfo }
+        let response = der(0x30, &[der(0x30, &der(0x02, &[0])), token.clone()].concat());
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: application/timestamp-reply\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            response.len()
+        )?;
+        stream.write_all(&response)?;
+        Ok((request, token))
+    });
+    Ok((url, handle))
+}
+
+/// Returns the DER-encoded message imprint and nonce of a time-stamp request, i.e.
+/// `SEQUENCE { version, messageImprint, nonce, certReq }` whose elements are all short.
+fn imprint_and_nonce(request: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let end = 7 + request[6] as usize;
+    (request[5..end].to_vec(), request[end..end + 2 + request[end + 1] as usize].to_vec())
+}
+
+/// Makes a time-stamp token of the message imprint and nonce, signed by the PKCS#12 key.
+fn timestamp_token(pkcs12: &[u8], imprint: &[u8], nonce: &[u8]) -> Result<Vec<u8>> {
+    // TSTInfo ::= SEQUENCE { version, policy, messageImprint, serialNumber, genTime, nonce }
+    let tst_info = der(
+        0x30,
+        &[
+            der(0x02, &[1]),
+            der(0x06, &[0x2A, 0x03]),
+            imprint.to_vec(),
+            der(0x02, &[1]),
+            der(0x18, b"20260101000000Z"),
+            nonce.to_vec(),
+        ]
+        .concat(),
+    );
+    let key = Pkcs12::from_der(pkcs12)?.parse2("typster")?;
+    let chain = Stack::<X509>::new()?;
+    Ok(Pkcs7::sign(&key.cert.unwrap(), &key.pkey.unwrap(), &chain, &tst_info, Pkcs7Flags::BINARY)?
+        .to_der()?)
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_sign_pdf_timestamp(
+    TypsterTestContext {
+        sign_pdf_timestamp: (out_signed, (out, params)), ..
+    }: &TypsterTestContext,
+) -> Result<()> {
+    let pkcs12 = std::fs::read(out.with_file_name("signer.p12"))?;
+    let key = pkcs12.clone();
+    let (url, tsa) = mock_tsa(move |request| {
+        let (imprint, nonce) = imprint_and_nonce(request);
+        timestamp_token(&key, &imprint, &nonce)
+    })?;
+
+    let signing_params = SigningParams {
+        pkcs12: pkcs12.clone(),
+        password: "typster".to_string(),
+        timestamp_url: Some(url),
+        ..Default::default()
+    };
+    assert!(compile(params).is_ok());
+    sign_pdf(out.clone(), out_signed.clone(), &signing_params)
+        .map_err(|e| anyhow!(e.to_string()))?;
+
+    // The request asks for a SHA-256 digest, i.e. 2.16.840.1.101.3.4.2.1.
+    let (request, token) = tsa.join().map_err(|_| anyhow!("mock TSA panicked"))??;
+    let sha256 = [0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+    assert!(request.windows(sha256.len()).any(|w| w == sha256));
+
+    // The signature embeds the token as the signatureTimeStampToken unsigned attribute, i.e.
+    // 1.2.840.113549.1.9.16.2.14.
+    let pdf = std::fs::read(out_signed)?;
+    let start = pdf.windows(10).position(|w| w == b"/Contents<").unwrap() + 10;
+    let end = start + pdf[start..].iter().position(|&byte| byte == b'>').unwrap();
+    let signature = (start..end)
+        .step_by(2)
+        .map(|i| u8::from_str_radix(std::str::from_utf8(&pdf[i..i + 2])?, 16).map_err(Into::into))
+        .collect::<Result<Vec<u8>>>()?;
+    let attribute = der(
+        0x30,
+        &[
+            &[0x06, 0x0B, 0x2A, 0x86, 0x48, 0x86, 0xF7, 0x0D, 0x01, 0x09, 0x10, 0x02, 0x0E][..],
+            &der(0x31, &token),
+        ]
+        .concat(),
+    );
+    assert!(signature.windows(attribute.len()).any(|w| w == attribute));
+
+    // The unsigned attribute doesn't break the signature.
+    let trusted = std::fs::read(out.with_file_name("signer.pem"))?;
+    let reports = verify_signatures(out_signed, &[trusted]).map_err(|e| anyhow!(e.to_string()))?;
+    assert!(reports[0].is_valid());
+
+    // A token over another digest, or with another nonce, is rejected.
+    for tamper_imprint in [true, false] {
+        let key = pkcs12.clone();
+        let (url, tsa) = mock_tsa(move |request| {
+            let (mut imprint, mut nonce) = imprint_and_nonce(request);
+            let tampered = if tamper_imprint { &mut imprint } else { &mut nonce };
+            *tampered.last_mut().unwrap() ^= 0xFF;
+            timestamp_token(&key, &imprint, &nonce)
+        })?;
+        let tampered = SigningParams { timestamp_url: Some(url), ..signing_params.clone() };
+        assert!(sign_pdf(out.clone(), out_signed.clone(), &tampered).is_err());
+        tsa.join().map_err(|_| anyhow!("mock TSA panicked"))??;
+    }
+
+    remove_file(out)?;
+    remove_file(out_signed)?;
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_format(
+    TypsterTestContext { format: (expected, params), .. }: &TypsterTestContext,
+) -> Result<()> {
+    assert_eq!(*expected, format(params).map_err(|e| anyhow!(e.to_string()))?.trim());
+
+    Ok(())
+}
+
+#[test_context(TypsterTestContext)]
+#[test]
+fn test_document_info(
+    TypsterTestContext { document_info: params, .. }: &TypsterTestContext,
+) -> Result<()> {
+    let info = document_info(params).map_err(|e| anyhow!(e.to_string()))?;
+    assert_eq!(info.title.as_deref(), Some("Typster Report"));
+    assert_eq!(info.authors, vec!["typster"]);
+    assert_eq!(info.keywords, vec!["typst", "pdf"]);
+    assert_eq!(info.date.map(|date| date.to_string()).as_deref(), Some("2024-10-18 00:00:00"));
+    assert!(!params.output.exists());
+
+    let mut metadata = PdfMetadata::default();
+    info.apply(&mut metadata);
+    assert_eq!(metadata.title.default_text(), "Typster Report");
+    assert_eq!(metadata.author, "typster");
+    assert_eq!(metadata.keywords, vec!["typst", "pdf"]);
+    assert_eq!(
+        metadata.creation_date.map(|date| date.to_rfc3339()).as_deref(),
+        Some("2024-10-18T00:00:00+00:00")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parse() -> Result<()> {
+    let tree = parse("= Introduction <intro>\nHello, *world*!");
+    assert_eq!(tree.root.kind, "Markup");
+    assert_eq!(tree.root.span, 0..38);
+    assert!(tree.errors.is_empty());
+
+    let heading = &tree.root.children[0];
+    assert_eq!(heading.kind, "Heading");
+    assert_eq!(heading.children[0].kind, "HeadingMarker");
+    assert_eq!(heading.children[0].text.as_deref(), Some("="));
+    assert_eq!(heading.children[0].span, 0..1);
+    assert!(tree.root.children.iter().any(|node| node.kind == "Strong"));
+
+    let tree = parse("#let x = (1, 2");
+    assert!(!tree.errors.is_empty());
+    assert!(tree.errors[0].message.contains("unclosed"));
+
+    Ok(())
+}
+
+#[test]
+fn test_index_symbols() -> Result<()> {
+    let symbols = index_symbols(
+        &PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("symbols"),
+    )
+    .map_err(|e| anyhow!(e.to_string()))?;
+    let symbol = |name: &str, kind, path: &str, line, column| Symbol {
+        name: name.to_string(),
+        kind,
+        location: SourceLocation { path: PathBuf::from(path), line, column },
+        signature: None,
+    };
+    assert_eq!(
+        symbols,
+        vec![
+            symbol("methods", SymbolKind::Label, "chapters/methods.typ", 1, 11),
+            symbol("year", SymbolKind::Variable, "main.typ", 4, 7),
+            symbol("edition", SymbolKind::Variable, "main.typ", 4, 13),
+            symbol("intro", SymbolKind::Label, "main.typ", 6, 16),
+            symbol("knuth", SymbolKind::BibliographyKey, "refs.bib", 1, 7),
+            symbol("lamport", SymbolKind::BibliographyKey, "refs.bib", 10, 11),
+            symbol("accent", SymbolKind::Variable, "template.typ", 1, 6),
+            Symbol {
+                signature: Some("(title: none, body)".to_string()),
+                ..symbol("template", SymbolKind::Function, "template.typ", 3, 6)
+            },
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_typst_version() -> Result<()> {
+    assert_eq!(typst_version(), "0.12.0");
+
+    Ok(())
+}
+
+fn count_pages(path: &Path) -> Result<usize> {
+    let pdf = String::from_utf8_lossy(&std::fs::read(path)?).to_string();
+    Ok(pdf.matches("/Type/Page").count() - pdf.matches("/Type/Pages").count())
+}
+
 fn get_properties(path: &Path) -> Result<HashMap<String, String>> {
     let out = String::from_utf8(Command::new("exiftool").arg(path).output()?.stdout)?;
     let props = out
@@ -0,0 +1,14 @@
+#set heading(numbering: "1.1")
+#show <note>: set text(style: "italic")
+
+= Introduction <intro>
+
+See @intro and @missing.
+
+= Methods <methods>
+
+#figure(rect(), caption: [A box]) <box>
+
+#figure(rect(), caption: [Another box]) <box>
+
+This is a note. <note>
@@ -0,0 +1,25 @@
+#set document(
+  title: "Typster Report",
+  author: "typster",
+  keywords: ("typst", "pdf"),
+  date: datetime(year: 2024, month: 10, day: 18),
+)
+#set heading(numbering: "1.1")
+
+= Introduction <intro>
+
+Typster works with Typst documents and PDF files. See @results and #link("https://typst.app")[the Typst website].
+
+== Background
+
+Typst is a markup-based typesetting system.
+
+#pagebreak()
+
+= Results <results>
+
+The results are shown here. 結果を示す。
+
+#heading(outlined: false)[Notes]
+
+These notes are not listed in the outline.
@@ -0,0 +1,16 @@
+#set page(paper: "presentation-16-9")
+
+= Welcome
+
+#metadata("Greet the audience") <notes>
+
+#pagebreak()
+
+= Agenda
+
+#pagebreak()
+
+= Results
+
+#metadata[Mention the *growth*] <notes>
+#metadata(("Compare with last year", "Take questions")) <notes>
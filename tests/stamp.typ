@@ -0,0 +1,8 @@
+#set page(
+  width: float(sys.inputs.width) * 1pt,
+  height: float(sys.inputs.height) * 1pt,
+  margin: 0pt,
+)
+
+#place(center + horizon, rotate(-30deg, text(48pt, fill: red.transparentize(70%), sys.inputs.text)))
+#place(bottom + right, dx: -24pt, dy: -24pt, text(9pt)[#sys.inputs.page / #sys.inputs.total])
@@ -0,0 +1 @@
+#let hidden = 1
@@ -0,0 +1,3 @@
+= Methods <methods>
+
+See @knuth.
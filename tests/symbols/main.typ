@@ -0,0 +1,10 @@
+#import "template.typ": template, accent
+#show: template.with(title: "Symbols")
+
+#let (year, edition) = (2024, 2)
+
+= Introduction <intro>
+
+#include "chapters/methods.typ"
+
+#bibliography("refs.bib")
@@ -0,0 +1,7 @@
+#let accent = rgb("#239dad")
+
+#let template(title: none, body) = {
+  let local = 1
+  set document(title: title)
+  body
+}